@@ -0,0 +1,37 @@
+//! operator dispatch benchmarks
+//! run `cargo bench --bench operators` and `cargo bench --bench operators --features no-fast-paths`
+//! to compare the fast paths with the prelude functions(`__add__`, ...)
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use minipython::eval::evaluator::{Engine, Evaluator};
+
+// only the script is measured, not loading the prelude
+fn run(c: &mut Criterion, group_name: &str, file_name: &str, result: &str) {
+    let mut group = c.benchmark_group(group_name);
+    for (name, engine) in [("tree", Engine::Tree), ("vm", Engine::Vm)] {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || Evaluator::with_engine(file_name, engine).unwrap(),
+                |mut evaluator| {
+                    evaluator.eval();
+                    black_box(evaluator.get_global(result))
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+// int arithmetic and == in a fizzbuzz loop
+fn fizzbuzz(c: &mut Criterion) {
+    run(c, "fizzbuzz", "testcase/bench_arith.py", "total");
+}
+
+// float arithmetic, ordering, str/list/tuple operands
+fn mixed(c: &mut Criterion) {
+    run(c, "mixed", "testcase/bench_operators.py", "hits");
+}
+
+criterion_group!(benches, fizzbuzz, mixed);
+criterion_main!(benches);
@@ -0,0 +1,68 @@
+//! value representation benchmarks
+//! run `cargo bench` and `cargo bench --features boxed-values` to compare
+//! the tagged representation with every value on the heap(as `Rc<py_val>`)
+
+use std::rc::Rc;
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use minipython::eval::evaluator::Evaluator;
+use minipython::eval::types::*;
+
+const N: i64 = 10_000;
+
+fn ints(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ints");
+    group.bench_function("Value", |b| {
+        b.iter(|| {
+            let values: Vec<py_val_t> = (0..N).map(|i| py_val::new(py_val::int(i))).collect();
+            black_box(values.iter().map(|v| v.as_i64().unwrap()).sum::<i64>())
+        })
+    });
+    group.bench_function("Rc<py_val>", |b| {
+        b.iter(|| {
+            let values: Vec<Rc<py_val>> = (0..N).map(|i| Rc::new(py_val::int(i))).collect();
+            black_box(values.iter().map(|v| v.as_i64().unwrap()).sum::<i64>())
+        })
+    });
+    group.finish();
+}
+
+fn clone_drop(c: &mut Criterion) {
+    let mut group = c.benchmark_group("clone_drop");
+    let values: Vec<py_val_t> = (0..N)
+        .map(|i| py_val::new(py_val::string((i % 100).to_string())))
+        .collect();
+    group.bench_function("Value", |b| b.iter(|| black_box(values.clone())));
+    let rcs: Vec<Rc<py_val>> = (0..N)
+        .map(|i| Rc::new(py_val::string((i % 100).to_string())))
+        .collect();
+    group.bench_function("Rc<py_val>", |b| b.iter(|| black_box(rcs.clone())));
+    group.finish();
+}
+
+// the script on the interpreter(not loading the prelude). compare with the result under
+// `--features boxed-values`
+fn run(c: &mut Criterion, name: &str, file_name: &str, result: &str) {
+    c.bench_function(name, |b| {
+        b.iter_batched(
+            || Evaluator::new(file_name).unwrap(),
+            |mut evaluator| {
+                evaluator.eval();
+                black_box(evaluator.get_global(result))
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn arith_loop(c: &mut Criterion) {
+    run(c, "arith_loop", "testcase/bench_arith.py", "total");
+}
+
+// values built and consumed by builtins, where allocation is most of the work
+fn builtins(c: &mut Criterion) {
+    run(c, "builtins", "testcase/bench_values.py", "longest");
+}
+
+criterion_group!(benches, ints, clone_drop, arith_loop, builtins);
+criterion_main!(benches);
@@ -2,6 +2,8 @@
 //! 抽象構文木の構成要素
 //! https://docs.python.org/ja/3/library/ast.html#abstract-grammar からパクってきた(自作は無理)
 
+use std::hash::{Hash, Hasher};
+
 use ordered_float::OrderedFloat;
 
 // represents file(module).
@@ -27,15 +29,84 @@ pub enum ASTConstant {
 // ???
 // pub type ASTObject;
 
+// position of a node in the source, as lineno/col_offset/end_lineno/end_col_offset of CPython.
+// lines are 1-based, columns are 0-based and the end is exclusive
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Span {
+    pub fn new(start: (usize, usize), end: (usize, usize)) -> Span {
+        Span {
+            line: start.0,
+            column: start.1,
+            end_line: end.0,
+            end_column: end.1,
+        }
+    }
+
+    // from the start of self to the end of other
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end_line: other.end_line,
+            end_column: other.end_column,
+            ..self
+        }
+    }
+}
+
+// a node(or token) with its span. the span is not compared(as ast.compare of CPython), so
+// nodes built by hand are equal to the parsed ones
+#[derive(Debug, Clone)]
+pub struct Located<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+impl<T> Located<T> {
+    pub fn new(node: T, span: Span) -> Located<T> {
+        Located { node, span }
+    }
+}
+
+impl<T> From<T> for Located<T> {
+    fn from(node: T) -> Located<T> {
+        Located::new(node, Span::default())
+    }
+}
+
+impl<T: PartialEq> PartialEq for Located<T> {
+    fn eq(&self, other: &Located<T>) -> bool {
+        self.node == other.node
+    }
+}
+
+impl<T: Eq> Eq for Located<T> {}
+
+impl<T: Hash> Hash for Located<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.node.hash(state)
+    }
+}
+
+pub type ASTStmt = Located<ASTStmtKind>;
+pub type ASTExpr = Located<ASTExprKind>;
+
 // AST parts
-#[derive(Debug, PartialEq, Eq, Ord, PartialOrd, Clone, Hash)]
-pub enum ASTStmt {
-    Init, // for initial state
-    End, // for final state
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum ASTStmtKind {
+    Init,  // for initial state
+    End,   // for final state
+    Error, // placeholder for a statement with a syntax error
     FuncDef(
-        String,       // name
-        ASTArguments, // arguments
-        Vec<ASTStmt>, // body
+        String,          // name
+        ASTArguments,    // arguments
+        Vec<ASTStmt>,    // body
+        Option<ASTExpr>, // returns(annotation, ignored at runtime)
     ),
     Return(
         Option<ASTExpr>, // value
@@ -47,6 +118,12 @@ pub enum ASTStmt {
         Vec<ASTExpr>, // targets
         ASTExpr,      // value
     ),
+    // `x: int = 1`. the annotation is ignored at runtime
+    AnnAssign(
+        ASTExpr,         // target
+        ASTExpr,         // annotation
+        Option<ASTExpr>, // value
+    ),
     For(
         ASTExpr,      // target
         ASTExpr,      // iter
@@ -56,6 +133,11 @@ pub enum ASTStmt {
         ASTExpr,      // test
         Vec<ASTStmt>, // body
     ),
+    // `with a as b, c:`. the managers are entered in order and exited in reverse
+    With(
+        Vec<ASTWithItem>, // items
+        Vec<ASTStmt>,     // body
+    ),
     If(
         ASTExpr,      // test
         Vec<ASTStmt>, // body
@@ -64,6 +146,15 @@ pub enum ASTStmt {
     Global(
         Vec<ASTIdentifier>, // names
     ),
+    // `import a.b as c`
+    Import(
+        Vec<ASTAlias>, // names
+    ),
+    // `from a.b import c as d`. relative imports are not supported(level is always 0)
+    ImportFrom(
+        ASTIdentifier, // module, dotted
+        Vec<ASTAlias>, // names
+    ),
     Expr(
         ASTExpr, // value
     ),
@@ -72,8 +163,27 @@ pub enum ASTStmt {
     Continue,
 }
 
-#[derive(Debug, PartialEq, Eq, Ord, PartialOrd, Clone, Hash)]
-pub enum ASTExpr {
+impl ASTStmtKind {
+    // names bound by an import statement: `import a.b` binds `a`, `from m import x as y` binds `y`
+    pub fn imported_names(&self) -> Vec<&str> {
+        let (names, dotted) = match self {
+            ASTStmtKind::Import(names) => (names, true),
+            ASTStmtKind::ImportFrom(_, names) => (names, false),
+            _ => return Vec::new(),
+        };
+        names
+            .iter()
+            .map(|alias| match &alias.node {
+                (_, Some(asname)) => asname.as_str(),
+                (name, None) if dotted => name.split('.').next().unwrap(),
+                (name, None) => name.as_str(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum ASTExprKind {
     BoolOp(
         ASTBoolOp,    // op
         Vec<ASTExpr>, // values
@@ -119,9 +229,9 @@ pub enum ASTExpr {
         Vec<ASTExpr>,  // comparators
     ),
     Call(
-        Box<ASTExpr>, // func
-        Vec<ASTExpr>, // args
-                      // omit keywords
+        Box<ASTExpr>,    // func
+        Vec<ASTExpr>,    // args
+        Vec<ASTKeyword>, // keywords
     ),
     // ???
     // FormattedValue(
@@ -138,11 +248,11 @@ pub enum ASTExpr {
     ),
     //  -- the following expression can appear in assignment context
     // ???
-    // Attribute(
-    //     Box<ASTExpr>, // value
-    //     ASTIdentifier, // attr,
-    //                   // ASTExprContext, // ctx <- ???
-    // ),
+    Attribute(
+        Box<ASTExpr>, // value
+        ASTIdentifier, // attr
+                      // ASTExprContext, // ctx <- ???
+    ),
     Subscript(
         Box<ASTExpr>, // value
         ASTSlice,     // slice
@@ -176,8 +286,10 @@ pub enum ASTExpr {
 //     AugStore, // 仮引数?
 // }
 
-#[derive(Debug, PartialEq, Eq, Ord, PartialOrd, Clone, Hash)]
-pub enum ASTSlice {
+pub type ASTSlice = Located<ASTSliceKind>;
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum ASTSliceKind {
     Slice(
         Option<Box<ASTExpr>>, // lower
         Option<Box<ASTExpr>>, // upper
@@ -239,6 +351,12 @@ pub type ASTComprehension = (
     Vec<ASTExpr>, // ifs
 );
 
+// keyword argument of a call(`key=value`)
+pub type ASTKeyword = Located<(
+    ASTIdentifier, // arg
+    ASTExpr,       // value
+)>;
+
 pub type ASTArguments = Vec<ASTArg>; // args
 
 // arguments = (
@@ -253,4 +371,19 @@ pub type ASTArguments = Vec<ASTArg>; // args
 // def hoge(positional_only_argument1, positional_only_argument2, /, argument1, argument2, *[vararg], keyword_only_argument1, keyword_only_argument2, **kwarg)
 // 今回は普通にargsのみ利用可能とする
 
-pub type ASTArg = ASTIdentifier; // arg
+pub type ASTArg = Located<(
+    ASTIdentifier,   // arg
+    Option<ASTExpr>, // annotation
+)>;
+
+// context manager of a with statement and the target its __enter__ value is stored to
+pub type ASTWithItem = Located<(
+    ASTExpr,         // context_expr
+    Option<ASTExpr>, // optional_vars
+)>;
+
+// imported name and the name it is bound to(`a.b as c`)
+pub type ASTAlias = Located<(
+    ASTIdentifier,         // name
+    Option<ASTIdentifier>, // asname
+)>;
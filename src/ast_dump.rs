@@ -0,0 +1,620 @@
+//! # ast_dump
+//! ASTの出力(JSON, CPythonのast.dump, S式)
+//! the AST is first converted to nodes named as in the ast module of CPython 3.11,
+//! so the three formats agree on node types and fields
+
+use crate::ast::*;
+
+// node of the ast module
+enum Node {
+    Ast(
+        &'static str,              // type
+        Vec<(&'static str, Node)>, // fields
+        Option<Span>,              // operators, contexts, arguments and modules have no span
+    ),
+    List(Vec<Node>),
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Const(&'static str), // None, True, False
+    Missing,             // optional field without a value(None in CPython)
+}
+
+/// JSON with a fixed schema: {"type": ..., <fields>..., "span": {...}}
+pub fn to_json(ast: &AST) -> String {
+    let mut res = String::new();
+    json(&module(ast), 0, &mut res);
+    res
+}
+
+/// the same as `ast.dump(ast.parse(source), indent=2)` of CPython
+pub fn dump(ast: &AST) -> String {
+    format_dump(&module(ast), 0).0
+}
+
+/// S-expression. fields are positional, a missing optional field is `nil`
+pub fn to_sexpr(ast: &AST) -> String {
+    format_sexpr(&module(ast), 0)
+}
+
+fn node(kind: &'static str, fields: Vec<(&'static str, Node)>, span: Span) -> Node {
+    Node::Ast(kind, fields, Some(span))
+}
+
+// node without fields, ex. Load() or Add()
+fn symbol(kind: &'static str) -> Node {
+    Node::Ast(kind, Vec::new(), None)
+}
+
+fn module(ast: &AST) -> Node {
+    Node::Ast(
+        "Module",
+        vec![
+            ("body", stmts(ast)),
+            ("type_ignores", Node::List(Vec::new())),
+        ],
+        None,
+    )
+}
+
+fn stmts(stmts: &[ASTStmt]) -> Node {
+    Node::List(stmts.iter().map(stmt).collect())
+}
+
+fn exprs(exprs: &[ASTExpr], ctx: &'static str) -> Node {
+    Node::List(exprs.iter().map(|e| expr(e, ctx)).collect())
+}
+
+// None is Missing
+fn optional(value: &Option<ASTExpr>) -> Node {
+    value.as_ref().map_or(Node::Missing, |v| expr(v, "Load"))
+}
+
+fn stmt(stmt: &ASTStmt) -> Node {
+    use ASTStmtKind::*;
+    let (kind, fields) = match &stmt.node {
+        Init => ("Init", vec![]),
+        End => ("End", vec![]),
+        Error => ("Error", vec![]),
+        FuncDef(name, args, body, returns) => (
+            "FunctionDef",
+            vec![
+                ("name", Node::Str(name.clone())),
+                ("args", arguments(args)),
+                ("body", stmts(body)),
+                ("decorator_list", Node::List(Vec::new())),
+                ("returns", optional(returns)),
+            ],
+        ),
+        Return(value) => ("Return", vec![("value", optional(value))]),
+        Delete(targets) => ("Delete", vec![("targets", exprs(targets, "Del"))]),
+        Assign(targets, value) => (
+            "Assign",
+            vec![
+                ("targets", exprs(targets, "Store")),
+                ("value", expr(value, "Load")),
+            ],
+        ),
+        AnnAssign(target, annotation, value) => (
+            "AnnAssign",
+            vec![
+                ("target", expr(target, "Store")),
+                ("annotation", expr(annotation, "Load")),
+                ("value", optional(value)),
+                // 1 for a bare name(parentheses are not kept in the AST)
+                (
+                    "simple",
+                    Node::Int(matches!(target.node, ASTExprKind::Name(_)) as i64),
+                ),
+            ],
+        ),
+        For(target, iter, body) => (
+            "For",
+            vec![
+                ("target", expr(target, "Store")),
+                ("iter", expr(iter, "Load")),
+                ("body", stmts(body)),
+                ("orelse", Node::List(Vec::new())),
+            ],
+        ),
+        While(test, body) => (
+            "While",
+            vec![
+                ("test", expr(test, "Load")),
+                ("body", stmts(body)),
+                ("orelse", Node::List(Vec::new())),
+            ],
+        ),
+        With(items, body) => (
+            "With",
+            vec![
+                (
+                    "items",
+                    Node::List(
+                        items
+                            .iter()
+                            .map(|item| {
+                                let (context_expr, optional_vars) = &item.node;
+                                // withitem has no location in CPython
+                                Node::Ast(
+                                    "withitem",
+                                    vec![
+                                        ("context_expr", expr(context_expr, "Load")),
+                                        (
+                                            "optional_vars",
+                                            optional_vars
+                                                .as_ref()
+                                                .map_or(Node::Missing, |v| expr(v, "Store")),
+                                        ),
+                                    ],
+                                    None,
+                                )
+                            })
+                            .collect(),
+                    ),
+                ),
+                ("body", stmts(body)),
+            ],
+        ),
+        If(test, body, orelse) => (
+            "If",
+            vec![
+                ("test", expr(test, "Load")),
+                ("body", stmts(body)),
+                ("orelse", stmts(orelse)),
+            ],
+        ),
+        Global(names) => (
+            "Global",
+            vec![(
+                "names",
+                Node::List(names.iter().map(|n| Node::Str(n.clone())).collect()),
+            )],
+        ),
+        Import(names) => ("Import", vec![("names", aliases(names))]),
+        ImportFrom(module, names) => (
+            "ImportFrom",
+            vec![
+                ("module", Node::Str(module.clone())),
+                ("names", aliases(names)),
+                ("level", Node::Int(0)),
+            ],
+        ),
+        Expr(value) => ("Expr", vec![("value", expr(value, "Load"))]),
+        Pass => ("Pass", vec![]),
+        Break => ("Break", vec![]),
+        Continue => ("Continue", vec![]),
+    };
+    node(kind, fields, stmt.span)
+}
+
+fn arguments(args: &[ASTArg]) -> Node {
+    let empty = || Node::List(Vec::new());
+    Node::Ast(
+        "arguments",
+        vec![
+            ("posonlyargs", empty()),
+            (
+                "args",
+                Node::List(
+                    args.iter()
+                        .map(|arg| {
+                            let (name, annotation) = &arg.node;
+                            node(
+                                "arg",
+                                vec![
+                                    ("arg", Node::Str(name.clone())),
+                                    ("annotation", optional(annotation)),
+                                ],
+                                arg.span,
+                            )
+                        })
+                        .collect(),
+                ),
+            ),
+            ("kwonlyargs", empty()),
+            ("kw_defaults", empty()),
+            ("defaults", empty()),
+        ],
+        None,
+    )
+}
+
+fn aliases(names: &[ASTAlias]) -> Node {
+    Node::List(
+        names
+            .iter()
+            .map(|alias| {
+                let (name, asname) = &alias.node;
+                node(
+                    "alias",
+                    vec![
+                        ("name", Node::Str(name.clone())),
+                        (
+                            "asname",
+                            asname
+                                .as_ref()
+                                .map_or(Node::Missing, |n| Node::Str(n.clone())),
+                        ),
+                    ],
+                    alias.span,
+                )
+            })
+            .collect(),
+    )
+}
+
+// ctx is the expr_context(Load, Store or Del) of the expression
+fn expr(expr_: &ASTExpr, ctx: &'static str) -> Node {
+    use ASTExprKind::*;
+    let (kind, fields) = match &expr_.node {
+        BoolOp(op, values) => (
+            "BoolOp",
+            vec![
+                (
+                    "op",
+                    symbol(match op {
+                        ASTBoolOp::And => "And",
+                        ASTBoolOp::Or => "Or",
+                    }),
+                ),
+                ("values", exprs(values, "Load")),
+            ],
+        ),
+        BinOp(left, op, right) => (
+            "BinOp",
+            vec![
+                ("left", expr(left, "Load")),
+                ("op", symbol(operator(op))),
+                ("right", expr(right, "Load")),
+            ],
+        ),
+        UnaryOp(op, operand) => (
+            "UnaryOp",
+            vec![
+                (
+                    "op",
+                    symbol(match op {
+                        ASTUnaryOp::Invert => "Invert",
+                        ASTUnaryOp::Not => "Not",
+                        ASTUnaryOp::UAdd => "UAdd",
+                        ASTUnaryOp::USub => "USub",
+                    }),
+                ),
+                ("operand", expr(operand, "Load")),
+            ],
+        ),
+        IfExp(test, body, orelse) => (
+            "IfExp",
+            vec![
+                ("test", expr(test, "Load")),
+                ("body", expr(body, "Load")),
+                ("orelse", expr(orelse, "Load")),
+            ],
+        ),
+        Dict(keys, values) => (
+            "Dict",
+            vec![
+                ("keys", exprs(keys, "Load")),
+                ("values", exprs(values, "Load")),
+            ],
+        ),
+        Set(elts) => ("Set", vec![("elts", exprs(elts, "Load"))]),
+        Compare(left, ops, comparators) => (
+            "Compare",
+            vec![
+                ("left", expr(left, "Load")),
+                (
+                    "ops",
+                    Node::List(ops.iter().map(|op| symbol(cmpop(op))).collect()),
+                ),
+                ("comparators", exprs(comparators, "Load")),
+            ],
+        ),
+        Call(func, args, keywords) => (
+            "Call",
+            vec![
+                ("func", expr(func, "Load")),
+                ("args", exprs(args, "Load")),
+                (
+                    "keywords",
+                    Node::List(
+                        keywords
+                            .iter()
+                            .map(|keyword| {
+                                let (arg, value) = &keyword.node;
+                                node(
+                                    "keyword",
+                                    vec![
+                                        ("arg", Node::Str(arg.clone())),
+                                        ("value", expr(value, "Load")),
+                                    ],
+                                    keyword.span,
+                                )
+                            })
+                            .collect(),
+                    ),
+                ),
+            ],
+        ),
+        Constant(value) => (
+            "Constant",
+            vec![(
+                "value",
+                match value {
+                    ASTConstant::Int(i) => Node::Int(*i),
+                    ASTConstant::Float(f) => Node::Float(f.into_inner()),
+                    ASTConstant::String(s) => Node::Str(s.clone()),
+                    ASTConstant::None => Node::Const("None"),
+                    ASTConstant::True => Node::Const("True"),
+                    ASTConstant::False => Node::Const("False"),
+                },
+            )],
+        ),
+        Attribute(value, attr) => (
+            "Attribute",
+            vec![
+                ("value", expr(value, "Load")),
+                ("attr", Node::Str(attr.clone())),
+                ("ctx", symbol(ctx)),
+            ],
+        ),
+        // Index was removed in Python 3.9, the index is the slice itself
+        Subscript(value, slice) => (
+            "Subscript",
+            vec![
+                ("value", expr(value, "Load")),
+                (
+                    "slice",
+                    match &slice.node {
+                        ASTSliceKind::Index(index) => expr(index, "Load"),
+                        ASTSliceKind::Slice(lower, upper, step) => {
+                            let part = |e: &Option<Box<ASTExpr>>| {
+                                e.as_ref().map_or(Node::Missing, |e| expr(e, "Load"))
+                            };
+                            node(
+                                "Slice",
+                                vec![
+                                    ("lower", part(lower)),
+                                    ("upper", part(upper)),
+                                    ("step", part(step)),
+                                ],
+                                slice.span,
+                            )
+                        }
+                    },
+                ),
+                ("ctx", symbol(ctx)),
+            ],
+        ),
+        Name(id) => (
+            "Name",
+            vec![("id", Node::Str(id.clone())), ("ctx", symbol(ctx))],
+        ),
+        // elements of a target are targets too
+        List(elts) => (
+            "List",
+            vec![("elts", exprs(elts, ctx)), ("ctx", symbol(ctx))],
+        ),
+        Tuple(elts) => (
+            "Tuple",
+            vec![("elts", exprs(elts, ctx)), ("ctx", symbol(ctx))],
+        ),
+    };
+    node(kind, fields, expr_.span)
+}
+
+fn operator(op: &ASTOperator) -> &'static str {
+    match op {
+        ASTOperator::Add => "Add",
+        ASTOperator::Sub => "Sub",
+        ASTOperator::Mul => "Mult",
+        ASTOperator::Div => "Div",
+        ASTOperator::Mod => "Mod",
+        ASTOperator::LShift => "LShift",
+        ASTOperator::RShift => "RShift",
+        ASTOperator::BitOr => "BitOr",
+        ASTOperator::BitXor => "BitXor",
+        ASTOperator::BitAnd => "BitAnd",
+    }
+}
+
+fn cmpop(op: &ASTCmpOp) -> &'static str {
+    match op {
+        ASTCmpOp::Eq => "Eq",
+        ASTCmpOp::NotEq => "NotEq",
+        ASTCmpOp::Lt => "Lt",
+        ASTCmpOp::LtE => "LtE",
+        ASTCmpOp::Gt => "Gt",
+        ASTCmpOp::GtE => "GtE",
+        ASTCmpOp::Is => "Is",
+        ASTCmpOp::IsNot => "IsNot",
+        ASTCmpOp::In => "In",
+        ASTCmpOp::NotIn => "NotIn",
+    }
+}
+
+// ast._format of CPython: a node is written on one line if its fields are simple(and at most 3),
+// returns (text, whether the node is simple)
+fn format_dump(node: &Node, level: usize) -> (String, bool) {
+    let level = level + 1;
+    let prefix = format!("\n{}", "  ".repeat(level));
+    let sep = format!(",\n{}", "  ".repeat(level));
+    match node {
+        Node::Ast(kind, fields, _) => {
+            let mut args = Vec::new();
+            let mut all_simple = true;
+            for (name, value) in fields {
+                if let Node::Missing = value {
+                    continue;
+                }
+                let (value, simple) = format_dump(value, level);
+                all_simple = all_simple && simple;
+                args.push(format!("{}={}", name, value));
+            }
+            if all_simple && args.len() <= 3 {
+                (format!("{}({})", kind, args.join(", ")), args.is_empty())
+            } else {
+                (format!("{}({}{})", kind, prefix, args.join(&sep)), false)
+            }
+        }
+        Node::List(elts) if elts.is_empty() => ("[]".to_string(), true),
+        Node::List(elts) => {
+            let elts: Vec<String> = elts.iter().map(|e| format_dump(e, level).0).collect();
+            (format!("[{}{}]", prefix, elts.join(&sep)), false)
+        }
+        Node::Str(s) => (py_repr(s), true),
+        Node::Int(i) => (i.to_string(), true),
+        Node::Float(f) => (float_repr(*f), true),
+        Node::Const(c) => (c.to_string(), true),
+        Node::Missing => ("None".to_string(), true),
+    }
+}
+
+fn json(node: &Node, level: usize, res: &mut String) {
+    let indent = "  ".repeat(level + 1);
+    match node {
+        Node::Ast(kind, fields, span) => {
+            res.push_str(&format!("{{\n{}\"type\": \"{}\"", indent, kind));
+            for (name, value) in fields {
+                res.push_str(&format!(",\n{}\"{}\": ", indent, name));
+                json(value, level + 1, res);
+            }
+            if let Some(span) = span {
+                res.push_str(&format!(
+                    ",\n{}\"span\": {{\"line\": {}, \"column\": {}, \"end_line\": {}, \"end_column\": {}}}",
+                    indent, span.line, span.column, span.end_line, span.end_column
+                ));
+            }
+            res.push_str(&format!("\n{}}}", "  ".repeat(level)));
+        }
+        Node::List(elts) if elts.is_empty() => res.push_str("[]"),
+        Node::List(elts) => {
+            res.push('[');
+            for (i, elt) in elts.iter().enumerate() {
+                res.push_str(if i == 0 { "\n" } else { ",\n" });
+                res.push_str(&indent);
+                json(elt, level + 1, res);
+            }
+            res.push_str(&format!("\n{}]", "  ".repeat(level)));
+        }
+        Node::Str(s) => res.push_str(&json_str(s)),
+        Node::Int(i) => res.push_str(&i.to_string()),
+        // JSON has no inf and nan
+        Node::Float(f) if !f.is_finite() => res.push_str(&json_str(&float_repr(*f))),
+        Node::Float(f) => res.push_str(&float_repr(*f)),
+        Node::Const("True") => res.push_str("true"),
+        Node::Const("False") => res.push_str("false"),
+        Node::Const(_) | Node::Missing => res.push_str("null"),
+    }
+}
+
+// a list is written on one line if it fits in SEXPR_WIDTH, otherwise one element per line
+const SEXPR_WIDTH: usize = 60;
+
+fn format_sexpr(node: &Node, level: usize) -> String {
+    let join = |head: &str, elts: Vec<String>| {
+        let mut items = vec![head.to_string()];
+        items.extend(elts);
+        let line = format!("({})", items.join(" ").trim_start());
+        if !line.contains('\n') && line.len() <= SEXPR_WIDTH {
+            line
+        } else {
+            let prefix = format!("\n{}", "  ".repeat(level + 1));
+            format!("({}{}{})", head, prefix, items[1..].join(&prefix))
+        }
+    };
+    match node {
+        // a node without fields is a symbol
+        Node::Ast(kind, fields, _) if fields.is_empty() => kind.to_string(),
+        Node::Ast(kind, fields, _) => join(
+            kind,
+            fields
+                .iter()
+                .map(|(_, f)| format_sexpr(f, level + 1))
+                .collect(),
+        ),
+        Node::List(elts) => join(
+            "",
+            elts.iter().map(|e| format_sexpr(e, level + 1)).collect(),
+        ),
+        Node::Str(s) => json_str(s),
+        Node::Int(i) => i.to_string(),
+        Node::Float(f) => float_repr(*f),
+        Node::Const(c) => c.to_string(),
+        Node::Missing => "nil".to_string(),
+    }
+}
+
+// repr() of a str in CPython
+fn py_repr(s: &str) -> String {
+    let quote = if s.contains('\'') && !s.contains('"') {
+        '"'
+    } else {
+        '\''
+    };
+    let mut res = quote.to_string();
+    for c in s.chars() {
+        match c {
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if c == quote => {
+                res.push('\\');
+                res.push(c);
+            }
+            c if (c as u32) < 0x20 || c as u32 == 0x7f => {
+                res.push_str(&format!("\\x{:02x}", c as u32))
+            }
+            c => res.push(c),
+        }
+    }
+    res.push(quote);
+    res
+}
+
+pub(crate) fn json_str(s: &str) -> String {
+    let mut res = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+// repr() of a float in CPython: the shortest digits, exponent form out of 1e-4 <= |f| < 1e16
+fn float_repr(f: f64) -> String {
+    if f.is_nan() {
+        return "nan".to_string();
+    }
+    if f.is_infinite() {
+        return if f > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    let sci = format!("{:e}", f);
+    let (mantissa, exp) = sci.split_at(sci.find('e').unwrap());
+    let exp: i32 = exp[1..].parse().unwrap();
+    if f == 0.0 || (-4..16).contains(&exp) {
+        let s = f.to_string();
+        if s.contains('.') {
+            s
+        } else {
+            format!("{}.0", s)
+        }
+    } else {
+        format!(
+            "{}e{}{:02}",
+            mantissa,
+            if exp < 0 { '-' } else { '+' },
+            exp.abs()
+        )
+    }
+}
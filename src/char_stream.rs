@@ -2,12 +2,12 @@
 //! file reader
 
 use std::fs::File;
-use std::io::{prelude::*, Bytes};
+use std::io::{prelude::*, BufReader, Bytes, Cursor};
 
 pub struct CharStream {
     file_name: String,
-    buf: Bytes<File>,
-    current_char: Option<u8>,
+    buf: Bytes<BufReader<Box<dyn Read>>>,
+    current_char: Option<char>,
     current_line: usize,
     current_column: usize,
     current_line_content: String,
@@ -16,29 +16,37 @@ pub struct CharStream {
 impl CharStream {
     pub fn new(file_name: &str) -> std::io::Result<CharStream> {
         let file = File::open(file_name)?;
-        let buf = file.bytes();
-        Ok(CharStream {
+        Ok(CharStream::from_reader(file_name, Box::new(file)))
+    }
+
+    // source in memory. file_name is only used in messages
+    pub fn from_source(file_name: &str, source: String) -> CharStream {
+        CharStream::from_reader(file_name, Box::new(Cursor::new(source.into_bytes())))
+    }
+
+    fn from_reader(file_name: &str, reader: Box<dyn Read>) -> CharStream {
+        CharStream {
             file_name: String::from(file_name),
-            buf: buf,
+            buf: BufReader::new(reader).bytes(),
             current_char: None,
             current_line: 0,
             current_column: 0,
             current_line_content: String::new(),
-        })
+        }
     }
 
     pub fn next_char(&mut self) {
-        if self.current_char == Some('\n' as u8) {
+        if self.current_char == Some('\n') {
             self.current_line += 1;
             self.current_column = 0;
             self.current_line_content.clear();
         }
 
-        match self.buf.next() {
+        match self.next_utf8_char() {
             Some(Ok(c)) => {
                 self.current_column += 1;
                 self.current_char = Some(c);
-                self.current_line_content.push(c as char);
+                self.current_line_content.push(c);
 
                 if self.current_line == 0 {
                     self.current_line = 1;
@@ -54,6 +62,33 @@ impl CharStream {
         }
     }
 
+    // decode one UTF-8 encoded char from the byte stream
+    fn next_utf8_char(&mut self) -> Option<std::io::Result<char>> {
+        let first = match self.buf.next()? {
+            Ok(b) => b,
+            Err(e) => return Some(Err(e)),
+        };
+        let len = match first {
+            0x00..=0x7f => 1,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            _ => 4,
+        };
+        let mut bytes = vec![first];
+        for _ in 1..len {
+            match self.buf.next() {
+                Some(Ok(b)) => bytes.push(b),
+                Some(Err(e)) => return Some(Err(e)),
+                None => break,
+            }
+        }
+        Some(
+            std::str::from_utf8(&bytes)
+                .map(|s| s.chars().next().unwrap())
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        )
+    }
+
     // count up line in the file
     pub fn lc(&mut self) {
         self.next_char();
@@ -67,7 +102,7 @@ impl CharStream {
     pub fn apos(&mut self) {
         self.next_char();
         while let Some(cur) = self.current_char {
-            if cur as char == 'a' {
+            if cur == 'a' {
                 println!("line {}, col {}", self.current_line, self.current_column);
             }
             self.next_char();
@@ -79,12 +114,14 @@ impl CharStream {
     }
 
     // None: EOF
+    // first byte of the UTF-8 encoding of the current char
     pub fn get_current_char_u8(&self) -> Option<u8> {
         self.current_char
+            .map(|c| c.encode_utf8(&mut [0; 4]).as_bytes()[0])
     }
 
     pub fn get_current_char(&self) -> Option<char> {
-        self.current_char.map(|x| x as char)
+        self.current_char
     }
 
     pub fn get_current_line(&self) -> usize {
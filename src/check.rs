@@ -0,0 +1,1064 @@
+//! # check
+//! 型検査(minipython check)
+//! gradual typing: annotations give the types of parameters, returns and variables, the other
+//! types are inferred from literals, operators and calls. what is not known is Any, which is
+//! compatible with everything, so unannotated programs get few reports.
+//! the operators accept what src/std/init.py accepts(e.g. int / int is int)
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::ast::*;
+use crate::lint::Diagnostic;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Type {
+    Any,
+    None,
+    Bool,
+    Int,
+    Float,
+    Str,
+    List(Box<Type>),
+    Tuple(Option<Vec<Type>>), // None if the length is not known(bare `tuple`)
+    Dict(Box<Type>, Box<Type>),
+    Set(Box<Type>),
+    Function(usize), // index of the signature
+}
+
+impl Type {
+    // name of the type without the parameters, as CPython reports it
+    fn name(&self) -> &'static str {
+        match self {
+            Type::Any => "object",
+            Type::None => "NoneType",
+            Type::Bool => "bool",
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::Str => "str",
+            Type::List(_) => "list",
+            Type::Tuple(_) => "tuple",
+            Type::Dict(..) => "dict",
+            Type::Set(_) => "set",
+            Type::Function(_) => "function",
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, Type::Int | Type::Float)
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Any => write!(f, "Any"),
+            Type::None => write!(f, "None"),
+            Type::List(elt) => write!(f, "list[{}]", elt),
+            Type::Tuple(Some(elts)) => {
+                let elts: Vec<String> = elts.iter().map(|t| t.to_string()).collect();
+                write!(f, "tuple[{}]", elts.join(", "))
+            }
+            Type::Dict(key, value) => write!(f, "dict[{}, {}]", key, value),
+            Type::Set(elt) => write!(f, "set[{}]", elt),
+            t => write!(f, "{}", t.name()),
+        }
+    }
+}
+
+// 型の合流(if の両辺、リストの要素など)
+fn join(a: &Type, b: &Type) -> Type {
+    match (a, b) {
+        _ if a == b => a.clone(),
+        (Type::Int, Type::Float) | (Type::Float, Type::Int) => Type::Float,
+        (Type::List(a), Type::List(b)) => Type::List(Box::new(join(a, b))),
+        (Type::Set(a), Type::Set(b)) => Type::Set(Box::new(join(a, b))),
+        (Type::Dict(ak, av), Type::Dict(bk, bv)) => {
+            Type::Dict(Box::new(join(ak, bk)), Box::new(join(av, bv)))
+        }
+        (Type::Tuple(Some(a)), Type::Tuple(Some(b))) if a.len() == b.len() => {
+            Type::Tuple(Some(a.iter().zip(b).map(|(a, b)| join(a, b)).collect()))
+        }
+        (Type::Tuple(_), Type::Tuple(_)) => Type::Tuple(None),
+        _ => Type::Any,
+    }
+}
+
+fn join_all(types: &[Type]) -> Type {
+    match types.split_first() {
+        Some((first, rest)) => rest.iter().fold(first.clone(), |acc, t| join(&acc, t)),
+        None => Type::Any,
+    }
+}
+
+// a value of actual can be used where expected is required(int is accepted as float)
+fn compatible(expected: &Type, actual: &Type) -> bool {
+    match (expected, actual) {
+        (Type::Any, _) | (_, Type::Any) => true,
+        _ if expected == actual => true,
+        (Type::Float, Type::Int) => true,
+        (Type::List(e), Type::List(a)) | (Type::Set(e), Type::Set(a)) => compatible(e, a),
+        (Type::Dict(ek, ev), Type::Dict(ak, av)) => compatible(ek, ak) && compatible(ev, av),
+        (Type::Tuple(None), Type::Tuple(_)) | (Type::Tuple(_), Type::Tuple(None)) => true,
+        (Type::Tuple(Some(e)), Type::Tuple(Some(a))) => {
+            e.len() == a.len() && e.iter().zip(a).all(|(e, a)| compatible(e, a))
+        }
+        (Type::Function(_), Type::Function(_)) => true,
+        _ => false,
+    }
+}
+
+// type of the elements when iterated, None if not iterable
+fn element_type(t: &Type) -> Option<Type> {
+    match t {
+        Type::Any => Some(Type::Any),
+        Type::Str => Some(Type::Str),
+        Type::List(elt) | Type::Set(elt) | Type::Dict(elt, _) => Some((**elt).clone()),
+        Type::Tuple(Some(elts)) => Some(join_all(elts)),
+        Type::Tuple(None) => Some(Type::Any),
+        _ => None,
+    }
+}
+
+// control never falls off the end of body
+fn always_returns(body: &[ASTStmt]) -> bool {
+    match body.last().map(|stmt| &stmt.node) {
+        Some(ASTStmtKind::Return(_)) => true,
+        Some(ASTStmtKind::If(_, body, orelse)) => always_returns(body) && always_returns(orelse),
+        _ => false,
+    }
+}
+
+fn operator_symbol(op: &ASTOperator) -> &'static str {
+    match op {
+        ASTOperator::Add => "+",
+        ASTOperator::Sub => "-",
+        ASTOperator::Mul => "*",
+        ASTOperator::Div => "/",
+        ASTOperator::Mod => "%",
+        ASTOperator::LShift => "<<",
+        ASTOperator::RShift => ">>",
+        ASTOperator::BitOr => "|",
+        ASTOperator::BitXor => "^",
+        ASTOperator::BitAnd => "&",
+    }
+}
+
+// result of `lhs op rhs`, None if init.py panics on it
+fn binop_type(op: &ASTOperator, lhs: &Type, rhs: &Type) -> Option<Type> {
+    use ASTOperator::*;
+    if *lhs == Type::Any || *rhs == Type::Any {
+        return Some(Type::Any);
+    }
+    let numeric = || match (lhs, rhs) {
+        (Type::Int, Type::Int) => Some(Type::Int),
+        _ if lhs.is_numeric() && rhs.is_numeric() => Some(Type::Float),
+        _ => None,
+    };
+    match (op, lhs, rhs) {
+        (Add, Type::Str, Type::Str) => Some(Type::Str),
+        (Add, Type::List(a), Type::List(b)) => Some(Type::List(Box::new(join(a, b)))),
+        (Add, Type::Tuple(Some(a)), Type::Tuple(Some(b))) => {
+            Some(Type::Tuple(Some(a.iter().chain(b).cloned().collect())))
+        }
+        (Add, Type::Tuple(_), Type::Tuple(_)) => Some(Type::Tuple(None)),
+        (Sub, Type::Set(a), Type::Set(b))
+        | (BitOr, Type::Set(a), Type::Set(b))
+        | (BitAnd, Type::Set(a), Type::Set(b))
+        | (BitXor, Type::Set(a), Type::Set(b)) => Some(Type::Set(Box::new(join(a, b)))),
+        (BitOr, Type::Dict(ak, av), Type::Dict(bk, bv)) => {
+            Some(Type::Dict(Box::new(join(ak, bk)), Box::new(join(av, bv))))
+        }
+        (Mul, Type::Int, Type::List(elt)) | (Mul, Type::List(elt), Type::Int) => {
+            Some(Type::List(elt.clone()))
+        }
+        (Mul, Type::Str, Type::Int) => Some(Type::Str),
+        (Add, ..) | (Sub, ..) | (Mul, ..) | (Div, ..) | (Mod, ..) => numeric(),
+        (_, Type::Int, Type::Int) => Some(Type::Int),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+struct Signature {
+    name: String,
+    params: Vec<(String, Type)>,
+    returns: Type,
+}
+
+// module or function
+#[derive(Debug, Default, Clone)]
+struct Env {
+    vars: HashMap<String, Type>,
+    // annotated variables keep their type
+    declared: HashMap<String, Type>,
+    globals: HashSet<String>,
+}
+
+#[derive(Debug, Default)]
+struct Function {
+    // None if the return type is inferred
+    returns: Option<Type>,
+    returned: Vec<Type>,
+}
+
+struct Checker {
+    functions: Vec<Signature>,
+    envs: Vec<Env>,
+    // one for each function being checked
+    returns: Vec<Function>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// type errors of the module, sorted by position
+pub fn check(ast: &AST) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        functions: Vec::new(),
+        envs: vec![Env::default()],
+        returns: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    checker.body(ast);
+    let mut diagnostics = checker.diagnostics;
+    diagnostics.sort_by_key(|d| (d.span.line, d.span.column));
+    diagnostics
+}
+
+impl Checker {
+    fn report(&mut self, code: &'static str, message: String, span: Span) {
+        self.diagnostics.push(Diagnostic::new(code, message, span));
+    }
+
+    fn env(&mut self) -> &mut Env {
+        self.envs.last_mut().unwrap()
+    }
+
+    // environment where name is bound: the module for globals
+    fn env_of(&mut self, name: &str) -> &mut Env {
+        if self.env().globals.contains(name) {
+            &mut self.envs[0]
+        } else {
+            self.env()
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Type> {
+        let env = self.envs.last().unwrap();
+        if !env.globals.contains(name) {
+            if let Some(t) = env.vars.get(name) {
+                return Some(t);
+            }
+        }
+        // closures are not supported: a function sees the globals only
+        self.envs[0].vars.get(name)
+    }
+
+    // type written in an annotation
+    fn annotation(&mut self, expr: &ASTExpr) -> Type {
+        match &expr.node {
+            ASTExprKind::Constant(ASTConstant::None) => Type::None,
+            ASTExprKind::Name(name) => match name.as_str() {
+                "int" => Type::Int,
+                "float" => Type::Float,
+                "str" => Type::Str,
+                "bool" => Type::Bool,
+                "list" => Type::List(Box::new(Type::Any)),
+                "tuple" => Type::Tuple(None),
+                "dict" => Type::Dict(Box::new(Type::Any), Box::new(Type::Any)),
+                "set" => Type::Set(Box::new(Type::Any)),
+                "object" | "Any" => Type::Any,
+                _ => {
+                    self.report("annotation", format!("unknown type '{}'", name), expr.span);
+                    Type::Any
+                }
+            },
+            ASTExprKind::Subscript(base, slice) => {
+                let params: Vec<&ASTExpr> = match &slice.node {
+                    ASTSliceKind::Index(index) => match &index.node {
+                        ASTExprKind::Tuple(elts) => elts.iter().collect(),
+                        _ => vec![index],
+                    },
+                    ASTSliceKind::Slice(..) => vec![],
+                };
+                let params: Vec<Type> = params.into_iter().map(|p| self.annotation(p)).collect();
+                let base = match &base.node {
+                    ASTExprKind::Name(name) => name.as_str(),
+                    _ => "",
+                };
+                match (base, params.as_slice()) {
+                    ("list", [elt]) => Type::List(Box::new(elt.clone())),
+                    ("set", [elt]) => Type::Set(Box::new(elt.clone())),
+                    ("dict", [key, value]) => {
+                        Type::Dict(Box::new(key.clone()), Box::new(value.clone()))
+                    }
+                    ("tuple", elts) if !elts.is_empty() => Type::Tuple(Some(elts.to_vec())),
+                    _ => {
+                        self.report(
+                            "annotation",
+                            String::from("invalid type annotation"),
+                            expr.span,
+                        );
+                        Type::Any
+                    }
+                }
+            }
+            _ => {
+                self.report(
+                    "annotation",
+                    String::from("invalid type annotation"),
+                    expr.span,
+                );
+                Type::Any
+            }
+        }
+    }
+
+    fn body(&mut self, body: &[ASTStmt]) {
+        body.iter().for_each(|stmt| self.stmt(stmt));
+    }
+
+    // 分岐の合流: 片方にしかない変数はそのまま
+    fn merge(&mut self, other: Env) {
+        let env = self.env();
+        for (name, t) in other.vars {
+            let joined = match env.vars.get(&name) {
+                Some(current) => join(current, &t),
+                None => t,
+            };
+            env.vars.insert(name, joined);
+        }
+    }
+
+    fn function(
+        &mut self,
+        name: &str,
+        args: &ASTArguments,
+        body: &[ASTStmt],
+        returns: &Option<ASTExpr>,
+    ) {
+        let mut env = Env::default();
+        let mut params = Vec::new();
+        for arg in args {
+            let (param, annotation) = &arg.node;
+            let t = match annotation {
+                Some(annotation) => {
+                    let t = self.annotation(annotation);
+                    env.declared.insert(param.clone(), t.clone());
+                    t
+                }
+                None => Type::Any,
+            };
+            env.vars.insert(param.clone(), t.clone());
+            params.push((param.clone(), t));
+        }
+        let returns = returns.as_ref().map(|returns| self.annotation(returns));
+
+        self.envs.push(env);
+        self.returns.push(Function {
+            returns: returns.clone(),
+            returned: Vec::new(),
+        });
+        self.body(body);
+        let function = self.returns.pop().unwrap();
+        self.envs.pop();
+
+        let returns = returns.unwrap_or_else(|| {
+            let mut returned = function.returned;
+            if !always_returns(body) {
+                returned.push(Type::None);
+            }
+            join_all(&returned)
+        });
+        self.functions.push(Signature {
+            name: name.to_string(),
+            params,
+            returns,
+        });
+        let t = Type::Function(self.functions.len() - 1);
+        self.assign_name(name, t, Span::default());
+    }
+
+    fn stmt(&mut self, stmt: &ASTStmt) {
+        use ASTStmtKind::*;
+        match &stmt.node {
+            FuncDef(name, args, body, returns) => self.function(name, args, body, returns),
+            Return(value) => {
+                let t = match value {
+                    Some(value) => self.expr(value),
+                    None => Type::None,
+                };
+                let span = value.as_ref().map_or(stmt.span, |value| value.span);
+                if let Some(function) = self.returns.last_mut() {
+                    function.returned.push(t.clone());
+                    if let Some(expected) = function.returns.clone() {
+                        if !compatible(&expected, &t) {
+                            self.report(
+                                "return-value",
+                                format!(
+                                    "incompatible return value type (got {}, expected {})",
+                                    t, expected
+                                ),
+                                span,
+                            );
+                        }
+                    }
+                }
+            }
+            Delete(targets) => {
+                for target in targets {
+                    self.expr(target);
+                }
+            }
+            Assign(targets, value) => {
+                let t = self.expr(value);
+                for target in targets {
+                    self.assign(target, t.clone());
+                }
+            }
+            AnnAssign(target, annotation, value) => {
+                let declared = self.annotation(annotation);
+                if let ASTExprKind::Name(name) = &target.node {
+                    let env = self.env_of(name);
+                    env.declared.insert(name.clone(), declared.clone());
+                    env.vars.insert(name.clone(), declared);
+                }
+                if let Some(value) = value {
+                    let t = self.expr(value);
+                    self.assign(target, t);
+                }
+            }
+            For(target, iter, body) => {
+                let t = self.expr(iter);
+                let elt = match element_type(&t) {
+                    Some(elt) => elt,
+                    None => {
+                        self.report(
+                            "not-iterable",
+                            format!("'{}' object is not iterable", t.name()),
+                            iter.span,
+                        );
+                        Type::Any
+                    }
+                };
+                let before = self.env().clone();
+                self.assign(target, elt);
+                self.body(body);
+                // the body may run no times
+                self.merge(before);
+            }
+            While(test, body) => {
+                self.expr(test);
+                let before = self.env().clone();
+                self.body(body);
+                self.merge(before);
+            }
+            // the value of __enter__ is not known
+            With(items, body) => {
+                for item in items {
+                    let (context_expr, optional_vars) = &item.node;
+                    self.expr(context_expr);
+                    if let Some(target) = optional_vars {
+                        self.assign(target, Type::Any);
+                    }
+                }
+                self.body(body);
+            }
+            If(test, body, orelse) => {
+                self.expr(test);
+                let before = self.env().clone();
+                self.body(body);
+                let then = std::mem::replace(self.env(), before);
+                self.body(orelse);
+                self.merge(then);
+            }
+            Global(names) => {
+                let env = self.env();
+                env.globals.extend(names.iter().cloned());
+            }
+            // modules are not checked, what they define is Any
+            Import(_) | ImportFrom(..) => {
+                for name in stmt.node.imported_names() {
+                    self.assign_name(name, Type::Any, stmt.span);
+                }
+            }
+            Expr(value) => {
+                self.expr(value);
+            }
+            Init | End | Error | Pass | Break | Continue => (),
+        }
+    }
+
+    fn assign_name(&mut self, name: &str, t: Type, span: Span) {
+        let env = self.env_of(name);
+        match env.declared.get(name).cloned() {
+            Some(declared) => {
+                if !compatible(&declared, &t) {
+                    self.report(
+                        "assignment",
+                        format!(
+                            "incompatible types in assignment (expression has type {}, variable has type {})",
+                            t, declared
+                        ),
+                        span,
+                    );
+                }
+            }
+            None => {
+                env.vars.insert(name.to_string(), t);
+            }
+        }
+    }
+
+    fn assign(&mut self, target: &ASTExpr, t: Type) {
+        match &target.node {
+            ASTExprKind::Name(name) => self.assign_name(name, t, target.span),
+            ASTExprKind::Tuple(elts) | ASTExprKind::List(elts) => {
+                let types = match &t {
+                    Type::Tuple(Some(types)) if types.len() == elts.len() => types.clone(),
+                    t => vec![element_type(t).unwrap_or(Type::Any); elts.len()],
+                };
+                for (elt, t) in elts.iter().zip(types) {
+                    self.assign(elt, t);
+                }
+            }
+            ASTExprKind::Subscript(value, slice) => {
+                let container = self.expr(value);
+                let index = match &slice.node {
+                    ASTSliceKind::Index(index) => self.expr(index),
+                    ASTSliceKind::Slice(..) => return,
+                };
+                let expected = match &container {
+                    Type::List(elt) => {
+                        self.check_index("list", &index, slice.span);
+                        (**elt).clone()
+                    }
+                    Type::Dict(key, value) => {
+                        self.check_key(key, &index, slice.span);
+                        (**value).clone()
+                    }
+                    Type::Any => Type::Any,
+                    t => {
+                        self.report(
+                            "index",
+                            format!("'{}' object does not support item assignment", t.name()),
+                            target.span,
+                        );
+                        Type::Any
+                    }
+                };
+                if !compatible(&expected, &t) {
+                    self.report(
+                        "assignment",
+                        format!(
+                            "incompatible types in assignment (expression has type {}, target has type {})",
+                            t, expected
+                        ),
+                        target.span,
+                    );
+                }
+            }
+            _ => {
+                self.expr(target);
+            }
+        }
+    }
+
+    // a bool is an index too
+    fn check_index(&mut self, container: &str, index: &Type, span: Span) {
+        if !compatible(&Type::Int, index) && *index != Type::Bool {
+            self.report(
+                "index",
+                format!(
+                    "{} indices must be integers, not {}",
+                    container,
+                    index.name()
+                ),
+                span,
+            );
+        }
+    }
+
+    fn check_key(&mut self, key: &Type, index: &Type, span: Span) {
+        if !compatible(key, index) {
+            self.report(
+                "index",
+                format!("invalid key type {} for dict with keys of {}", index, key),
+                span,
+            );
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[ASTExpr]) -> Vec<Type> {
+        exprs.iter().map(|expr| self.expr(expr)).collect()
+    }
+
+    fn expr(&mut self, expr: &ASTExpr) -> Type {
+        use ASTExprKind::*;
+        match &expr.node {
+            BoolOp(_, values) => {
+                let types = self.exprs(values);
+                join_all(&types)
+            }
+            BinOp(lhs, op, rhs) => {
+                let (lt, rt) = (self.expr(lhs), self.expr(rhs));
+                match binop_type(op, &lt, &rt) {
+                    Some(t) => t,
+                    None => {
+                        self.report(
+                            "operator",
+                            format!(
+                                "unsupported operand type(s) for {}: '{}' and '{}'",
+                                operator_symbol(op),
+                                lt.name(),
+                                rt.name()
+                            ),
+                            expr.span,
+                        );
+                        Type::Any
+                    }
+                }
+            }
+            UnaryOp(op, operand) => {
+                let t = self.expr(operand);
+                let (symbol, ok) = match op {
+                    ASTUnaryOp::Not => return Type::Bool,
+                    ASTUnaryOp::Invert => ("~", t == Type::Int),
+                    ASTUnaryOp::UAdd => ("+", t.is_numeric()),
+                    ASTUnaryOp::USub => ("-", t.is_numeric()),
+                };
+                if ok || t == Type::Any {
+                    t
+                } else {
+                    self.report(
+                        "operator",
+                        format!("bad operand type for unary {}: '{}'", symbol, t.name()),
+                        expr.span,
+                    );
+                    Type::Any
+                }
+            }
+            IfExp(test, body, orelse) => {
+                self.expr(test);
+                let (body, orelse) = (self.expr(body), self.expr(orelse));
+                join(&body, &orelse)
+            }
+            Dict(keys, values) => {
+                let (keys, values) = (self.exprs(keys), self.exprs(values));
+                Type::Dict(Box::new(join_all(&keys)), Box::new(join_all(&values)))
+            }
+            Set(elts) => Type::Set(Box::new(join_all(&self.exprs(elts)))),
+            List(elts) => Type::List(Box::new(join_all(&self.exprs(elts)))),
+            Tuple(elts) => Type::Tuple(Some(self.exprs(elts))),
+            Compare(left, ops, comparators) => {
+                let mut lhs = self.expr(left);
+                for (op, comparator) in ops.iter().zip(comparators) {
+                    let rhs = self.expr(comparator);
+                    if let ASTCmpOp::In | ASTCmpOp::NotIn = op {
+                        self.check_contains(&lhs, &rhs, comparator.span);
+                    }
+                    lhs = rhs;
+                }
+                Type::Bool
+            }
+            Call(func, args, keywords) => self.call(expr, func, args, keywords),
+            Constant(constant) => match constant {
+                ASTConstant::Int(_) => Type::Int,
+                ASTConstant::Float(_) => Type::Float,
+                ASTConstant::String(_) => Type::Str,
+                ASTConstant::None => Type::None,
+                ASTConstant::True | ASTConstant::False => Type::Bool,
+            },
+            Attribute(value, _) => {
+                self.expr(value);
+                Type::Any
+            }
+            Subscript(value, slice) => {
+                let t = self.expr(value);
+                self.subscript(expr, &t, slice)
+            }
+            Name(name) => self.lookup(name).cloned().unwrap_or(Type::Any),
+        }
+    }
+
+    fn check_contains(&mut self, lhs: &Type, rhs: &Type, span: Span) {
+        match rhs {
+            Type::Str if !compatible(&Type::Str, lhs) => self.report(
+                "operator",
+                format!(
+                    "'in <string>' requires string as left operand, not {}",
+                    lhs.name()
+                ),
+                span,
+            ),
+            t if element_type(t).is_none() => self.report(
+                "operator",
+                format!("argument of type '{}' is not iterable", t.name()),
+                span,
+            ),
+            _ => (),
+        }
+    }
+
+    fn subscript(&mut self, expr: &ASTExpr, t: &Type, slice: &ASTSlice) -> Type {
+        let index = match &slice.node {
+            ASTSliceKind::Index(index) => index,
+            ASTSliceKind::Slice(lower, upper, step) => {
+                for bound in [lower, upper, step].iter().copied().flatten() {
+                    let bound_type = self.expr(bound);
+                    if !compatible(&Type::Int, &bound_type) && bound_type != Type::None {
+                        self.report(
+                            "index",
+                            String::from("slice indices must be integers or None"),
+                            bound.span,
+                        );
+                    }
+                }
+                return match t {
+                    Type::Str | Type::List(_) | Type::Any => t.clone(),
+                    Type::Tuple(_) => Type::Tuple(None),
+                    t => self.not_subscriptable(t, expr.span),
+                };
+            }
+        };
+        let index_type = self.expr(index);
+        match t {
+            Type::Any => Type::Any,
+            Type::Str => {
+                self.check_index("string", &index_type, index.span);
+                Type::Str
+            }
+            Type::List(elt) => {
+                self.check_index("list", &index_type, index.span);
+                (**elt).clone()
+            }
+            Type::Tuple(elts) => {
+                self.check_index("tuple", &index_type, index.span);
+                let elts = match elts {
+                    Some(elts) => elts,
+                    None => return Type::Any,
+                };
+                // 定数の添字なら要素の型が分かる
+                let constant = match &index.node {
+                    ASTExprKind::Constant(ASTConstant::Int(i)) => Some(*i),
+                    ASTExprKind::UnaryOp(ASTUnaryOp::USub, operand) => match &operand.node {
+                        ASTExprKind::Constant(ASTConstant::Int(i)) => Some(-*i),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                let len = elts.len() as i64;
+                match constant {
+                    Some(i) if -len <= i && i < len => elts[i.rem_euclid(len) as usize].clone(),
+                    Some(_) => {
+                        self.report(
+                            "index",
+                            String::from("tuple index out of range"),
+                            index.span,
+                        );
+                        Type::Any
+                    }
+                    None => join_all(elts),
+                }
+            }
+            Type::Dict(key, value) => {
+                self.check_key(key, &index_type, index.span);
+                (**value).clone()
+            }
+            t => self.not_subscriptable(t, expr.span),
+        }
+    }
+
+    fn not_subscriptable(&mut self, t: &Type, span: Span) -> Type {
+        self.report(
+            "index",
+            format!("'{}' object is not subscriptable", t.name()),
+            span,
+        );
+        Type::Any
+    }
+
+    fn call(
+        &mut self,
+        expr: &ASTExpr,
+        func: &ASTExpr,
+        args: &[ASTExpr],
+        keywords: &[ASTKeyword],
+    ) -> Type {
+        let arg_types = self.exprs(args);
+        let keyword_types: Vec<(String, Type)> = keywords
+            .iter()
+            .map(|keyword| (keyword.node.0.clone(), self.expr(&keyword.node.1)))
+            .collect();
+        match &func.node {
+            ASTExprKind::Name(name) if self.lookup(name).is_none() => {
+                self.builtin(name, &arg_types, args, expr.span)
+            }
+            ASTExprKind::Attribute(value, method) => {
+                let t = self.expr(value);
+                self.method(&t, method, &arg_types, args, func.span)
+            }
+            _ => match self.expr(func) {
+                Type::Function(i) => {
+                    self.call_function(i, &arg_types, args, &keyword_types, expr.span)
+                }
+                Type::Any => Type::Any,
+                t => {
+                    self.report(
+                        "not-callable",
+                        format!("'{}' object is not callable", t.name()),
+                        func.span,
+                    );
+                    Type::Any
+                }
+            },
+        }
+    }
+
+    fn call_function(
+        &mut self,
+        i: usize,
+        arg_types: &[Type],
+        args: &[ASTExpr],
+        keywords: &[(String, Type)],
+        span: Span,
+    ) -> Type {
+        let signature = &self.functions[i];
+        let (name, returns) = (signature.name.clone(), signature.returns.clone());
+        let params = signature.params.clone();
+        if params.len() < arg_types.len() {
+            self.report(
+                "call-arg",
+                format!(
+                    "{}() takes {} arguments but {} were given",
+                    name,
+                    params.len(),
+                    arg_types.len()
+                ),
+                span,
+            );
+            return returns;
+        }
+        let mut bound: Vec<bool> = vec![false; params.len()];
+        for (k, ((param, expected), actual)) in params.iter().zip(arg_types).enumerate() {
+            bound[k] = true;
+            if !compatible(expected, actual) {
+                self.report(
+                    "arg-type",
+                    format!(
+                        "argument '{}' to {}() has incompatible type {}; expected {}",
+                        param, name, actual, expected
+                    ),
+                    args[k].span,
+                );
+            }
+        }
+        for (keyword, actual) in keywords {
+            match params.iter().position(|(param, _)| param == keyword) {
+                Some(k) if bound[k] => self.report(
+                    "call-arg",
+                    format!("{}() got multiple values for argument '{}'", name, keyword),
+                    span,
+                ),
+                Some(k) => {
+                    bound[k] = true;
+                    if !compatible(&params[k].1, actual) {
+                        self.report(
+                            "arg-type",
+                            format!(
+                                "argument '{}' to {}() has incompatible type {}; expected {}",
+                                keyword, name, actual, params[k].1
+                            ),
+                            span,
+                        );
+                    }
+                }
+                None => self.report(
+                    "call-arg",
+                    format!(
+                        "{}() got an unexpected keyword argument '{}'",
+                        name, keyword
+                    ),
+                    span,
+                ),
+            }
+        }
+        if let Some(k) = bound.iter().position(|bound| !bound) {
+            self.report(
+                "call-arg",
+                format!("{}() missing required argument: '{}'", name, params[k].0),
+                span,
+            );
+        }
+        returns
+    }
+
+    // builtins of the evaluator. unknown names are Any(reporting them is the job of lint)
+    fn builtin(&mut self, name: &str, types: &[Type], args: &[ASTExpr], span: Span) -> Type {
+        let first = types.first().cloned().unwrap_or(Type::Any);
+        let elt = |checker: &mut Checker, t: &Type, k: usize| match element_type(t) {
+            Some(elt) => elt,
+            None => {
+                checker.report(
+                    "not-iterable",
+                    format!("'{}' object is not iterable", t.name()),
+                    args.get(k).map_or(span, |arg| arg.span),
+                );
+                Type::Any
+            }
+        };
+        match name {
+            "print" => Type::None,
+            "len" => {
+                if let Type::Int | Type::Float | Type::Bool | Type::None = first {
+                    self.report(
+                        "arg-type",
+                        format!("object of type '{}' has no len()", first.name()),
+                        args[0].span,
+                    );
+                }
+                Type::Int
+            }
+            "abs" => {
+                if first.is_numeric() || first == Type::Any {
+                    first
+                } else {
+                    self.report(
+                        "arg-type",
+                        format!("bad operand type for abs(): '{}'", first.name()),
+                        args.first().map_or(span, |arg| arg.span),
+                    );
+                    Type::Any
+                }
+            }
+            "min" | "max" if types.len() == 1 => elt(self, &first, 0),
+            "min" | "max" => join_all(types),
+            "sum" => match elt(self, &first, 0) {
+                t if t.is_numeric() => t,
+                _ => Type::Any,
+            },
+            "sorted" | "reversed" | "filter" => {
+                let k = if name == "filter" { 1 } else { 0 };
+                let t = types.get(k).cloned().unwrap_or(Type::Any);
+                Type::List(Box::new(elt(self, &t, k)))
+            }
+            "enumerate" => Type::List(Box::new(Type::Tuple(Some(vec![
+                Type::Int,
+                elt(self, &first, 0),
+            ])))),
+            "zip" => {
+                let elts = types
+                    .iter()
+                    .enumerate()
+                    .map(|(k, t)| elt(self, t, k))
+                    .collect();
+                Type::List(Box::new(Type::Tuple(Some(elts))))
+            }
+            "map" => Type::List(Box::new(Type::Any)),
+            "any" | "all" => Type::Bool,
+            "range" => {
+                for (t, arg) in types.iter().zip(args) {
+                    if !compatible(&Type::Int, t) {
+                        self.report(
+                            "arg-type",
+                            format!("range() argument must be int, not {}", t.name()),
+                            arg.span,
+                        );
+                    }
+                }
+                Type::List(Box::new(Type::Int))
+            }
+            "hash" => Type::Int,
+            "input" => Type::Str,
+            "dict" => Type::Dict(Box::new(Type::Any), Box::new(Type::Any)),
+            "set" if types.is_empty() => Type::Set(Box::new(Type::Any)),
+            "set" => Type::Set(Box::new(elt(self, &first, 0))),
+            _ => Type::Any,
+        }
+    }
+
+    // methods of the evaluator's method table
+    fn method(
+        &mut self,
+        t: &Type,
+        method: &str,
+        types: &[Type],
+        args: &[ASTExpr],
+        span: Span,
+    ) -> Type {
+        let list = |t: Type| Type::List(Box::new(t));
+        let res = match (t, method) {
+            (Type::Any, _) => Some(Type::Any),
+            (Type::Str, "split") | (Type::Str, "rsplit") | (Type::Str, "splitlines") => {
+                Some(list(Type::Str))
+            }
+            (Type::Str, "join") => {
+                let elt = types.first().and_then(element_type).unwrap_or(Type::Any);
+                if !compatible(&Type::Str, &elt) {
+                    self.report(
+                        "arg-type",
+                        format!("sequence item: expected str instance, {} found", elt.name()),
+                        args[0].span,
+                    );
+                }
+                Some(Type::Str)
+            }
+            (Type::Str, "strip")
+            | (Type::Str, "lstrip")
+            | (Type::Str, "rstrip")
+            | (Type::Str, "replace")
+            | (Type::Str, "upper")
+            | (Type::Str, "lower")
+            | (Type::Str, "title")
+            | (Type::Str, "zfill")
+            | (Type::Str, "center") => Some(Type::Str),
+            (Type::Str, "find")
+            | (Type::Str, "rfind")
+            | (Type::Str, "index")
+            | (Type::Str, "count") => Some(Type::Int),
+            (Type::Str, "startswith")
+            | (Type::Str, "endswith")
+            | (Type::Str, "isdigit")
+            | (Type::Str, "isalpha")
+            | (Type::Str, "isspace") => Some(Type::Bool),
+            (Type::Str, "partition") => Some(Type::Tuple(Some(vec![Type::Str; 3]))),
+            (Type::List(_), "append")
+            | (Type::List(_), "extend")
+            | (Type::List(_), "insert")
+            | (Type::List(_), "reverse")
+            | (Type::List(_), "clear")
+            | (Type::List(_), "remove")
+            | (Type::List(_), "sort") => Some(Type::None),
+            (Type::List(elt), "pop") => Some((**elt).clone()),
+            (Type::List(_), "copy") => Some(t.clone()),
+            (Type::List(_), "index") | (Type::List(_), "count") => Some(Type::Int),
+            (Type::Dict(key, _), "keys") => Some(list((**key).clone())),
+            (Type::Dict(_, value), "values") => Some(list((**value).clone())),
+            (Type::Dict(key, value), "items") => Some(list(Type::Tuple(Some(vec![
+                (**key).clone(),
+                (**value).clone(),
+            ])))),
+            (Type::Dict(_, value), "get")
+            | (Type::Dict(_, value), "setdefault")
+            | (Type::Dict(_, value), "pop") => Some((**value).clone()),
+            (Type::Dict(..), "copy") => Some(t.clone()),
+            (Type::Dict(..), "update") | (Type::Dict(..), "clear") => Some(Type::None),
+            (Type::Set(_), "copy")
+            | (Type::Set(_), "union")
+            | (Type::Set(_), "intersection")
+            | (Type::Set(_), "difference") => Some(t.clone()),
+            (Type::Set(_), "add")
+            | (Type::Set(_), "remove")
+            | (Type::Set(_), "discard")
+            | (Type::Set(_), "clear") => Some(Type::None),
+            _ => None,
+        };
+        res.unwrap_or_else(|| {
+            self.report(
+                "attribute",
+                format!("'{}' object has no attribute '{}'", t.name(), method),
+                span,
+            );
+            Type::Any
+        })
+    }
+}
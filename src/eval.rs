@@ -1,4 +1,20 @@
+pub mod builtins;
+pub mod compiler;
+pub mod convert;
+pub mod debugger;
+pub mod dict;
 pub mod evaluator;
+pub mod file;
+pub mod gc;
+pub mod import;
+pub mod io;
+pub mod limits;
+pub mod native_func;
+pub mod native_module;
+pub mod operators;
+pub mod scope;
 pub mod types;
+pub mod unicode;
 pub mod utils;
-pub mod native_func;
+pub mod value;
+pub mod vm;
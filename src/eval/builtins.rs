@@ -0,0 +1,831 @@
+//! # builtins
+//! user-facing builtin functions(len, sorted, ...)
+//! unlike native functions, they check their own arguments and may call back into the evaluator
+
+use std::cmp::Ordering;
+
+use crate::ast::ASTCmpOp;
+use crate::eval::dict::DictTable;
+use crate::eval::evaluator::Evaluator;
+use crate::eval::limits::vec_with_capacity;
+use crate::eval::types::*;
+use ordered_float::OrderedFloat;
+
+// check the number of positional arguments(min..=max, None: unlimited)
+// and pick out the keyword arguments listed in `keywords`, in that order
+pub(crate) fn parse_args(
+    evaluator: &Evaluator,
+    name: &str,
+    args: &[py_val_t],
+    kwargs: py_kwargs_t,
+    min: usize,
+    max: Option<usize>,
+    keywords: &[&str],
+) -> Vec<Option<py_val_t>> {
+    if args.len() < min {
+        evaluator.error(format!(
+            "TypeError: {}() expected at least {} arguments, got {}",
+            name,
+            min,
+            args.len()
+        ));
+    }
+    if let Some(max) = max {
+        if args.len() > max {
+            evaluator.error(format!(
+                "TypeError: {}() expected at most {} arguments, got {}",
+                name,
+                max,
+                args.len()
+            ));
+        }
+    }
+    let mut res = vec![None; keywords.len()];
+    for (key, value) in kwargs {
+        match keywords.iter().position(|k| *k == key) {
+            Some(i) => res[i] = Some(value),
+            None => evaluator.error(format!(
+                "TypeError: {}() got an unexpected keyword argument '{}'",
+                name, key
+            )),
+        }
+    }
+    res
+}
+
+// treat `None` given to an optional argument as if it were omitted
+fn not_none(value: Option<py_val_t>) -> Option<py_val_t> {
+    value.filter(|v| !v.is_none())
+}
+
+pub(crate) fn expect_int(evaluator: &Evaluator, name: &str, value: &py_val_t) -> i64 {
+    match value.as_i64() {
+        Some(i) => i,
+        None => evaluator.error(format!(
+            "TypeError: {}() expected int, got '{}'",
+            name,
+            value.type_name()
+        )),
+    }
+}
+
+// apply `key` if given
+fn key_of(evaluator: &mut Evaluator, key: &Option<py_val_t>, value: &py_val_t) -> py_val_t {
+    match key {
+        Some(f) => evaluator.call_func(f.clone(), vec![value.clone()], Vec::new()),
+        None => value.clone(),
+    }
+}
+
+fn compare(evaluator: &Evaluator, op: &str, lhs: &py_val_t, rhs: &py_val_t) -> Ordering {
+    match lhs.py_cmp(rhs) {
+        Some(ord) => ord,
+        None => evaluator.error(format!(
+            "TypeError: '{}' not supported between instances of '{}' and '{}'",
+            op,
+            lhs.type_name(),
+            rhs.type_name()
+        )),
+    }
+}
+
+// `lhs == rhs` through __eq__, so user overrides are honoured
+fn equals(evaluator: &mut Evaluator, lhs: &py_val_t, rhs: &py_val_t) -> bool {
+    evaluator.compare_op(&ASTCmpOp::Eq, lhs.clone(), rhs.clone())
+}
+
+// len(s)
+pub fn builtin_len(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "len", &args, kwargs, 1, Some(1), &[]);
+    let len = match *args[0].view() {
+        py_val::string(ref s) => s.chars().count(),
+        py_val::list(ref v) => v.borrow().len(),
+        py_val::tuple(ref v) => v.len(),
+        py_val::dict(ref d) => d.borrow().len(),
+        py_val::set(ref s) => s.borrow().len(),
+        _ => evaluator.error(format!(
+            "TypeError: object of type '{}' has no len()",
+            args[0].type_name()
+        )),
+    };
+    py_val::new(py_val::int(len as i64))
+}
+
+// abs(x)
+pub fn builtin_abs(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "abs", &args, kwargs, 1, Some(1), &[]);
+    match *args[0].view() {
+        py_val::float(f) => py_val::new(py_val::float(OrderedFloat(f.abs()))),
+        _ => match args[0].as_i64() {
+            Some(i) => py_val::new(py_val::int(i.abs())),
+            None => evaluator.error(format!(
+                "TypeError: bad operand type for abs(): '{}'",
+                args[0].type_name()
+            )),
+        },
+    }
+}
+
+// min(iterable, *, key=None, default=...) / min(arg1, arg2, *args, key=None)
+// max is the same except for the direction
+fn min_max(
+    evaluator: &mut Evaluator,
+    name: &str,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+    wanted: Ordering,
+) -> py_val_t {
+    let mut options = parse_args(evaluator, name, &args, kwargs, 1, None, &["key", "default"]);
+    let default = options.pop().unwrap();
+    let key = not_none(options.pop().unwrap());
+    let candidates = if args.len() == 1 {
+        evaluator.iter_values(&args[0])
+    } else {
+        if default.is_some() {
+            evaluator.error(format!(
+                "TypeError: Cannot specify a default for {}() with multiple positional arguments",
+                name
+            ));
+        }
+        args
+    };
+
+    let mut best: Option<(py_val_t, py_val_t)> = None;
+    for candidate in candidates {
+        let k = key_of(evaluator, &key, &candidate);
+        best = match best {
+            // the first one wins among equal elements
+            Some((best_key, best_val))
+                if compare(
+                    evaluator,
+                    if wanted == Ordering::Less { "<" } else { ">" },
+                    &k,
+                    &best_key,
+                ) != wanted =>
+            {
+                Some((best_key, best_val))
+            }
+            _ => Some((k, candidate)),
+        };
+    }
+    match (best, default) {
+        (Some((_, v)), _) => v,
+        (None, Some(d)) => d,
+        (None, None) => evaluator.error(format!("ValueError: {}() arg is an empty sequence", name)),
+    }
+}
+
+pub fn builtin_min(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    min_max(evaluator, "min", args, kwargs, Ordering::Less)
+}
+
+pub fn builtin_max(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    min_max(evaluator, "max", args, kwargs, Ordering::Greater)
+}
+
+// sum(iterable, start=0)
+// elements are added with `+`(__add__), so user overrides are honoured
+pub fn builtin_sum(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    let mut options = parse_args(evaluator, "sum", &args, kwargs, 1, Some(2), &["start"]);
+    let start = args
+        .get(1)
+        .cloned()
+        .or_else(|| options.pop().unwrap())
+        .unwrap_or_else(|| py_val::new(py_val::int(0)));
+    if let py_val::string(_) = *start.view() {
+        evaluator
+            .error("TypeError: sum() can't sum strings [use ''.join(seq) instead]".to_string());
+    }
+    let add = evaluator.get_global("__add__").unwrap();
+    evaluator
+        .iter_values(&args[0])
+        .into_iter()
+        .fold(start, |acc, v| {
+            evaluator.call_func(add.clone(), vec![acc, v], Vec::new())
+        })
+}
+
+// sorted(iterable, *, key=None, reverse=False)
+// the sort is stable, also when reverse=True
+pub fn builtin_sorted(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    let mut options = parse_args(
+        evaluator,
+        "sorted",
+        &args,
+        kwargs,
+        1,
+        Some(1),
+        &["key", "reverse"],
+    );
+    let reverse = options.pop().unwrap().is_some_and(|r| r.is_true());
+    let key = not_none(options.pop().unwrap());
+    let values = evaluator.iter_values(&args[0]);
+    py_val::new_list(sort_values(evaluator, values, &key, reverse))
+}
+
+// stable sort shared by sorted() and list.sort()
+fn sort_values(
+    evaluator: &mut Evaluator,
+    values: Vec<py_val_t>,
+    key: &Option<py_val_t>,
+    reverse: bool,
+) -> Vec<py_val_t> {
+    let mut keyed: Vec<(py_val_t, py_val_t)> = values
+        .into_iter()
+        .map(|v| (key_of(evaluator, key, &v), v))
+        .collect();
+    // the comparator can't bail out, so remember the first failure and report it afterwards
+    let mut failure: Option<(py_val_t, py_val_t)> = None;
+    keyed.sort_by(|(a, _), (b, _)| {
+        let (lhs, rhs) = if reverse { (b, a) } else { (a, b) };
+        lhs.py_cmp(rhs).unwrap_or_else(|| {
+            failure.get_or_insert_with(|| (lhs.clone(), rhs.clone()));
+            Ordering::Equal
+        })
+    });
+    if let Some((lhs, rhs)) = failure {
+        compare(evaluator, "<", &lhs, &rhs);
+    }
+    keyed.into_iter().map(|(_, v)| v).collect()
+}
+
+// reversed(seq)
+// returns a list instead of an iterator
+pub fn builtin_reversed(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "reversed", &args, kwargs, 1, Some(1), &[]);
+    match *args[0].view() {
+        py_val::list(_) | py_val::tuple(_) | py_val::string(_) => {
+            let mut values = evaluator.iter_values(&args[0]);
+            values.reverse();
+            py_val::new_list(values)
+        }
+        _ => evaluator.error(format!(
+            "TypeError: '{}' object is not reversible",
+            args[0].type_name()
+        )),
+    }
+}
+
+// enumerate(iterable, start=0)
+// returns a list of (index, value) tuples
+pub fn builtin_enumerate(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    let mut options = parse_args(
+        evaluator,
+        "enumerate",
+        &args,
+        kwargs,
+        1,
+        Some(2),
+        &["start"],
+    );
+    let start = match args.get(1).cloned().or_else(|| options.pop().unwrap()) {
+        Some(v) => expect_int(evaluator, "enumerate", &v),
+        None => 0,
+    };
+    py_val::new(py_val::list(
+        evaluator
+            .iter_values(&args[0])
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| {
+                py_val::new(py_val::tuple(vec![
+                    py_val::new(py_val::int(start + i as i64)),
+                    v,
+                ]))
+            })
+            .collect(),
+    ))
+}
+
+// zip(*iterables)
+// returns a list of tuples, as long as the shortest iterable
+pub fn builtin_zip(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "zip", &args, kwargs, 0, None, &[]);
+    let columns: Vec<Vec<py_val_t>> = args.iter().map(|arg| evaluator.iter_values(arg)).collect();
+    let len = columns.iter().map(|c| c.len()).min().unwrap_or(0);
+    py_val::new(py_val::list(
+        (0..len)
+            .map(|i| {
+                py_val::new(py_val::tuple(
+                    columns.iter().map(|c| c[i].clone()).collect(),
+                ))
+            })
+            .collect(),
+    ))
+}
+
+// map(function, iterable, *iterables)
+// returns a list instead of an iterator
+pub fn builtin_map(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "map", &args, kwargs, 2, None, &[]);
+    let columns: Vec<Vec<py_val_t>> = args[1..]
+        .iter()
+        .map(|arg| evaluator.iter_values(arg))
+        .collect();
+    let len = columns.iter().map(|c| c.len()).min().unwrap_or(0);
+    py_val::new(py_val::list(
+        (0..len)
+            .map(|i| {
+                let f_args = columns.iter().map(|c| c[i].clone()).collect();
+                evaluator.call_func(args[0].clone(), f_args, Vec::new())
+            })
+            .collect(),
+    ))
+}
+
+// filter(function, iterable)
+// with function None, keeps the truthy elements. returns a list
+pub fn builtin_filter(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "filter", &args, kwargs, 2, Some(2), &[]);
+    let f = not_none(Some(args[0].clone()));
+    py_val::new(py_val::list(
+        evaluator
+            .iter_values(&args[1])
+            .into_iter()
+            .filter(|v| key_of(evaluator, &f, v).is_true())
+            .collect(),
+    ))
+}
+
+// any(iterable)
+pub fn builtin_any(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "any", &args, kwargs, 1, Some(1), &[]);
+    py_val::from_bool(evaluator.iter_values(&args[0]).iter().any(|v| v.is_true()))
+}
+
+// all(iterable)
+pub fn builtin_all(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "all", &args, kwargs, 1, Some(1), &[]);
+    py_val::from_bool(evaluator.iter_values(&args[0]).iter().all(|v| v.is_true()))
+}
+
+// range(stop) / range(start, stop[, step])
+// returns a list
+pub fn builtin_range(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "range", &args, kwargs, 1, Some(3), &[]);
+    let ints: Vec<i64> = args
+        .iter()
+        .map(|arg| expect_int(evaluator, "range", arg))
+        .collect();
+    let (start, stop, step) = match ints[..] {
+        [stop] => (0, stop, 1),
+        [start, stop] => (start, stop, 1),
+        [start, stop, step] => (start, stop, step),
+        _ => unreachable!(),
+    };
+    if step == 0 {
+        evaluator.error("ValueError: range() arg 3 must not be zero".to_string());
+    }
+    // the length, to be checked against the memory limit before the list is made
+    let (start, stop, step) = (start as i128, stop as i128, step as i128);
+    let len = if (step > 0 && start < stop) || (step < 0 && start > stop) {
+        (stop - start - step.signum()) / step + 1
+    } else {
+        0
+    };
+    let mut res = match vec_with_capacity(len.min(usize::MAX as i128) as usize) {
+        Ok(res) => res,
+        Err(message) => evaluator.error(message),
+    };
+    for k in 0..len {
+        res.push(py_val::new(py_val::int((start + k * step) as i64)));
+    }
+    py_val::new_list(res)
+}
+
+// list methods that compare elements. args[0] is the receiver(a list)
+// the elements are copied out before comparing, since __eq__ or key may touch the list
+
+fn list_elements(args: &[py_val_t]) -> Vec<py_val_t> {
+    match *args[0].view() {
+        py_val::list(ref l) => l.borrow().clone(),
+        _ => panic!(),
+    }
+}
+
+fn list_position(evaluator: &mut Evaluator, name: &str, args: &[py_val_t]) -> Option<usize> {
+    let elements = list_elements(args);
+    let len = elements.len() as i64;
+    let adjust = |i: i64| if i < 0 { (i + len).max(0) } else { i.min(len) };
+    let start = args
+        .get(2)
+        .map_or(0, |v| adjust(expect_int(evaluator, name, v)));
+    let end = args
+        .get(3)
+        .map_or(len, |v| adjust(expect_int(evaluator, name, v)));
+    (start..end)
+        .find(|&i| equals(evaluator, &elements[i as usize], &args[1]))
+        .map(|i| i as usize)
+}
+
+// list.index(x[, start[, end]])
+pub fn builtin_list_index(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "index", &args, kwargs, 2, Some(4), &[]);
+    match list_position(evaluator, "index", &args) {
+        Some(i) => py_val::new(py_val::int(i as i64)),
+        None => evaluator.error("ValueError: list.index(x): x not in list".to_string()),
+    }
+}
+
+// list.count(x)
+pub fn builtin_list_count(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "count", &args, kwargs, 2, Some(2), &[]);
+    let count = list_elements(&args)
+        .iter()
+        .filter(|v| equals(evaluator, v, &args[1]))
+        .count();
+    py_val::new(py_val::int(count as i64))
+}
+
+// list.remove(x)
+// removes the first occurrence
+pub fn builtin_list_remove(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "remove", &args, kwargs, 2, Some(2), &[]);
+    match list_position(evaluator, "remove", &args) {
+        Some(i) => {
+            if let py_val::list(ref l) = *args[0].view() {
+                l.borrow_mut().remove(i);
+            }
+            py_val::new(py_val::None)
+        }
+        None => evaluator.error("ValueError: list.remove(x): x not in list".to_string()),
+    }
+}
+
+// list.sort(*, key=None, reverse=False)
+pub fn builtin_list_sort(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    let mut options = parse_args(
+        evaluator,
+        "sort",
+        &args,
+        kwargs,
+        1,
+        Some(1),
+        &["key", "reverse"],
+    );
+    let reverse = options.pop().unwrap().is_some_and(|r| r.is_true());
+    let key = not_none(options.pop().unwrap());
+    if let py_val::list(ref l) = *args[0].view() {
+        // the list looks empty while it is being sorted, as in CPython
+        let values = std::mem::take(&mut *l.borrow_mut());
+        let sorted = sort_values(evaluator, values, &key, reverse);
+        *l.borrow_mut() = sorted;
+    }
+    py_val::new(py_val::None)
+}
+
+// ntv_print_string(s), which print of the prelude calls with the formatted value.
+// writes a line to the stdout of the evaluator
+pub fn builtin_print_string(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    _kwargs: py_kwargs_t,
+) -> py_val_t {
+    let s = args[0].as_str().expect("ntv_print_string takes a str");
+    if let Err(error) = writeln!(evaluator.io.get_mut().stdout(), "{}", s) {
+        evaluator.error(format!("OSError: {}", error));
+    }
+    py_val::new(py_val::None)
+}
+
+// hash(object)
+// through __hash__, as dict and set do
+pub fn builtin_hash(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "hash", &args, kwargs, 1, Some(1), &[]);
+    py_val::new(py_val::int(evaluator.hash_of(&args[0])))
+}
+
+// add (key, value) pairs from a dict or an iterable of pairs, then the keyword arguments
+fn update_table(
+    evaluator: &mut Evaluator,
+    table: &mut DictTable,
+    other: Option<&py_val_t>,
+    kwargs: py_kwargs_t,
+) {
+    match other.map(|o| o.heap()) {
+        Some(Some(py_val::dict(ref d))) => *table = table.merge(&d.borrow()),
+        Some(_) => {
+            for (i, item) in evaluator
+                .iter_values(other.unwrap())
+                .into_iter()
+                .enumerate()
+            {
+                match item.iter_values() {
+                    Some(ref pair) if pair.len() == 2 => {
+                        let hash = evaluator.hash_of(&pair[0]);
+                        table.insert(hash, pair[0].clone(), pair[1].clone());
+                    }
+                    Some(pair) => evaluator.error(format!(
+                        "ValueError: dictionary update sequence element #{} has length {}; 2 is required",
+                        i,
+                        pair.len()
+                    )),
+                    None => evaluator.error(format!(
+                        "TypeError: cannot convert dictionary update sequence element #{} to a sequence",
+                        i
+                    )),
+                }
+            }
+        }
+        None => (),
+    }
+    for (key, value) in kwargs {
+        let key = py_val::new(py_val::string(key));
+        let hash = evaluator.hash_of(&key);
+        table.insert(hash, key, value);
+    }
+}
+
+// table of the elements of an iterable, used as a set
+fn table_of(evaluator: &mut Evaluator, iterable: &py_val_t) -> DictTable {
+    match *iterable.view() {
+        py_val::set(ref s) => s.borrow().clone(),
+        _ => {
+            let mut table = DictTable::new();
+            for elt in evaluator.iter_values(iterable) {
+                let hash = evaluator.hash_of(&elt);
+                table.insert(hash, elt.clone(), elt);
+            }
+            table
+        }
+    }
+}
+
+// dict(iterable=(), **kwargs)
+pub fn builtin_dict(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    if args.len() > 1 {
+        evaluator.error(format!(
+            "TypeError: dict expected at most 1 argument, got {}",
+            args.len()
+        ));
+    }
+    let mut table = DictTable::new();
+    update_table(evaluator, &mut table, args.first(), kwargs);
+    py_val::new(py_val::dict(table.into()))
+}
+
+// set(iterable=())
+pub fn builtin_set(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "set", &args, kwargs, 0, Some(1), &[]);
+    let table = match args.first() {
+        Some(iterable) => table_of(evaluator, iterable),
+        None => DictTable::new(),
+    };
+    py_val::new(py_val::set(table.into()))
+}
+
+// dict/set methods that hash keys. args[0] is the receiver
+// the hash is computed before borrowing the table, since __hash__ may touch it
+
+fn dict_of(args: &[py_val_t]) -> &py_dict {
+    match args[0].heap() {
+        Some(py_val::dict(ref d)) => d,
+        _ => panic!(),
+    }
+}
+
+fn set_of(args: &[py_val_t]) -> &py_set {
+    match args[0].heap() {
+        Some(py_val::set(ref s)) => s,
+        _ => panic!(),
+    }
+}
+
+// dict.get(key, default=None)
+pub fn builtin_dict_get(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "get", &args, kwargs, 2, Some(3), &[]);
+    let hash = evaluator.hash_of(&args[1]);
+    let value = dict_of(&args).borrow().get(hash, &args[1]);
+    value
+        .or_else(|| args.get(2).cloned())
+        .unwrap_or_else(|| py_val::new(py_val::None))
+}
+
+// dict.setdefault(key, default=None)
+pub fn builtin_dict_setdefault(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "setdefault", &args, kwargs, 2, Some(3), &[]);
+    let hash = evaluator.hash_of(&args[1]);
+    let mut d = dict_of(&args).borrow_mut();
+    match d.get(hash, &args[1]) {
+        Some(value) => value,
+        None => {
+            let default = args
+                .get(2)
+                .cloned()
+                .unwrap_or_else(|| py_val::new(py_val::None));
+            d.insert(hash, args[1].clone(), default.clone());
+            default
+        }
+    }
+}
+
+// dict.pop(key[, default])
+pub fn builtin_dict_pop(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "pop", &args, kwargs, 2, Some(3), &[]);
+    let hash = evaluator.hash_of(&args[1]);
+    let removed = dict_of(&args).borrow_mut().remove(hash, &args[1]);
+    match (removed, args.get(2)) {
+        (Some(entry), _) => entry.value,
+        (None, Some(default)) => default.clone(),
+        (None, None) => evaluator.error(format!("KeyError: {}", args[1].repr())),
+    }
+}
+
+// dict.update([other], **kwargs)
+pub fn builtin_dict_update(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    if args.len() > 2 {
+        evaluator.error(format!(
+            "TypeError: update expected at most 1 argument, got {}",
+            args.len() - 1
+        ));
+    }
+    // updated on a copy, so that the other dict may be the receiver itself
+    let mut table = dict_of(&args).borrow().clone();
+    update_table(evaluator, &mut table, args.get(1), kwargs);
+    *dict_of(&args).borrow_mut() = table;
+    py_val::new(py_val::None)
+}
+
+// set.union(*others)
+pub fn builtin_set_union(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "union", &args, kwargs, 1, None, &[]);
+    let mut res = set_of(&args).borrow().clone();
+    for other in &args[1..] {
+        res = res.merge(&table_of(evaluator, other));
+    }
+    py_val::new(py_val::set(res.into()))
+}
+
+// set.intersection(*others)
+pub fn builtin_set_intersection(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "intersection", &args, kwargs, 1, None, &[]);
+    let mut res = set_of(&args).borrow().clone();
+    for other in &args[1..] {
+        res = res.filter_by(&table_of(evaluator, other), true);
+    }
+    py_val::new(py_val::set(res.into()))
+}
+
+// set.difference(*others)
+pub fn builtin_set_difference(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "difference", &args, kwargs, 1, None, &[]);
+    let mut res = set_of(&args).borrow().clone();
+    for other in &args[1..] {
+        res = res.filter_by(&table_of(evaluator, other), false);
+    }
+    py_val::new(py_val::set(res.into()))
+}
+
+// set.add(elem)
+pub fn builtin_set_add(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "add", &args, kwargs, 2, Some(2), &[]);
+    let hash = evaluator.hash_of(&args[1]);
+    set_of(&args)
+        .borrow_mut()
+        .insert(hash, args[1].clone(), args[1].clone());
+    py_val::new(py_val::None)
+}
+
+// set.remove(elem)
+pub fn builtin_set_remove(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "remove", &args, kwargs, 2, Some(2), &[]);
+    let hash = evaluator.hash_of(&args[1]);
+    let removed = set_of(&args).borrow_mut().remove(hash, &args[1]);
+    if removed.is_none() {
+        evaluator.error(format!("KeyError: {}", args[1].repr()));
+    }
+    py_val::new(py_val::None)
+}
+
+// set.discard(elem)
+// as remove, but missing elements are ignored
+pub fn builtin_set_discard(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "discard", &args, kwargs, 2, Some(2), &[]);
+    let hash = evaluator.hash_of(&args[1]);
+    set_of(&args).borrow_mut().remove(hash, &args[1]);
+    py_val::new(py_val::None)
+}
@@ -0,0 +1,597 @@
+//! # compiler
+//! ASTをスタックVM(vm.rs)のバイトコードに変換する
+//! names bound in a function body are resolved to local slots(scope.rs) when the function is compiled,
+//! every other name is looked up in the global environment by its index in the name pool.
+//! operators are dispatched by Evaluator::binary_op etc., as in the tree-walker
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ast::*;
+use crate::eval::scope::Scope;
+use crate::eval::types::*;
+use crate::parser::SyntaxError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instr {
+    LoadConst(usize),   // index in consts
+    LoadLocal(usize),   // slot
+    StoreLocal(usize),  // slot
+    LoadGlobal(usize),  // index in names
+    StoreGlobal(usize), // index in names
+    LoadAttr(usize),    // index in names
+    ImportName(usize),  // index in names. push the module(not the top-level package)
+    ImportFrom(usize),  // index in names. module -> module, module.name
+    GetItem,            // obj, index -> obj[index]
+    SetItem,            // value, obj, index -> (obj[index] = value)
+    BinaryOp(ASTOperator),
+    UnaryOp(ASTUnaryOp),
+    // the result is always a bool
+    CompareOp(ASTCmpOp),
+    BuildList(usize),
+    BuildTuple(usize),
+    BuildDict(usize), // key, value pairs
+    BuildSet(usize),
+    // func, positional arguments
+    Call(usize),
+    // func, positional arguments, keyword arguments. the names are a tuple in consts
+    CallKw(usize, usize),
+    MakeFunction(usize), // index in codes
+    Jump(usize),
+    PopJumpIfFalse(usize),
+    PopJumpIfTrue(usize),
+    // jump keeping the top if it is false, pop it otherwise
+    JumpIfFalseOrPop(usize),
+    // iterators live on their own stack while the loop runs
+    GetIter,
+    // push the next element, or drop the iterator and jump when it is exhausted
+    ForIter(usize),
+    PopIter,
+    // the exits of context managers live on their own stack too. manager -> __enter__ value
+    BeforeWith,
+    // call the innermost exit with (None, None, None) and drop it
+    ExitWith,
+    PopTop,
+    DupTop,
+    RotTwo,
+    RotThree, // lift the top to the third position
+    ReturnValue,
+}
+
+// compiled function(or module) body
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Code {
+    pub name: String,
+    // parameters take the first slots
+    pub arg_names: Vec<String>,
+    // name of each local slot. empty at module level, where every name is global
+    pub local_names: Vec<String>,
+    pub instrs: Vec<Instr>,
+    // source span of each instruction, for tracebacks
+    pub spans: Vec<Span>,
+    pub consts: Vec<py_val_t>,
+    pub names: Vec<String>,
+    pub codes: Vec<Rc<Code>>,
+}
+
+struct Loop {
+    start: usize,
+    // jumps to be patched to the end of the loop
+    breaks: Vec<usize>,
+    is_for: bool,
+    // with blocks entered outside the loop
+    withs: usize,
+}
+
+pub struct Compiler {
+    code: Code,
+    // None at module level
+    scope: Option<Scope>,
+    const_indices: HashMap<ASTConstant, usize>,
+    name_indices: HashMap<String, usize>,
+    loops: Vec<Loop>,
+    // with blocks the current statement is in
+    withs: usize,
+    // span of the statement or expression being compiled
+    span: Span,
+    // the first syntax error found while compiling
+    error: Option<SyntaxError>,
+}
+
+// top level statements. names are all global
+pub fn compile_module(body: &[ASTStmt]) -> Result<Code, SyntaxError> {
+    Scope::check_module(body)?;
+    let mut compiler = Compiler::new("<module>", Vec::new(), None);
+    compiler.compile_body(body);
+    compiler.finish()
+}
+
+// enclosing is the scope of the function it is defined in(None at top level)
+pub fn compile_function(
+    name: &str,
+    args: &[ASTArg],
+    body: &[ASTStmt],
+    enclosing: Option<&Scope>,
+) -> Result<Code, SyntaxError> {
+    let scope = Scope::analyze(args, body, enclosing)?;
+    let mut compiler = Compiler::new(name, scope.locals.clone(), Some(scope));
+    compiler.code.arg_names = args.iter().map(|arg| arg.node.0.clone()).collect();
+    compiler.compile_body(body);
+    compiler.finish()
+}
+
+impl Compiler {
+    fn new(name: &str, local_names: Vec<String>, scope: Option<Scope>) -> Compiler {
+        Compiler {
+            code: Code {
+                name: name.to_string(),
+                arg_names: Vec::new(),
+                local_names,
+                instrs: Vec::new(),
+                spans: Vec::new(),
+                consts: Vec::new(),
+                names: Vec::new(),
+                codes: Vec::new(),
+            },
+            scope,
+            const_indices: HashMap::new(),
+            name_indices: HashMap::new(),
+            loops: Vec::new(),
+            withs: 0,
+            span: Span::default(),
+            error: None,
+        }
+    }
+
+    fn finish(mut self) -> Result<Code, SyntaxError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        // falling off the end returns None
+        let none = self.constant(&ASTConstant::None);
+        self.emit(Instr::LoadConst(none));
+        self.emit(Instr::ReturnValue);
+        Ok(self.code)
+    }
+
+    // keeps only the first error. the code is not run when there is one
+    fn syntax_error(&mut self, error: SyntaxError) {
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+    }
+
+    fn emit(&mut self, instr: Instr) -> usize {
+        self.code.instrs.push(instr);
+        self.code.spans.push(self.span);
+        self.code.instrs.len() - 1
+    }
+
+    fn here(&self) -> usize {
+        self.code.instrs.len()
+    }
+
+    // point the jump at `at` to the next instruction
+    fn patch(&mut self, at: usize) {
+        let here = self.here();
+        match &mut self.code.instrs[at] {
+            Instr::Jump(target)
+            | Instr::PopJumpIfFalse(target)
+            | Instr::PopJumpIfTrue(target)
+            | Instr::JumpIfFalseOrPop(target)
+            | Instr::ForIter(target) => *target = here,
+            instr => panic!("{:?} is not a jump", instr),
+        }
+    }
+
+    fn constant(&mut self, constant: &ASTConstant) -> usize {
+        if let Some(&i) = self.const_indices.get(constant) {
+            return i;
+        }
+        let value = match constant {
+            ASTConstant::Int(v) => py_val::int(*v),
+            ASTConstant::Float(v) => py_val::float(*v),
+            ASTConstant::String(s) => py_val::string(s.clone()),
+            ASTConstant::None => py_val::None,
+            ASTConstant::True => py_val::True,
+            ASTConstant::False => py_val::False,
+        };
+        self.code.consts.push(py_val::new(value));
+        let i = self.code.consts.len() - 1;
+        self.const_indices.insert(constant.clone(), i);
+        i
+    }
+
+    fn name(&mut self, name: &str) -> usize {
+        if let Some(&i) = self.name_indices.get(name) {
+            return i;
+        }
+        self.code.names.push(name.to_string());
+        let i = self.code.names.len() - 1;
+        self.name_indices.insert(name.to_string(), i);
+        i
+    }
+
+    // free names are global too, since closures are not supported
+    fn slot(&self, name: &str) -> Option<usize> {
+        self.scope.as_ref()?.slot(name)
+    }
+
+    fn load_name(&mut self, name: &str) {
+        let instr = match self.slot(name) {
+            Some(slot) => Instr::LoadLocal(slot),
+            None => Instr::LoadGlobal(self.name(name)),
+        };
+        self.emit(instr);
+    }
+
+    fn store_name(&mut self, name: &str) {
+        let instr = match self.slot(name) {
+            Some(slot) => Instr::StoreLocal(slot),
+            None => Instr::StoreGlobal(self.name(name)),
+        };
+        self.emit(instr);
+    }
+
+    fn compile_body(&mut self, body: &[ASTStmt]) {
+        for stmt in body {
+            self.compile_stmt(stmt);
+        }
+    }
+
+    // store the value on the stack to target
+    fn compile_store(&mut self, target: &ASTExpr) {
+        match &target.node {
+            ASTExprKind::Name(n) => self.store_name(n),
+            ASTExprKind::Subscript(value, slice) => match &slice.node {
+                ASTSliceKind::Index(index) => {
+                    self.compile_expr(value);
+                    self.compile_expr(index);
+                    let outer = std::mem::replace(&mut self.span, target.span);
+                    self.emit(Instr::SetItem);
+                    self.span = outer;
+                }
+                _ => panic!("can't assign"),
+            },
+            _ => panic!("can't assign"),
+        }
+    }
+
+    fn compile_stmt(&mut self, stmt: &ASTStmt) {
+        use ASTStmtKind::*;
+        let outer = std::mem::replace(&mut self.span, stmt.span);
+        match &stmt.node {
+            Init | Error => panic!(), // should not happen
+            End => (),
+            FuncDef(name, arguments, body, _) => {
+                let code = match compile_function(name, arguments, body, self.scope.as_ref()) {
+                    Ok(code) => code,
+                    Err(error) => {
+                        self.syntax_error(error);
+                        Code::default()
+                    }
+                };
+                self.code.codes.push(Rc::new(code));
+                self.emit(Instr::MakeFunction(self.code.codes.len() - 1));
+                self.store_name(name);
+            }
+            Return(value) => {
+                match value {
+                    Some(value) => self.compile_expr(value),
+                    None => {
+                        let none = self.constant(&ASTConstant::None);
+                        self.emit(Instr::LoadConst(none));
+                    }
+                }
+                // the value is computed before the with blocks are left
+                self.exit_withs(0);
+                self.emit(Instr::ReturnValue);
+            }
+            Delete(_) => unreachable!("rejected by the scope analysis"),
+            Assign(targets, value) => {
+                self.compile_expr(value);
+                for (i, target) in targets.iter().enumerate() {
+                    if i + 1 < targets.len() {
+                        self.emit(Instr::DupTop);
+                    }
+                    self.compile_store(target);
+                }
+            }
+            // 型注釈は実行時には無視する
+            AnnAssign(target, _, value) => {
+                if let Some(value) = value {
+                    self.compile_expr(value);
+                    self.compile_store(target);
+                }
+            }
+            For(target, iter, body) => {
+                // とりあえずアンパック代入はないことにする
+                let target = match &target.node {
+                    ASTExprKind::Name(name) => name,
+                    _ => panic!(),
+                };
+                self.compile_expr(iter);
+                self.span = iter.span;
+                self.emit(Instr::GetIter);
+                self.span = stmt.span;
+                let start = self.here();
+                let exit = self.emit(Instr::ForIter(0));
+                self.store_name(target);
+                self.compile_loop_body(start, true, body);
+                self.emit(Instr::Jump(start));
+                self.patch(exit);
+                self.patch_breaks();
+            }
+            While(test, body) => {
+                let start = self.here();
+                self.compile_expr(test);
+                let exit = self.emit(Instr::PopJumpIfFalse(0));
+                self.compile_loop_body(start, false, body);
+                self.emit(Instr::Jump(start));
+                self.patch(exit);
+                self.patch_breaks();
+            }
+            With(items, body) => {
+                for item in items {
+                    let (context_expr, optional_vars) = &item.node;
+                    self.compile_expr(context_expr);
+                    self.span = item.span;
+                    self.emit(Instr::BeforeWith);
+                    self.span = stmt.span;
+                    match optional_vars {
+                        Some(target) => self.compile_store(target),
+                        None => {
+                            self.emit(Instr::PopTop);
+                        }
+                    }
+                    self.withs += 1;
+                }
+                self.compile_body(body);
+                self.withs -= items.len();
+                for _ in items {
+                    self.emit(Instr::ExitWith);
+                }
+            }
+            If(test, body, orelse) => {
+                self.compile_expr(test);
+                let to_else = self.emit(Instr::PopJumpIfFalse(0));
+                self.compile_body(body);
+                if orelse.is_empty() {
+                    self.patch(to_else);
+                } else {
+                    let to_end = self.emit(Instr::Jump(0));
+                    self.patch(to_else);
+                    self.compile_body(orelse);
+                    self.patch(to_end);
+                }
+            }
+            // already taken into account by the scope
+            Global(_) => (),
+            Import(names) => {
+                for alias in names {
+                    let (name, asname) = &alias.node;
+                    let i = self.name(name);
+                    // errors point at the module, as on the tree-walker
+                    self.span = alias.span;
+                    self.emit(Instr::ImportName(i));
+                    self.span = stmt.span;
+                    match asname {
+                        Some(asname) => self.store_name(asname),
+                        // `import a.b` binds the package a, which is imported by now
+                        None => {
+                            let top = name.split('.').next().unwrap();
+                            if top != name {
+                                self.emit(Instr::PopTop);
+                                let i = self.name(top);
+                                self.emit(Instr::ImportName(i));
+                            }
+                            self.store_name(top);
+                        }
+                    }
+                }
+            }
+            ImportFrom(module, names) => {
+                let i = self.name(module);
+                self.emit(Instr::ImportName(i));
+                for alias in names {
+                    let (name, asname) = &alias.node;
+                    let i = self.name(name);
+                    self.span = alias.span;
+                    self.emit(Instr::ImportFrom(i));
+                    self.span = stmt.span;
+                    self.store_name(asname.as_ref().unwrap_or(name));
+                }
+                self.emit(Instr::PopTop);
+            }
+            Expr(expr) => {
+                self.compile_expr(expr);
+                self.emit(Instr::PopTop);
+            }
+            Pass => (),
+            Break => {
+                let (is_for, withs) = match self.loops.last() {
+                    Some(l) => (l.is_for, l.withs),
+                    None => unreachable!("rejected by the scope analysis"),
+                };
+                self.exit_withs(withs);
+                if is_for {
+                    self.emit(Instr::PopIter);
+                }
+                let jump = self.emit(Instr::Jump(0));
+                self.loops.last_mut().unwrap().breaks.push(jump);
+            }
+            Continue => {
+                let (start, withs) = match self.loops.last() {
+                    Some(l) => (l.start, l.withs),
+                    None => unreachable!("rejected by the scope analysis"),
+                };
+                self.exit_withs(withs);
+                self.emit(Instr::Jump(start));
+            }
+        }
+        self.span = outer;
+    }
+
+    fn compile_loop_body(&mut self, start: usize, is_for: bool, body: &[ASTStmt]) {
+        self.loops.push(Loop {
+            start,
+            breaks: Vec::new(),
+            is_for,
+            withs: self.withs,
+        });
+        self.compile_body(body);
+    }
+
+    // leave the with blocks entered after the first `outer` ones
+    fn exit_withs(&mut self, outer: usize) {
+        for _ in outer..self.withs {
+            self.emit(Instr::ExitWith);
+        }
+    }
+
+    fn patch_breaks(&mut self) {
+        for jump in self.loops.pop().unwrap().breaks {
+            self.patch(jump);
+        }
+    }
+
+    fn compile_expr(&mut self, expr: &ASTExpr) {
+        use ASTExprKind::*;
+        let outer = std::mem::replace(&mut self.span, expr.span);
+        match &expr.node {
+            // the result is a bool, as in the tree-walker
+            BoolOp(op, values) => {
+                let (jump, short, otherwise) = match op {
+                    ASTBoolOp::And => (
+                        Instr::PopJumpIfFalse(0),
+                        ASTConstant::False,
+                        ASTConstant::True,
+                    ),
+                    ASTBoolOp::Or => (
+                        Instr::PopJumpIfTrue(0),
+                        ASTConstant::True,
+                        ASTConstant::False,
+                    ),
+                };
+                let mut jumps = Vec::new();
+                for value in values {
+                    self.compile_expr(value);
+                    jumps.push(self.emit(jump.clone()));
+                }
+                let otherwise = self.constant(&otherwise);
+                self.emit(Instr::LoadConst(otherwise));
+                let to_end = self.emit(Instr::Jump(0));
+                for jump in jumps {
+                    self.patch(jump);
+                }
+                let short = self.constant(&short);
+                self.emit(Instr::LoadConst(short));
+                self.patch(to_end);
+            }
+            BinOp(lhs, op, rhs) => {
+                self.compile_expr(lhs);
+                self.compile_expr(rhs);
+                self.emit(Instr::BinaryOp(op.clone()));
+            }
+            UnaryOp(op, operand) => {
+                self.compile_expr(operand);
+                self.emit(Instr::UnaryOp(op.clone()));
+            }
+            IfExp(test, body, orelse) => {
+                self.compile_expr(test);
+                let to_orelse = self.emit(Instr::PopJumpIfFalse(0));
+                self.compile_expr(body);
+                let to_end = self.emit(Instr::Jump(0));
+                self.patch(to_orelse);
+                self.compile_expr(orelse);
+                self.patch(to_end);
+            }
+            Dict(keys, values) => {
+                for (key, value) in keys.iter().zip(values) {
+                    self.compile_expr(key);
+                    self.compile_expr(value);
+                }
+                self.emit(Instr::BuildDict(keys.len()));
+            }
+            Set(elts) => {
+                for elt in elts {
+                    self.compile_expr(elt);
+                }
+                self.emit(Instr::BuildSet(elts.len()));
+            }
+            Compare(left, ops, comparators) => {
+                self.compile_expr(left);
+                // a < b < c: each comparator is kept as the left operand of the next one
+                let mut cleanups = Vec::new();
+                for (op, comparator) in ops.iter().zip(comparators).take(ops.len() - 1) {
+                    self.compile_expr(comparator);
+                    self.emit(Instr::DupTop);
+                    self.emit(Instr::RotThree);
+                    self.emit(Instr::CompareOp(op.clone()));
+                    cleanups.push(self.emit(Instr::JumpIfFalseOrPop(0)));
+                }
+                self.compile_expr(comparators.last().unwrap());
+                self.emit(Instr::CompareOp(ops.last().unwrap().clone()));
+                if !cleanups.is_empty() {
+                    let to_end = self.emit(Instr::Jump(0));
+                    for cleanup in cleanups {
+                        self.patch(cleanup);
+                    }
+                    // drop the pending comparator under the False
+                    self.emit(Instr::RotTwo);
+                    self.emit(Instr::PopTop);
+                    self.patch(to_end);
+                }
+            }
+            Call(func, args, keywords) => {
+                self.compile_expr(func);
+                for arg in args {
+                    self.compile_expr(arg);
+                }
+                if keywords.is_empty() {
+                    self.emit(Instr::Call(args.len()));
+                } else {
+                    let mut names = Vec::new();
+                    for keyword in keywords {
+                        let (name, value) = &keyword.node;
+                        self.compile_expr(value);
+                        names.push(py_val::new(py_val::string(name.clone())));
+                    }
+                    self.code.consts.push(py_val::new(py_val::tuple(names)));
+                    self.emit(Instr::CallKw(args.len(), self.code.consts.len() - 1));
+                }
+            }
+            Constant(constant) => {
+                let i = self.constant(constant);
+                self.emit(Instr::LoadConst(i));
+            }
+            Subscript(value, slice) => match &slice.node {
+                ASTSliceKind::Index(index) => {
+                    self.compile_expr(value);
+                    self.compile_expr(index);
+                    self.emit(Instr::GetItem);
+                }
+                ASTSliceKind::Slice(..) => unreachable!("rejected by the scope analysis"),
+            },
+            Attribute(value, attr) => {
+                self.compile_expr(value);
+                let i = self.name(attr);
+                self.emit(Instr::LoadAttr(i));
+            }
+            Name(name) => self.load_name(name),
+            List(elts) => {
+                for elt in elts {
+                    self.compile_expr(elt);
+                }
+                self.emit(Instr::BuildList(elts.len()));
+            }
+            Tuple(elts) => {
+                for elt in elts {
+                    self.compile_expr(elt);
+                }
+                self.emit(Instr::BuildTuple(elts.len()));
+            }
+        }
+        self.span = outer;
+    }
+}
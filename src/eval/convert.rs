@@ -0,0 +1,247 @@
+//! # convert
+//! Rustの値とpy_valの相互変換
+//! IntoPy builds a value from Rust data, FromPy reads one back and fails with a TypeError
+//! if the value has another type. containers are converted element by element(copied)
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::eval::dict::DictTable;
+use crate::eval::types::*;
+use ordered_float::OrderedFloat;
+
+pub trait IntoPy {
+    fn into_py(self) -> Value;
+}
+
+pub trait FromPy: Sized {
+    fn from_py(value: &Value) -> Result<Self, PyError>;
+}
+
+// arguments of a function called from Rust: (), a tuple of IntoPy values or a Vec<Value>
+pub trait IntoPyArgs {
+    fn into_py_args(self) -> Vec<Value>;
+}
+
+fn type_error<T>(expected: &str, value: &Value) -> Result<T, PyError> {
+    Err(PyError::new(format!(
+        "TypeError: expected {}, got {}",
+        expected,
+        value.type_name()
+    )))
+}
+
+impl IntoPy for Value {
+    fn into_py(self) -> Value {
+        self
+    }
+}
+
+impl FromPy for Value {
+    fn from_py(value: &Value) -> Result<Value, PyError> {
+        Ok(value.clone())
+    }
+}
+
+impl IntoPy for () {
+    fn into_py(self) -> Value {
+        py_val::new(py_val::None)
+    }
+}
+
+impl FromPy for () {
+    fn from_py(value: &Value) -> Result<(), PyError> {
+        match *value.view() {
+            py_val::None => Ok(()),
+            _ => type_error("None", value),
+        }
+    }
+}
+
+impl IntoPy for i64 {
+    fn into_py(self) -> Value {
+        py_val::new(py_val::int(self))
+    }
+}
+
+// bool is not int here
+impl FromPy for i64 {
+    fn from_py(value: &Value) -> Result<i64, PyError> {
+        match *value.view() {
+            py_val::int(i) => Ok(i),
+            _ => type_error("int", value),
+        }
+    }
+}
+
+impl IntoPy for f64 {
+    fn into_py(self) -> Value {
+        py_val::new(py_val::float(OrderedFloat(self)))
+    }
+}
+
+// int is accepted as float, as in the type checker
+impl FromPy for f64 {
+    fn from_py(value: &Value) -> Result<f64, PyError> {
+        match *value.view() {
+            py_val::int(i) => Ok(i as f64),
+            py_val::float(f) => Ok(f.into_inner()),
+            _ => type_error("float", value),
+        }
+    }
+}
+
+impl IntoPy for bool {
+    fn into_py(self) -> Value {
+        py_val::from_bool(self)
+    }
+}
+
+impl FromPy for bool {
+    fn from_py(value: &Value) -> Result<bool, PyError> {
+        match *value.view() {
+            py_val::True => Ok(true),
+            py_val::False => Ok(false),
+            _ => type_error("bool", value),
+        }
+    }
+}
+
+impl IntoPy for &str {
+    fn into_py(self) -> Value {
+        py_val::new(py_val::string(self.to_string()))
+    }
+}
+
+impl IntoPy for String {
+    fn into_py(self) -> Value {
+        py_val::new(py_val::string(self))
+    }
+}
+
+impl FromPy for String {
+    fn from_py(value: &Value) -> Result<String, PyError> {
+        match value.as_str() {
+            Some(s) => Ok(s.to_string()),
+            None => type_error("str", value),
+        }
+    }
+}
+
+impl<T: IntoPy> IntoPy for Option<T> {
+    fn into_py(self) -> Value {
+        match self {
+            Some(v) => v.into_py(),
+            None => py_val::new(py_val::None),
+        }
+    }
+}
+
+impl<T: FromPy> FromPy for Option<T> {
+    fn from_py(value: &Value) -> Result<Option<T>, PyError> {
+        match *value.view() {
+            py_val::None => Ok(None),
+            _ => T::from_py(value).map(Some),
+        }
+    }
+}
+
+// Vec is a list
+impl<T: IntoPy> IntoPy for Vec<T> {
+    fn into_py(self) -> Value {
+        py_val::new_list(self.into_iter().map(IntoPy::into_py).collect())
+    }
+}
+
+// from a list or a tuple
+impl<T: FromPy> FromPy for Vec<T> {
+    fn from_py(value: &Value) -> Result<Vec<T>, PyError> {
+        match value.heap() {
+            Some(py_val::list(_)) | Some(py_val::tuple(_)) => value
+                .iter_values()
+                .unwrap()
+                .iter()
+                .map(T::from_py)
+                .collect(),
+            _ => type_error("list", value),
+        }
+    }
+}
+
+// the keys are hashed by the default __hash__
+impl<K: IntoPy, V: IntoPy> IntoPy for HashMap<K, V> {
+    fn into_py(self) -> Value {
+        let mut table = DictTable::new();
+        for (key, value) in self {
+            let key = key.into_py();
+            let hash = key.py_hash().expect("unhashable key");
+            table.insert(hash, key, value.into_py());
+        }
+        py_val::new(py_val::dict(table.into()))
+    }
+}
+
+impl<K: FromPy + Eq + Hash, V: FromPy> FromPy for HashMap<K, V> {
+    fn from_py(value: &Value) -> Result<HashMap<K, V>, PyError> {
+        match value.heap() {
+            Some(py_val::dict(d)) => d
+                .borrow()
+                .iter()
+                .map(|entry| Ok((K::from_py(&entry.key)?, V::from_py(&entry.value)?)))
+                .collect(),
+            _ => type_error("dict", value),
+        }
+    }
+}
+
+impl IntoPyArgs for () {
+    fn into_py_args(self) -> Vec<Value> {
+        Vec::new()
+    }
+}
+
+impl IntoPyArgs for Vec<Value> {
+    fn into_py_args(self) -> Vec<Value> {
+        self
+    }
+}
+
+// tuples are tuples, and the arguments of a call
+macro_rules! impl_tuple {
+    ($len: expr, $(($t: ident, $i: tt)),*) => {
+        impl<$($t: IntoPy),*> IntoPy for ($($t,)*) {
+            fn into_py(self) -> Value {
+                py_val::new(py_val::tuple(self.into_py_args()))
+            }
+        }
+
+        impl<$($t: FromPy),*> FromPy for ($($t,)*) {
+            fn from_py(value: &Value) -> Result<Self, PyError> {
+                match value.heap() {
+                    Some(py_val::tuple(elts)) if elts.len() == $len => {
+                        Ok(($($t::from_py(&elts[$i])?,)*))
+                    }
+                    _ => type_error(concat!("tuple of length ", $len), value),
+                }
+            }
+        }
+
+        impl<$($t: IntoPy),*> IntoPyArgs for ($($t,)*) {
+            fn into_py_args(self) -> Vec<Value> {
+                vec![$(self.$i.into_py()),*]
+            }
+        }
+    };
+}
+
+impl_tuple!(1, (A, 0));
+impl_tuple!(2, (A, 0), (B, 1));
+impl_tuple!(3, (A, 0), (B, 1), (C, 2));
+impl_tuple!(4, (A, 0), (B, 1), (C, 2), (D, 3));
+
+impl Value {
+    /// the value as a Rust type
+    pub fn extract<T: FromPy>(&self) -> Result<T, PyError> {
+        T::from_py(self)
+    }
+}
@@ -0,0 +1,379 @@
+//! # debugger
+//! pdbのようなデバッガ
+//! `minipython <file> debug` runs the file on the tree-walker and stops before its first
+//! statement with the `(Pdb) ` prompt. eval_stmt asks here whether to stop before each
+//! statement; the commands are read from the stdin of the evaluator and the answers are
+//! written to its stdout, so a session can be scripted.
+//! the prelude is never stopped in, and `return` stops at the next statement of the caller
+//! (there is no `--Return--` event)
+
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::path::Path;
+
+use crate::ast::*;
+use crate::eval::evaluator::{Engine, Evaluator, LocalEnv, PRELUDE_FILE_NAME};
+use crate::eval::io::read_line;
+use crate::eval::types::*;
+use crate::parser::Parser;
+use crate::traceback::source_line;
+
+const HELP: &str = "\
+s(tep)            stop at the next statement, in a called function too
+n(ext)            stop at the next statement of this function
+r(eturn)          stop after this function returns
+c(ont(inue))      stop only at breakpoints
+b(reak) [N | file:N | function]
+                  set a breakpoint(without argument, list them)
+cl(ear) [N]       delete the breakpoint N(without argument, all of them)
+p expression      print the value of the expression in this frame
+w(here)           print the stack trace
+l(ist)            print the source around the current line
+q(uit)            stop the program
+";
+
+struct Breakpoint {
+    file_name: String,
+    line: usize,
+}
+
+// when the next stop is. depths are those of the back trace
+#[derive(Clone, Copy)]
+enum Mode {
+    Step,
+    // stop in a frame at this depth or an outer one
+    Next(usize),
+    // stop in a frame outer than this depth
+    Finish(usize),
+    Continue,
+}
+
+// unwinds the program when the user quits
+struct Quit;
+
+pub(crate) struct Debugger {
+    // numbered from 1. deleted ones are None, so the numbers don't change
+    breakpoints: Vec<Option<Breakpoint>>,
+    mode: Mode,
+    // repeated by an empty line
+    last_command: String,
+}
+
+impl Debugger {
+    fn new() -> Debugger {
+        Debugger {
+            breakpoints: Vec::new(),
+            mode: Mode::Step,
+            last_command: String::new(),
+        }
+    }
+
+    fn has_breakpoint(&self, file_name: &str, line: usize) -> bool {
+        self.breakpoints.iter().flatten().any(|breakpoint| {
+            breakpoint.line == line && Path::new(file_name).ends_with(&breakpoint.file_name)
+        })
+    }
+
+    fn should_stop(&self, file_name: &str, line: usize, depth: usize) -> bool {
+        let stop = match self.mode {
+            Mode::Step => true,
+            Mode::Next(frame) => depth <= frame,
+            Mode::Finish(frame) => depth < frame,
+            Mode::Continue => false,
+        };
+        stop || self.has_breakpoint(file_name, line)
+    }
+}
+
+impl Evaluator {
+    /// run the file under the debugger, reading the commands from stdin
+    pub fn debug(&mut self) {
+        // the VM has no statements to stop at
+        self.engine = Engine::Tree;
+        self.debugger = Some(Box::new(Debugger::new()));
+        let result = catch_unwind(AssertUnwindSafe(|| self.eval()));
+        self.debugger = None;
+        if let Err(payload) = result {
+            if !payload.is::<Quit>() {
+                resume_unwind(payload);
+            }
+            self.recover();
+        }
+    }
+
+    // called by eval_stmt before stmt runs
+    pub(crate) fn debug_stmt(&mut self, stmt: &ASTStmt, local_env: &mut LocalEnv) {
+        if let ASTStmtKind::End | ASTStmtKind::Init = stmt.node {
+            return;
+        }
+        // no stop while a command(`p f()`) runs code
+        let mut debugger = match self.debugger.take() {
+            Some(debugger) => debugger,
+            None => return,
+        };
+        let file_name = self.current_file_name().to_string();
+        if file_name == PRELUDE_FILE_NAME
+            || !debugger.should_stop(&file_name, stmt.span.line, self.back_trace.len())
+        {
+            self.debugger = Some(debugger);
+            return;
+        }
+        self.set_span(stmt.span);
+        self.debug_write(&self.format_location(self.back_trace.len() - 1, "> "));
+        loop {
+            self.debug_write("(Pdb) ");
+            let read = read_line(self.io.borrow_mut().stdin());
+            let line = match read {
+                Ok(line) if !line.is_empty() => line,
+                // EOF quits as pdb does
+                _ => {
+                    self.debug_write("\n");
+                    resume_unwind(Box::new(Quit));
+                }
+            };
+            let mut line = line.trim().to_string();
+            if line.is_empty() {
+                line = debugger.last_command.clone();
+            } else {
+                debugger.last_command = line.clone();
+            }
+            let (command, arg) = match line.find(char::is_whitespace) {
+                Some(i) => (&line[..i], line[i..].trim()),
+                None => (line.as_str(), ""),
+            };
+            let depth = self.back_trace.len();
+            match command {
+                "s" | "step" => debugger.mode = Mode::Step,
+                "n" | "next" => debugger.mode = Mode::Next(depth),
+                "r" | "return" | "finish" => debugger.mode = Mode::Finish(depth),
+                "c" | "cont" | "continue" => debugger.mode = Mode::Continue,
+                "q" | "quit" => resume_unwind(Box::new(Quit)),
+                "b" | "break" => {
+                    self.debug_break(&mut debugger, arg, local_env);
+                    continue;
+                }
+                "cl" | "clear" => {
+                    self.debug_clear(&mut debugger, arg);
+                    continue;
+                }
+                "p" => {
+                    match self.debug_eval(arg, stmt.span, local_env) {
+                        Ok(value) => self.debug_write(&format!("{}\n", value.repr())),
+                        Err(message) => self.debug_write(&format!("*** {}\n", message)),
+                    }
+                    continue;
+                }
+                "w" | "where" | "bt" => {
+                    for i in 0..self.back_trace.len() {
+                        let prefix = if i == self.back_trace.len() - 1 {
+                            "> "
+                        } else {
+                            "  "
+                        };
+                        self.debug_write(&self.format_location(i, prefix));
+                    }
+                    continue;
+                }
+                "l" | "list" => {
+                    self.debug_write(&format_listing(&debugger, &file_name, stmt.span.line));
+                    continue;
+                }
+                "h" | "help" => {
+                    self.debug_write(HELP);
+                    continue;
+                }
+                _ => {
+                    self.debug_write(&format!("*** Unknown command: {}\n", command));
+                    continue;
+                }
+            }
+            break;
+        }
+        self.debugger = Some(debugger);
+    }
+
+    // `> file(line)function()` and `-> source line` for the frame at index of the back trace
+    fn format_location(&self, index: usize, prefix: &str) -> String {
+        let frame = &self.back_trace[index];
+        let line = frame.position.line;
+        let source = source_line(&frame.file_name, line).unwrap_or_default();
+        format!(
+            "{}{}({}){}()\n-> {}\n",
+            prefix,
+            frame.file_name,
+            line,
+            frame.function_name,
+            source.trim()
+        )
+    }
+
+    fn debug_write(&self, text: &str) {
+        let mut io = self.io.borrow_mut();
+        let _ = write!(io.stdout(), "{}", text);
+        let _ = io.stdout().flush();
+    }
+
+    // the value of source in the frame of the stopped statement, or the message of its error
+    fn debug_eval(
+        &mut self,
+        source: &str,
+        span: Span,
+        local_env: &mut LocalEnv,
+    ) -> Result<py_val_t, String> {
+        let mut parser = Parser::from_source("<stdin>", format!("{}\n", source));
+        let ast = parser.parse();
+        if let Some(error) = parser.errors().first() {
+            return Err(format!("SyntaxError: {}", error.message));
+        }
+        let expr = match ast.as_slice() {
+            [ASTStmt {
+                node: ASTStmtKind::Expr(expr),
+                ..
+            }] => expr,
+            _ => return Err("SyntaxError: expected a single expression".to_string()),
+        };
+        // an error of the expression comes back here instead of ending the program
+        let depth = self.back_trace.len();
+        let module = self.current_module;
+        let result = catch_unwind(AssertUnwindSafe(|| self.eval_expr(expr, local_env)));
+        self.back_trace.truncate(depth);
+        self.current_module = module;
+        self.set_span(span);
+        match result {
+            Ok(value) => Ok(value),
+            Err(payload) => match payload.downcast::<PyError>() {
+                Ok(error) => Err(error.message),
+                Err(payload) => resume_unwind(payload),
+            },
+        }
+    }
+
+    // `b N`, `b file:N`, `b function`, or the list of the breakpoints
+    fn debug_break(&mut self, debugger: &mut Debugger, arg: &str, local_env: &mut LocalEnv) {
+        if arg.is_empty() {
+            let mut listing = String::new();
+            for (i, breakpoint) in debugger.breakpoints.iter().enumerate() {
+                if let Some(breakpoint) = breakpoint {
+                    if listing.is_empty() {
+                        listing.push_str("Num Type         Disp Enb   Where\n");
+                    }
+                    listing.push_str(&format!(
+                        "{:<3} breakpoint   keep yes   at {}:{}\n",
+                        i + 1,
+                        breakpoint.file_name,
+                        breakpoint.line
+                    ));
+                }
+            }
+            self.debug_write(&listing);
+            return;
+        }
+        let (file_name, line) = match arg.rsplit_once(':') {
+            Some((file_name, line)) if line.parse::<usize>().is_ok() => {
+                (file_name.to_string(), line.parse().unwrap())
+            }
+            _ => match arg.parse::<usize>() {
+                Ok(line) => (self.current_file_name().to_string(), line),
+                Err(_) => {
+                    let span = self.back_trace.last().unwrap().position;
+                    let value = match self.debug_eval(arg, span, local_env) {
+                        Ok(value) => value,
+                        Err(message) => {
+                            self.debug_write(&format!("*** {}\n", message));
+                            return;
+                        }
+                    };
+                    match value.heap() {
+                        // the first statement of the body
+                        Some(py_val::func(func)) if !func.stmt.is_empty() => {
+                            (func.file_name.clone(), func.stmt[0].span.line)
+                        }
+                        _ => {
+                            self.debug_write(&format!(
+                                "*** The specified object '{}' is not a function\n",
+                                arg
+                            ));
+                            return;
+                        }
+                    }
+                }
+            },
+        };
+        match source_line(&file_name, line) {
+            None => self.debug_write(&format!(
+                "*** Line {} of '{}' does not exist\n",
+                line, file_name
+            )),
+            Some(source) if source.trim().is_empty() || source.trim().starts_with('#') => {
+                self.debug_write("*** Blank or comment\n")
+            }
+            Some(_) => {
+                debugger.breakpoints.push(Some(Breakpoint {
+                    file_name: file_name.clone(),
+                    line,
+                }));
+                self.debug_write(&format!(
+                    "Breakpoint {} at {}:{}\n",
+                    debugger.breakpoints.len(),
+                    file_name,
+                    line
+                ));
+            }
+        }
+    }
+
+    // `cl N`, or all of the breakpoints
+    fn debug_clear(&mut self, debugger: &mut Debugger, arg: &str) {
+        let numbers: Vec<usize> = if arg.is_empty() {
+            (1..=debugger.breakpoints.len()).collect()
+        } else {
+            match arg.parse::<usize>() {
+                Ok(number) if number >= 1 && number <= debugger.breakpoints.len() => vec![number],
+                _ => {
+                    self.debug_write(&format!("*** Invalid breakpoint number: {}\n", arg));
+                    return;
+                }
+            }
+        };
+        for number in numbers {
+            match debugger.breakpoints[number - 1].take() {
+                Some(breakpoint) => self.debug_write(&format!(
+                    "Deleted breakpoint {} at {}:{}\n",
+                    number, breakpoint.file_name, breakpoint.line
+                )),
+                None if !arg.is_empty() => {
+                    self.debug_write(&format!("*** Breakpoint {} already deleted\n", number))
+                }
+                None => (),
+            }
+        }
+    }
+}
+
+// 11 lines around line as pdb lists them: number, B for a breakpoint, -> for the current line
+fn format_listing(debugger: &Debugger, file_name: &str, line: usize) -> String {
+    let source = std::fs::read_to_string(file_name).unwrap_or_default();
+    let first = line.saturating_sub(5).max(1);
+    let mut res = String::new();
+    for (number, text) in source
+        .lines()
+        .enumerate()
+        .map(|(i, text)| (i + 1, text))
+        .skip(first - 1)
+        .take(11)
+    {
+        let mut prefix = format!("{:>3} ", number);
+        prefix.push(if debugger.has_breakpoint(file_name, number) {
+            'B'
+        } else {
+            ' '
+        });
+        if number == line {
+            prefix.push_str("->");
+        }
+        res.push_str(&format!("{}\t{}\n", prefix, text));
+    }
+    if res.is_empty() {
+        res.push_str("[EOF]\n");
+    }
+    res
+}
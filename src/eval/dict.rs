@@ -0,0 +1,150 @@
+//! # dict
+//! insertion ordered hash table backing dict and set
+//! hashes are computed by the caller(through `__hash__`) and stored with each entry,
+//! so that tables can be merged or copied without hashing the keys again
+//! keys of the same hash are compared by the builtin equality(Value::py_eq), not through
+//! `__eq__`: the table is used by native functions, which can't call back into the script.
+//! so a user-defined `__eq__` changes `==` but not which keys a dict considers the same
+
+use std::collections::HashMap;
+
+use crate::eval::types::*;
+
+#[derive(Debug, Clone)]
+pub struct DictEntry {
+    pub hash: i64,
+    pub key: py_val_t,
+    pub value: py_val_t,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DictTable {
+    // in insertion order. removed entries are left as None until the next compaction
+    entries: Vec<Option<DictEntry>>,
+    // hash -> positions in entries
+    indices: HashMap<i64, Vec<usize>>,
+    len: usize,
+}
+
+// keys are the same object or equal. (identity first, as CPython does)
+// a user-defined __eq__ is not called here
+fn same_key(a: &py_val_t, b: &py_val_t) -> bool {
+    Value::ptr_eq(a, b) || a.py_eq(b)
+}
+
+impl DictTable {
+    pub fn new() -> DictTable {
+        DictTable::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn position(&self, hash: i64, key: &py_val_t) -> Option<usize> {
+        self.indices.get(&hash)?.iter().copied().find(|&i| {
+            self.entries[i]
+                .as_ref()
+                .is_some_and(|entry| same_key(&entry.key, key))
+        })
+    }
+
+    pub fn get(&self, hash: i64, key: &py_val_t) -> Option<py_val_t> {
+        self.position(hash, key)
+            .map(|i| self.entries[i].as_ref().unwrap().value.clone())
+    }
+
+    pub fn contains(&self, hash: i64, key: &py_val_t) -> bool {
+        self.position(hash, key).is_some()
+    }
+
+    // an existing key keeps its position(and the original key object). returns the old value
+    pub fn insert(&mut self, hash: i64, key: py_val_t, value: py_val_t) -> Option<py_val_t> {
+        if let Some(i) = self.position(hash, &key) {
+            let entry = self.entries[i].as_mut().unwrap();
+            return Some(std::mem::replace(&mut entry.value, value));
+        }
+        self.indices
+            .entry(hash)
+            .or_default()
+            .push(self.entries.len());
+        self.entries.push(Some(DictEntry { hash, key, value }));
+        self.len += 1;
+        None
+    }
+
+    pub fn remove(&mut self, hash: i64, key: &py_val_t) -> Option<DictEntry> {
+        let i = self.position(hash, key)?;
+        let positions = self.indices.get_mut(&hash).unwrap();
+        positions.retain(|&p| p != i);
+        if positions.is_empty() {
+            self.indices.remove(&hash);
+        }
+        let entry = self.entries[i].take();
+        self.len -= 1;
+        // drop the holes once they outnumber the live entries
+        if self.entries.len() > 2 * self.len + 8 {
+            self.compact();
+        }
+        entry
+    }
+
+    fn compact(&mut self) {
+        let entries: Vec<DictEntry> = self.entries.drain(..).flatten().collect();
+        self.indices.clear();
+        self.len = 0;
+        for entry in entries {
+            self.insert(entry.hash, entry.key, entry.value);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = DictTable::new();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DictEntry> {
+        self.entries.iter().flatten()
+    }
+
+    pub fn keys(&self) -> Vec<py_val_t> {
+        self.iter().map(|entry| entry.key.clone()).collect()
+    }
+
+    pub fn values(&self) -> Vec<py_val_t> {
+        self.iter().map(|entry| entry.value.clone()).collect()
+    }
+
+    // same keys, and values equal by `==` on builtin types. the order doesn't matter
+    pub fn py_eq(&self, other: &DictTable) -> bool {
+        self.len == other.len
+            && self.iter().all(|entry| {
+                other
+                    .get(entry.hash, &entry.key)
+                    .is_some_and(|value| value.py_eq(&entry.value))
+            })
+    }
+
+    // entries of self whose key is (or is not) in other
+    pub fn filter_by(&self, other: &DictTable, contained: bool) -> DictTable {
+        let mut res = DictTable::new();
+        for entry in self.iter() {
+            if other.contains(entry.hash, &entry.key) == contained {
+                res.insert(entry.hash, entry.key.clone(), entry.value.clone());
+            }
+        }
+        res
+    }
+
+    // entries of other are added after those of self, overwriting the values of common keys
+    pub fn merge(&self, other: &DictTable) -> DictTable {
+        let mut res = self.clone();
+        for entry in other.iter() {
+            res.insert(entry.hash, entry.key.clone(), entry.value.clone());
+        }
+        res
+    }
+}
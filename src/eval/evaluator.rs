@@ -1,44 +1,154 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::io::{BufRead, Write};
 use std::iter::Iterator;
-use std::rc::Rc;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::rc::{Rc, Weak};
 
 use crate::ast::*;
-use crate::eval::{native_func::*, types::*, utils::*};
-use crate::parser::Parser;
-
-// None appears only in local environment and indicates it is global variable
-type LocalEnv = Option<HashMap<String, Option<py_val_t>>>;
-type GlobalEnv = HashMap<String, py_val_t>;
-struct BackTraceItem {
-    file_name: String,
-    function_name: String,
-    position: usize,
+use crate::eval::{
+    builtins::*,
+    compiler::{compile_module, Code},
+    debugger::Debugger,
+    dict::DictTable,
+    file::*,
+    gc::{self, gc_module},
+    import::{module_search_path, Module},
+    io::Io,
+    limits::{Budget, CancelHandle, Limits},
+    native_func::*,
+    scope::{NameKind, Scope},
+    types::*,
+};
+use crate::parser::{Parser, SyntaxError};
+use crate::traceback::format_frame;
+
+// local variables of a function call, in the slots given by the scope of the function.
+// None at top level
+pub(crate) struct Frame {
+    scope: Rc<Scope>,
+    slots: Vec<Option<py_val_t>>,
+}
+pub(crate) type LocalEnv = Option<Frame>;
+pub(crate) type GlobalEnv = HashMap<String, py_val_t>;
+// a frame of the traceback. position is the span being evaluated in it
+pub(crate) struct BackTraceItem {
+    pub(crate) file_name: String,
+    pub(crate) function_name: String,
+    pub(crate) position: Span,
+}
+pub(crate) type BackTrace = Vec<BackTraceItem>;
+// type name -> method name -> method
+type MethodTable = HashMap<&'static str, HashMap<String, py_val_t>>;
+
+// how statements are executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    // walk the AST directly
+    Tree,
+    // compile to bytecode and run it on the stack VM(vm.rs)
+    Vm,
 }
-type BackTrace = Vec<BackTraceItem>;
 
 pub struct Evaluator {
-    global_env: GlobalEnv,
-    back_trace: BackTrace,
+    // namespaces of the imported modules. modules[0] is __main__, which shares its namespace
+    // with the builtins and the prelude(so overriding `__add__` there changes the operators)
+    pub(crate) modules: Vec<Module>,
+    // the module whose code is running
+    pub(crate) current_module: usize,
+    // builtins and prelude as they were before the main file ran.
+    // the other modules look names up here after their own globals
+    pub(crate) builtins: GlobalEnv,
+    // dotted name -> index in modules
+    pub(crate) module_indices: HashMap<String, usize>,
+    // directories searched for modules(script directory, then MINIPYTHON_PATH)
+    pub(crate) search_path: Vec<PathBuf>,
+    // operator functions(`__add__`, ...) as the prelude defined them
+    pub(crate) prelude_ops: GlobalEnv,
+    // some of them have been reassigned since
+    pub(crate) ops_overridden: bool,
+    methods: MethodTable,
+    pub(crate) back_trace: BackTrace,
     parser: Parser,
+    pub(crate) engine: Engine,
+    // errors unwind to the Interpreter as PyError. otherwise eval reports them and exits
+    pub(crate) embedded: bool,
+    pub(crate) budget: Budget,
+    // stdout, stderr and stdin of the script. borrowed only while writing or reading
+    pub(crate) io: RefCell<Io>,
+    // open() may access files
+    pub(crate) filesystem: bool,
+    // the files open() opened for writing, flushed before the program exits on an error
+    pub(crate) written_files: Vec<Weak<RefCell<FileHandle>>>,
+    // stops the statements for the prompt of `debug`(debugger.rs)
+    pub(crate) debugger: Option<Box<Debugger>>,
+}
+
+macro_rules! insert_builtin_functions {
+    ($env: expr, [$(($name: expr, $body: ident)),*]) => {
+        $(
+            $env.insert($name.to_string(),
+            py_val::new(
+                py_val::builtin_func(py_builtin_func {
+                    name: $name.to_string(),
+                    body: $body as fn(&mut Evaluator, Vec<py_val_t>, py_kwargs_t) -> py_val_t
+                })
+            ));
+        )*
+    }
 }
 
 macro_rules! insert_native_functions {
     ($env: expr, [$(($name: ident, $arity: expr)),*]) => {
         $(
             $env.insert(stringify!($name).to_string(),
-            Rc::new(
-                py_val::native_func(py_native_func {
-                    name: stringify!($name).to_string(),
-                    arity: $arity,
-                    body: $name as fn(Vec<py_val_t>) -> py_val_t
-                })
+            py_val::new(
+                py_val::native_func(py_native_func::new(stringify!($name), Arity::from($arity), $name))
             ));
         )*
     }
 }
 
+// arity of a method includes the receiver
+macro_rules! insert_methods {
+    ($table: expr, $type_name: expr, [$(($name: expr, $body: ident, $arity: expr)),*]) => {
+        let methods = $table.entry($type_name).or_insert_with(HashMap::new);
+        $(
+            methods.insert($name.to_string(),
+            py_val::new(
+                py_val::native_func(py_native_func::new($name, Arity::from($arity), $body))
+            ));
+        )*
+    }
+}
+
+macro_rules! insert_builtin_methods {
+    ($table: expr, $type_name: expr, [$(($name: expr, $body: ident)),*]) => {
+        insert_builtin_functions!($table.entry($type_name).or_insert_with(HashMap::new), [$(($name, $body)),*]);
+    }
+}
+
 impl Evaluator {
     pub fn new(file_name: &str) -> std::io::Result<Evaluator> {
+        Evaluator::with_engine(file_name, Engine::Tree)
+    }
+
+    pub fn with_engine(file_name: &str, engine: Engine) -> std::io::Result<Evaluator> {
+        Evaluator::with_parser(Parser::new(file_name)?, engine)
+    }
+
+    /// the program is source instead of a file. file_name is the one shown in tracebacks
+    pub fn from_source(
+        file_name: &str,
+        source: &str,
+        engine: Engine,
+    ) -> std::io::Result<Evaluator> {
+        Evaluator::with_parser(Parser::from_source(file_name, source.to_string()), engine)
+    }
+
+    fn with_parser(parser: Parser, engine: Engine) -> std::io::Result<Evaluator> {
+        let file_name = &parser.get_file_name().clone();
         // native関数の登録
         let mut global_env: GlobalEnv = GlobalEnv::new();
         insert_native_functions!(
@@ -79,8 +189,25 @@ impl Evaluator {
                 (ntv_len_tuple, 2),
                 (ntv_getitem_tuple, 2),
                 (ntv_add_list, 2),
-                (ntv_print_string, 1),
-                (ntv_range, 1),
+                (ntv_mul_list, 2),
+                (ntv_getitem_list, 2),
+                (ntv_setitem_list, 3),
+                (ntv_contains_string, 2),
+                (ntv_eq, 2),
+                (ntv_lt, 2),
+                (ntv_le, 2),
+                (ntv_gt, 2),
+                (ntv_ge, 2),
+                (ntv_is, 2),
+                (ntv_hash, 1),
+                (ntv_getitem_dict, 3),
+                (ntv_setitem_dict, 4),
+                (ntv_contains_hashed, 3),
+                (ntv_or_dict, 2),
+                (ntv_or_set, 2),
+                (ntv_and_set, 2),
+                (ntv_sub_set, 2),
+                (ntv_xor_set, 2),
                 (ntv_panic, 0),
                 (ntv_not, 1),
                 (ntv_is_int, 1),
@@ -92,44 +219,363 @@ impl Evaluator {
                 (ntv_is_set, 1)
             ]
         );
+        insert_builtin_functions!(
+            global_env,
+            [
+                ("len", builtin_len),
+                ("abs", builtin_abs),
+                ("min", builtin_min),
+                ("max", builtin_max),
+                ("sum", builtin_sum),
+                ("sorted", builtin_sorted),
+                ("reversed", builtin_reversed),
+                ("enumerate", builtin_enumerate),
+                ("zip", builtin_zip),
+                ("map", builtin_map),
+                ("filter", builtin_filter),
+                ("any", builtin_any),
+                ("all", builtin_all),
+                ("range", builtin_range),
+                ("hash", builtin_hash),
+                ("dict", builtin_dict),
+                ("set", builtin_set),
+                ("input", builtin_input),
+                ("open", builtin_open),
+                ("ntv_print_string", builtin_print_string)
+            ]
+        );
+        let mut methods = MethodTable::new();
+        insert_methods!(
+            methods,
+            "str",
+            [
+                ("split", ntv_str_split, 1..=3),
+                ("rsplit", ntv_str_rsplit, 1..=3),
+                ("join", ntv_str_join, 2),
+                ("strip", ntv_str_strip, 1..=2),
+                ("lstrip", ntv_str_lstrip, 1..=2),
+                ("rstrip", ntv_str_rstrip, 1..=2),
+                ("replace", ntv_str_replace, 3..=4),
+                ("find", ntv_str_find, 2..=4),
+                ("rfind", ntv_str_rfind, 2..=4),
+                ("index", ntv_str_index, 2..=4),
+                ("startswith", ntv_str_startswith, 2..=4),
+                ("endswith", ntv_str_endswith, 2..=4),
+                ("upper", ntv_str_upper, 1),
+                ("lower", ntv_str_lower, 1),
+                ("title", ntv_str_title, 1),
+                ("isdigit", ntv_str_isdigit, 1),
+                ("isalpha", ntv_str_isalpha, 1),
+                ("isspace", ntv_str_isspace, 1),
+                ("count", ntv_str_count, 2..=4),
+                ("splitlines", ntv_str_splitlines, 1..=2),
+                ("partition", ntv_str_partition, 2),
+                ("zfill", ntv_str_zfill, 2),
+                ("center", ntv_str_center, 2..=3)
+            ]
+        );
+        insert_methods!(
+            methods,
+            "list",
+            [
+                ("append", ntv_list_append, 2),
+                ("extend", ntv_list_extend, 2),
+                ("insert", ntv_list_insert, 3),
+                ("pop", ntv_list_pop, 1..=2),
+                ("reverse", ntv_list_reverse, 1),
+                ("copy", ntv_list_copy, 1),
+                ("clear", ntv_list_clear, 1)
+            ]
+        );
+        insert_builtin_methods!(
+            methods,
+            "list",
+            [
+                ("index", builtin_list_index),
+                ("count", builtin_list_count),
+                ("remove", builtin_list_remove),
+                ("sort", builtin_list_sort)
+            ]
+        );
+        insert_methods!(
+            methods,
+            "dict",
+            [
+                ("keys", ntv_dict_keys, 1),
+                ("values", ntv_dict_values, 1),
+                ("items", ntv_dict_items, 1),
+                ("copy", ntv_dict_copy, 1),
+                ("clear", ntv_dict_clear, 1)
+            ]
+        );
+        insert_builtin_methods!(
+            methods,
+            "dict",
+            [
+                ("get", builtin_dict_get),
+                ("setdefault", builtin_dict_setdefault),
+                ("pop", builtin_dict_pop),
+                ("update", builtin_dict_update)
+            ]
+        );
+        insert_methods!(
+            methods,
+            "set",
+            [("copy", ntv_set_copy, 1), ("clear", ntv_set_clear, 1)]
+        );
+        insert_builtin_methods!(
+            methods,
+            "set",
+            [
+                ("union", builtin_set_union),
+                ("intersection", builtin_set_intersection),
+                ("difference", builtin_set_difference),
+                ("add", builtin_set_add),
+                ("remove", builtin_set_remove),
+                ("discard", builtin_set_discard)
+            ]
+        );
+        insert_builtin_methods!(
+            methods,
+            "_io.TextIOWrapper",
+            [
+                ("read", builtin_file_read),
+                ("readline", builtin_file_readline),
+                ("readlines", builtin_file_readlines),
+                ("write", builtin_file_write),
+                ("close", builtin_file_close),
+                ("__enter__", builtin_file_enter),
+                ("__exit__", builtin_file_exit)
+            ]
+        );
         let mut evaluator = Evaluator {
-            global_env: global_env,
+            modules: vec![Module::new("__main__", file_name, 0, global_env)],
+            current_module: 0,
+            builtins: GlobalEnv::new(),
+            module_indices: HashMap::new(),
+            search_path: module_search_path(file_name, std::env::var_os("MINIPYTHON_PATH")),
+            prelude_ops: GlobalEnv::new(),
+            ops_overridden: false,
+            methods,
             back_trace: BackTrace::new(),
-            parser: Parser::new(&format!("{}/src/std/init.py", env!("PWD")))?,
+            parser: Parser::from_source(PRELUDE_FILE_NAME, PRELUDE.to_string()),
+            engine,
+            embedded: false,
+            budget: Budget::new(Limits::new()),
+            io: RefCell::new(Io::default()),
+            filesystem: true,
+            written_files: Vec::new(),
+            debugger: None,
         };
+        evaluator.back_trace.push(BackTraceItem {
+            file_name: evaluator.parser.get_file_name().clone(),
+            function_name: "<module>".to_string(),
+            position: Span::default(),
+        });
         evaluator.eval();
+        evaluator.builtins = evaluator.globals().clone();
+        evaluator.prelude_ops = evaluator
+            .builtins
+            .iter()
+            .filter(|(name, _)| name.starts_with("__"))
+            .map(|(name, f)| (name.clone(), f.clone()))
+            .collect();
 
-        evaluator.parser = Parser::new(file_name)?;
+        evaluator.parser = parser;
+        evaluator.back_trace[0].file_name = file_name.to_string();
+        evaluator.set_global(
+            "__name__",
+            py_val::new(py_val::string("__main__".to_string())),
+        );
+        evaluator.set_global(
+            "__file__",
+            py_val::new(py_val::string(file_name.to_string())),
+        );
+        evaluator.module_indices.insert("__main__".to_string(), 0);
+        evaluator.register_module(sys_module());
+        evaluator.register_module(gc_module());
         Ok(evaluator)
     }
 
     pub fn eval(&mut self) {
+        self.exit_on_error(Evaluator::eval_file);
+    }
+
+    fn eval_file(&mut self) {
+        self.budget.start();
         loop {
             let stmt = self.parser.get_next_stmt();
+            if let Some(error) = self.parser.errors().first() {
+                // a broken file is not run further, as CPython reports the first syntax error
+                let traceback = error.format(self.parser.get_file_name());
+                self.raise(format!("SyntaxError: {}", error.message), traceback);
+            }
+            if self.engine == Engine::Vm {
+                if stmt.node == ASTStmtKind::End {
+                    break;
+                }
+                let code = self.compile(&[stmt]);
+                self.run_code(&code, Vec::new());
+                continue;
+            }
+            if let Err(error) = Scope::check_module(std::slice::from_ref(&stmt)) {
+                self.syntax_error(error);
+            }
             match self.eval_stmt(&stmt, &mut None) {
                 StmtResult::Next => (),
-                StmtResult::Continue | StmtResult::Break => panic!("outside loop"),
-                StmtResult::Return(_) => panic!("outside function."),
+                StmtResult::Continue | StmtResult::Break | StmtResult::Return(_) => {
+                    unreachable!("rejected by the scope analysis")
+                }
                 StmtResult::End => break,
             };
         }
     }
 
+    /// where print writes. the process stdout by default
+    pub fn set_stdout(&mut self, stdout: impl Write + 'static) {
+        self.io.get_mut().set_stdout(stdout);
+    }
+
+    /// where tracebacks are written when the evaluator exits. the process stderr by default
+    pub fn set_stderr(&mut self, stderr: impl Write + 'static) {
+        self.io.get_mut().set_stderr(stderr);
+    }
+
+    /// the input of the script. the process stdin by default
+    pub fn set_stdin(&mut self, stdin: impl BufRead + 'static) {
+        self.io.get_mut().set_stdin(stdin);
+    }
+
+    /// whether open() may access files. allowed by default
+    pub fn set_filesystem_access(&mut self, allowed: bool) {
+        self.filesystem = allowed;
+    }
+
+    /// limits of every run(`eval`) from now on
+    pub fn with_limits(mut self, limits: Limits) -> Evaluator {
+        self.set_limits(limits);
+        self
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.budget.limits = limits;
+        self.budget.start();
+    }
+
+    /// a handle to stop the running code from another thread
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.budget.cancel_handle()
+    }
+
+    // count a step of the running code(at span) against the limits
+    #[inline]
+    pub(crate) fn step(&mut self, span: Span) {
+        if self.budget.tick() {
+            // garbage cycles are freed before the memory is looked at
+            gc::collect_if_due();
+            if let Some(message) = self.budget.check() {
+                self.set_span(span);
+                self.error(message);
+            }
+        }
+    }
+
+    // check the limits now, at the end of a run. a trailing expression takes no step
+    pub(crate) fn check_budget(&mut self) {
+        if let Some(message) = self.budget.check() {
+            self.error(message);
+        }
+    }
+
+    // top level statements of the running module, with the engine
+    pub(crate) fn exec_module_body(&mut self, body: &[ASTStmt]) {
+        if self.engine == Engine::Vm {
+            let code = self.compile(body);
+            self.run_code(&code, Vec::new());
+        } else {
+            self.exec_ast(body);
+        }
+    }
+
+    // expression at top level(the tree-walker evaluates it with either engine)
+    pub(crate) fn eval_module_expr(&mut self, expr: &ASTExpr) -> py_val_t {
+        self.eval_expr(expr, &mut None)
+    }
+
+    // back to __main__ after an error unwound the frames.
+    // modules that failed to initialize are forgotten, so importing them runs them again
+    pub(crate) fn recover(&mut self) {
+        self.back_trace.truncate(1);
+        self.current_module = 0;
+        let modules = &self.modules;
+        self.module_indices
+            .retain(|_, index| !modules[*index].initializing);
+    }
+
     /// legacy
-    pub fn eval_ast(&mut self, ast: &AST) {
+    pub fn eval_ast(&mut self, ast: &[ASTStmt]) {
+        self.exit_on_error(|evaluator| evaluator.exec_ast(ast));
+    }
+
+    fn exec_ast(&mut self, ast: &[ASTStmt]) {
+        if let Err(error) = Scope::check_module(ast) {
+            self.syntax_error(error);
+        }
         for stmt in ast {
             match self.eval_stmt(stmt, &mut None) {
                 StmtResult::Next => (),
-                StmtResult::Continue | StmtResult::Break => panic!("outside loop"),
-                StmtResult::Return(_) => panic!("outside function."),
+                StmtResult::Continue | StmtResult::Break | StmtResult::Return(_) => {
+                    unreachable!("rejected by the scope analysis")
+                }
                 StmtResult::End => break,
             };
         }
     }
 
-    fn eval_expr(&mut self, expr: &ASTExpr, local_env: &mut LocalEnv) -> py_val_t {
-        use ASTExpr::*;
-        match expr {
+    /// names of the global variables of `__main__`, sorted. before `eval` these are the builtins
+    pub fn global_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.modules[0].globals.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// value of a global variable of `__main__` (after `eval`)
+    pub fn get_global(&self, name: &str) -> Option<py_val_t> {
+        self.modules[0].globals.get(name).cloned()
+    }
+
+    /// make `name` a builtin function of every module, implemented by f.
+    /// f gets the arguments once their number is checked against arity,
+    /// and returns Err("ValueError: ...") to raise an error
+    pub fn register_native<F>(&mut self, name: &str, arity: impl Into<Arity>, f: F)
+    where
+        F: Fn(Vec<py_val_t>) -> py_result_t + 'static,
+    {
+        let func = py_val::new(py_val::native_func(py_native_func::new(
+            name,
+            arity.into(),
+            f,
+        )));
+        self.modules[0]
+            .globals
+            .insert(name.to_string(), func.clone());
+        self.builtins.insert(name.to_string(), func);
+    }
+
+    // globals of the running module
+    pub(crate) fn globals(&self) -> &GlobalEnv {
+        &self.modules[self.current_module].globals
+    }
+
+    // a global of the running module, or a builtin
+    pub(crate) fn lookup_global(&self, name: &str) -> Option<&py_val_t> {
+        self.globals().get(name).or_else(|| self.builtins.get(name))
+    }
+
+    // spans are set just before the operations that may fail, after the operands are evaluated
+    pub(crate) fn eval_expr(&mut self, expr: &ASTExpr, local_env: &mut LocalEnv) -> py_val_t {
+        use ASTExprKind::*;
+        match &expr.node {
             BoolOp(ASTBoolOp::And, values) => {
                 for val in values {
                     if self.eval_expr(val, local_env).is_false() {
@@ -147,56 +593,72 @@ impl Evaluator {
                 py_val::new(py_val::False)
             }
             BinOp(lhs, op, rhs) => {
-                let f = self
-                    .global_env
-                    .get(operator_to_function_name(&op))
-                    .unwrap()
-                    .clone();
-                self.call_func(f, &vec![lhs, rhs], local_env)
+                let lhs = self.eval_expr(lhs, local_env);
+                let rhs = self.eval_expr(rhs, local_env);
+                self.set_span(expr.span);
+                self.binary_op(op, lhs, rhs)
             }
             UnaryOp(op, operand) => {
-                let f = self
-                    .global_env
-                    .get(unary_operator_to_function_name(&op))
-                    .unwrap()
-                    .clone();
-                self.call_func(f, &vec![operand], local_env)
-            }
-            // IfExp
-            Dict(keys, values) => py_val::new(py_val::dict(
-                keys.into_iter()
-                    .zip(values.into_iter())
-                    .map(|(k, v)| (self.eval_expr(k, local_env), self.eval_expr(v, local_env)))
-                    .collect(),
-            )),
-            Set(elts) => py_val::new(py_val::set(
-                elts.into_iter()
-                    .map(|el| self.eval_expr(el, local_env))
-                    .collect(),
-            )),
+                let operand = self.eval_expr(operand, local_env);
+                self.set_span(expr.span);
+                self.unary_op(op, operand)
+            }
+            IfExp(test, body, orelse) => {
+                if self.eval_expr(test, local_env).is_false() {
+                    self.eval_expr(orelse, local_env)
+                } else {
+                    self.eval_expr(body, local_env)
+                }
+            }
+            Dict(keys, values) => {
+                let mut table = DictTable::new();
+                for (key, value) in keys.iter().zip(values) {
+                    let key = self.eval_expr(key, local_env);
+                    let value = self.eval_expr(value, local_env);
+                    self.set_span(expr.span);
+                    let hash = self.hash_of(&key);
+                    table.insert(hash, key, value);
+                }
+                py_val::new(py_val::dict(table.into()))
+            }
+            Set(elts) => {
+                let mut table = DictTable::new();
+                for elt in elts {
+                    let elt = self.eval_expr(elt, local_env);
+                    self.set_span(expr.span);
+                    let hash = self.hash_of(&elt);
+                    table.insert(hash, elt.clone(), elt);
+                }
+                py_val::new(py_val::set(table.into()))
+            }
             Compare(left, ops, comparators) => {
-                let mut current_left = &**left;
+                let mut current_left = self.eval_expr(left, local_env);
                 for (op, comparator) in ops.iter().zip(comparators.iter()) {
-                    let f = self
-                        .global_env
-                        .get(compare_operator_to_function_name(&op))
-                        .unwrap()
-                        .clone();
-                    if self
-                        .call_func(f, &vec![current_left, comparator], local_env)
-                        .is_false()
-                    {
+                    let right = self.eval_expr(comparator, local_env);
+                    self.set_span(expr.span);
+                    if !self.compare_op(op, current_left, right.clone()) {
                         return py_val::new(py_val::False);
                     }
-                    current_left = comparator;
+                    current_left = right;
                 }
                 py_val::new(py_val::True)
             }
-            Call(func, args) => {
+            Call(func, args, keywords) => {
                 // func will be moved
                 let f = self.eval_expr(func, local_env);
-                let refs = args.iter().collect();
-                self.call_func(f, &refs, local_env)
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval_expr(arg, local_env))
+                    .collect();
+                let kwargs = keywords
+                    .iter()
+                    .map(|keyword| {
+                        let (name, value) = &keyword.node;
+                        (name.clone(), self.eval_expr(value, local_env))
+                    })
+                    .collect();
+                self.set_span(expr.span);
+                self.call_func(f, args, kwargs)
             }
             Constant(ASTConstant::Int(v)) => py_val::new(py_val::int(*v)),
             // v will be moved
@@ -206,40 +668,95 @@ impl Evaluator {
             Constant(ASTConstant::False) => py_val::new(py_val::False),
             // s will be moved
             Constant(ASTConstant::String(s)) => py_val::new(py_val::string(s.clone())),
-            Subscript(value, ASTSlice::Index(index)) => {
-                let f = self.global_env.get("__getitem__").unwrap().clone();
-                self.call_func(f, &vec![value, index], local_env)
-                // Subscript(value, ASTSlice::Slice(lower, upper, step))が未実装
+            Subscript(value, slice) => match &slice.node {
+                ASTSliceKind::Index(index) => {
+                    let f = self.lookup_global("__getitem__").unwrap().clone();
+                    let args = vec![
+                        self.eval_expr(value, local_env),
+                        self.eval_expr(index, local_env),
+                    ];
+                    self.set_span(expr.span);
+                    self.call_func(f, args, Vec::new())
+                }
+                // ASTSliceKind::Slice(lower, upper, step)が未実装
                 // __getitem__の引数を2~4つにする?
+                // (an expression given to eval_module_expr is not analyzed)
+                ASTSliceKind::Slice(..) => {
+                    self.set_span(expr.span);
+                    self.error("SyntaxError: slices are not supported".to_string())
+                }
+            },
+            Attribute(value, attr) => {
+                let receiver = self.eval_expr(value, local_env);
+                self.set_span(expr.span);
+                self.get_attribute(receiver, attr)
+            }
+            Name(name) => {
+                self.set_span(expr.span);
+                self.get_env(local_env, name)
             }
-            Name(name) => self.get_env(local_env, &name),
             List(elts) => py_val::new(py_val::list(
-                elts.into_iter()
+                elts.iter()
                     .map(|el| self.eval_expr(el, local_env))
                     .collect(),
             )),
             Tuple(elts) => py_val::new(py_val::tuple(
-                elts.into_iter()
+                elts.iter()
                     .map(|el| self.eval_expr(el, local_env))
                     .collect(),
             )),
-            _ => unimplemented!(),
         }
     }
 
+    // `target = val`
+    fn assign(&mut self, target: &ASTExpr, val: py_val_t, local_env: &mut LocalEnv) {
+        match &target.node {
+            ASTExprKind::Name(n) => self.set_env(local_env, n, val),
+            ASTExprKind::Subscript(value, slice) => match &slice.node {
+                ASTSliceKind::Index(index) => {
+                    let f = self.lookup_global("__setitem__").unwrap().clone();
+                    let args = vec![
+                        self.eval_expr(value, local_env),
+                        self.eval_expr(index, local_env),
+                        val,
+                    ];
+                    self.set_span(target.span);
+                    self.call_func(f, args, Vec::new());
+                }
+                _ => panic!("can't assign"),
+            },
+            _ => panic!("can't assign"),
+        };
+    }
+
     fn eval_stmt(&mut self, stmt: &ASTStmt, local_env: &mut LocalEnv) -> StmtResult {
-        use ASTStmt::*;
-        match stmt {
-            Init => panic!(), // should not happen
+        use ASTStmtKind::*;
+        self.step(stmt.span);
+        if self.debugger.is_some() {
+            self.debug_stmt(stmt, local_env);
+        }
+        match &stmt.node {
+            Init | Error => panic!(), // should not happen
             End => StmtResult::End,
-            FuncDef(name, arguments, body) => {
+            FuncDef(name, arguments, body, _) => {
                 // each values will be moved
+                let scope = match Scope::analyze(
+                    arguments,
+                    body,
+                    local_env.as_ref().map(|frame| &*frame.scope),
+                ) {
+                    Ok(scope) => scope,
+                    Err(error) => self.syntax_error(error),
+                };
                 let func = py_val::new(py_val::func(py_func {
                     name: name.clone(),
-                    args: arguments.clone(),
+                    args: arguments.iter().map(|arg| arg.node.0.clone()).collect(),
                     stmt: body.clone(),
+                    scope: Rc::new(scope),
+                    file_name: self.current_file_name().to_string(),
+                    module: self.current_module,
                 }));
-                self.set_env(local_env, &name, func);
+                self.set_env(local_env, name, func);
                 StmtResult::Next
             }
             Return(value) => StmtResult::Return(if value.is_none() {
@@ -247,18 +764,19 @@ impl Evaluator {
             } else {
                 self.eval_expr(value.as_ref().unwrap(), local_env)
             }),
-            Delete(_targets) => {
-                // 変数に適用するとその変数が環境から消える...?
-                unimplemented!();
-            }
+            Delete(_) => unreachable!("rejected by the scope analysis"),
             Assign(targets, value) => {
                 let val = self.eval_expr(value, local_env);
                 for target in targets {
-                    match target {
-                        ASTExpr::Name(n) => self.set_env(local_env, &n, val.clone()),
-                        ASTExpr::Subscript(_val, ASTSlice::Index(_i)) => unimplemented!(),
-                        _ => panic!("can't assign"),
-                    };
+                    self.assign(target, val.clone(), local_env);
+                }
+                StmtResult::Next
+            }
+            // 型注釈は実行時には無視する
+            AnnAssign(target, _, value) => {
+                if let Some(value) = value {
+                    let val = self.eval_expr(value, local_env);
+                    self.assign(target, val, local_env);
                 }
                 StmtResult::Next
             }
@@ -266,39 +784,20 @@ impl Evaluator {
                 let iterator = self.eval_expr(iter, local_env);
                 let body_ref = body.iter().collect();
                 // とりあえずアンパック代入はないことにする
-                let target = match target {
-                    ASTExpr::Name(name) => name,
+                let target = match &target.node {
+                    ASTExprKind::Name(name) => name,
                     _ => panic!(),
                 };
-                match *iterator {
-                    py_val::list(ref elts) | py_val::tuple(ref elts) => {
-                        for elt in elts.iter() {
-                            self.set_env(local_env, &target, elt.clone());
-                            match self.eval_stmt_vec(&body_ref, local_env) {
-                                StmtResult::Next | StmtResult::Continue => (),
-                                StmtResult::Break => break,
-                                r @ StmtResult::Return(_) | r @ StmtResult::End => return r,
-                            };
-                        }
-                        StmtResult::Next
-                    }
-                    py_val::string(ref s) => {
-                        for c in s.chars() {
-                            self.set_env(
-                                local_env,
-                                &target,
-                                py_val::new(py_val::string(c.to_string())),
-                            );
-                            match self.eval_stmt_vec(&body_ref, local_env) {
-                                StmtResult::Next | StmtResult::Continue => (),
-                                StmtResult::Break => break,
-                                r @ StmtResult::Return(_) | r @ StmtResult::End => return r,
-                            };
-                        }
-                        StmtResult::Next
-                    }
-                    _ => panic!("cannot iterate over non iterable"),
+                self.set_span(iter.span);
+                for elt in self.iter_values(&iterator) {
+                    self.set_env(local_env, target, elt);
+                    match self.eval_stmt_vec(&body_ref, local_env) {
+                        StmtResult::Next | StmtResult::Continue => (),
+                        StmtResult::Break => break,
+                        r @ StmtResult::Return(_) | r @ StmtResult::End => return r,
+                    };
                 }
+                StmtResult::Next
             }
             While(test, body) => {
                 let body_ref = body.iter().collect();
@@ -311,6 +810,25 @@ impl Evaluator {
                 }
                 StmtResult::Next
             }
+            With(items, body) => self.with_exits(|evaluator, exits| {
+                for item in items {
+                    let (context_expr, optional_vars) = &item.node;
+                    let manager = evaluator.eval_expr(context_expr, local_env);
+                    evaluator.set_span(item.span);
+                    let (exit, value) = evaluator.enter_context(manager);
+                    exits.push(exit);
+                    if let Some(target) = optional_vars {
+                        evaluator.assign(target, value, local_env);
+                    }
+                }
+                let body_ref = body.iter().collect();
+                let res = evaluator.eval_stmt_vec(&body_ref, local_env);
+                // break, continue and return leave the block too
+                while let Some(exit) = exits.pop() {
+                    evaluator.exit_context(exit);
+                }
+                res
+            }),
             If(test, body, orelse) => {
                 if self.eval_expr(test, local_env).is_true() {
                     let body_ref = body.iter().collect();
@@ -320,17 +838,34 @@ impl Evaluator {
                     self.eval_stmt_vec(&orelse_ref, local_env)
                 }
             }
-            Global(names) => {
-                if let Some(local) = local_env {
-                    for name in names.iter() {
-                        if local.get(name).is_none() {
-                            local.insert(name.to_string(), None);
-                        } else {
-                            panic!("name {} is assigned before global declaration", name);
+            // 関数定義時のスコープ解析で処理済み
+            Global(_) => StmtResult::Next,
+            Import(names) => {
+                for alias in names {
+                    let (name, asname) = &alias.node;
+                    self.set_span(alias.span);
+                    let module = self.import_module(name);
+                    match asname {
+                        Some(asname) => self.set_env(local_env, asname, module),
+                        // `import a.b` binds the package a, which is imported by now
+                        None => {
+                            let top = name.split('.').next().unwrap();
+                            let module = self.import_module(top);
+                            self.set_env(local_env, top, module);
                         }
                     }
                 }
-                // 大域環境では特に何もしない
+                StmtResult::Next
+            }
+            ImportFrom(module, names) => {
+                self.set_span(stmt.span);
+                let module = self.import_module(module);
+                for alias in names {
+                    let (name, asname) = &alias.node;
+                    self.set_span(alias.span);
+                    let value = self.import_from(&module, name);
+                    self.set_env(local_env, asname.as_ref().unwrap_or(name), value);
+                }
                 StmtResult::Next
             }
             Expr(expr) => {
@@ -345,27 +880,34 @@ impl Evaluator {
 
     fn eval_stmt_vec(&mut self, body: &Vec<&ASTStmt>, local_env: &mut LocalEnv) -> StmtResult {
         for stmt in body {
-            match self.eval_stmt(stmt.clone(), local_env) {
+            match self.eval_stmt(stmt, local_env) {
                 StmtResult::Next => (),
-                r @ _ => return r,
+                r => return r,
             };
         }
         StmtResult::Next
     }
 
-    fn call_func(
+    pub(crate) fn call_func(
         &mut self,
         func: py_val_t,
-        args: &Vec<&ASTExpr>,
-        local_env: &mut LocalEnv,
+        args: Vec<py_val_t>,
+        kwargs: py_kwargs_t,
     ) -> py_val_t {
-        match *func {
+        match *func.view() {
+            py_val::method(ref method) => {
+                let mut args = args;
+                args.insert(0, method.receiver.clone());
+                self.call_func(method.func.clone(), args, kwargs)
+            }
             py_val::native_func(ref native_func) => {
-                let args: Vec<py_val_t> = args
-                    .into_iter()
-                    .map(|arg| self.eval_expr(arg, local_env))
-                    .collect();
-                if native_func.arity != args.len() {
+                if !kwargs.is_empty() {
+                    self.error(format!(
+                        "TypeError: {}() takes no keyword arguments",
+                        native_func.name
+                    ));
+                }
+                if !native_func.arity.accepts(args.len()) {
                     self.error(format!(
                         "TypeError: {}() takes {} arguments but {} were given",
                         native_func.name,
@@ -373,115 +915,403 @@ impl Evaluator {
                         args.len()
                     ));
                 }
-                self.back_trace.push(BackTraceItem {
-                    file_name: self.parser.get_file_name().clone(),
-                    function_name: native_func.name.clone(),
-                    position: self.parser.get_current_line(),
-                });
-                let res = (native_func.body)(args);
-                self.back_trace.pop();
-                res
+                // built-in functions have no frame, errors are reported at the call site
+                match (native_func.body)(args) {
+                    Ok(v) => v,
+                    Err(message) => self.error(message),
+                }
             }
+            py_val::builtin_func(ref builtin_func) => (builtin_func.body)(self, args, kwargs),
             py_val::func(ref py_func) => {
-                let args: Vec<py_val_t> = args
-                    .into_iter()
-                    .map(|arg| self.eval_expr(arg, local_env))
-                    .collect();
-                if py_func.args.len() != args.len() {
-                    self.error(format!(
-                        "TypeError: {}() takes {} arguments but {} were given",
-                        py_func.name,
-                        py_func.args.len(),
-                        args.len()
-                    ));
-                }
+                let args = self.bind_args(&py_func.name, &py_func.args, args, kwargs);
 
                 // prepare for function call
+                self.check_depth();
                 self.back_trace.push(BackTraceItem {
-                    file_name: self.parser.get_file_name().clone(),
+                    file_name: py_func.file_name.clone(),
                     function_name: py_func.name.clone(),
-                    position: self.parser.get_current_line(),
+                    position: Span::default(),
+                });
+                let caller_module = std::mem::replace(&mut self.current_module, py_func.module);
+                let mut slots: Vec<Option<py_val_t>> = args.into_iter().map(Some).collect();
+                slots.resize(py_func.scope.locals.len(), None);
+                let mut new_local_env: LocalEnv = Some(Frame {
+                    scope: Rc::clone(&py_func.scope),
+                    slots,
                 });
-                let mut new_local_env: LocalEnv = Some(
-                    py_func
-                        .args
-                        .iter()
-                        .zip(args.iter())
-                        .map(|(v, r)| (v.clone(), Some(r.clone())))
-                        .collect(),
-                );
                 // call
                 let refs = py_func.stmt.iter().collect();
-                let res = self.eval_stmt_vec(&refs, &mut new_local_env);
+                let res = grow_stack(|| self.eval_stmt_vec(&refs, &mut new_local_env));
                 // return from function
                 self.back_trace.pop();
+                self.current_module = caller_module;
 
                 match res {
                     StmtResult::Continue | StmtResult::Break => {
-                        panic!("continue/break outside loop");
+                        unreachable!("rejected by the scope analysis")
                     }
                     StmtResult::Return(v) => v,
                     StmtResult::Next | StmtResult::End => py_val::new(py_val::None),
                 }
             }
-            _ => panic!("this should not occur..."),
+            py_val::code_func(ref code_func) => {
+                let code = &code_func.code;
+                let args = self.bind_args(&code.name, &code.arg_names, args, kwargs);
+                self.check_depth();
+                self.back_trace.push(BackTraceItem {
+                    file_name: code_func.file_name.clone(),
+                    function_name: code.name.clone(),
+                    position: Span::default(),
+                });
+                let mut locals: Vec<Option<py_val_t>> = args.into_iter().map(Some).collect();
+                locals.resize(code.local_names.len(), None);
+                let caller_module = std::mem::replace(&mut self.current_module, code_func.module);
+                let res = grow_stack(|| self.run_code(code, locals));
+                self.back_trace.pop();
+                self.current_module = caller_module;
+                res
+            }
+            _ => self.error(format!(
+                "TypeError: '{}' object is not callable",
+                func.type_name()
+            )),
         }
     }
 
-    fn set_env(&mut self, local_env: &mut LocalEnv, key: &str, value: py_val_t) {
-        if let Some(local) = local_env {
-            if let Some(v) = local.get(key) {
-                if v.is_none() {
-                    // `global`
-                    self.global_env.insert(key.to_string(), value);
-                    return;
+    // before a python function pushes its frame
+    fn check_depth(&self) {
+        if self.budget.depth_exceeded(self.back_trace.len() + 1) {
+            self.error("RecursionError: maximum recursion depth exceeded".to_string());
+        }
+    }
+
+    // call the global function `name`(`__getitem__`, `__add__`, ...), which may be overridden
+    pub(crate) fn call_global(&mut self, name: &str, args: Vec<py_val_t>) -> py_val_t {
+        let f = self.lookup_global(name).unwrap().clone();
+        self.call_func(f, args, Vec::new())
+    }
+
+    // hash of a dict key or set element through __hash__, so user overrides are honoured
+    pub(crate) fn hash_of(&mut self, key: &py_val_t) -> i64 {
+        let f = self.lookup_global("__hash__").unwrap().clone();
+        let hash = self.call_func(f, vec![key.clone()], Vec::new());
+        match *hash.view() {
+            py_val::int(i) => i,
+            _ => self.error("TypeError: __hash__ method should return an integer".to_string()),
+        }
+    }
+
+    // bound method looked up from the method table of the receiver's type,
+    // or a global of a module
+    pub(crate) fn get_attribute(&self, receiver: py_val_t, attr: &str) -> py_val_t {
+        if let Some(py_val::module(module)) = receiver.heap() {
+            return match self.modules[module.index].globals.get(attr) {
+                Some(value) => value.clone(),
+                None => self.error(format!(
+                    "AttributeError: module '{}' has no attribute '{}'",
+                    module.name, attr
+                )),
+            };
+        }
+        match self
+            .methods
+            .get(receiver.type_name())
+            .and_then(|methods| methods.get(attr))
+        {
+            Some(func) => py_val::new(py_val::method(py_method {
+                receiver,
+                func: func.clone(),
+            })),
+            None => self.error(format!(
+                "AttributeError: '{}' object has no attribute '{}'",
+                receiver.type_name(),
+                attr
+            )),
+        }
+    }
+
+    // `with manager:` calls manager.__enter__() and returns its value with the bound __exit__
+    pub(crate) fn enter_context(&mut self, manager: py_val_t) -> (py_val_t, py_val_t) {
+        let methods = self.methods.get(manager.type_name());
+        let method = |name: &str| methods.and_then(|methods| methods.get(name)).cloned();
+        let (enter, exit) = match (method("__enter__"), method("__exit__")) {
+            (Some(enter), Some(exit)) => (enter, exit),
+            _ => self.error(format!(
+                "TypeError: '{}' object does not support the context manager protocol",
+                manager.type_name()
+            )),
+        };
+        let exit = py_val::new(py_val::method(py_method {
+            receiver: manager.clone(),
+            func: exit,
+        }));
+        let value = self.call_func(enter, vec![manager], Vec::new());
+        (exit, value)
+    }
+
+    // exit(None, None, None) of enter_context. it is given no exception even when an error
+    // leaves the block, and cannot suppress it
+    pub(crate) fn exit_context(&mut self, exit: py_val_t) {
+        let none = py_val::new(py_val::None);
+        self.call_func(exit, vec![none.clone(), none.clone(), none], Vec::new());
+    }
+
+    // run f, which pushes the exits of the with blocks it enters and pops those it leaves.
+    // if an error unwinds through f, the exits still pushed are called, innermost first, in the
+    // frame f was called in, and the error goes on
+    pub(crate) fn with_exits<T>(
+        &mut self,
+        f: impl FnOnce(&mut Evaluator, &mut Vec<py_val_t>) -> T,
+    ) -> T {
+        let mut exits = Vec::new();
+        let depth = self.back_trace.len();
+        let module = self.current_module;
+        match catch_unwind(AssertUnwindSafe(|| f(self, &mut exits))) {
+            Ok(res) => res,
+            Err(payload) => {
+                self.back_trace.truncate(depth);
+                self.current_module = module;
+                while let Some(exit) = exits.pop() {
+                    self.exit_context(exit);
                 }
+                resume_unwind(payload)
             }
-            local.insert(key.to_string(), Some(value));
-        } else {
+        }
+    }
+
+    // match positional and keyword arguments to the parameters of a function
+    fn bind_args(
+        &self,
+        func_name: &str,
+        params: &[String],
+        args: Vec<py_val_t>,
+        kwargs: py_kwargs_t,
+    ) -> Vec<py_val_t> {
+        if params.len() < args.len() {
+            self.error(format!(
+                "TypeError: {}() takes {} arguments but {} were given",
+                func_name,
+                params.len(),
+                args.len()
+            ));
+        }
+        let mut bound: Vec<Option<py_val_t>> = args.into_iter().map(Some).collect();
+        bound.resize(params.len(), None);
+        for (name, value) in kwargs {
+            match params.iter().position(|arg| *arg == name) {
+                Some(i) if bound[i].is_none() => bound[i] = Some(value),
+                Some(_) => self.error(format!(
+                    "TypeError: {}() got multiple values for argument '{}'",
+                    func_name, name
+                )),
+                None => self.error(format!(
+                    "TypeError: {}() got an unexpected keyword argument '{}'",
+                    func_name, name
+                )),
+            }
+        }
+        bound
+            .into_iter()
+            .zip(params.iter())
+            .map(|(value, name)| match value {
+                Some(v) => v,
+                None => self.error(format!(
+                    "TypeError: {}() missing required argument: '{}'",
+                    func_name, name
+                )),
+            })
+            .collect()
+    }
+
+    // elements produced by iterating over val(`for x in val`)
+    pub(crate) fn iter_values(&self, val: &py_val_t) -> Vec<py_val_t> {
+        if let Some(py_val::file(file)) = val.heap() {
+            return file_lines(self, file);
+        }
+        match val.iter_values() {
+            Some(values) => values,
+            None => self.error(format!(
+                "TypeError: '{}' object is not iterable",
+                val.type_name()
+            )),
+        }
+    }
+
+    fn set_env(&mut self, local_env: &mut LocalEnv, key: &str, value: py_val_t) {
+        match local_env {
+            Some(frame) => match frame.scope.kind(key) {
+                NameKind::Local(slot) => frame.slots[slot] = Some(value),
+                // `global`
+                _ => self.set_global(key, value),
+            },
             // Top level
-            self.global_env.insert(key.to_string(), value);
+            None => self.set_global(key, value),
+        }
+    }
+
+    pub(crate) fn set_global(&mut self, key: &str, value: py_val_t) {
+        if key.starts_with("__") && self.prelude_ops.contains_key(key) {
+            self.ops_overridden = true;
+        }
+        let globals = &mut self.modules[self.current_module].globals;
+        match globals.get_mut(key) {
+            Some(v) => *v = value,
+            None => {
+                globals.insert(key.to_string(), value);
+            }
         }
     }
 
     fn get_env(&self, local_env: &LocalEnv, key: &str) -> py_val_t {
-        if let Some(local) = local_env {
-            if let Some(val) = local.get(key) {
-                match val {
-                    Some(v) => v.clone(),                              // local variable
-                    None => self.global_env.get(key).unwrap().clone(), // explicit global variable
+        if let Some(frame) = local_env {
+            if let NameKind::Local(slot) = frame.scope.kind(key) {
+                return match frame.slots[slot] {
+                    Some(ref v) => v.clone(), // local variable
+                    None => self.error(format!(
+                        "UnboundLocalError: local variable '{}' referenced before assignment",
+                        key
+                    )),
+                };
+            }
+        }
+        // explicit or implicit global variable(free variables too, as there are no closures)
+        match self.lookup_global(key) {
+            Some(v) => v.clone(),
+            None => self.error(format!("NameError: {} is not defined", key)),
+        }
+    }
+
+    // frame of a module body or a function, for the traceback
+    pub(crate) fn push_frame(&mut self, file_name: &str, function_name: &str) {
+        self.back_trace.push(BackTraceItem {
+            file_name: file_name.to_string(),
+            function_name: function_name.to_string(),
+            position: Span::default(),
+        });
+    }
+
+    pub(crate) fn pop_frame(&mut self) {
+        self.back_trace.pop();
+    }
+
+    // the span being evaluated in the innermost frame
+    pub(crate) fn set_span(&mut self, span: Span) {
+        if let Some(frame) = self.back_trace.last_mut() {
+            frame.position = span;
+        }
+    }
+
+    pub(crate) fn current_file_name(&self) -> &str {
+        match self.back_trace.last() {
+            Some(frame) => &frame.file_name,
+            None => self.parser.get_file_name(),
+        }
+    }
+
+    // "Traceback (most recent call last):" followed by the frames and the message.
+    // a frame repeated more than 3 times in a row is shown 3 times, as CPython does
+    pub(crate) fn format_traceback(&self, message: &str) -> String {
+        let mut res = "Traceback (most recent call last):\n".to_string();
+        let mut last = String::new();
+        let mut repeated = 0;
+        for frame in self.back_trace.iter() {
+            let formatted =
+                format_frame(&frame.file_name, frame.position, Some(&frame.function_name));
+            if formatted == last {
+                repeated += 1;
+                if repeated >= 3 {
+                    continue;
                 }
             } else {
-                match self.global_env.get(key) {
-                    Some(v) => v.clone(), // implicit global variable
-                    None => self.error(format!("NameError: {} is not defined", key)),
-                }
+                push_repeated(&mut res, repeated);
+                repeated = 0;
             }
-        } else {
-            match self.global_env.get(key) {
-                Some(v) => v.clone(), // implicit global variable
-                None => self.error(format!("NameError: {} is not defined", key)),
+            res.push_str(&formatted);
+            last = formatted;
+        }
+        push_repeated(&mut res, repeated);
+        res.push_str(message);
+        res.push('\n');
+        res
+    }
+
+    // module level statements for the VM
+    fn compile(&mut self, body: &[ASTStmt]) -> Code {
+        match compile_module(body) {
+            Ok(code) => code,
+            Err(error) => self.syntax_error(error),
+        }
+    }
+
+    // a syntax error found after parsing, at its span
+    fn syntax_error(&mut self, error: SyntaxError) -> ! {
+        self.set_span(error.span);
+        self.error(format!("SyntaxError: {}", error.message))
+    }
+
+    pub(crate) fn error(&self, message: String) -> ! {
+        let traceback = self.format_traceback(&message);
+        self.raise(message, traceback)
+    }
+
+    // the error unwinds as a PyError, through the with blocks, to the Interpreter or exit_on_error
+    fn raise(&self, message: String, traceback: String) -> ! {
+        // resume_unwind doesn't run the panic hook, nothing is printed
+        resume_unwind(Box::new(PyError { message, traceback }));
+    }
+
+    // run f. an error ends the program unless the evaluator is embedded
+    fn exit_on_error(&mut self, f: impl FnOnce(&mut Evaluator)) {
+        if self.embedded {
+            return f(self);
+        }
+        if let Err(payload) = catch_unwind(AssertUnwindSafe(|| f(self))) {
+            match payload.downcast::<PyError>() {
+                Ok(error) => self.exit_with(&error.traceback),
+                Err(payload) => resume_unwind(payload),
             }
         }
     }
 
-    fn error(&self, message: String) -> ! {
-        eprintln!("TraceBack");
-        for trace in self.back_trace.iter() {
-            eprintln!("File {}, line {}, in {}",
-                trace.file_name,
-                trace.position,
-                trace.function_name
-            );
+    // report the error on stderr and exit, after what the script printed and wrote.
+    // process::exit drops nothing, so the buffers of the open files are flushed here
+    fn exit_with(&self, message: &str) -> ! {
+        for file in self.written_files.iter().filter_map(Weak::upgrade) {
+            if let FileHandle::Writer(writer) = &mut *file.borrow_mut() {
+                let _ = writer.flush();
+            }
+        }
+        match self.io.try_borrow_mut() {
+            Ok(mut io) => {
+                let _ = io.stdout().flush();
+                let _ = write!(io.stderr(), "{}", message);
+                let _ = io.stderr().flush();
+            }
+            Err(_) => eprint!("{}", message),
         }
-        eprintln!(
-            "File {}, line {}\n{}\n{}\n{}",
-            self.parser.get_file_name(),
-            self.parser.get_current_line(),
-            self.parser.get_current_line_content(),
-            " ".repeat(self.parser.get_current_line_content().len()),
-            message
-        );
         std::process::exit(1);
     }
 }
+
+fn push_repeated(traceback: &mut String, repeated: usize) {
+    if repeated >= 3 {
+        traceback.push_str(&format!(
+            "  [Previous line repeated {} more times]\n",
+            repeated - 2
+        ));
+    }
+}
+
+// the native stack left when a python function is called. below it, the call runs on a new
+// segment, so the call depth is limited by Limits rather than by the stack of the thread
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_SEGMENT_SIZE: usize = 4 * 1024 * 1024;
+
+fn grow_stack<R>(f: impl FnOnce() -> R) -> R {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, f)
+}
+
+// the prelude(builtins written in Python), run by every evaluator before the file. it is
+// built into the binary; the name is its path in the source tree, for tracebacks
+const PRELUDE: &str = include_str!("../std/init.py");
+pub(crate) const PRELUDE_FILE_NAME: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/std/init.py");
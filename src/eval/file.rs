@@ -0,0 +1,348 @@
+//! # file
+//! ファイルオブジェクトと入力
+//! sys.stdin and input() read the input of the evaluator(Evaluator::set_stdin).
+//! open() opens files as text in UTF-8, if the evaluator has filesystem access.
+//! iterating over a file object reads the rest of its lines
+
+use std::cell::RefCell;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::rc::Rc;
+
+use crate::eval::builtins::{expect_int, parse_args};
+use crate::eval::evaluator::Evaluator;
+use crate::eval::io::{read_chars, read_line};
+use crate::eval::native_module::NativeModule;
+use crate::eval::types::*;
+
+// the sys module. only stdin for now
+pub(crate) fn sys_module() -> NativeModule {
+    let mut sys = NativeModule::new("sys");
+    let stdin = py_file {
+        name: "<stdin>".to_string(),
+        mode: "r".to_string(),
+        stream: py_stream::Stdin,
+    };
+    sys.add_value("stdin", py_val::new(py_val::file(stdin)));
+    sys
+}
+
+// a file opened by open(). it is either read or written, as the modes with '+' are not supported
+#[derive(Debug)]
+pub enum FileHandle {
+    Reader(BufReader<File>),
+    Writer(BufWriter<File>),
+    Closed,
+}
+
+const CLOSED: &str = "ValueError: I/O operation on closed file.";
+
+// args[0] is the receiver of the file methods
+fn file_of(args: &[py_val_t]) -> &py_file {
+    match args[0].heap() {
+        Some(py_val::file(file)) => file,
+        _ => panic!(),
+    }
+}
+
+// run f on the stream. a failure is an OSError
+fn with_stream<T, F>(evaluator: &Evaluator, stream: &py_stream, f: F) -> T
+where
+    F: FnOnce(&mut dyn BufRead) -> io::Result<T>,
+{
+    let res = match stream {
+        py_stream::Stdin => f(evaluator.io.borrow_mut().stdin()),
+        py_stream::File(handle) => match &mut *handle.borrow_mut() {
+            FileHandle::Reader(reader) => f(reader),
+            FileHandle::Writer(_) => {
+                evaluator.error("io.UnsupportedOperation: not readable".to_string())
+            }
+            FileHandle::Closed => evaluator.error(CLOSED.to_string()),
+        },
+    };
+    match res {
+        Ok(value) => value,
+        Err(error) => evaluator.error(format!("OSError: {}", error)),
+    }
+}
+
+// the remaining lines, for `for line in f` and f.readlines()
+pub(crate) fn file_lines(evaluator: &Evaluator, file: &py_file) -> Vec<py_val_t> {
+    with_stream(evaluator, &file.stream, |stream| {
+        let mut lines = Vec::new();
+        loop {
+            let line = read_line(stream)?;
+            if line.is_empty() {
+                return Ok(lines);
+            }
+            lines.push(py_val::new(py_val::string(line)));
+        }
+    })
+}
+
+// OSError of CPython for the failure of an operation on path
+// (`FileNotFoundError: [Errno 2] No such file or directory: 'a.txt'`)
+fn os_error(error: &io::Error, path: &str) -> String {
+    let errno = match error.raw_os_error() {
+        Some(errno) => errno,
+        None => return format!("OSError: {}", error),
+    };
+    let class = match error.kind() {
+        io::ErrorKind::NotFound => "FileNotFoundError",
+        io::ErrorKind::PermissionDenied => "PermissionError",
+        io::ErrorKind::AlreadyExists => "FileExistsError",
+        _ if errno == 21 => "IsADirectoryError",
+        _ => "OSError",
+    };
+    // io::Error shows "No such file or directory (os error 2)"
+    let message = error.to_string();
+    let message = match message.rfind(" (os error") {
+        Some(end) => &message[..end],
+        None => &message,
+    };
+    format!("{}: [Errno {}] {}: '{}'", class, errno, message, path)
+}
+
+// open(file, mode="r", encoding=None)
+// modes are "r", "w", "a" and "x", with an optional "t". files are always UTF-8 text
+pub fn builtin_open(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    let kw = parse_args(
+        evaluator,
+        "open",
+        &args,
+        kwargs,
+        1,
+        Some(2),
+        &["mode", "encoding"],
+    );
+    if !evaluator.filesystem {
+        evaluator.error("PermissionError: filesystem access is disabled".to_string());
+    }
+    let path = match args[0].as_str() {
+        Some(path) => path.to_string(),
+        None => evaluator.error(format!(
+            "TypeError: expected str for file path, got '{}'",
+            args[0].type_name()
+        )),
+    };
+    let mode = match args.get(1).cloned().or_else(|| kw[0].clone()) {
+        Some(mode) => match mode.as_str() {
+            Some(mode) => mode.to_string(),
+            None => evaluator.error(format!(
+                "TypeError: open() argument 'mode' must be str, not {}",
+                mode.type_name()
+            )),
+        },
+        None => "r".to_string(),
+    };
+    if let Some(encoding) = kw[1].as_ref().filter(|encoding| !encoding.is_none()) {
+        let name = encoding
+            .as_str()
+            .map(|name| name.to_lowercase().replace('_', "-"));
+        if name.as_deref() != Some("utf-8") && name.as_deref() != Some("utf8") {
+            evaluator.error(format!(
+                "LookupError: unsupported encoding: {}",
+                encoding.repr()
+            ));
+        }
+    }
+    if mode.contains('b') {
+        evaluator.error("ValueError: binary mode is not supported".to_string());
+    }
+    let mut options = OpenOptions::new();
+    match mode.strip_suffix('t').unwrap_or(&mode) {
+        "r" => options.read(true),
+        "w" => options.write(true).create(true).truncate(true),
+        "a" => options.append(true).create(true),
+        "x" => options.write(true).create_new(true),
+        _ => evaluator.error(format!("ValueError: invalid mode: '{}'", mode)),
+    };
+    let file = match options.open(&path) {
+        Ok(file) => file,
+        Err(error) => evaluator.error(os_error(&error, &path)),
+    };
+    // a directory can be opened for reading, but not read
+    if file.metadata().is_ok_and(|metadata| metadata.is_dir()) {
+        evaluator.error(format!(
+            "IsADirectoryError: [Errno 21] Is a directory: '{}'",
+            path
+        ));
+    }
+    let handle = if mode.starts_with('r') {
+        Rc::new(RefCell::new(FileHandle::Reader(BufReader::new(file))))
+    } else {
+        let handle = Rc::new(RefCell::new(FileHandle::Writer(BufWriter::new(file))));
+        let written_files = &mut evaluator.written_files;
+        written_files.retain(|file| file.strong_count() > 0);
+        written_files.push(Rc::downgrade(&handle));
+        handle
+    };
+    py_val::new(py_val::file(py_file {
+        name: path,
+        mode,
+        stream: py_stream::File(handle),
+    }))
+}
+
+// input(prompt="")
+// the prompt is written to stdout without a newline, and the line read has none
+pub fn builtin_input(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "input", &args, kwargs, 0, Some(1), &[]);
+    if let Some(prompt) = args.first() {
+        let prompt = match prompt.as_str() {
+            Some(s) => s.to_string(),
+            None => prompt.repr(),
+        };
+        let stdout = evaluator.io.get_mut().stdout();
+        if let Err(error) = write!(stdout, "{}", prompt).and_then(|_| stdout.flush()) {
+            evaluator.error(format!("OSError: {}", error));
+        }
+    }
+    let mut line = with_stream(evaluator, &py_stream::Stdin, read_line);
+    if line.is_empty() {
+        evaluator.error("EOFError: EOF when reading a line".to_string());
+    }
+    // "\r\n" too, as the universal newlines of CPython
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    py_val::new(py_val::string(line))
+}
+
+// f.read(size=-1)
+// size is in characters. the rest of the stream if it is negative or None
+pub fn builtin_file_read(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "read", &args, kwargs, 1, Some(2), &[]);
+    let size = match args.get(1) {
+        Some(size) if !size.is_none() => {
+            let size = expect_int(evaluator, "read", size);
+            if size < 0 {
+                None
+            } else {
+                Some(size as usize)
+            }
+        }
+        _ => None,
+    };
+    let stream = &file_of(&args).stream;
+    let s = with_stream(evaluator, stream, |stream| read_chars(stream, size));
+    py_val::new(py_val::string(s))
+}
+
+// f.readline()
+// keeps the newline. "" at the end of the file
+pub fn builtin_file_readline(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "readline", &args, kwargs, 1, Some(1), &[]);
+    let line = with_stream(evaluator, &file_of(&args).stream, read_line);
+    py_val::new(py_val::string(line))
+}
+
+// f.readlines()
+pub fn builtin_file_readlines(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "readlines", &args, kwargs, 1, Some(1), &[]);
+    py_val::new_list(file_lines(evaluator, file_of(&args)))
+}
+
+// f.write(s)
+// returns the number of characters written
+pub fn builtin_file_write(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "write", &args, kwargs, 2, Some(2), &[]);
+    let s = match args[1].as_str() {
+        Some(s) => s,
+        None => evaluator.error(format!(
+            "TypeError: write() argument must be str, not {}",
+            args[1].type_name()
+        )),
+    };
+    let handle = match &file_of(&args).stream {
+        py_stream::File(handle) => handle,
+        py_stream::Stdin => evaluator.error("io.UnsupportedOperation: not writable".to_string()),
+    };
+    let res = match &mut *handle.borrow_mut() {
+        FileHandle::Writer(writer) => writer.write_all(s.as_bytes()),
+        FileHandle::Reader(_) => {
+            evaluator.error("io.UnsupportedOperation: not writable".to_string())
+        }
+        FileHandle::Closed => evaluator.error(CLOSED.to_string()),
+    };
+    if let Err(error) = res {
+        evaluator.error(format!("OSError: {}", error));
+    }
+    py_val::new(py_val::int(s.chars().count() as i64))
+}
+
+// flush and close the file. closing it again does nothing, and sys.stdin is kept open
+fn close(evaluator: &Evaluator, file: &py_file) {
+    if let py_stream::File(handle) = &file.stream {
+        let handle = std::mem::replace(&mut *handle.borrow_mut(), FileHandle::Closed);
+        if let FileHandle::Writer(mut writer) = handle {
+            if let Err(error) = writer.flush() {
+                evaluator.error(format!("OSError: {}", error));
+            }
+        }
+    }
+}
+
+// f.close()
+pub fn builtin_file_close(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "close", &args, kwargs, 1, Some(1), &[]);
+    close(evaluator, file_of(&args));
+    py_val::new(py_val::None)
+}
+
+// `with open(path) as f:` binds the file itself
+pub fn builtin_file_enter(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "__enter__", &args, kwargs, 1, Some(1), &[]);
+    if let py_stream::File(handle) = &file_of(&args).stream {
+        if let FileHandle::Closed = *handle.borrow() {
+            evaluator.error(CLOSED.to_string());
+        }
+    }
+    args[0].clone()
+}
+
+// f.__exit__(exc_type, exc_value, traceback) closes the file
+pub fn builtin_file_exit(
+    evaluator: &mut Evaluator,
+    args: Vec<py_val_t>,
+    kwargs: py_kwargs_t,
+) -> py_val_t {
+    parse_args(evaluator, "__exit__", &args, kwargs, 1, None, &[]);
+    close(evaluator, file_of(&args));
+    py_val::new(py_val::None)
+}
@@ -0,0 +1,209 @@
+//! # gc
+//! 循環参照のガベージコレクション
+//! values are reference counted, so containers referring to each other(`a.append(a)`)
+//! are never freed by themselves. the containers alive on this thread are tracked,
+//! and a collection finds those referred to only from inside the tracked ones(trial deletion
+//! as in CPython): the references from the other tracked containers are subtracted from
+//! the reference count of each, and what is left with none, and is not reachable from
+//! one that has some, is garbage. the garbage lists/dicts/sets are cleared, which breaks
+//! the cycles and lets the reference counts free everything.
+//! functions refer to their globals by the module index, so they are never part of a cycle
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::mem::ManuallyDrop;
+
+use crate::eval::dict::DictTable;
+use crate::eval::native_module::NativeModule;
+use crate::eval::types::*;
+
+// collect after this many containers more than were freed, as the first generation of CPython
+const DEFAULT_THRESHOLD: usize = 700;
+
+thread_local! {
+    // addresses of the tracked containers
+    static TRACKED: RefCell<HashSet<usize>> = RefCell::new(HashSet::new());
+    // containers made minus those freed since the last collection
+    static COUNT: Cell<usize> = const { Cell::new(0) };
+    static THRESHOLD: Cell<usize> = const { Cell::new(DEFAULT_THRESHOLD) };
+    static ENABLED: Cell<bool> = const { Cell::new(true) };
+}
+
+// values which may refer to other values
+#[inline]
+pub(crate) fn is_container(value: &py_val) -> bool {
+    matches!(
+        value,
+        py_val::list(_) | py_val::tuple(_) | py_val::dict(_) | py_val::set(_) | py_val::method(_)
+    )
+}
+
+// a container was made at addr(Value::new)
+pub(crate) fn track(addr: usize) {
+    TRACKED.with(|tracked| tracked.borrow_mut().insert(addr));
+    COUNT.with(|count| count.set(count.get() + 1));
+}
+
+// the container at addr is being freed
+pub(crate) fn untrack(addr: usize) {
+    TRACKED.with(|tracked| tracked.borrow_mut().remove(&addr));
+    COUNT.with(|count| count.set(count.get().saturating_sub(1)));
+}
+
+/// number of the containers alive on this thread
+pub fn tracked_objects() -> usize {
+    TRACKED.with(|tracked| tracked.borrow().len())
+}
+
+/// automatic collection once this many more containers are alive than after the last one
+pub fn set_threshold(threshold: usize) {
+    THRESHOLD.with(|t| t.set(threshold));
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.with(|e| e.set(enabled));
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.with(Cell::get)
+}
+
+// called by the evaluator where no container is being modified
+pub(crate) fn collect_if_due() {
+    if is_enabled() && COUNT.with(Cell::get) > THRESHOLD.with(Cell::get) {
+        collect();
+    }
+}
+
+// the values held by a container. false if it is borrowed mutably now
+fn for_each_child(value: &py_val, f: &mut dyn FnMut(&py_val_t)) -> bool {
+    match value {
+        py_val::list(l) => match l.try_borrow() {
+            Ok(l) => l.iter().for_each(f),
+            Err(_) => return false,
+        },
+        py_val::tuple(elts) => elts.iter().for_each(f),
+        py_val::dict(d) => match d.try_borrow() {
+            Ok(d) => table_children(&d, f),
+            Err(_) => return false,
+        },
+        py_val::set(s) => match s.try_borrow() {
+            Ok(s) => table_children(&s, f),
+            Err(_) => return false,
+        },
+        py_val::method(m) => {
+            f(&m.receiver);
+            f(&m.func);
+        }
+        _ => (),
+    }
+    true
+}
+
+// the values of a set are its keys again, which are references too
+fn table_children(table: &DictTable, f: &mut dyn FnMut(&py_val_t)) {
+    for entry in table.iter() {
+        f(&entry.key);
+        f(&entry.value);
+    }
+}
+
+// empty a list/dict/set. the contents are dropped after the borrow ends
+fn clear(value: &py_val) {
+    match value {
+        py_val::list(l) => {
+            let contents = std::mem::take(&mut *l.borrow_mut());
+            drop(contents);
+        }
+        py_val::dict(d) => {
+            let contents = std::mem::take(&mut *d.borrow_mut());
+            drop(contents);
+        }
+        py_val::set(s) => {
+            let contents = std::mem::take(&mut *s.borrow_mut());
+            drop(contents);
+        }
+        // tuples and methods are freed when the cycle through a mutable container is broken
+        _ => (),
+    }
+}
+
+/// free the containers unreachable from outside of them. returns how many there were
+pub fn collect() -> usize {
+    let addrs: Vec<usize> = TRACKED.with(|tracked| tracked.borrow().iter().copied().collect());
+    // the tracked containers are alive while this runs(nothing is dropped until the garbage is cleared)
+    let objects: Vec<ManuallyDrop<Value>> = addrs
+        .iter()
+        .map(|&addr| unsafe { Value::from_addr(addr) })
+        .collect();
+
+    // references from outside of the tracked containers
+    let mut refs: HashMap<usize, usize> = addrs
+        .iter()
+        .zip(objects.iter())
+        .map(|(&addr, object)| (addr, object.strong_count()))
+        .collect();
+    let mut reachable: Vec<usize> = Vec::new();
+    for (&addr, object) in addrs.iter().zip(objects.iter()) {
+        let traversed = for_each_child(object.heap().unwrap(), &mut |child| {
+            if let Some(count) = child.heap_addr().and_then(|child| refs.get_mut(&child)) {
+                *count -= 1;
+            }
+        });
+        // someone is modifying it, so it is in use
+        if !traversed {
+            reachable.push(addr);
+        }
+    }
+    reachable.extend(
+        refs.iter()
+            .filter(|(_, &count)| count > 0)
+            .map(|(&addr, _)| addr),
+    );
+
+    // and what they refer to
+    let mut marked: HashSet<usize> = reachable.iter().copied().collect();
+    while let Some(addr) = reachable.pop() {
+        let object = unsafe { Value::from_addr(addr) };
+        for_each_child(object.heap().unwrap(), &mut |child| {
+            if let Some(child) = child.heap_addr() {
+                if refs.contains_key(&child) && marked.insert(child) {
+                    reachable.push(child);
+                }
+            }
+        });
+    }
+
+    // own the garbage while it is cleared, so that it is freed only at the end
+    let garbage: Vec<Value> = addrs
+        .iter()
+        .zip(objects.iter())
+        .filter(|(addr, _)| !marked.contains(addr))
+        .map(|(_, object)| Value::clone(object))
+        .collect();
+    let collected = garbage.len();
+    for object in &garbage {
+        clear(object.heap().unwrap());
+    }
+    drop(garbage);
+    COUNT.with(|count| count.set(0));
+    collected
+}
+
+// the gc module: collect(), enable(), disable() and isenabled()
+pub(crate) fn gc_module() -> NativeModule {
+    let mut gc = NativeModule::new("gc");
+    gc.add_function("collect", 0, |_| {
+        Ok(py_val::new(py_val::int(collect() as i64)))
+    });
+    gc.add_function("enable", 0, |_| {
+        set_enabled(true);
+        Ok(py_val::new(py_val::None))
+    });
+    gc.add_function("disable", 0, |_| {
+        set_enabled(false);
+        Ok(py_val::new(py_val::None))
+    });
+    gc.add_function("isenabled", 0, |_| Ok(py_val::from_bool(is_enabled())));
+    gc
+}
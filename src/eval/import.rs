@@ -0,0 +1,195 @@
+//! # import
+//! モジュールの検索と読み込み
+//! `import a.b` finds a/b.py(or a/b/__init__.py) in the search path, after importing the
+//! package a. each module is run once in its own namespace and cached by its dotted name.
+//! a module is registered before its body runs, so a circular import gets the partially
+//! initialized module as CPython does
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::eval::evaluator::{Evaluator, GlobalEnv};
+use crate::eval::types::*;
+use crate::parser::Parser;
+
+pub(crate) struct Module {
+    pub name: String,
+    pub file_name: String,
+    pub globals: GlobalEnv,
+    // the module object, the same one every time it is imported
+    pub object: py_val_t,
+    // still running its body. names it has not defined yet can't be imported from it
+    pub initializing: bool,
+}
+
+impl Module {
+    // index is the one in Evaluator::modules
+    pub fn new(name: &str, file_name: &str, index: usize, globals: GlobalEnv) -> Module {
+        Module {
+            name: name.to_string(),
+            file_name: file_name.to_string(),
+            globals,
+            object: py_val::new(py_val::module(py_module {
+                name: name.to_string(),
+                file_name: file_name.to_string(),
+                index,
+            })),
+            initializing: false,
+        }
+    }
+
+    // a package is a directory with __init__.py
+    fn package_dir(&self) -> Option<&Path> {
+        let path = Path::new(&self.file_name);
+        if path.file_name()? == "__init__.py" {
+            path.parent()
+        } else {
+            None
+        }
+    }
+}
+
+// the directory of the script, then the directories in path(MINIPYTHON_PATH)
+pub(crate) fn module_search_path(script: &str, path: Option<OsString>) -> Vec<PathBuf> {
+    let dir = match Path::new(script).parent() {
+        Some(dir) if dir != Path::new("") => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let mut dirs = vec![dir];
+    if let Some(path) = path {
+        dirs.extend(std::env::split_paths(&path).filter(|dir| dir != Path::new("")));
+    }
+    dirs
+}
+
+// name.py or name/__init__.py in the first directory that has one
+fn find_module(dirs: &[PathBuf], name: &str) -> Option<PathBuf> {
+    dirs.iter().find_map(|dir| {
+        let file = dir.join(format!("{}.py", name));
+        let package = dir.join(name).join("__init__.py");
+        if file.is_file() {
+            Some(file)
+        } else if package.is_file() {
+            Some(package)
+        } else {
+            None
+        }
+    })
+}
+
+impl Evaluator {
+    // the module named by the dotted name, running it if it is not imported yet
+    pub(crate) fn import_module(&mut self, name: &str) -> py_val_t {
+        if let Some(&index) = self.module_indices.get(name) {
+            return self.modules[index].object.clone();
+        }
+        let file = match self.locate(name) {
+            Ok(Some(file)) => file,
+            Ok(None) => self.error(format!("ModuleNotFoundError: No module named '{}'", name)),
+            Err(message) => self.error(message),
+        };
+        let module = self.load(name, &file);
+        // the submodule is an attribute of its package
+        if let Some(dot) = name.rfind('.') {
+            let parent = self.module_indices[&name[..dot]];
+            self.modules[parent]
+                .globals
+                .insert(name[dot + 1..].to_string(), module.clone());
+        }
+        module
+    }
+
+    // `from module import name`. a submodule of a package is imported if needed
+    pub(crate) fn import_from(&mut self, module: &py_val_t, name: &str) -> py_val_t {
+        let index = match module.heap() {
+            Some(py_val::module(module)) => module.index,
+            _ => unreachable!(),
+        };
+        if let Some(value) = self.modules[index].globals.get(name) {
+            return value.clone();
+        }
+        let full_name = format!("{}.{}", self.modules[index].name, name);
+        if let Ok(Some(_)) = self.locate(&full_name) {
+            return self.import_module(&full_name);
+        }
+        let module = &self.modules[index];
+        let partially = if module.initializing {
+            "partially initialized module "
+        } else {
+            ""
+        };
+        let circular = if module.initializing {
+            " (most likely due to a circular import)"
+        } else {
+            ""
+        };
+        let location = if module.file_name.is_empty() {
+            "unknown location"
+        } else {
+            &module.file_name
+        };
+        self.error(format!(
+            "ImportError: cannot import name '{}' from {}'{}'{} ({})",
+            name, partially, module.name, circular, location
+        ))
+    }
+
+    // file of the module. the parent package is imported first, and only its directory
+    // is searched for the submodule
+    fn locate(&mut self, name: &str) -> Result<Option<PathBuf>, String> {
+        match name.rfind('.') {
+            Some(dot) => {
+                let parent = self.import_module(&name[..dot]);
+                let index = match parent.heap() {
+                    Some(py_val::module(module)) => module.index,
+                    _ => unreachable!(),
+                };
+                match self.modules[index].package_dir() {
+                    Some(dir) => Ok(find_module(&[dir.to_path_buf()], &name[dot + 1..])),
+                    None => Err(format!(
+                        "ModuleNotFoundError: No module named '{}'; '{}' is not a package",
+                        name,
+                        &name[..dot]
+                    )),
+                }
+            }
+            None => Ok(find_module(&self.search_path, name)),
+        }
+    }
+
+    // run the file as the module name, in a new namespace
+    fn load(&mut self, name: &str, file: &Path) -> py_val_t {
+        let file_name = file.to_string_lossy().to_string();
+        let mut parser = match Parser::new(&file_name) {
+            Ok(parser) => parser,
+            Err(error) => self.error(format!("ImportError: {}: {}", file_name, error)),
+        };
+        let ast = parser.parse();
+        if let Some(error) = parser.errors().first() {
+            let message = error.format(&file_name);
+            self.error(message.trim_end().to_string());
+        }
+
+        let index = self.modules.len();
+        let mut module = Module::new(name, &file_name, index, GlobalEnv::new());
+        module.initializing = true;
+        module.globals.insert(
+            "__name__".to_string(),
+            py_val::new(py_val::string(name.to_string())),
+        );
+        module.globals.insert(
+            "__file__".to_string(),
+            py_val::new(py_val::string(file_name.clone())),
+        );
+        self.modules.push(module);
+        self.module_indices.insert(name.to_string(), index);
+
+        let importer = std::mem::replace(&mut self.current_module, index);
+        self.push_frame(&file_name, "<module>");
+        self.exec_module_body(&ast);
+        self.pop_frame();
+        self.current_module = importer;
+        self.modules[index].initializing = false;
+        self.modules[index].object.clone()
+    }
+}
@@ -0,0 +1,115 @@
+//! # io
+//! 標準入出力の差し替え
+//! the evaluator writes what print outputs to its stdout and tracebacks to its stderr,
+//! and reads its stdin. they are the process ones unless the embedder sets others,
+//! for example OutputBuffers to capture the output of a script
+
+use std::cell::RefCell;
+use std::io::{self, BufRead, BufReader, Write};
+use std::rc::Rc;
+
+pub struct Io {
+    stdout: Box<dyn Write>,
+    stderr: Box<dyn Write>,
+    stdin: Box<dyn BufRead>,
+}
+
+impl Default for Io {
+    fn default() -> Io {
+        Io {
+            stdout: Box::new(io::stdout()),
+            stderr: Box::new(io::stderr()),
+            stdin: Box::new(BufReader::new(io::stdin())),
+        }
+    }
+}
+
+impl Io {
+    pub fn set_stdout(&mut self, stdout: impl Write + 'static) {
+        self.stdout = Box::new(stdout);
+    }
+
+    pub fn set_stderr(&mut self, stderr: impl Write + 'static) {
+        self.stderr = Box::new(stderr);
+    }
+
+    pub fn set_stdin(&mut self, stdin: impl BufRead + 'static) {
+        self.stdin = Box::new(stdin);
+    }
+
+    pub fn stdout(&mut self) -> &mut dyn Write {
+        &mut self.stdout
+    }
+
+    pub fn stderr(&mut self) -> &mut dyn Write {
+        &mut self.stderr
+    }
+
+    pub fn stdin(&mut self) -> &mut dyn BufRead {
+        &mut self.stdin
+    }
+}
+
+// a line with its newline. "" at the end of the stream
+pub(crate) fn read_line(stream: &mut dyn BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    stream.read_line(&mut line)?;
+    Ok(line)
+}
+
+// up to size characters, or the rest of the stream
+pub(crate) fn read_chars(stream: &mut dyn BufRead, size: Option<usize>) -> io::Result<String> {
+    let mut bytes = Vec::new();
+    match size {
+        None => {
+            stream.read_to_end(&mut bytes)?;
+        }
+        Some(size) => {
+            // a byte at a time, stopping before the first byte of the next character
+            let mut chars = 0;
+            while let Some(&byte) = stream.fill_buf()?.first() {
+                let starts_char = byte & 0xc0 != 0x80;
+                if starts_char && chars == size {
+                    break;
+                }
+                chars += starts_char as usize;
+                bytes.push(byte);
+                stream.consume(1);
+            }
+        }
+    }
+    String::from_utf8(bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// in-memory output. clones share the buffer, so one can be given to the evaluator
+/// and the other kept to read what was written
+#[derive(Debug, Default, Clone)]
+pub struct OutputBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl OutputBuffer {
+    pub fn new() -> OutputBuffer {
+        OutputBuffer::default()
+    }
+
+    /// what was written so far(invalid UTF-8 is replaced)
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+
+    /// the contents, leaving the buffer empty
+    pub fn take(&self) -> String {
+        let bytes = std::mem::take(&mut *self.0.borrow_mut());
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+//! # limits
+//! 実行の制限(sandbox)
+//! an evaluator running untrusted code can be given a maximum call depth, a number of steps,
+//! a wall-clock timeout and an approximate cap on the memory of its values.
+//! steps are statements in the tree-walker and instructions on the VM.
+//! each run(`eval`, or a call of the Interpreter) starts with a new budget
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::eval::types::*;
+
+// the clock, the cancel flag and the memory are looked at once in this many steps
+const CHECK_INTERVAL: u64 = 1024;
+
+// the default recursion limit of CPython(sys.getrecursionlimit())
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    max_call_depth: Option<usize>,
+    max_steps: Option<u64>,
+    timeout: Option<Duration>,
+    max_memory: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_call_depth: Some(DEFAULT_MAX_CALL_DEPTH),
+            max_steps: None,
+            timeout: None,
+            max_memory: None,
+        }
+    }
+}
+
+impl Limits {
+    /// the limits an evaluator has by default: only the call depth, `DEFAULT_MAX_CALL_DEPTH`
+    pub fn new() -> Limits {
+        Limits::default()
+    }
+
+    /// nested calls of python functions. RecursionError beyond it
+    pub fn max_call_depth(mut self, depth: usize) -> Limits {
+        self.max_call_depth = Some(depth);
+        self
+    }
+
+    pub fn max_steps(mut self, steps: u64) -> Limits {
+        self.max_steps = Some(steps);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Limits {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// bytes of the live values, estimated from their contents. MemoryError beyond it
+    pub fn max_memory(mut self, bytes: usize) -> Limits {
+        self.max_memory = Some(bytes);
+        self
+    }
+}
+
+/// stops the running code(with KeyboardInterrupt) from another thread
+#[derive(Debug, Clone)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+// what is left of the limits in the current run
+pub(crate) struct Budget {
+    pub(crate) limits: Limits,
+    steps: u64,
+    next_check: u64,
+    deadline: Option<Instant>,
+    memory_base: usize,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Budget {
+    pub(crate) fn new(limits: Limits) -> Budget {
+        let mut budget = Budget {
+            limits,
+            steps: 0,
+            next_check: 0,
+            deadline: None,
+            memory_base: 0,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        };
+        budget.start();
+        budget
+    }
+
+    // a new run begins
+    pub(crate) fn start(&mut self) {
+        self.steps = 0;
+        self.next_check = self.next_check_after(0);
+        self.deadline = self.limits.timeout.map(|timeout| Instant::now() + timeout);
+        self.memory_base = heap_bytes();
+        HEAP_EXCEEDED.with(|exceeded| exceeded.set(false));
+        HEAP_LIMIT.with(|limit| match self.limits.max_memory {
+            Some(max) => limit.set(self.memory_base.saturating_add(max)),
+            None => limit.set(usize::MAX),
+        });
+    }
+
+    pub(crate) fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle(Arc::clone(&self.cancelled))
+    }
+
+    // count a step. true if the limits have to be checked
+    #[inline]
+    pub(crate) fn tick(&mut self) -> bool {
+        self.steps += 1;
+        self.steps >= self.next_check || HEAP_EXCEEDED.with(Cell::get)
+    }
+
+    // the error to raise, if a limit is exceeded
+    pub(crate) fn check(&mut self) -> Option<String> {
+        if self.limits.max_steps.is_some_and(|max| self.steps > max) {
+            return Some("RuntimeError: maximum number of steps exceeded".to_string());
+        }
+        self.next_check = self.next_check_after(self.steps);
+        if self.cancelled.swap(false, Ordering::Relaxed) {
+            return Some("KeyboardInterrupt".to_string());
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Some("TimeoutError: execution timed out".to_string());
+        }
+        if HEAP_EXCEEDED.with(|exceeded| exceeded.replace(false)) {
+            // values may have been freed since
+            let used = heap_bytes().saturating_sub(self.memory_base);
+            if self.limits.max_memory.is_some_and(|max| used > max) {
+                return Some("MemoryError".to_string());
+            }
+        }
+        None
+    }
+
+    pub(crate) fn depth_exceeded(&self, depth: usize) -> bool {
+        self.limits.max_call_depth.is_some_and(|max| depth > max)
+    }
+
+    fn next_check_after(&self, steps: u64) -> u64 {
+        let next = steps + CHECK_INTERVAL;
+        match self.limits.max_steps {
+            Some(max) => next.min(max + 1),
+            None => next,
+        }
+    }
+}
+
+// approximate size of the heap values alive on this thread.
+// values are counted when they are made and when they are dropped,
+// and lists/dicts/sets when they grow or shrink(through their borrow_mut)
+thread_local! {
+    static HEAP_BYTES: Cell<usize> = const { Cell::new(0) };
+    static HEAP_LIMIT: Cell<usize> = const { Cell::new(usize::MAX) };
+    static HEAP_EXCEEDED: Cell<bool> = const { Cell::new(false) };
+}
+
+const WORD: usize = std::mem::size_of::<usize>();
+// a list element is a word, an entry of a table its hash, key, value and index
+pub(crate) const LIST_ELEMENT_SIZE: usize = WORD;
+pub(crate) const TABLE_ENTRY_SIZE: usize = 4 * WORD;
+
+pub(crate) fn heap_bytes() -> usize {
+    HEAP_BYTES.with(Cell::get)
+}
+
+pub(crate) fn track_alloc(bytes: usize) {
+    HEAP_BYTES.with(|heap| {
+        let bytes = heap.get().saturating_add(bytes);
+        heap.set(bytes);
+        if bytes > HEAP_LIMIT.with(Cell::get) {
+            HEAP_EXCEEDED.with(|exceeded| exceeded.set(true));
+        }
+    });
+}
+
+// MemoryError if bytes more would go over the memory limit. the values whose size the script
+// chooses(`"a" * n`, range(n)) are checked before they are made, since counting them after
+// would be too late to stop a huge allocation
+pub(crate) fn reserve(bytes: usize) -> Result<(), String> {
+    if heap_bytes().saturating_add(bytes) > HEAP_LIMIT.with(Cell::get) {
+        return Err("MemoryError".to_string());
+    }
+    Ok(())
+}
+
+// an empty list with room for len elements, reserved first. MemoryError if the allocator
+// refuses(with no memory limit, a huge size is not an abort)
+pub(crate) fn vec_with_capacity<T>(len: usize) -> Result<Vec<T>, String> {
+    reserve(len.saturating_mul(LIST_ELEMENT_SIZE))?;
+    let mut res = Vec::new();
+    match res.try_reserve_exact(len) {
+        Ok(()) => Ok(res),
+        Err(_) => Err("MemoryError".to_string()),
+    }
+}
+
+// the same for a string of len bytes
+pub(crate) fn string_with_capacity(len: usize) -> Result<String, String> {
+    reserve(len)?;
+    let mut res = String::new();
+    match res.try_reserve_exact(len) {
+        Ok(()) => Ok(res),
+        Err(_) => Err("MemoryError".to_string()),
+    }
+}
+
+pub(crate) fn track_free(bytes: usize) {
+    HEAP_BYTES.with(|heap| heap.set(heap.get().saturating_sub(bytes)));
+}
+
+// a container went from `before` to `after` elements
+pub(crate) fn track_resize(before: usize, after: usize, element_size: usize) {
+    if after > before {
+        track_alloc((after - before) * element_size);
+    } else {
+        track_free((before - after) * element_size);
+    }
+}
+
+// estimate of the memory a heap value holds(the Rc and its contents)
+pub(crate) fn value_size(value: &py_val) -> usize {
+    let contents = match value {
+        py_val::string(s) => s.len(),
+        py_val::tuple(elts) => elts.len() * LIST_ELEMENT_SIZE,
+        py_val::list(l) => l.try_borrow().map_or(0, |l| l.len()) * LIST_ELEMENT_SIZE,
+        py_val::dict(d) => d.try_borrow().map_or(0, |d| d.len()) * TABLE_ENTRY_SIZE,
+        py_val::set(s) => s.try_borrow().map_or(0, |s| s.len()) * TABLE_ENTRY_SIZE,
+        _ => 0,
+    };
+    std::mem::size_of::<py_val>() + 2 * WORD + contents
+}
@@ -1,426 +1,1236 @@
 // use crate::errors;
+use crate::eval::dict::DictTable;
+use crate::eval::limits::{string_with_capacity, vec_with_capacity};
 use crate::eval::types::*;
+use crate::eval::unicode;
 use ordered_float::OrderedFloat;
-#[allow(unused_imports)]
-use std::collections::{BTreeMap, BTreeSet};
+use std::cmp::Ordering;
 #[allow(unused_imports)]
 use std::iter::FromIterator;
 
 // all arguments are assumed to be type checked
+// (except for the methods, which are called directly from scripts)
 
-pub fn ntv_panic(_: Vec<py_val_t>) -> py_val_t {
-    panic!();
+// index counted from the end if negative. None if it is out of range
+fn normalize_index(i: i64, len: usize) -> Option<usize> {
+    let i = if i < 0 { i + len as i64 } else { i };
+    if 0 <= i && (i as usize) < len {
+        Some(i as usize)
+    } else {
+        None
+    }
+}
+
+// index of a list or tuple(an int or a bool)
+fn sequence_index(type_name: &str, index: &py_val_t) -> Result<i64, String> {
+    index.as_i64().ok_or_else(|| {
+        format!(
+            "TypeError: {} indices must be integers or slices, not {}",
+            type_name,
+            index.type_name()
+        )
+    })
+}
+
+// s n times, checked against the memory limit before it is made
+fn repeat(s: &str, n: usize) -> Result<String, String> {
+    if s.is_empty() {
+        return Ok(String::new());
+    }
+    let mut res = string_with_capacity(s.len().saturating_mul(n))?;
+    for _ in 0..n {
+        res.push_str(s);
+    }
+    Ok(res)
+}
+
+// the prelude calls this when no operand type matches
+pub fn ntv_panic(_: Vec<py_val_t>) -> py_result_t {
+    Err("TypeError: unsupported operand type(s)".to_string())
 }
 
 // cast
-pub fn ntv_itof(values: Vec<py_val_t>) -> py_val_t {
-    match *values[0] {
-        py_val::int(i) => py_val::new(py_val::float(OrderedFloat(i as f64))),
+pub fn ntv_itof(values: Vec<py_val_t>) -> py_result_t {
+    match *values[0].view() {
+        py_val::int(i) => Ok(py_val::new(py_val::float(OrderedFloat(i as f64)))),
         _ => panic!(),
     }
 }
 
-pub fn ntv_ftoi(values: Vec<py_val_t>) -> py_val_t {
-    match *values[0] {
-        py_val::float(f) => py_val::new(py_val::int(f.floor() as i64)),
+pub fn ntv_ftoi(values: Vec<py_val_t>) -> py_result_t {
+    match *values[0].view() {
+        py_val::float(f) => Ok(py_val::new(py_val::int(f.floor() as i64))),
         _ => panic!(),
     }
 }
 
-pub fn ntv_repr_int(values: Vec<py_val_t>) -> py_val_t {
-    match *values[0] {
-        py_val::int(i) => py_val::new(py_val::string(format!("{}", i))),
+pub fn ntv_repr_int(values: Vec<py_val_t>) -> py_result_t {
+    match *values[0].view() {
+        py_val::int(i) => Ok(py_val::new(py_val::string(format!("{}", i)))),
         _ => panic!(),
     }
 }
 
-pub fn ntv_repr_float(values: Vec<py_val_t>) -> py_val_t {
-    match *values[0] {
-        py_val::float(f) => py_val::new(py_val::string(format!("{}", f))),
+pub fn ntv_repr_float(values: Vec<py_val_t>) -> py_result_t {
+    match *values[0].view() {
+        py_val::float(f) => Ok(py_val::new(py_val::string(format!("{}", f)))),
         _ => panic!(),
     }
 }
 
 // int. caller has checked values are int(although get_int also checks)
-pub fn ntv_add_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(py_val::int(i + j));
+pub fn ntv_add_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            return Ok(py_val::new(py_val::int(i + j)));
         }
     }
     panic!();
 }
 
-pub fn ntv_sub_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(py_val::int(i - j));
+pub fn ntv_sub_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            return Ok(py_val::new(py_val::int(i - j)));
         }
     }
     panic!();
 }
 
-pub fn ntv_mul_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(py_val::int(i * j));
+pub fn ntv_mul_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            return Ok(py_val::new(py_val::int(i * j)));
         }
     }
     panic!();
 }
 
-pub fn ntv_div_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(py_val::int(i / j));
+pub fn ntv_div_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            if j == 0 {
+                return Err("ZeroDivisionError: division by zero".to_string());
+            }
+            return Ok(py_val::new(py_val::int(i / j)));
         }
     }
     panic!();
 }
 
-pub fn ntv_mod_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(py_val::int(i % j));
+pub fn ntv_mod_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            if j == 0 {
+                return Err("ZeroDivisionError: integer modulo by zero".to_string());
+            }
+            return Ok(py_val::new(py_val::int(i % j)));
         }
     }
     panic!();
 }
 
-pub fn ntv_cmp_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(py_val::int(if i < j {
+pub fn ntv_cmp_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            return Ok(py_val::new(py_val::int(if i < j {
                 -1
             } else if i == j {
                 0
             } else {
                 1
-            }));
+            })));
         }
     }
     panic!();
 }
 
-pub fn ntv_eq_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(if i == j { py_val::True } else { py_val::False });
+pub fn ntv_eq_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            return Ok(py_val::new(if i == j {
+                py_val::True
+            } else {
+                py_val::False
+            }));
         }
     }
     panic!();
 }
 
-pub fn ntv_ne_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(if i != j { py_val::True } else { py_val::False });
+pub fn ntv_ne_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            return Ok(py_val::new(if i != j {
+                py_val::True
+            } else {
+                py_val::False
+            }));
         }
     }
     panic!();
 }
 
-pub fn ntv_gt_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(if i > j { py_val::True } else { py_val::False });
+pub fn ntv_gt_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            return Ok(py_val::new(if i > j {
+                py_val::True
+            } else {
+                py_val::False
+            }));
         }
     }
     panic!();
 }
 
-pub fn ntv_ge_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(if i >= j { py_val::True } else { py_val::False });
+pub fn ntv_ge_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            return Ok(py_val::new(if i >= j {
+                py_val::True
+            } else {
+                py_val::False
+            }));
         }
     }
     panic!();
 }
 
-pub fn ntv_lt_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(if i < j { py_val::True } else { py_val::False });
+pub fn ntv_lt_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            return Ok(py_val::new(if i < j {
+                py_val::True
+            } else {
+                py_val::False
+            }));
         }
     }
     panic!();
 }
 
-pub fn ntv_le_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(if i <= j { py_val::True } else { py_val::False });
+pub fn ntv_le_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            return Ok(py_val::new(if i <= j {
+                py_val::True
+            } else {
+                py_val::False
+            }));
         }
     }
     panic!();
 }
 
-pub fn ntv_invert_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        return py_val::new(py_val::int(!i));
+pub fn ntv_invert_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        return Ok(py_val::new(py_val::int(!i)));
     }
     panic!();
 }
 
-pub fn ntv_and_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(py_val::int(i & j));
+pub fn ntv_and_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            return Ok(py_val::new(py_val::int(i & j)));
         }
     }
     panic!();
 }
 
-pub fn ntv_or_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(py_val::int(i | j));
+pub fn ntv_or_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            return Ok(py_val::new(py_val::int(i | j)));
         }
     }
     panic!();
 }
 
-pub fn ntv_xor_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(py_val::int(i ^ j));
+pub fn ntv_xor_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            return Ok(py_val::new(py_val::int(i ^ j)));
         }
     }
     panic!();
 }
 
-pub fn ntv_lshift_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(py_val::int(i << j));
+pub fn ntv_lshift_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            return Ok(py_val::new(py_val::int(i << j)));
         }
     }
     panic!();
 }
 
-pub fn ntv_rshift_int(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        if let py_val::int(j) = *values[1] {
-            return py_val::new(py_val::int(i >> j));
+pub fn ntv_rshift_int(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::int(i) = *values[0].view() {
+        if let py_val::int(j) = *values[1].view() {
+            return Ok(py_val::new(py_val::int(i >> j)));
         }
     }
     panic!();
 }
 
 // float. caller has checked values are int(although get_int also checks)
-pub fn ntv_add_float(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::float(i) = *values[0] {
-        if let py_val::float(j) = *values[1] {
-            return py_val::new(py_val::float(OrderedFloat(i.into_inner() + j.into_inner())));
+pub fn ntv_add_float(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::float(i) = *values[0].view() {
+        if let py_val::float(j) = *values[1].view() {
+            return Ok(py_val::new(py_val::float(OrderedFloat(
+                i.into_inner() + j.into_inner(),
+            ))));
         }
     }
     panic!();
 }
 
-pub fn ntv_sub_float(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::float(i) = *values[0] {
-        if let py_val::float(j) = *values[1] {
-            return py_val::new(py_val::float(OrderedFloat(i.into_inner() - j.into_inner())));
+pub fn ntv_sub_float(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::float(i) = *values[0].view() {
+        if let py_val::float(j) = *values[1].view() {
+            return Ok(py_val::new(py_val::float(OrderedFloat(
+                i.into_inner() - j.into_inner(),
+            ))));
         }
     }
     panic!();
 }
 
-pub fn ntv_mul_float(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::float(i) = *values[0] {
-        if let py_val::float(j) = *values[1] {
-            return py_val::new(py_val::float(OrderedFloat(i.into_inner() * j.into_inner())));
+pub fn ntv_mul_float(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::float(i) = *values[0].view() {
+        if let py_val::float(j) = *values[1].view() {
+            return Ok(py_val::new(py_val::float(OrderedFloat(
+                i.into_inner() * j.into_inner(),
+            ))));
         }
     }
     panic!();
 }
 
-pub fn ntv_div_float(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::float(i) = *values[0] {
-        if let py_val::float(j) = *values[1] {
-            return py_val::new(py_val::float(OrderedFloat(i.into_inner() / j.into_inner())));
+pub fn ntv_div_float(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::float(i) = *values[0].view() {
+        if let py_val::float(j) = *values[1].view() {
+            if j.into_inner() == 0.0 {
+                return Err("ZeroDivisionError: float division by zero".to_string());
+            }
+            return Ok(py_val::new(py_val::float(OrderedFloat(
+                i.into_inner() / j.into_inner(),
+            ))));
         }
     }
     panic!();
 }
 
-pub fn ntv_mod_float(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::float(i) = *values[0] {
-        if let py_val::float(j) = *values[1] {
-            return py_val::new(py_val::float(OrderedFloat(i.into_inner() % j.into_inner())));
+pub fn ntv_mod_float(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::float(i) = *values[0].view() {
+        if let py_val::float(j) = *values[1].view() {
+            if j.into_inner() == 0.0 {
+                return Err("ZeroDivisionError: float modulo by zero".to_string());
+            }
+            return Ok(py_val::new(py_val::float(OrderedFloat(
+                i.into_inner() % j.into_inner(),
+            ))));
         }
     }
     panic!();
 }
 
-pub fn ntv_cmp_float(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::float(i) = *values[0] {
-        if let py_val::float(j) = *values[1] {
-            return py_val::new(py_val::int(if i < j {
+pub fn ntv_cmp_float(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::float(i) = *values[0].view() {
+        if let py_val::float(j) = *values[1].view() {
+            return Ok(py_val::new(py_val::int(if i < j {
                 -1
             } else if i == j {
                 0
             } else {
                 1
-            }));
+            })));
         }
     }
     panic!();
 }
 
-pub fn ntv_len_string(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::string(ref s) = *values[0] {
-        return py_val::new(py_val::int(s.len() as i64));
+pub fn ntv_len_string(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::string(ref s) = *values[0].view() {
+        return Ok(py_val::new(py_val::int(s.chars().count() as i64)));
     }
     panic!();
 }
 
-pub fn ntv_add_string(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::string(ref s) = *values[0] {
-        if let py_val::string(ref t) = *values[1] {
-            return py_val::new(py_val::string(s.clone() + t));
+pub fn ntv_add_string(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::string(ref s) = *values[0].view() {
+        if let py_val::string(ref t) = *values[1].view() {
+            return Ok(py_val::new(py_val::string(s.clone() + t)));
         }
     }
     panic!();
 }
 
-pub fn ntv_getitem_string(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::string(ref s) = *values[0] {
-        if let py_val::int(i) = *values[1] {
-            if let Some(c) = s.get((i as usize)..(i as usize) + 1) {
-                return py_val::new(py_val::string(c.to_string()));
-            }
+// str * n. n <= 0 gives an empty string
+pub fn ntv_mul_string(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::string(ref s) = *values[0].view() {
+        if let py_val::int(n) = *values[1].view() {
+            return Ok(py_val::new(py_val::string(repeat(s, n.max(0) as usize)?)));
         }
     }
     panic!();
 }
 
-pub fn ntv_add_tuple(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::tuple(ref t) = *values[0] {
-        if let py_val::tuple(ref u) = *values[1] {
+pub fn ntv_getitem_string(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::string(ref s) = *values[0].view() {
+        let i = values[1].as_i64().ok_or_else(|| {
+            format!(
+                "TypeError: string indices must be integers, not '{}'",
+                values[1].type_name()
+            )
+        })?;
+        let len = s.chars().count();
+        return match normalize_index(i, len).and_then(|i| s.chars().nth(i)) {
+            Some(c) => Ok(py_val::new(py_val::string(c.to_string()))),
+            None => Err("IndexError: string index out of range".to_string()),
+        };
+    }
+    panic!();
+}
+
+pub fn ntv_add_tuple(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::tuple(ref t) = *values[0].view() {
+        if let py_val::tuple(ref u) = *values[1].view() {
             let mut res = t.clone();
             res.extend(u.clone());
-            return py_val::new(py_val::tuple(res));
+            return Ok(py_val::new(py_val::tuple(res)));
         }
     }
     panic!();
 }
 
-pub fn ntv_len_tuple(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::tuple(ref t) = *values[0] {
-        return py_val::new(py_val::int(t.len() as i64));
+pub fn ntv_len_tuple(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::tuple(ref t) = *values[0].view() {
+        return Ok(py_val::new(py_val::int(t.len() as i64)));
     }
     panic!();
 }
 
-pub fn ntv_getitem_tuple(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::tuple(ref t) = *values[0] {
-        if let py_val::int(i) = *values[1] {
-            if let Some(v) = t.get(i as usize) {
-                return v.clone();
-            }
+pub fn ntv_getitem_tuple(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::tuple(ref t) = *values[0].view() {
+        let i = sequence_index("tuple", &values[1])?;
+        return match normalize_index(i, t.len()).and_then(|i| t.get(i)) {
+            Some(v) => Ok(v.clone()),
+            None => Err("IndexError: tuple index out of range".to_string()),
+        };
+    }
+    panic!();
+}
+
+pub fn ntv_add_list(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::list(ref t) = *values[0].view() {
+        if let py_val::list(ref u) = *values[1].view() {
+            let mut res = t.borrow().clone();
+            res.extend(u.borrow().iter().cloned());
+            return Ok(py_val::new_list(res));
         }
     }
     panic!();
 }
 
-pub fn ntv_add_list(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::list(ref t) = *values[0] {
-        if let py_val::list(ref u) = *values[1] {
-            let mut res = t.clone();
-            res.extend(u.clone());
-            return py_val::new(py_val::tuple(res));
+// list * n. n <= 0 gives an empty list
+pub fn ntv_mul_list(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::list(ref t) = *values[0].view() {
+        if let py_val::int(n) = *values[1].view() {
+            let t = t.borrow();
+            let n = if t.is_empty() { 0 } else { n.max(0) as usize };
+            let mut res = vec_with_capacity(t.len().saturating_mul(n))?;
+            for _ in 0..n {
+                res.extend(t.iter().cloned());
+            }
+            return Ok(py_val::new_list(res));
         }
     }
     panic!();
 }
 
-// dict, set unimplemented
+pub fn ntv_getitem_list(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::list(ref t) = *values[0].view() {
+        let i = sequence_index("list", &values[1])?;
+        let t = t.borrow();
+        return match normalize_index(i, t.len()).and_then(|i| t.get(i)) {
+            Some(v) => Ok(v.clone()),
+            None => Err("IndexError: list index out of range".to_string()),
+        };
+    }
+    panic!();
+}
 
-// print
-pub fn ntv_print_string(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::string(ref s) = *values[0] {
-        println!("{}", s);
-    } else {
-        panic!();
+pub fn ntv_setitem_list(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::list(ref t) = *values[0].view() {
+        let i = sequence_index("list", &values[1])?;
+        let mut t = t.borrow_mut();
+        let len = t.len();
+        return match normalize_index(i, len) {
+            Some(i) => {
+                t[i] = values[2].clone();
+                Ok(py_val::new(py_val::None))
+            }
+            None => Err("IndexError: list assignment index out of range".to_string()),
+        };
     }
-    py_val::new(py_val::None)
+    panic!();
 }
 
-pub fn ntv_range(values: Vec<py_val_t>) -> py_val_t {
-    if let py_val::int(i) = *values[0] {
-        py_val::new(py_val::list(
-            (0..(i as usize))
-                .map(|x| (py_val::new(py_val::int(x as i64))))
-                .collect(),
-        ))
-    } else {
-        panic!("TypeError");
+// dict, set
+// hashes are computed by the caller through __hash__ and passed along with the key
+
+fn hash_arg(value: &py_val_t) -> i64 {
+    match *value.view() {
+        py_val::int(i) => i,
+        _ => panic!(),
+    }
+}
+
+pub fn ntv_hash(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::new(py_val::int(values[0].py_hash()?)))
+}
+
+// (dict, hash, key)
+pub fn ntv_getitem_dict(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::dict(ref d) = *values[0].view() {
+        return d
+            .borrow()
+            .get(hash_arg(&values[1]), &values[2])
+            .ok_or_else(|| format!("KeyError: {}", values[2].repr()));
+    }
+    panic!();
+}
+
+// (dict, hash, key, value)
+pub fn ntv_setitem_dict(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::dict(ref d) = *values[0].view() {
+        d.borrow_mut()
+            .insert(hash_arg(&values[1]), values[2].clone(), values[3].clone());
+        return Ok(py_val::new(py_val::None));
+    }
+    panic!();
+}
+
+// (dict or set, hash, key)
+pub fn ntv_contains_hashed(values: Vec<py_val_t>) -> py_result_t {
+    let hash = hash_arg(&values[1]);
+    Ok(py_val::from_bool(match *values[0].view() {
+        py_val::dict(ref d) => d.borrow().contains(hash, &values[2]),
+        py_val::set(ref s) => s.borrow().contains(hash, &values[2]),
+        _ => panic!(),
+    }))
+}
+
+// dict | dict
+pub fn ntv_or_dict(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::dict(ref d) = *values[0].view() {
+        if let py_val::dict(ref e) = *values[1].view() {
+            let merged = d.borrow().merge(&e.borrow());
+            return Ok(py_val::new(py_val::dict(merged.into())));
+        }
     }
+    panic!();
+}
+
+fn set_operands(values: &[py_val_t]) -> (&py_set, &py_set) {
+    match (values[0].heap(), values[1].heap()) {
+        (Some(py_val::set(ref s)), Some(py_val::set(ref t))) => (s, t),
+        _ => panic!(),
+    }
+}
+
+fn new_set(table: DictTable) -> py_result_t {
+    Ok(py_val::new(py_val::set(table.into())))
+}
+
+// set | set
+pub fn ntv_or_set(values: Vec<py_val_t>) -> py_result_t {
+    let (s, t) = set_operands(&values);
+    let res = s.borrow().merge(&t.borrow());
+    new_set(res)
+}
+
+// set & set
+pub fn ntv_and_set(values: Vec<py_val_t>) -> py_result_t {
+    let (s, t) = set_operands(&values);
+    let res = s.borrow().filter_by(&t.borrow(), true);
+    new_set(res)
+}
+
+// set - set
+pub fn ntv_sub_set(values: Vec<py_val_t>) -> py_result_t {
+    let (s, t) = set_operands(&values);
+    let res = s.borrow().filter_by(&t.borrow(), false);
+    new_set(res)
+}
+
+// set ^ set
+pub fn ntv_xor_set(values: Vec<py_val_t>) -> py_result_t {
+    let (s, t) = set_operands(&values);
+    let (s, t) = (s.borrow(), t.borrow());
+    let res = s.filter_by(&t, false).merge(&t.filter_by(&s, false));
+    new_set(res)
 }
 
 // type check
-pub fn ntv_is_int(values: Vec<py_val_t>) -> py_val_t {
-    py_val::new(if matches!(*values[0], py_val::int(_)) {
-        py_val::True
-    } else {
-        py_val::False
-    })
+pub fn ntv_is_int(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::from_bool(values[0].type_name() == "int"))
 }
 
-pub fn ntv_is_float(values: Vec<py_val_t>) -> py_val_t {
-    py_val::new(if matches!(*values[0], py_val::float(_)) {
-        py_val::True
-    } else {
-        py_val::False
-    })
+pub fn ntv_is_float(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::from_bool(values[0].type_name() == "float"))
 }
 
-pub fn ntv_is_string(values: Vec<py_val_t>) -> py_val_t {
-    py_val::new(if matches!(*values[0], py_val::string(_)) {
-        py_val::True
-    } else {
-        py_val::False
-    })
+pub fn ntv_is_string(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::from_bool(values[0].type_name() == "str"))
 }
 
-pub fn ntv_is_tuple(values: Vec<py_val_t>) -> py_val_t {
-    py_val::new(if matches!(*values[0], py_val::tuple(_)) {
-        py_val::True
-    } else {
-        py_val::False
-    })
+pub fn ntv_is_tuple(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::from_bool(values[0].type_name() == "tuple"))
 }
 
-pub fn ntv_is_list(values: Vec<py_val_t>) -> py_val_t {
-    py_val::new(if matches!(*values[0], py_val::list(_)) {
-        py_val::True
-    } else {
-        py_val::False
-    })
+pub fn ntv_is_list(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::from_bool(values[0].type_name() == "list"))
 }
 
-pub fn ntv_is_dict(values: Vec<py_val_t>) -> py_val_t {
-    py_val::new(if matches!(*values[0], py_val::dict(_)) {
-        py_val::True
-    } else {
-        py_val::False
-    })
+pub fn ntv_is_dict(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::from_bool(values[0].type_name() == "dict"))
 }
 
-pub fn ntv_is_set(values: Vec<py_val_t>) -> py_val_t {
-    py_val::new(if matches!(*values[0], py_val::set(_)) {
-        py_val::True
-    } else {
-        py_val::False
+pub fn ntv_is_set(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::from_bool(values[0].type_name() == "set"))
+}
+
+// structural equality of builtin values
+pub fn ntv_eq(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::from_bool(values[0].py_eq(&values[1])))
+}
+
+// ordering of numbers, strings, lists and tuples. TypeError for the others
+fn ordering(values: &[py_val_t], op: &str) -> Result<Ordering, String> {
+    values[0].py_cmp(&values[1]).ok_or_else(|| {
+        format!(
+            "TypeError: '{}' not supported between instances of '{}' and '{}'",
+            op,
+            values[0].type_name(),
+            values[1].type_name()
+        )
     })
 }
 
-pub fn ntv_not(values: Vec<py_val_t>) -> py_val_t {
-    py_val::new(if values[0].is_true() {
+pub fn ntv_lt(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::from_bool(ordering(&values, "<")? == Ordering::Less))
+}
+
+pub fn ntv_le(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::from_bool(
+        ordering(&values, "<=")? != Ordering::Greater,
+    ))
+}
+
+pub fn ntv_gt(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::from_bool(
+        ordering(&values, ">")? == Ordering::Greater,
+    ))
+}
+
+pub fn ntv_ge(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::from_bool(
+        ordering(&values, ">=")? != Ordering::Less,
+    ))
+}
+
+// identity(`is`)
+pub fn ntv_is(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::from_bool(Value::ptr_eq(&values[0], &values[1])))
+}
+
+// substring test(`sub in s`)
+pub fn ntv_contains_string(values: Vec<py_val_t>) -> py_result_t {
+    if let py_val::string(ref s) = *values[0].view() {
+        if let py_val::string(ref sub) = *values[1].view() {
+            return Ok(py_val::from_bool(s.contains(sub.as_str())));
+        }
+        return Err(format!(
+            "TypeError: 'in <string>' requires string as left operand, not {}",
+            values[1].type_name()
+        ));
+    }
+    panic!();
+}
+
+pub fn ntv_not(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::new(if values[0].is_true() {
         py_val::False
     } else {
         py_val::True
+    }))
+}
+
+// str methods. values[0] is the receiver, which is always a str
+// indices are counted in chars, not in bytes
+
+fn string(s: impl Into<String>) -> py_val_t {
+    py_val::new(py_val::string(s.into()))
+}
+
+fn string_list<'a>(parts: impl Iterator<Item = &'a str>) -> py_val_t {
+    py_val::new(py_val::list(parts.map(string).collect()))
+}
+
+fn receiver(values: &[py_val_t]) -> &str {
+    values[0].as_str().unwrap()
+}
+
+// optional argument. None is treated as omitted
+fn opt_arg(values: &[py_val_t], i: usize) -> Option<&py_val_t> {
+    values.get(i).filter(|v| !v.is_none())
+}
+
+fn str_arg<'a>(method: &str, value: &'a py_val_t) -> Result<&'a str, String> {
+    match value.as_str() {
+        Some(s) => Ok(s),
+        None => Err(format!(
+            "TypeError: {}() argument must be str, not {}",
+            method,
+            value.type_name()
+        )),
+    }
+}
+
+fn opt_str_arg<'a>(
+    method: &str,
+    values: &'a [py_val_t],
+    i: usize,
+) -> Result<Option<&'a str>, String> {
+    opt_arg(values, i).map(|v| str_arg(method, v)).transpose()
+}
+
+fn int_arg(method: &str, value: &py_val_t) -> Result<i64, String> {
+    value.as_i64().ok_or_else(|| {
+        format!(
+            "TypeError: {}() argument must be int, not {}",
+            method,
+            value.type_name()
+        )
+    })
+}
+
+fn opt_int_arg(method: &str, values: &[py_val_t], i: usize) -> Result<Option<i64>, String> {
+    opt_arg(values, i).map(|v| int_arg(method, v)).transpose()
+}
+
+// CPython's str.isspace()
+fn is_space(c: char) -> bool {
+    c.is_whitespace() || ('\x1c'..='\x1f').contains(&c)
+}
+
+// the part of s designated by start/end(as s[start:end]), and the char offset of its start.
+// None if start is past the end of s
+fn substring<'a>(
+    method: &str,
+    s: &'a str,
+    values: &[py_val_t],
+    start_index: usize,
+) -> Result<Option<(&'a str, usize)>, String> {
+    let len = s.chars().count() as i64;
+    let adjust = |i: i64| if i < 0 { (i + len).max(0) } else { i };
+    let start = adjust(opt_int_arg(method, values, start_index)?.unwrap_or(0));
+    let end = adjust(opt_int_arg(method, values, start_index + 1)?.unwrap_or(len)).min(len);
+    if start > len {
+        return Ok(None);
+    }
+    let byte_offset = |i: i64| s.char_indices().nth(i as usize).map_or(s.len(), |(b, _)| b);
+    let (start_byte, end_byte) = (byte_offset(start), byte_offset(end.max(start)));
+    Ok(Some((&s[start_byte..end_byte], start as usize)))
+}
+
+fn char_index(s: &str, byte_index: usize) -> i64 {
+    s[..byte_index].chars().count() as i64
+}
+
+// str.split(sep=None, maxsplit=-1)
+pub fn ntv_str_split(values: Vec<py_val_t>) -> py_result_t {
+    let s = receiver(&values);
+    let sep = opt_str_arg("split", &values, 1)?;
+    let maxsplit = opt_int_arg("split", &values, 2)?.unwrap_or(-1);
+    match sep {
+        Some("") => Err("ValueError: empty separator".to_string()),
+        Some(sep) if maxsplit < 0 => Ok(string_list(s.split(sep))),
+        Some(sep) => Ok(string_list(s.splitn(maxsplit as usize + 1, sep))),
+        None => {
+            // runs of whitespace are separators, and leading/trailing whitespace is ignored
+            let mut res = Vec::new();
+            let mut rest = s.trim_start_matches(is_space);
+            while !rest.is_empty() {
+                if maxsplit >= 0 && res.len() as i64 == maxsplit {
+                    res.push(rest);
+                    break;
+                }
+                let end = rest.find(is_space).unwrap_or(rest.len());
+                res.push(&rest[..end]);
+                rest = rest[end..].trim_start_matches(is_space);
+            }
+            Ok(string_list(res.into_iter()))
+        }
+    }
+}
+
+// str.rsplit(sep=None, maxsplit=-1)
+pub fn ntv_str_rsplit(values: Vec<py_val_t>) -> py_result_t {
+    let s = receiver(&values);
+    let sep = opt_str_arg("rsplit", &values, 1)?;
+    let maxsplit = opt_int_arg("rsplit", &values, 2)?.unwrap_or(-1);
+    let mut res: Vec<&str> = match sep {
+        Some("") => return Err("ValueError: empty separator".to_string()),
+        Some(sep) if maxsplit < 0 => s.rsplit(sep).collect(),
+        Some(sep) => s.rsplitn(maxsplit as usize + 1, sep).collect(),
+        None => {
+            let mut res = Vec::new();
+            let mut rest = s.trim_end_matches(is_space);
+            while !rest.is_empty() {
+                if maxsplit >= 0 && res.len() as i64 == maxsplit {
+                    res.push(rest);
+                    break;
+                }
+                let start = rest
+                    .rfind(is_space)
+                    .map_or(0, |i| i + rest[i..].chars().next().unwrap().len_utf8());
+                res.push(&rest[start..]);
+                rest = rest[..start].trim_end_matches(is_space);
+            }
+            res
+        }
+    };
+    res.reverse();
+    Ok(string_list(res.into_iter()))
+}
+
+// str.join(iterable)
+pub fn ntv_str_join(values: Vec<py_val_t>) -> py_result_t {
+    let s = receiver(&values);
+    let items = values[1]
+        .iter_values()
+        .ok_or_else(|| "TypeError: can only join an iterable".to_string())?;
+    let mut parts = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        match item.as_str() {
+            Some(t) => parts.push(t),
+            None => {
+                return Err(format!(
+                    "TypeError: sequence item {}: expected str instance, {} found",
+                    i,
+                    item.type_name()
+                ))
+            }
+        }
+    }
+    Ok(string(parts.join(s)))
+}
+
+fn strip_chars(method: &str, values: &[py_val_t]) -> Result<impl Fn(char) -> bool, String> {
+    let chars: Option<Vec<char>> = opt_str_arg(method, values, 1)?.map(|c| c.chars().collect());
+    Ok(move |c: char| match chars {
+        Some(ref chars) => chars.contains(&c),
+        None => is_space(c),
     })
 }
+
+// str.strip(chars=None)
+pub fn ntv_str_strip(values: Vec<py_val_t>) -> py_result_t {
+    let pred = strip_chars("strip", &values)?;
+    Ok(string(receiver(&values).trim_matches(pred)))
+}
+
+// str.lstrip(chars=None)
+pub fn ntv_str_lstrip(values: Vec<py_val_t>) -> py_result_t {
+    let pred = strip_chars("lstrip", &values)?;
+    Ok(string(receiver(&values).trim_start_matches(pred)))
+}
+
+// str.rstrip(chars=None)
+pub fn ntv_str_rstrip(values: Vec<py_val_t>) -> py_result_t {
+    let pred = strip_chars("rstrip", &values)?;
+    Ok(string(receiver(&values).trim_end_matches(pred)))
+}
+
+// str.replace(old, new, count=-1)
+pub fn ntv_str_replace(values: Vec<py_val_t>) -> py_result_t {
+    let s = receiver(&values);
+    let old = str_arg("replace", &values[1])?;
+    let new = str_arg("replace", &values[2])?;
+    Ok(string(match opt_int_arg("replace", &values, 3)? {
+        Some(count) if count >= 0 => s.replacen(old, new, count as usize),
+        _ => s.replace(old, new),
+    }))
+}
+
+// str.find(sub[, start[, end]])
+pub fn ntv_str_find(values: Vec<py_val_t>) -> py_result_t {
+    let s = receiver(&values);
+    let sub = str_arg("find", &values[1])?;
+    Ok(py_val::new(py_val::int(
+        match substring("find", s, &values, 2)? {
+            Some((part, offset)) => part
+                .find(sub)
+                .map_or(-1, |i| char_index(part, i) + offset as i64),
+            None => -1,
+        },
+    )))
+}
+
+// str.rfind(sub[, start[, end]])
+pub fn ntv_str_rfind(values: Vec<py_val_t>) -> py_result_t {
+    let s = receiver(&values);
+    let sub = str_arg("rfind", &values[1])?;
+    Ok(py_val::new(py_val::int(
+        match substring("rfind", s, &values, 2)? {
+            Some((part, offset)) => part
+                .rfind(sub)
+                .map_or(-1, |i| char_index(part, i) + offset as i64),
+            None => -1,
+        },
+    )))
+}
+
+// str.index(sub[, start[, end]])
+pub fn ntv_str_index(values: Vec<py_val_t>) -> py_result_t {
+    let res = ntv_str_find(values)?;
+    if res.as_i64() == Some(-1) {
+        Err("ValueError: substring not found".to_string())
+    } else {
+        Ok(res)
+    }
+}
+
+// prefix/suffix given to startswith/endswith: a str or a tuple of str
+fn affixes<'a>(method: &str, value: &'a py_val_t) -> Result<Vec<&'a str>, String> {
+    if let Some(s) = value.as_str() {
+        return Ok(vec![s]);
+    }
+    match value.heap() {
+        Some(py_val::tuple(ref t)) => t.iter().map(|v| str_arg(method, v)).collect(),
+        _ => Err(format!(
+            "TypeError: {} first arg must be str or a tuple of str, not {}",
+            method,
+            value.type_name()
+        )),
+    }
+}
+
+// str.startswith(prefix[, start[, end]])
+pub fn ntv_str_startswith(values: Vec<py_val_t>) -> py_result_t {
+    let s = receiver(&values);
+    let prefixes = affixes("startswith", &values[1])?;
+    Ok(py_val::from_bool(
+        match substring("startswith", s, &values, 2)? {
+            Some((part, _)) => prefixes.iter().any(|p| part.starts_with(p)),
+            None => false,
+        },
+    ))
+}
+
+// str.endswith(suffix[, start[, end]])
+pub fn ntv_str_endswith(values: Vec<py_val_t>) -> py_result_t {
+    let s = receiver(&values);
+    let suffixes = affixes("endswith", &values[1])?;
+    Ok(py_val::from_bool(
+        match substring("endswith", s, &values, 2)? {
+            Some((part, _)) => suffixes.iter().any(|p| part.ends_with(p)),
+            None => false,
+        },
+    ))
+}
+
+// str.upper()
+pub fn ntv_str_upper(values: Vec<py_val_t>) -> py_result_t {
+    Ok(string(receiver(&values).to_uppercase()))
+}
+
+// str.lower()
+pub fn ntv_str_lower(values: Vec<py_val_t>) -> py_result_t {
+    Ok(string(receiver(&values).to_lowercase()))
+}
+
+// str.title()
+// a cased char following an uncased one starts a word
+pub fn ntv_str_title(values: Vec<py_val_t>) -> py_result_t {
+    let mut res = String::new();
+    let mut prev_cased = false;
+    for c in receiver(&values).chars() {
+        if prev_cased {
+            res.extend(c.to_lowercase());
+        } else {
+            unicode::push_titlecase(&mut res, c);
+        }
+        prev_cased = unicode::is_cased(c);
+    }
+    Ok(string(res))
+}
+
+fn all_chars(values: &[py_val_t], pred: impl Fn(char) -> bool) -> py_result_t {
+    let s = receiver(values);
+    Ok(py_val::from_bool(!s.is_empty() && s.chars().all(pred)))
+}
+
+// str.isdigit()
+pub fn ntv_str_isdigit(values: Vec<py_val_t>) -> py_result_t {
+    all_chars(&values, unicode::is_digit)
+}
+
+// str.isalpha()
+pub fn ntv_str_isalpha(values: Vec<py_val_t>) -> py_result_t {
+    all_chars(&values, char::is_alphabetic)
+}
+
+// str.isspace()
+pub fn ntv_str_isspace(values: Vec<py_val_t>) -> py_result_t {
+    all_chars(&values, is_space)
+}
+
+// str.count(sub[, start[, end]])
+pub fn ntv_str_count(values: Vec<py_val_t>) -> py_result_t {
+    let s = receiver(&values);
+    let sub = str_arg("count", &values[1])?;
+    Ok(py_val::new(py_val::int(
+        match substring("count", s, &values, 2)? {
+            Some((part, _)) if sub.is_empty() => part.chars().count() as i64 + 1,
+            Some((part, _)) => part.matches(sub).count() as i64,
+            None => 0,
+        },
+    )))
+}
+
+// str.splitlines(keepends=False)
+pub fn ntv_str_splitlines(values: Vec<py_val_t>) -> py_result_t {
+    let s = receiver(&values);
+    let keepends = opt_arg(&values, 1).is_some_and(|v| v.is_true());
+    let is_line_break = |c: char| {
+        matches!(
+            c,
+            '\n' | '\r'
+                | '\x0b'
+                | '\x0c'
+                | '\x1c'
+                | '\x1d'
+                | '\x1e'
+                | '\u{85}'
+                | '\u{2028}'
+                | '\u{2029}'
+        )
+    };
+    let mut res = Vec::new();
+    let mut rest = s;
+    while let Some(i) = rest.find(is_line_break) {
+        let mut end = i + rest[i..].chars().next().unwrap().len_utf8();
+        if rest[i..].starts_with("\r\n") {
+            end += 1;
+        }
+        res.push(if keepends { &rest[..end] } else { &rest[..i] });
+        rest = &rest[end..];
+    }
+    if !rest.is_empty() {
+        res.push(rest);
+    }
+    Ok(string_list(res.into_iter()))
+}
+
+// str.partition(sep)
+pub fn ntv_str_partition(values: Vec<py_val_t>) -> py_result_t {
+    let s = receiver(&values);
+    let sep = str_arg("partition", &values[1])?;
+    if sep.is_empty() {
+        return Err("ValueError: empty separator".to_string());
+    }
+    let parts = match s.find(sep) {
+        Some(i) => vec![&s[..i], sep, &s[i + sep.len()..]],
+        None => vec![s, "", ""],
+    };
+    Ok(py_val::new(py_val::tuple(
+        parts.into_iter().map(string).collect(),
+    )))
+}
+
+// str.zfill(width)
+// zeros go after the sign
+pub fn ntv_str_zfill(values: Vec<py_val_t>) -> py_result_t {
+    let s = receiver(&values);
+    let width = int_arg("zfill", &values[1])?;
+    let len = s.chars().count() as i64;
+    if width <= len {
+        return Ok(string(s));
+    }
+    let zeros = repeat("0", (width - len) as usize)?;
+    Ok(string(match s.chars().next() {
+        Some(sign @ '+') | Some(sign @ '-') => format!("{}{}{}", sign, zeros, &s[1..]),
+        _ => zeros + s,
+    }))
+}
+
+// str.center(width, fillchar=' ')
+pub fn ntv_str_center(values: Vec<py_val_t>) -> py_result_t {
+    let s = receiver(&values);
+    let width = int_arg("center", &values[1])?;
+    let fillchar = match opt_str_arg("center", &values, 2)? {
+        Some(f) if f.chars().count() == 1 => f,
+        Some(_) => {
+            return Err(
+                "TypeError: The fill character must be exactly one character long".to_string(),
+            )
+        }
+        None => " ",
+    };
+    let len = s.chars().count() as i64;
+    if width <= len {
+        return Ok(string(s));
+    }
+    // same rounding as CPython: the extra char goes left only when width is odd
+    let margin = width - len;
+    let left = margin / 2 + (margin & width & 1);
+    Ok(string(format!(
+        "{}{}{}",
+        repeat(fillchar, left as usize)?,
+        s,
+        repeat(fillchar, (margin - left) as usize)?
+    )))
+}
+
+// list methods. values[0] is the receiver, which is always a list.
+// they mutate the storage shared by every reference to the list
+// (index, count, remove and sort compare elements, so they are builtins)
+
+fn list_receiver(values: &[py_val_t]) -> &py_list {
+    match values[0].heap() {
+        Some(py_val::list(ref l)) => l,
+        _ => panic!(),
+    }
+}
+
+fn none() -> py_result_t {
+    Ok(py_val::new(py_val::None))
+}
+
+// list.append(x)
+pub fn ntv_list_append(values: Vec<py_val_t>) -> py_result_t {
+    list_receiver(&values).borrow_mut().push(values[1].clone());
+    none()
+}
+
+// list.extend(iterable)
+pub fn ntv_list_extend(values: Vec<py_val_t>) -> py_result_t {
+    // collected before borrowing the receiver, since it may be the receiver itself
+    let items = values[1].iter_values().ok_or_else(|| {
+        format!(
+            "TypeError: '{}' object is not iterable",
+            values[1].type_name()
+        )
+    })?;
+    list_receiver(&values).borrow_mut().extend(items);
+    none()
+}
+
+// list.insert(i, x)
+// out of range indices are clamped to either end
+pub fn ntv_list_insert(values: Vec<py_val_t>) -> py_result_t {
+    let i = int_arg("insert", &values[1])?;
+    let mut l = list_receiver(&values).borrow_mut();
+    let len = l.len() as i64;
+    let i = if i < 0 { (i + len).max(0) } else { i.min(len) };
+    l.insert(i as usize, values[2].clone());
+    none()
+}
+
+// list.pop(i=-1)
+pub fn ntv_list_pop(values: Vec<py_val_t>) -> py_result_t {
+    let i = opt_int_arg("pop", &values, 1)?.unwrap_or(-1);
+    let mut l = list_receiver(&values).borrow_mut();
+    if l.is_empty() {
+        return Err("IndexError: pop from empty list".to_string());
+    }
+    match normalize_index(i, l.len()) {
+        Some(i) => Ok(l.remove(i)),
+        None => Err("IndexError: pop index out of range".to_string()),
+    }
+}
+
+// list.reverse()
+pub fn ntv_list_reverse(values: Vec<py_val_t>) -> py_result_t {
+    list_receiver(&values).borrow_mut().reverse();
+    none()
+}
+
+// list.copy()
+pub fn ntv_list_copy(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::new(py_val::list(list_receiver(&values).clone())))
+}
+
+// list.clear()
+pub fn ntv_list_clear(values: Vec<py_val_t>) -> py_result_t {
+    list_receiver(&values).borrow_mut().clear();
+    none()
+}
+
+// dict/set methods that don't need to hash keys. values[0] is the receiver
+// (the others are builtins, since they hash through __hash__)
+
+fn dict_receiver(values: &[py_val_t]) -> &py_dict {
+    match values[0].heap() {
+        Some(py_val::dict(ref d)) => d,
+        _ => panic!(),
+    }
+}
+
+fn set_receiver(values: &[py_val_t]) -> &py_set {
+    match values[0].heap() {
+        Some(py_val::set(ref s)) => s,
+        _ => panic!(),
+    }
+}
+
+// dict.keys()
+// returns a list instead of a view
+pub fn ntv_dict_keys(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::new_list(dict_receiver(&values).borrow().keys()))
+}
+
+// dict.values()
+pub fn ntv_dict_values(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::new_list(dict_receiver(&values).borrow().values()))
+}
+
+// dict.items()
+// list of (key, value) tuples
+pub fn ntv_dict_items(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::new_list(
+        dict_receiver(&values)
+            .borrow()
+            .iter()
+            .map(|entry| py_val::new(py_val::tuple(vec![entry.key.clone(), entry.value.clone()])))
+            .collect(),
+    ))
+}
+
+// dict.copy()
+pub fn ntv_dict_copy(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::new(py_val::dict(dict_receiver(&values).clone())))
+}
+
+// dict.clear()
+pub fn ntv_dict_clear(values: Vec<py_val_t>) -> py_result_t {
+    dict_receiver(&values).borrow_mut().clear();
+    none()
+}
+
+// set.copy()
+pub fn ntv_set_copy(values: Vec<py_val_t>) -> py_result_t {
+    Ok(py_val::new(py_val::set(set_receiver(&values).clone())))
+}
+
+// set.clear()
+pub fn ntv_set_clear(values: Vec<py_val_t>) -> py_result_t {
+    set_receiver(&values).borrow_mut().clear();
+    none()
+}
@@ -0,0 +1,71 @@
+//! # native_module
+//! Rustで実装されたモジュール
+//! the embedding application groups its functions(and values) into a module,
+//! which scripts import by name as if it were a .py file
+
+use crate::eval::evaluator::{Evaluator, GlobalEnv};
+use crate::eval::import::Module;
+use crate::eval::types::*;
+
+pub struct NativeModule {
+    // dotted name(`app.geometry`)
+    name: String,
+    globals: GlobalEnv,
+}
+
+impl NativeModule {
+    pub fn new(name: &str) -> NativeModule {
+        let mut globals = GlobalEnv::new();
+        globals.insert(
+            "__name__".to_string(),
+            py_val::new(py_val::string(name.to_string())),
+        );
+        NativeModule {
+            name: name.to_string(),
+            globals,
+        }
+    }
+
+    /// function of the module. f works as in Evaluator::register_native
+    pub fn add_function<F>(&mut self, name: &str, arity: impl Into<Arity>, f: F)
+    where
+        F: Fn(Vec<py_val_t>) -> py_result_t + 'static,
+    {
+        let func = py_native_func::new(name, arity.into(), f);
+        self.add_value(name, py_val::new(py_val::native_func(func)));
+    }
+
+    /// constant(or any other value) of the module
+    pub fn add_value(&mut self, name: &str, value: py_val_t) {
+        self.globals.insert(name.to_string(), value);
+    }
+}
+
+impl Evaluator {
+    /// make the module importable. it takes precedence over a file of the same name.
+    /// the packages of a dotted name are created if they are not registered yet,
+    /// and registering one of them later adds to it
+    pub fn register_module(&mut self, module: NativeModule) {
+        let name = module.name;
+        if let Some(&index) = self.module_indices.get(&name) {
+            self.modules[index].globals.extend(module.globals);
+            return;
+        }
+        let index = self.modules.len();
+        // 組み込みモジュールにはファイルがない
+        self.modules
+            .push(Module::new(&name, "", index, module.globals));
+        self.module_indices.insert(name.clone(), index);
+        if let Some(dot) = name.rfind('.') {
+            let (package, leaf) = (&name[..dot], &name[dot + 1..]);
+            if !self.module_indices.contains_key(package) {
+                self.register_module(NativeModule::new(package));
+            }
+            let object = self.modules[index].object.clone();
+            let package = self.module_indices[package];
+            self.modules[package]
+                .globals
+                .insert(leaf.to_string(), object);
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! # operators
+//! 演算子の組み込みディスパッチ(fast path)
+//! operators on int/float/str/list/tuple operands are computed here, with the same results
+//! as the prelude functions(`__add__`, `__eq__`, ...) in src/std/init.py.
+//! the prelude function is still called when an operand has another type, when the fast path
+//! can't decide(e.g. division by zero, overflow), or when the script has overridden it
+
+use std::cmp::Ordering;
+
+use ordered_float::OrderedFloat;
+
+use crate::ast::*;
+use crate::eval::evaluator::Evaluator;
+use crate::eval::native_func::*;
+use crate::eval::types::*;
+use crate::eval::utils::*;
+
+// the `no-fast-paths` feature runs every operator through the prelude, for benchmarks
+const FAST_PATHS: bool = cfg!(not(feature = "no-fast-paths"));
+
+// int operand(bool is not an int here, as in ntv_is_int)
+fn int_of(v: &py_val_t) -> Option<i64> {
+    match v.unpack() {
+        Unpacked::Int(i) => Some(i),
+        Unpacked::Heap(py_val::int(i)) => Some(*i),
+        _ => None,
+    }
+}
+
+// int or float operand
+fn float_of(v: &py_val_t) -> Option<f64> {
+    match v.unpack() {
+        Unpacked::Heap(py_val::float(f)) => Some(f.into_inner()),
+        _ => int_of(v).map(|i| i as f64),
+    }
+}
+
+fn is_sequence(v: &py_val_t) -> bool {
+    matches!(
+        v.unpack(),
+        Unpacked::Str(_)
+            | Unpacked::Heap(py_val::string(_))
+            | Unpacked::Heap(py_val::list(_))
+            | Unpacked::Heap(py_val::tuple(_))
+    )
+}
+
+// operands the fast paths of comparisons know about
+fn is_builtin_operand(v: &py_val_t) -> bool {
+    float_of(v).is_some() || is_sequence(v)
+}
+
+fn binary_int(op: &ASTOperator, i: i64, j: i64) -> Option<i64> {
+    use ASTOperator::*;
+    match op {
+        Add => i.checked_add(j),
+        Sub => i.checked_sub(j),
+        Mul => i.checked_mul(j),
+        // None by zero, the prelude raises ZeroDivisionError
+        Div => i.checked_div(j),
+        Mod => i.checked_rem(j),
+        LShift => i.checked_shl(j as u32).filter(|_| (0..64).contains(&j)),
+        RShift => i.checked_shr(j as u32).filter(|_| (0..64).contains(&j)),
+        BitOr => Some(i | j),
+        BitXor => Some(i ^ j),
+        BitAnd => Some(i & j),
+    }
+}
+
+// `lhs op rhs` as the prelude computes it. None if the prelude has to decide
+pub fn fast_binary_op(op: &ASTOperator, lhs: &py_val_t, rhs: &py_val_t) -> Option<py_val_t> {
+    use ASTOperator::*;
+    if let (Some(i), Some(j)) = (int_of(lhs), int_of(rhs)) {
+        return binary_int(op, i, j).map(|k| py_val::new(py_val::int(k)));
+    }
+    if let (Some(x), Some(y)) = (float_of(lhs), float_of(rhs)) {
+        // at least one of them is a float
+        if matches!(op, Div | Mod) && y == 0.0 {
+            return None;
+        }
+        let res = match op {
+            Add => x + y,
+            Sub => x - y,
+            Mul => x * y,
+            Div => x / y,
+            Mod => x % y,
+            _ => return None,
+        };
+        return Some(py_val::new(py_val::float(OrderedFloat(res))));
+    }
+    let args = || vec![lhs.clone(), rhs.clone()];
+    let res = match (op, lhs.type_name(), rhs.type_name()) {
+        (Add, "str", "str") => ntv_add_string(args()),
+        (Add, "list", "list") => ntv_add_list(args()),
+        (Add, "tuple", "tuple") => ntv_add_tuple(args()),
+        (Mul, "str", "int") => ntv_mul_string(args()),
+        (Mul, "list", "int") => ntv_mul_list(args()),
+        (Mul, "int", "list") => ntv_mul_list(vec![rhs.clone(), lhs.clone()]),
+        _ => return None,
+    };
+    res.ok()
+}
+
+pub fn fast_unary_op(op: &ASTUnaryOp, operand: &py_val_t) -> Option<py_val_t> {
+    use ASTUnaryOp::*;
+    match op {
+        Not => Some(py_val::from_bool(operand.is_false())),
+        Invert => int_of(operand).map(|i| py_val::new(py_val::int(!i))),
+        UAdd => float_of(operand).map(|_| operand.clone()),
+        // `0 - val`
+        USub => match int_of(operand) {
+            Some(i) => i.checked_neg().map(|i| py_val::new(py_val::int(i))),
+            None => float_of(operand).map(|f| py_val::new(py_val::float(OrderedFloat(0.0 - f)))),
+        },
+    }
+}
+
+// `lhs op rhs` as the prelude computes it. None if the prelude has to decide
+// (membership in lists and tuples is left to the caller, since it depends on __eq__)
+pub fn fast_compare_op(op: &ASTCmpOp, lhs: &py_val_t, rhs: &py_val_t) -> Option<bool> {
+    use ASTCmpOp::*;
+    match op {
+        Is => return Some(Value::ptr_eq(lhs, rhs)),
+        IsNot => return Some(!Value::ptr_eq(lhs, rhs)),
+        In | NotIn => {
+            let contained = rhs.as_str()?.contains(lhs.as_str()?);
+            return Some(contained == (*op == In));
+        }
+        _ => (),
+    }
+    if !is_builtin_operand(lhs) || !is_builtin_operand(rhs) {
+        return None;
+    }
+    match op {
+        Eq => Some(lhs.py_eq(rhs)),
+        NotEq => Some(!lhs.py_eq(rhs)),
+        // unordered operands raise TypeError in the prelude
+        Lt => Some(lhs.py_cmp(rhs)? == Ordering::Less),
+        LtE => Some(lhs.py_cmp(rhs)? != Ordering::Greater),
+        Gt => Some(lhs.py_cmp(rhs)? == Ordering::Greater),
+        GtE => Some(lhs.py_cmp(rhs)? != Ordering::Less),
+        _ => None,
+    }
+}
+
+// prelude functions that call other operator functions
+fn dependencies(name: &str) -> &'static [&'static str] {
+    match name {
+        "__mul__" => &["__add__"], // str * int
+        "__minus__" => &["__sub__"],
+        "__neq__" | "__in__" => &["__eq__"],
+        "__not_in__" => &["__in__", "__eq__"],
+        _ => &[],
+    }
+}
+
+impl Evaluator {
+    // the global `name`(and the functions it calls) is still the prelude's function
+    fn is_prelude_op(&self, name: &str) -> bool {
+        if !FAST_PATHS {
+            return false;
+        }
+        if !self.ops_overridden {
+            return true;
+        }
+        let unchanged = |name: &str| match (self.lookup_global(name), self.prelude_ops.get(name)) {
+            (Some(f), Some(g)) => Value::ptr_eq(f, g),
+            _ => false,
+        };
+        unchanged(name) && dependencies(name).iter().all(|name| unchanged(name))
+    }
+
+    pub(crate) fn binary_op(&mut self, op: &ASTOperator, lhs: py_val_t, rhs: py_val_t) -> py_val_t {
+        let name = operator_to_function_name(op);
+        if self.is_prelude_op(name) {
+            if let Some(res) = fast_binary_op(op, &lhs, &rhs) {
+                return res;
+            }
+        }
+        self.call_global(name, vec![lhs, rhs])
+    }
+
+    pub(crate) fn unary_op(&mut self, op: &ASTUnaryOp, operand: py_val_t) -> py_val_t {
+        let name = unary_operator_to_function_name(op);
+        if self.is_prelude_op(name) {
+            if let Some(res) = fast_unary_op(op, &operand) {
+                return res;
+            }
+        }
+        self.call_global(name, vec![operand])
+    }
+
+    // the result of the comparison as a bool
+    pub(crate) fn compare_op(&mut self, op: &ASTCmpOp, lhs: py_val_t, rhs: py_val_t) -> bool {
+        let name = compare_operator_to_function_name(op);
+        if self.is_prelude_op(name) {
+            if let Some(res) = fast_compare_op(op, &lhs, &rhs) {
+                return res;
+            }
+            // `x in [...]` compares the elements with ==
+            if *op == ASTCmpOp::In || *op == ASTCmpOp::NotIn {
+                if let Some(py_val::list(_)) | Some(py_val::tuple(_)) = rhs.heap() {
+                    let contained = rhs.iter_values().unwrap().iter().any(|v| v.py_eq(&lhs));
+                    return contained == (*op == ASTCmpOp::In);
+                }
+            }
+        }
+        self.call_global(name, vec![lhs, rhs]).is_true()
+    }
+}
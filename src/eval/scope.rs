@@ -0,0 +1,271 @@
+//! # scope
+//! 関数本体のスコープ解析
+//! run once when a function is defined. every name used in the body is classified as
+//! local(with the index of its slot), global or free, as CPython's symtable does.
+//! closures are not supported, so free names are looked up in the global environment.
+//! the same pass finds the syntax errors CPython reports after parsing(a misplaced `global`,
+//! `break` outside a loop, ...), for function bodies and the statements of modules
+
+use std::collections::HashMap;
+
+use crate::ast::*;
+use crate::parser::SyntaxError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameKind {
+    // bound in the body(parameter, assignment, for target, def, import)
+    Local(usize),
+    // declared `global`, or only read and not bound in any enclosing function
+    Global,
+    // only read, and bound in an enclosing function
+    Free,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Scope {
+    // name of each local slot. parameters first, then the other names in order of appearance
+    pub locals: Vec<String>,
+    kinds: HashMap<String, NameKind>,
+    // locals of the enclosing functions
+    enclosing: Vec<String>,
+}
+
+#[derive(Default)]
+struct Names {
+    bound: Vec<String>,
+    globals: Vec<String>,
+    used: Vec<String>,
+    // false for the statements of a module
+    function: bool,
+    // loops the current statement is in
+    loops: usize,
+    // the first syntax error
+    error: Option<SyntaxError>,
+}
+
+impl Names {
+    fn bind(&mut self, name: &str) {
+        self.bound.push(name.to_string());
+    }
+
+    fn syntax_error(&mut self, message: &str, span: Span) {
+        if self.error.is_none() {
+            self.error = Some(SyntaxError::new(message, span));
+        }
+    }
+
+    fn declare_global(&mut self, names: &[ASTIdentifier], span: Span) {
+        for name in names {
+            let message = if self.bound.contains(name) {
+                format!("name '{}' is assigned to before global declaration", name)
+            } else if self.used.contains(name) {
+                format!("name '{}' is used prior to global declaration", name)
+            } else {
+                self.globals.push(name.clone());
+                continue;
+            };
+            self.syntax_error(&message, span);
+        }
+    }
+
+    fn target(&mut self, target: &ASTExpr) {
+        match &target.node {
+            ASTExprKind::Name(name) => self.bind(name),
+            _ => self.expr(target),
+        }
+    }
+
+    fn body(&mut self, body: &[ASTStmt]) {
+        for stmt in body {
+            self.stmt(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &ASTStmt) {
+        use ASTStmtKind::*;
+        match &stmt.node {
+            // the body of a nested function has its own scope
+            FuncDef(name, _, _, _) => self.bind(name),
+            Return(value) => {
+                if !self.function {
+                    self.syntax_error("'return' outside function", stmt.span);
+                }
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            // not implemented by the engines
+            Delete(_) => self.syntax_error("'del' is not supported", stmt.span),
+            Assign(targets, value) => {
+                self.expr(value);
+                targets.iter().for_each(|target| self.target(target));
+            }
+            // the annotation is not evaluated
+            AnnAssign(target, _, value) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+                self.target(target);
+            }
+            For(target, iter, body) => {
+                self.expr(iter);
+                self.target(target);
+                self.loop_body(body);
+            }
+            While(test, body) => {
+                self.expr(test);
+                self.loop_body(body);
+            }
+            With(items, body) => {
+                for item in items {
+                    let (context_expr, optional_vars) = &item.node;
+                    self.expr(context_expr);
+                    if let Some(target) = optional_vars {
+                        self.target(target);
+                    }
+                }
+                self.body(body);
+            }
+            If(test, body, orelse) => {
+                self.expr(test);
+                self.body(body);
+                self.body(orelse);
+            }
+            Global(names) => self.declare_global(names, stmt.span),
+            Import(_) | ImportFrom(..) => {
+                for name in stmt.node.imported_names() {
+                    self.bind(name);
+                }
+            }
+            Expr(value) => self.expr(value),
+            Break if self.loops == 0 => self.syntax_error("'break' outside loop", stmt.span),
+            Continue if self.loops == 0 => {
+                self.syntax_error("'continue' not properly in loop", stmt.span)
+            }
+            Init | End | Error | Pass | Break | Continue => (),
+        }
+    }
+
+    fn loop_body(&mut self, body: &[ASTStmt]) {
+        self.loops += 1;
+        self.body(body);
+        self.loops -= 1;
+    }
+
+    fn exprs(&mut self, exprs: &[ASTExpr]) {
+        exprs.iter().for_each(|expr| self.expr(expr));
+    }
+
+    fn expr(&mut self, expr: &ASTExpr) {
+        use ASTExprKind::*;
+        match &expr.node {
+            BoolOp(_, values) => self.exprs(values),
+            BinOp(lhs, _, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            UnaryOp(_, operand) => self.expr(operand),
+            IfExp(test, body, orelse) => {
+                self.expr(test);
+                self.expr(body);
+                self.expr(orelse);
+            }
+            Dict(keys, values) => {
+                self.exprs(keys);
+                self.exprs(values);
+            }
+            Set(elts) | List(elts) | Tuple(elts) => self.exprs(elts),
+            Compare(left, _, comparators) => {
+                self.expr(left);
+                self.exprs(comparators);
+            }
+            Call(func, args, keywords) => {
+                self.expr(func);
+                self.exprs(args);
+                keywords
+                    .iter()
+                    .for_each(|keyword| self.expr(&keyword.node.1));
+            }
+            Constant(_) => (),
+            Attribute(value, _) => self.expr(value),
+            Subscript(value, slice) => {
+                self.expr(value);
+                match &slice.node {
+                    ASTSliceKind::Index(index) => self.expr(index),
+                    // not implemented by the engines
+                    ASTSliceKind::Slice(..) => {
+                        self.syntax_error("slices are not supported", expr.span)
+                    }
+                }
+            }
+            Name(name) => self.used.push(name.clone()),
+        }
+    }
+}
+
+impl Scope {
+    // scope of a function with the parameters args. enclosing is the scope of the function
+    // it is defined in(None at top level). a misplaced `global` is a syntax error
+    pub fn analyze(
+        args: &[ASTArg],
+        body: &[ASTStmt],
+        enclosing: Option<&Scope>,
+    ) -> Result<Scope, SyntaxError> {
+        let mut names = Names {
+            function: true,
+            ..Names::default()
+        };
+        names.body(body);
+        if let Some(error) = names.error {
+            return Err(error);
+        }
+
+        let mut scope = Scope::default();
+        if let Some(outer) = enclosing {
+            scope.enclosing = outer.enclosing.clone();
+            scope.enclosing.extend(outer.locals.iter().cloned());
+        }
+        for name in args.iter().map(|arg| &arg.node.0).chain(names.bound.iter()) {
+            if !scope.kinds.contains_key(name) && !names.globals.contains(name) {
+                scope
+                    .kinds
+                    .insert(name.clone(), NameKind::Local(scope.locals.len()));
+                scope.locals.push(name.clone());
+            }
+        }
+        for name in names.globals {
+            scope.kinds.insert(name, NameKind::Global);
+        }
+        for name in names.used {
+            if !scope.kinds.contains_key(&name) {
+                let kind = if scope.enclosing.contains(&name) {
+                    NameKind::Free
+                } else {
+                    NameKind::Global
+                };
+                scope.kinds.insert(name, kind);
+            }
+        }
+        Ok(scope)
+    }
+
+    // the syntax errors of statements of a module. function bodies are checked when they are
+    // analyzed
+    pub fn check_module(body: &[ASTStmt]) -> Result<(), SyntaxError> {
+        let mut names = Names::default();
+        names.body(body);
+        names.error.map_or(Ok(()), Err)
+    }
+
+    // names that don't appear in the body are global
+    pub fn kind(&self, name: &str) -> NameKind {
+        self.kinds.get(name).copied().unwrap_or(NameKind::Global)
+    }
+
+    pub fn slot(&self, name: &str) -> Option<usize> {
+        match self.kind(name) {
+            NameKind::Local(slot) => Some(slot),
+            _ => None,
+        }
+    }
+}
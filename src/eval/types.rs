@@ -1,39 +1,363 @@
 #![allow(non_camel_case_types)]
-#![allow(illegal_floating_point_literal_pattern)]
 
-use std::collections::{BTreeMap, BTreeSet};
+use std::cell::{RefCell, RefMut};
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut, RangeFrom, RangeInclusive};
 use std::rc::Rc;
 
 use crate::ast::*;
+use crate::eval::compiler::Code;
+use crate::eval::dict::DictTable;
+use crate::eval::evaluator::Evaluator;
+use crate::eval::file::FileHandle;
+use crate::eval::limits::{track_resize, LIST_ELEMENT_SIZE, TABLE_ENTRY_SIZE};
+use crate::eval::scope::Scope;
+pub use crate::eval::value::{Unpacked, Value};
 use ordered_float::OrderedFloat;
 
-#[derive(Debug, PartialEq, Eq, Ord, PartialOrd, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct py_func {
     pub name: String,
     pub args: Vec<String>,
     pub stmt: Vec<ASTStmt>,
+    // analyzed when the function is defined
+    pub scope: Rc<Scope>,
+    // file it is defined in, for tracebacks
+    pub file_name: String,
+    // module whose globals the body sees(index in Evaluator::modules)
+    pub module: usize,
 }
 
-#[derive(Debug, PartialEq, Eq, Ord, PartialOrd, Clone, Hash)]
+// number of arguments a native function accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exact(usize),
+    Range(usize, usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, n: usize) -> bool {
+        match *self {
+            Arity::Exact(m) => n == m,
+            Arity::Range(min, max) => min <= n && n <= max,
+            Arity::AtLeast(min) => min <= n,
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Arity::Exact(n) => write!(f, "{}", n),
+            Arity::Range(min, max) => write!(f, "from {} to {}", min, max),
+            Arity::AtLeast(min) => write!(f, "at least {}", min),
+        }
+    }
+}
+
+impl From<usize> for Arity {
+    fn from(n: usize) -> Arity {
+        Arity::Exact(n)
+    }
+}
+
+impl From<RangeInclusive<usize>> for Arity {
+    fn from(r: RangeInclusive<usize>) -> Arity {
+        Arity::Range(*r.start(), *r.end())
+    }
+}
+
+impl From<RangeFrom<usize>> for Arity {
+    fn from(r: RangeFrom<usize>) -> Arity {
+        Arity::AtLeast(r.start)
+    }
+}
+
+// Err holds the message of the raised error(e.g. "TypeError: ...")
+pub type py_result_t = Result<py_val_t, String>;
+
+/// error raised by a script, as the embedding application sees it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PyError {
+    /// "TypeError: ..."
+    pub message: String,
+    /// "Traceback (most recent call last):" and the frames, ending with the message.
+    /// errors raised outside of any code(conversions, syntax errors) have no frames
+    pub traceback: String,
+}
+
+impl PyError {
+    pub fn new(message: String) -> PyError {
+        let traceback = format!("{}\n", message);
+        PyError { message, traceback }
+    }
+}
+
+impl fmt::Display for PyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.traceback.trim_end())
+    }
+}
+
+impl std::error::Error for PyError {}
+
+// body of a native function. a closure may capture the state of the embedding application
+pub type py_native_body_t = Rc<dyn Fn(Vec<py_val_t>) -> py_result_t>;
+
+#[derive(Clone)]
 pub struct py_native_func {
     pub name: String,
-    pub arity: usize,
-    pub body: fn(Vec<py_val_t>) -> py_val_t,
+    pub arity: Arity,
+    pub body: py_native_body_t,
+}
+
+impl py_native_func {
+    pub fn new<F>(name: &str, arity: Arity, body: F) -> py_native_func
+    where
+        F: Fn(Vec<py_val_t>) -> py_result_t + 'static,
+    {
+        py_native_func {
+            name: name.to_string(),
+            arity,
+            body: Rc::new(body),
+        }
+    }
+}
+
+impl fmt::Debug for py_native_func {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("py_native_func")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+
+// keyword arguments of a call, in the order they were written
+pub type py_kwargs_t = Vec<(String, py_val_t)>;
+
+// user-facing builtin functions (len, sorted, ...)
+// unlike py_native_func, they check their own arguments and can call back into the evaluator
+#[derive(Debug, Clone)]
+pub struct py_builtin_func {
+    pub name: String,
+    pub body: fn(&mut Evaluator, Vec<py_val_t>, py_kwargs_t) -> py_val_t,
+}
+
+// function pointers can't be compared meaningfully, so functions are identified by their name
+macro_rules! impl_cmp_by_name {
+    ($t: ty) => {
+        impl PartialEq for $t {
+            fn eq(&self, other: &Self) -> bool {
+                self.name == other.name
+            }
+        }
+
+        impl Eq for $t {}
+    };
 }
 
+impl_cmp_by_name!(py_native_func);
+impl_cmp_by_name!(py_builtin_func);
+
+// function compiled to bytecode(`--engine=vm`). the code is shared by every call
+#[derive(Debug, Clone)]
+pub struct py_code_func {
+    pub name: String,
+    pub code: Rc<Code>,
+    pub file_name: String,
+    pub module: usize,
+}
+
+impl_cmp_by_name!(py_code_func);
+
+// imported module. the namespace itself is kept by the evaluator
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct py_module {
+    pub name: String,
+    pub file_name: String,
+    // index in Evaluator::modules
+    pub index: usize,
+}
+
+// file object(`sys.stdin`, `open(path)`). the stream is read through the evaluator
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct py_file {
+    pub name: String,
+    pub mode: String,
+    pub stream: py_stream,
+}
+
+#[derive(Debug, Clone)]
+pub enum py_stream {
+    // the input of the evaluator(Evaluator::set_stdin)
+    Stdin,
+    // opened by open(). shared by the copies of the object
+    File(Rc<RefCell<FileHandle>>),
+}
+
+impl PartialEq for py_stream {
+    fn eq(&self, other: &py_stream) -> bool {
+        match (self, other) {
+            (py_stream::Stdin, py_stream::Stdin) => true,
+            (py_stream::File(a), py_stream::File(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for py_stream {}
+
+// method bound to its receiver(`"a b".split`). the receiver is passed as the first argument
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct py_method {
+    pub receiver: py_val_t,
+    pub func: py_val_t,
+}
+
+// storage of a list. every reference to the same list object shares it,
+// so methods like append mutate it in place through the RefCell
+#[derive(Debug, Default)]
+pub struct py_list(RefCell<Vec<py_val_t>>);
+
+impl Deref for py_list {
+    type Target = RefCell<Vec<py_val_t>>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl py_list {
+    // shadows RefCell::borrow_mut, so that growing the list is counted(eval/limits.rs)
+    pub fn borrow_mut(&self) -> TrackedMut<'_, Vec<py_val_t>> {
+        TrackedMut::new(self.0.borrow_mut(), Vec::len, LIST_ELEMENT_SIZE)
+    }
+}
+
+impl From<Vec<py_val_t>> for py_list {
+    fn from(v: Vec<py_val_t>) -> Self {
+        py_list(RefCell::new(v))
+    }
+}
+
+impl FromIterator<py_val_t> for py_list {
+    fn from_iter<I: IntoIterator<Item = py_val_t>>(iter: I) -> Self {
+        iter.into_iter().collect::<Vec<_>>().into()
+    }
+}
+
+// copies the elements(shallow copy, as list.copy())
+impl Clone for py_list {
+    fn clone(&self) -> Self {
+        self.borrow().clone().into()
+    }
+}
+
+impl PartialEq for py_list {
+    fn eq(&self, other: &Self) -> bool {
+        *self.borrow() == *other.borrow()
+    }
+}
+
+impl Eq for py_list {}
+
+// mutable borrow of a list/dict/set. the change of its length is counted when it ends
+pub struct TrackedMut<'a, T> {
+    inner: RefMut<'a, T>,
+    len: fn(&T) -> usize,
+    before: usize,
+    element_size: usize,
+}
+
+impl<'a, T> TrackedMut<'a, T> {
+    fn new(inner: RefMut<'a, T>, len: fn(&T) -> usize, element_size: usize) -> Self {
+        let before = len(&inner);
+        TrackedMut {
+            inner,
+            len,
+            before,
+            element_size,
+        }
+    }
+}
+
+impl<'a, T> Deref for TrackedMut<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<'a, T> DerefMut for TrackedMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<'a, T> Drop for TrackedMut<'a, T> {
+    fn drop(&mut self) {
+        track_resize(self.before, (self.len)(&self.inner), self.element_size);
+    }
+}
+
+// storage of a dict/set. shared and mutated in place like py_list
+// (a set is a table whose values are the keys themselves)
+macro_rules! impl_table_storage {
+    ($t: ident) => {
+        #[derive(Debug, Default)]
+        pub struct $t(RefCell<DictTable>);
+
+        impl Deref for $t {
+            type Target = RefCell<DictTable>;
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl $t {
+            pub fn borrow_mut(&self) -> TrackedMut<'_, DictTable> {
+                TrackedMut::new(self.0.borrow_mut(), DictTable::len, TABLE_ENTRY_SIZE)
+            }
+        }
+
+        impl From<DictTable> for $t {
+            fn from(table: DictTable) -> Self {
+                $t(RefCell::new(table))
+            }
+        }
+
+        impl Clone for $t {
+            fn clone(&self) -> Self {
+                self.borrow().clone().into()
+            }
+        }
+
+        impl PartialEq for $t {
+            fn eq(&self, other: &Self) -> bool {
+                self.borrow().py_eq(&other.borrow())
+            }
+        }
+
+        impl Eq for $t {}
+    };
+}
+
+impl_table_storage!(py_dict);
+impl_table_storage!(py_set);
+
 // represents python values
-// except for:
-//  int: ...01
-//  char(str with length 1): ...010
-//  None: ...0110
-// Boxは所有権があるので直接渡すとそれ以降使えない
-// ※&BoxはBoxのアドレスなので使おうとするとセグフォ
-// →基本(所有権のない)生ポインタ使うしかない?
-// ただし、Drop traitなどがないので自分で処理する必要がある
-// https://qiita.com/tatsuya6502/items/b9801d92f71e24874c9d#ffi-%E3%81%AB%E3%81%8A%E3%81%91%E3%82%8B%E6%A7%8B%E9%80%A0%E4%BD%93%E3%81%AE%E6%AD%A3%E3%81%97%E3%81%84%E6%B8%A1%E3%81%97%E3%81%8B%E3%81%9F
-// 作るときはBox::newしてからBox::into_rawで生ポを作成
-// Drop traitはBox::from_rawすればok
-#[derive(Debug, PartialEq, Eq, Ord, PartialOrd, Clone, Hash)]
+// except for(see eval/value.rs):
+//  int: ...1
+//  short str(up to 7 bytes): ...010
+//  None, True, False: ...0110
+// which are stored in the word of py_val_t itself.
+// the others are put on the heap by Rc::into_raw, and the raw pointer is the py_val_t
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum py_val {
     int(i64),
     True,
@@ -42,14 +366,19 @@ pub enum py_val {
     float(OrderedFloat<f64>),
     string(String),
     func(py_func),
+    code_func(py_code_func),
     native_func(py_native_func),
-    list(Vec<py_val_t>),                // need tuning
-    tuple(Vec<py_val_t>),               // need tuning
-    dict(BTreeMap<py_val_t, py_val_t>), // need tuning?
-    set(BTreeSet<py_val_t>),            // need tuning?
+    builtin_func(py_builtin_func),
+    method(py_method),
+    module(py_module),
+    file(py_file),
+    list(py_list),        // need tuning
+    tuple(Vec<py_val_t>), // need tuning
+    dict(py_dict),
+    set(py_set),
 }
 
-pub type py_val_t = Rc<py_val>;
+pub type py_val_t = Value;
 
 pub enum StmtResult {
     Return(py_val_t),
@@ -60,36 +389,247 @@ pub enum StmtResult {
 }
 
 impl py_val {
+    // py_val_t is not Self but a (possibly tagged) handle to it
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(v: py_val) -> py_val_t {
-        Rc::new(v)
+        Value::new(v)
+    }
+
+    pub fn new_list(v: Vec<py_val_t>) -> py_val_t {
+        py_val::new(py_val::list(v.into()))
     }
 
     pub fn is_none(&self) -> bool {
-        match self {
-            py_val::None => true,
-            _ => false,
-        }
+        matches!(self, py_val::None)
     }
 
     pub fn is_true(&self) -> bool {
         match self {
-            py_val::int(0)
-            | py_val::float(OrderedFloat(0.0))
-            | py_val::False
-            | py_val::None => false,
-            py_val::string(ref s) if s.len() == 0 => false,
+            py_val::int(0) | py_val::float(OrderedFloat(0.0)) | py_val::False | py_val::None => {
+                false
+            }
+            py_val::string(ref s) if s.is_empty() => false,
+            py_val::list(ref v) if v.borrow().is_empty() => false,
+            py_val::tuple(ref v) if v.is_empty() => false,
+            py_val::dict(ref d) if d.borrow().is_empty() => false,
+            py_val::set(ref s) if s.borrow().is_empty() => false,
             _ => true,
         }
     }
 
     pub fn is_false(&self) -> bool {
+        !self.is_true()
+    }
+
+    pub fn from_bool(b: bool) -> py_val_t {
+        py_val::new(if b { py_val::True } else { py_val::False })
+    }
+
+    // name of the type, as CPython's type(v).__name__
+    pub fn type_name(&self) -> &'static str {
         match self {
-            py_val::int(0)
-            | py_val::float(OrderedFloat(0.0))
-            | py_val::False
-            | py_val::None => true,
-            py_val::string(ref s) if s.len() == 0 => true,
-            _ => false,
+            py_val::int(_) => "int",
+            py_val::True | py_val::False => "bool",
+            py_val::None => "NoneType",
+            py_val::float(_) => "float",
+            py_val::string(_) => "str",
+            py_val::func(_) | py_val::code_func(_) => "function",
+            py_val::native_func(_) | py_val::builtin_func(_) => "builtin_function_or_method",
+            py_val::method(_) => "method",
+            py_val::module(_) => "module",
+            py_val::file(_) => "_io.TextIOWrapper",
+            py_val::list(_) => "list",
+            py_val::tuple(_) => "tuple",
+            py_val::dict(_) => "dict",
+            py_val::set(_) => "set",
+        }
+    }
+
+    // numeric value for int/bool/float operands. None otherwise
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            py_val::int(i) => Some(*i as f64),
+            py_val::True => Some(1.0),
+            py_val::False => Some(0.0),
+            py_val::float(f) => Some(f.into_inner()),
+            _ => None,
+        }
+    }
+
+    // integer value for int/bool operands. None otherwise
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            py_val::int(i) => Some(*i),
+            py_val::True => Some(1),
+            py_val::False => Some(0),
+            _ => None,
+        }
+    }
+}
+
+// operations on values, which look at immediates without decoding them
+impl Value {
+    // elements produced by iterating over the value. None if it is not iterable
+    pub fn iter_values(&self) -> Option<Vec<py_val_t>> {
+        if let Some(s) = self.as_str() {
+            return Some(
+                s.chars()
+                    .map(|c| py_val::new(py_val::string(c.to_string())))
+                    .collect(),
+            );
+        }
+        match self.unpack() {
+            Unpacked::Heap(py_val::list(ref elts)) => Some(elts.borrow().clone()),
+            Unpacked::Heap(py_val::tuple(ref elts)) => Some(elts.clone()),
+            Unpacked::Heap(py_val::dict(ref d)) => Some(d.borrow().keys()),
+            Unpacked::Heap(py_val::set(ref s)) => Some(s.borrow().keys()),
+            _ => None,
+        }
+    }
+
+    // ordering used by `<` on builtin types (numbers, str, list, tuple).
+    // None if the two values are not comparable
+    pub fn py_cmp(&self, other: &Value) -> Option<Ordering> {
+        if let (Some(s), Some(t)) = (self.as_str(), other.as_str()) {
+            return Some(s.cmp(t));
+        }
+        match (self.unpack(), other.unpack()) {
+            (Unpacked::Heap(py_val::list(v)), Unpacked::Heap(py_val::list(w))) => {
+                cmp_seq(&v.borrow(), &w.borrow())
+            }
+            (Unpacked::Heap(py_val::tuple(v)), Unpacked::Heap(py_val::tuple(w))) => cmp_seq(v, w),
+            _ => match (self.as_i64(), other.as_i64()) {
+                (Some(i), Some(j)) => Some(i.cmp(&j)),
+                _ => self.as_f64()?.partial_cmp(&other.as_f64()?),
+            },
+        }
+    }
+
+    // hash used by dict and set(the default __hash__). equal values have equal hashes,
+    // so numbers with an integral value hash to that value as CPython does
+    pub fn py_hash(&self) -> Result<i64, String> {
+        if let Some(i) = self.as_i64() {
+            return Ok(i);
+        }
+        let mut hasher = DefaultHasher::new();
+        if let Some(s) = self.as_str() {
+            s.hash(&mut hasher);
+            return Ok(hasher.finish() as i64);
+        }
+        match self.unpack() {
+            Unpacked::None => "None".hash(&mut hasher),
+            Unpacked::Heap(py_val::float(f)) => {
+                let f = f.into_inner();
+                if f.fract() == 0.0 && f.abs() < i64::MAX as f64 {
+                    return Ok(f as i64);
+                }
+                f.to_bits().hash(&mut hasher);
+            }
+            Unpacked::Heap(py_val::tuple(v)) => {
+                for elt in v {
+                    elt.py_hash()?.hash(&mut hasher);
+                }
+            }
+            Unpacked::Heap(py_val::func(py_func { name, .. }))
+            | Unpacked::Heap(py_val::code_func(py_code_func { name, .. }))
+            | Unpacked::Heap(py_val::native_func(py_native_func { name, .. }))
+            | Unpacked::Heap(py_val::builtin_func(py_builtin_func { name, .. }))
+            | Unpacked::Heap(py_val::module(py_module { name, .. }))
+            | Unpacked::Heap(py_val::file(py_file { name, .. })) => name.hash(&mut hasher),
+            Unpacked::Heap(py_val::method(m)) => {
+                m.receiver.py_hash()?.hash(&mut hasher);
+                m.func.py_hash()?.hash(&mut hasher);
+            }
+            _ => {
+                return Err(format!(
+                    "TypeError: unhashable type: '{}'",
+                    self.type_name()
+                ))
+            }
+        }
+        Ok(hasher.finish() as i64)
+    }
+
+    // printable representation, as CPython's repr()
+    pub fn repr(&self) -> String {
+        fn join(elts: &[py_val_t]) -> String {
+            elts.iter().map(|v| v.repr()).collect::<Vec<_>>().join(", ")
+        }
+        if let Some(s) = self.as_str() {
+            return format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"));
+        }
+        match *self.view() {
+            py_val::int(i) => i.to_string(),
+            py_val::True => "True".to_string(),
+            py_val::False => "False".to_string(),
+            py_val::None => "None".to_string(),
+            py_val::float(f) if f.fract() == 0.0 && f.is_finite() => format!("{:.1}", f),
+            py_val::float(f) => f.to_string(),
+            py_val::string(_) => unreachable!(),
+            py_val::func(py_func { ref name, .. })
+            | py_val::code_func(py_code_func { ref name, .. }) => format!("<function {}>", name),
+            py_val::native_func(py_native_func { ref name, .. })
+            | py_val::builtin_func(py_builtin_func { ref name, .. }) => {
+                format!("<built-in function {}>", name)
+            }
+            py_val::method(ref m) => format!("<bound method of {}>", m.receiver.repr()),
+            // native modules have no file
+            py_val::module(ref m) if m.file_name.is_empty() => {
+                format!("<module '{}' (built-in)>", m.name)
+            }
+            py_val::module(ref m) => format!("<module '{}' from '{}'>", m.name, m.file_name),
+            py_val::file(ref f) => format!(
+                "<_io.TextIOWrapper name='{}' mode='{}' encoding='utf-8'>",
+                f.name, f.mode
+            ),
+            py_val::list(ref v) => format!("[{}]", join(&v.borrow())),
+            py_val::tuple(ref v) if v.len() == 1 => format!("({},)", v[0].repr()),
+            py_val::tuple(ref v) => format!("({})", join(v)),
+            py_val::dict(ref d) => format!(
+                "{{{}}}",
+                d.borrow()
+                    .iter()
+                    .map(|entry| format!("{}: {}", entry.key.repr(), entry.value.repr()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            py_val::set(ref s) if s.borrow().is_empty() => "set()".to_string(),
+            py_val::set(ref s) => format!("{{{}}}", join(&s.borrow().keys())),
+        }
+    }
+
+    // equality used by `==` on builtin types. numbers are equal by value(1 == 1.0 == True)
+    pub fn py_eq(&self, other: &Value) -> bool {
+        if let (Some(_), Some(_)) = (self.as_f64(), other.as_f64()) {
+            return self.py_cmp(other) == Some(Ordering::Equal);
+        }
+        match (self.unpack(), other.unpack()) {
+            (Unpacked::Heap(py_val::list(v)), Unpacked::Heap(py_val::list(w))) => {
+                eq_seq(&v.borrow(), &w.borrow())
+            }
+            (Unpacked::Heap(py_val::tuple(v)), Unpacked::Heap(py_val::tuple(w))) => eq_seq(v, w),
+            (Unpacked::Heap(py_val::dict(d)), Unpacked::Heap(py_val::dict(e))) => {
+                d.borrow().py_eq(&e.borrow())
+            }
+            (Unpacked::Heap(py_val::set(s)), Unpacked::Heap(py_val::set(t))) => {
+                s.borrow().py_eq(&t.borrow())
+            }
+            _ => self == other,
+        }
+    }
+}
+
+fn eq_seq(v: &[py_val_t], w: &[py_val_t]) -> bool {
+    v.len() == w.len() && v.iter().zip(w.iter()).all(|(a, b)| a.py_eq(b))
+}
+
+// lexicographic ordering of list/tuple elements
+fn cmp_seq(v: &[py_val_t], w: &[py_val_t]) -> Option<Ordering> {
+    for (a, b) in v.iter().zip(w.iter()) {
+        match a.py_cmp(b)? {
+            Ordering::Equal => (),
+            ord => return Some(ord),
         }
     }
+    Some(v.len().cmp(&w.len()))
 }
@@ -0,0 +1,189 @@
+//! # unicode
+//! 標準ライブラリにないUnicodeの文字データ
+//! the tables follow the Unicode database of CPython's unicodedata, for str methods that
+//! don't match a char method of Rust
+
+// chars of which Numeric_Type is Decimal or Digit, as ranges(str.isdigit). "½" or "Ⅻ" is
+// numeric but not a digit
+const DIGITS: &[(char, char)] = &[
+    ('\u{30}', '\u{39}'),
+    ('\u{B2}', '\u{B3}'),
+    ('\u{B9}', '\u{B9}'),
+    ('\u{660}', '\u{669}'),
+    ('\u{6F0}', '\u{6F9}'),
+    ('\u{7C0}', '\u{7C9}'),
+    ('\u{966}', '\u{96F}'),
+    ('\u{9E6}', '\u{9EF}'),
+    ('\u{A66}', '\u{A6F}'),
+    ('\u{AE6}', '\u{AEF}'),
+    ('\u{B66}', '\u{B6F}'),
+    ('\u{BE6}', '\u{BEF}'),
+    ('\u{C66}', '\u{C6F}'),
+    ('\u{CE6}', '\u{CEF}'),
+    ('\u{D66}', '\u{D6F}'),
+    ('\u{DE6}', '\u{DEF}'),
+    ('\u{E50}', '\u{E59}'),
+    ('\u{ED0}', '\u{ED9}'),
+    ('\u{F20}', '\u{F29}'),
+    ('\u{1040}', '\u{1049}'),
+    ('\u{1090}', '\u{1099}'),
+    ('\u{1369}', '\u{1371}'),
+    ('\u{17E0}', '\u{17E9}'),
+    ('\u{1810}', '\u{1819}'),
+    ('\u{1946}', '\u{194F}'),
+    ('\u{19D0}', '\u{19DA}'),
+    ('\u{1A80}', '\u{1A89}'),
+    ('\u{1A90}', '\u{1A99}'),
+    ('\u{1B50}', '\u{1B59}'),
+    ('\u{1BB0}', '\u{1BB9}'),
+    ('\u{1C40}', '\u{1C49}'),
+    ('\u{1C50}', '\u{1C59}'),
+    ('\u{2070}', '\u{2070}'),
+    ('\u{2074}', '\u{2079}'),
+    ('\u{2080}', '\u{2089}'),
+    ('\u{2460}', '\u{2468}'),
+    ('\u{2474}', '\u{247C}'),
+    ('\u{2488}', '\u{2490}'),
+    ('\u{24EA}', '\u{24EA}'),
+    ('\u{24F5}', '\u{24FD}'),
+    ('\u{24FF}', '\u{24FF}'),
+    ('\u{2776}', '\u{277E}'),
+    ('\u{2780}', '\u{2788}'),
+    ('\u{278A}', '\u{2792}'),
+    ('\u{A620}', '\u{A629}'),
+    ('\u{A8D0}', '\u{A8D9}'),
+    ('\u{A900}', '\u{A909}'),
+    ('\u{A9D0}', '\u{A9D9}'),
+    ('\u{A9F0}', '\u{A9F9}'),
+    ('\u{AA50}', '\u{AA59}'),
+    ('\u{ABF0}', '\u{ABF9}'),
+    ('\u{FF10}', '\u{FF19}'),
+    ('\u{104A0}', '\u{104A9}'),
+    ('\u{10A40}', '\u{10A43}'),
+    ('\u{10D30}', '\u{10D39}'),
+    ('\u{10E60}', '\u{10E68}'),
+    ('\u{11052}', '\u{1105A}'),
+    ('\u{11066}', '\u{1106F}'),
+    ('\u{110F0}', '\u{110F9}'),
+    ('\u{11136}', '\u{1113F}'),
+    ('\u{111D0}', '\u{111D9}'),
+    ('\u{112F0}', '\u{112F9}'),
+    ('\u{11450}', '\u{11459}'),
+    ('\u{114D0}', '\u{114D9}'),
+    ('\u{11650}', '\u{11659}'),
+    ('\u{116C0}', '\u{116C9}'),
+    ('\u{11730}', '\u{11739}'),
+    ('\u{118E0}', '\u{118E9}'),
+    ('\u{11950}', '\u{11959}'),
+    ('\u{11C50}', '\u{11C59}'),
+    ('\u{11D50}', '\u{11D59}'),
+    ('\u{11DA0}', '\u{11DA9}'),
+    ('\u{16A60}', '\u{16A69}'),
+    ('\u{16AC0}', '\u{16AC9}'),
+    ('\u{16B50}', '\u{16B59}'),
+    ('\u{1D7CE}', '\u{1D7FF}'),
+    ('\u{1E140}', '\u{1E149}'),
+    ('\u{1E2F0}', '\u{1E2F9}'),
+    ('\u{1E950}', '\u{1E959}'),
+    ('\u{1F100}', '\u{1F10A}'),
+    ('\u{1FBF0}', '\u{1FBF9}'),
+];
+
+// the titlecase mapping where it differs from the uppercase one, except the ranges in
+// push_titlecase
+const TITLECASE: &[(char, &str)] = &[
+    ('\u{DF}', "Ss"),
+    ('\u{1C4}', "ǅ"),
+    ('\u{1C5}', "ǅ"),
+    ('\u{1C6}', "ǅ"),
+    ('\u{1C7}', "ǈ"),
+    ('\u{1C8}', "ǈ"),
+    ('\u{1C9}', "ǈ"),
+    ('\u{1CA}', "ǋ"),
+    ('\u{1CB}', "ǋ"),
+    ('\u{1CC}', "ǋ"),
+    ('\u{1F1}', "ǲ"),
+    ('\u{1F2}', "ǲ"),
+    ('\u{1F3}', "ǲ"),
+    ('\u{587}', "Եւ"),
+    ('\u{1FB2}', "Ὰ\u{345}"),
+    ('\u{1FB3}', "ᾼ"),
+    ('\u{1FB4}', "Ά\u{345}"),
+    ('\u{1FB7}', "Α\u{342}\u{345}"),
+    ('\u{1FBC}', "ᾼ"),
+    ('\u{1FC2}', "Ὴ\u{345}"),
+    ('\u{1FC3}', "ῌ"),
+    ('\u{1FC4}', "Ή\u{345}"),
+    ('\u{1FC7}', "Η\u{342}\u{345}"),
+    ('\u{1FCC}', "ῌ"),
+    ('\u{1FF2}', "Ὼ\u{345}"),
+    ('\u{1FF3}', "ῼ"),
+    ('\u{1FF4}', "Ώ\u{345}"),
+    ('\u{1FF7}', "Ω\u{342}\u{345}"),
+    ('\u{1FFC}', "ῼ"),
+    ('\u{FB00}', "Ff"),
+    ('\u{FB01}', "Fi"),
+    ('\u{FB02}', "Fl"),
+    ('\u{FB03}', "Ffi"),
+    ('\u{FB04}', "Ffl"),
+    ('\u{FB05}', "St"),
+    ('\u{FB06}', "St"),
+    ('\u{FB13}', "Մն"),
+    ('\u{FB14}', "Մե"),
+    ('\u{FB15}', "Մի"),
+    ('\u{FB16}', "Վն"),
+    ('\u{FB17}', "Մխ"),
+];
+
+fn in_ranges(c: char, ranges: &[(char, char)]) -> bool {
+    ranges
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                std::cmp::Ordering::Less
+            } else if c < start {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+pub fn is_digit(c: char) -> bool {
+    in_ranges(c, DIGITS)
+}
+
+// general category Lt, neither upper nor lower case("ǅ")
+pub fn is_titlecase(c: char) -> bool {
+    matches!(
+        c,
+        '\u{1C5}'
+            | '\u{1C8}'
+            | '\u{1CB}'
+            | '\u{1F2}'
+            | '\u{1F88}'..='\u{1F8F}'
+            | '\u{1F98}'..='\u{1F9F}'
+            | '\u{1FA8}'..='\u{1FAF}'
+            | '\u{1FBC}'
+            | '\u{1FCC}'
+            | '\u{1FFC}'
+    )
+}
+
+// the Cased property, which str.title() uses
+pub fn is_cased(c: char) -> bool {
+    c.is_lowercase() || c.is_uppercase() || is_titlecase(c)
+}
+
+pub fn push_titlecase(res: &mut String, c: char) {
+    match c {
+        // Georgian Mkhedruli has an uppercase(Mtavruli) but is its own titlecase
+        '\u{10D0}'..='\u{10FF}' => res.push(c),
+        // Greek with ypogegrammeni: the titlecase one of each pair
+        '\u{1F80}'..='\u{1FAF}' => res.push(std::char::from_u32(c as u32 | 0x8).unwrap()),
+        _ => match TITLECASE.binary_search_by_key(&c, |&(from, _)| from) {
+            Ok(i) => res.push_str(TITLECASE[i].1),
+            Err(_) => res.extend(c.to_uppercase()),
+        },
+    }
+}
@@ -0,0 +1,312 @@
+//! # value
+//! tagged pointer representation of python values(py_val_t)
+//!
+//! a value is one word. the low bits tell what it holds:
+//!  int:         ...1    (63-bit signed integer in the upper bits)
+//!  small str:   ...010  (length in bits 3..6, up to 7 bytes of UTF-8 in bytes 1..8)
+//!  None/bools:  ...0110 (0: None, 1: False, 2: True in the upper bits)
+//!  heap object: ...000  (pointer made by Rc::into_raw)
+//! ints, None, bools and short strings therefore never allocate.
+//! the encoding is canonical(a value that fits in a word is never put on the heap),
+//! so two words with different bits hold different values unless both are heap objects.
+//! with the `boxed-values` feature every value goes on the heap, as `Rc<py_val>` did
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use crate::eval::gc;
+use crate::eval::limits::{track_alloc, track_free, value_size};
+use crate::eval::types::*;
+
+const INT_TAG: usize = 0b1;
+const STR_TAG: usize = 0b010;
+const STR_MASK: usize = 0b111;
+const CONST_TAG: usize = 0b0110;
+const CONST_MASK: usize = 0b1111;
+const PTR_MASK: usize = 0b111;
+
+const NONE: usize = CONST_TAG;
+const FALSE: usize = 1 << 4 | CONST_TAG;
+const TRUE: usize = 2 << 4 | CONST_TAG;
+
+// small strings borrow their bytes from the word itself, so the byte order must be known
+const IMMEDIATES: bool = cfg!(all(
+    target_pointer_width = "64",
+    target_endian = "little",
+    not(feature = "boxed-values")
+));
+const SMALL_STR_MAX: usize = 7;
+const INT_MIN: i64 = i64::MIN >> 1;
+const INT_MAX: i64 = i64::MAX >> 1;
+
+pub struct Value {
+    bits: usize,
+    // owns an Rc<py_val> when it holds a heap object(so it is neither Send nor Sync)
+    _marker: PhantomData<Rc<py_val>>,
+}
+
+// what a value holds, without touching the heap for immediates
+pub enum Unpacked<'a> {
+    Int(i64),
+    Str(&'a str),
+    None,
+    True,
+    False,
+    Heap(&'a py_val),
+}
+
+// a value seen as py_val. immediates are decoded into a temporary
+// (short strings are copied into a String, so prefer Value::as_str for them)
+pub enum ValueRef<'a> {
+    Heap(&'a py_val),
+    Decoded(py_val),
+}
+
+impl<'a> Deref for ValueRef<'a> {
+    type Target = py_val;
+    fn deref(&self) -> &py_val {
+        match self {
+            ValueRef::Heap(v) => v,
+            ValueRef::Decoded(v) => v,
+        }
+    }
+}
+
+impl Value {
+    #[inline]
+    fn from_bits(bits: usize) -> Value {
+        Value {
+            bits,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn new(v: py_val) -> Value {
+        if IMMEDIATES {
+            match v {
+                py_val::int(i) if (INT_MIN..=INT_MAX).contains(&i) => {
+                    return Value::from_bits(((i << 1) as usize) | INT_TAG)
+                }
+                py_val::None => return Value::from_bits(NONE),
+                py_val::False => return Value::from_bits(FALSE),
+                py_val::True => return Value::from_bits(TRUE),
+                py_val::string(ref s) if s.len() <= SMALL_STR_MAX => {
+                    let mut bytes = [0u8; 8];
+                    bytes[0] = (s.len() << 3 | STR_TAG) as u8;
+                    bytes[1..=s.len()].copy_from_slice(s.as_bytes());
+                    return Value::from_bits(usize::from_le_bytes(bytes));
+                }
+                _ => (),
+            }
+        }
+        track_alloc(value_size(&v));
+        let container = gc::is_container(&v);
+        let value = Value::from_rc(Rc::new(v));
+        if container {
+            gc::track(value.bits);
+        }
+        value
+    }
+
+    // the Rc must be new: it is neither tracked nor counted here
+    fn from_rc(rc: Rc<py_val>) -> Value {
+        let bits = Rc::into_raw(rc) as usize;
+        debug_assert_eq!(bits & PTR_MASK, 0);
+        Value::from_bits(bits)
+    }
+
+    // the heap value at addr(a heap_addr) without taking a reference.
+    // it must be alive, and the result must not be dropped
+    pub(crate) unsafe fn from_addr(addr: usize) -> ManuallyDrop<Value> {
+        ManuallyDrop::new(Value::from_bits(addr))
+    }
+
+    // references to the heap object(1 for immediates)
+    pub(crate) fn strong_count(&self) -> usize {
+        if self.is_heap() {
+            Rc::strong_count(&self.rc())
+        } else {
+            1
+        }
+    }
+
+    #[inline]
+    fn is_heap(&self) -> bool {
+        self.bits & PTR_MASK == 0
+    }
+
+    // the Rc this value owns. must only be called on heap values
+    fn rc(&self) -> ManuallyDrop<Rc<py_val>> {
+        ManuallyDrop::new(unsafe { Rc::from_raw(self.bits as *const py_val) })
+    }
+
+    #[inline]
+    pub fn unpack(&self) -> Unpacked<'_> {
+        if self.bits & INT_TAG != 0 {
+            Unpacked::Int((self.bits as i64) >> 1)
+        } else if self.bits & STR_MASK == STR_TAG {
+            let len = (self.bits >> 3) & 0b111;
+            // the word is little endian(checked by IMMEDIATES), so byte 0 is the tag
+            let bytes = unsafe {
+                std::slice::from_raw_parts((&self.bits as *const usize as *const u8).add(1), len)
+            };
+            Unpacked::Str(unsafe { std::str::from_utf8_unchecked(bytes) })
+        } else if self.bits & CONST_MASK == CONST_TAG {
+            match self.bits {
+                NONE => Unpacked::None,
+                FALSE => Unpacked::False,
+                _ => Unpacked::True,
+            }
+        } else {
+            Unpacked::Heap(unsafe { &*(self.bits as *const py_val) })
+        }
+    }
+
+    pub fn view(&self) -> ValueRef<'_> {
+        match self.unpack() {
+            Unpacked::Int(i) => ValueRef::Decoded(py_val::int(i)),
+            Unpacked::Str(s) => ValueRef::Decoded(py_val::string(s.to_string())),
+            Unpacked::None => ValueRef::Decoded(py_val::None),
+            Unpacked::False => ValueRef::Decoded(py_val::False),
+            Unpacked::True => ValueRef::Decoded(py_val::True),
+            Unpacked::Heap(v) => ValueRef::Heap(v),
+        }
+    }
+
+    // the same object(`is`). immediates are the same when they are equal
+    pub fn ptr_eq(a: &Value, b: &Value) -> bool {
+        a.bits == b.bits
+    }
+
+    // address of the heap object, if any
+    pub fn heap_addr(&self) -> Option<usize> {
+        if self.is_heap() {
+            Some(self.bits)
+        } else {
+            None
+        }
+    }
+
+    // the py_val of a heap object. None for immediates
+    pub fn heap(&self) -> Option<&py_val> {
+        match self.unpack() {
+            Unpacked::Heap(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> Option<&str> {
+        match self.unpack() {
+            Unpacked::Str(s) => Some(s),
+            Unpacked::Heap(py_val::string(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    // integer value for int/bool operands. None otherwise
+    #[inline]
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.unpack() {
+            Unpacked::Int(i) => Some(i),
+            Unpacked::True => Some(1),
+            Unpacked::False => Some(0),
+            Unpacked::Heap(v) => v.as_i64(),
+            _ => None,
+        }
+    }
+
+    // numeric value for int/bool/float operands. None otherwise
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.unpack() {
+            Unpacked::Heap(v) => v.as_f64(),
+            _ => self.as_i64().map(|i| i as f64),
+        }
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.bits == NONE || matches!(self.unpack(), Unpacked::Heap(py_val::None))
+    }
+
+    pub fn is_true(&self) -> bool {
+        match self.unpack() {
+            Unpacked::Int(i) => i != 0,
+            Unpacked::Str(s) => !s.is_empty(),
+            Unpacked::None | Unpacked::False => false,
+            Unpacked::True => true,
+            Unpacked::Heap(v) => v.is_true(),
+        }
+    }
+
+    pub fn is_false(&self) -> bool {
+        !self.is_true()
+    }
+
+    // name of the type, as CPython's type(v).__name__
+    pub fn type_name(&self) -> &'static str {
+        match self.unpack() {
+            Unpacked::Int(_) => "int",
+            Unpacked::Str(_) => "str",
+            Unpacked::None => "NoneType",
+            Unpacked::True | Unpacked::False => "bool",
+            Unpacked::Heap(v) => v.type_name(),
+        }
+    }
+}
+
+impl Clone for Value {
+    #[inline]
+    fn clone(&self) -> Value {
+        if self.is_heap() {
+            Value::from_rc(Rc::clone(&self.rc()))
+        } else {
+            Value::from_bits(self.bits)
+        }
+    }
+}
+
+impl Drop for Value {
+    #[inline]
+    fn drop(&mut self) {
+        if self.is_heap() {
+            let rc = self.rc();
+            if Rc::strong_count(&rc) == 1 {
+                track_free(value_size(&rc));
+                if gc::is_container(&rc) {
+                    gc::untrack(self.bits);
+                }
+            }
+            ManuallyDrop::into_inner(rc);
+        }
+    }
+}
+
+// made as Value::new makes it: immediates are unboxed, containers are tracked, the memory
+// is counted. the contents of a shared Rc are copied, since the collector has to see every
+// reference to a container
+impl From<Rc<py_val>> for Value {
+    fn from(rc: Rc<py_val>) -> Value {
+        Value::new(Rc::try_unwrap(rc).unwrap_or_else(|rc| (*rc).clone()))
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self.unpack(), other.unpack()) {
+            (Unpacked::Heap(a), Unpacked::Heap(b)) => a == b,
+            // canonical encoding: an immediate never equals a heap value
+            _ => self.bits == other.bits,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.view().fmt(f)
+    }
+}
@@ -0,0 +1,210 @@
+//! # vm
+//! compiler.rsが出力したバイトコードを実行するスタックVM
+//! it is a part of Evaluator, so the global environment, native functions, methods
+//! and error reporting are shared with the tree-walker
+
+use std::rc::Rc;
+
+use crate::eval::compiler::*;
+use crate::eval::dict::DictTable;
+use crate::eval::evaluator::Evaluator;
+use crate::eval::types::*;
+
+impl Evaluator {
+    // run a code object. locals has a slot for each of code.local_names, with the arguments first
+    pub(crate) fn run_code(&mut self, code: &Code, locals: Vec<Option<py_val_t>>) -> py_val_t {
+        self.with_exits(|evaluator, exits| evaluator.run_instrs(code, locals, exits))
+    }
+
+    // exits are those of the with blocks the code is in, left by an error too(with_exits)
+    fn run_instrs(
+        &mut self,
+        code: &Code,
+        mut locals: Vec<Option<py_val_t>>,
+        exits: &mut Vec<py_val_t>,
+    ) -> py_val_t {
+        let mut stack: Vec<py_val_t> = Vec::new();
+        let mut iters: Vec<std::vec::IntoIter<py_val_t>> = Vec::new();
+        let mut pc = 0;
+        loop {
+            let instr = &code.instrs[pc];
+            self.set_span(code.spans[pc]);
+            self.step(code.spans[pc]);
+            pc += 1;
+            match *instr {
+                Instr::LoadConst(i) => stack.push(code.consts[i].clone()),
+                Instr::LoadLocal(slot) => match locals[slot] {
+                    Some(ref v) => stack.push(v.clone()),
+                    None => self.error(format!(
+                        "UnboundLocalError: local variable '{}' referenced before assignment",
+                        code.local_names[slot]
+                    )),
+                },
+                Instr::StoreLocal(slot) => locals[slot] = stack.pop(),
+                Instr::LoadGlobal(i) => {
+                    let name = &code.names[i];
+                    match self.lookup_global(name) {
+                        Some(v) => stack.push(v.clone()),
+                        None => self.error(format!("NameError: {} is not defined", name)),
+                    }
+                }
+                Instr::StoreGlobal(i) => {
+                    let value = stack.pop().unwrap();
+                    self.set_global(&code.names[i], value);
+                }
+                Instr::ImportName(i) => {
+                    let module = self.import_module(&code.names[i]);
+                    stack.push(module);
+                }
+                Instr::ImportFrom(i) => {
+                    let value = self.import_from(stack.last().unwrap(), &code.names[i]);
+                    stack.push(value);
+                }
+                Instr::LoadAttr(i) => {
+                    let receiver = stack.pop().unwrap();
+                    let attr = self.get_attribute(receiver, &code.names[i]);
+                    stack.push(attr);
+                }
+                Instr::GetItem => {
+                    let index = stack.pop().unwrap();
+                    let value = stack.pop().unwrap();
+                    let res = self.call_global("__getitem__", vec![value, index]);
+                    stack.push(res);
+                }
+                Instr::SetItem => {
+                    let index = stack.pop().unwrap();
+                    let target = stack.pop().unwrap();
+                    let value = stack.pop().unwrap();
+                    self.call_global("__setitem__", vec![target, index, value]);
+                }
+                Instr::BinaryOp(ref op) => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    let res = self.binary_op(op, lhs, rhs);
+                    stack.push(res);
+                }
+                Instr::UnaryOp(ref op) => {
+                    let operand = stack.pop().unwrap();
+                    let res = self.unary_op(op, operand);
+                    stack.push(res);
+                }
+                Instr::CompareOp(ref op) => {
+                    let right = stack.pop().unwrap();
+                    let left = stack.pop().unwrap();
+                    let res = self.compare_op(op, left, right);
+                    stack.push(py_val::from_bool(res));
+                }
+                Instr::BuildList(n) => {
+                    let elts = stack.split_off(stack.len() - n);
+                    stack.push(py_val::new_list(elts));
+                }
+                Instr::BuildTuple(n) => {
+                    let elts = stack.split_off(stack.len() - n);
+                    stack.push(py_val::new(py_val::tuple(elts)));
+                }
+                Instr::BuildDict(n) => {
+                    let mut table = DictTable::new();
+                    let mut items = stack.split_off(stack.len() - 2 * n).into_iter();
+                    while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                        let hash = self.hash_of(&key);
+                        table.insert(hash, key, value);
+                    }
+                    stack.push(py_val::new(py_val::dict(table.into())));
+                }
+                Instr::BuildSet(n) => {
+                    let mut table = DictTable::new();
+                    for elt in stack.split_off(stack.len() - n) {
+                        let hash = self.hash_of(&elt);
+                        table.insert(hash, elt.clone(), elt);
+                    }
+                    stack.push(py_val::new(py_val::set(table.into())));
+                }
+                Instr::Call(n) => {
+                    let args = stack.split_off(stack.len() - n);
+                    let func = stack.pop().unwrap();
+                    let res = self.call_func(func, args, Vec::new());
+                    stack.push(res);
+                }
+                Instr::CallKw(n, names) => {
+                    let names = match code.consts[names].heap() {
+                        Some(py_val::tuple(names)) => names,
+                        _ => unreachable!(),
+                    };
+                    let kwargs = names
+                        .iter()
+                        .map(|name| name.as_str().unwrap().to_string())
+                        .zip(stack.split_off(stack.len() - names.len()))
+                        .collect();
+                    let args = stack.split_off(stack.len() - n);
+                    let func = stack.pop().unwrap();
+                    let res = self.call_func(func, args, kwargs);
+                    stack.push(res);
+                }
+                Instr::MakeFunction(i) => {
+                    let code = Rc::clone(&code.codes[i]);
+                    stack.push(py_val::new(py_val::code_func(py_code_func {
+                        name: code.name.clone(),
+                        code,
+                        file_name: self.current_file_name().to_string(),
+                        module: self.current_module,
+                    })));
+                }
+                Instr::Jump(target) => pc = target,
+                Instr::PopJumpIfFalse(target) => {
+                    if stack.pop().unwrap().is_false() {
+                        pc = target;
+                    }
+                }
+                Instr::PopJumpIfTrue(target) => {
+                    if stack.pop().unwrap().is_true() {
+                        pc = target;
+                    }
+                }
+                Instr::JumpIfFalseOrPop(target) => {
+                    if stack.last().unwrap().is_false() {
+                        pc = target;
+                    } else {
+                        stack.pop();
+                    }
+                }
+                Instr::GetIter => {
+                    let iterable = stack.pop().unwrap();
+                    iters.push(self.iter_values(&iterable).into_iter());
+                }
+                Instr::ForIter(target) => match iters.last_mut().unwrap().next() {
+                    Some(v) => stack.push(v),
+                    None => {
+                        iters.pop();
+                        pc = target;
+                    }
+                },
+                Instr::PopIter => {
+                    iters.pop();
+                }
+                Instr::BeforeWith => {
+                    let manager = stack.pop().unwrap();
+                    let (exit, value) = self.enter_context(manager);
+                    exits.push(exit);
+                    stack.push(value);
+                }
+                Instr::ExitWith => {
+                    let exit = exits.pop().unwrap();
+                    self.exit_context(exit);
+                }
+                Instr::PopTop => {
+                    stack.pop();
+                }
+                Instr::DupTop => stack.push(stack.last().unwrap().clone()),
+                Instr::RotTwo => {
+                    let len = stack.len();
+                    stack.swap(len - 1, len - 2);
+                }
+                Instr::RotThree => {
+                    let top = stack.pop().unwrap();
+                    stack.insert(stack.len() - 2, top);
+                }
+                Instr::ReturnValue => return stack.pop().unwrap(),
+            }
+        }
+    }
+}
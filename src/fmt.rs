@@ -0,0 +1,131 @@
+//! # fmt
+//! ソースコードの整形(minipython fmt)
+//! the file is parsed and regenerated by unparse, with the comments kept by the tokenizer
+
+use crate::parser::{Parser, SyntaxError};
+use crate::unparse::unparse_with_comments;
+
+/// formatted source of the file. the file must have no syntax errors
+pub fn format_file(file_name: &str) -> std::io::Result<Result<String, Vec<SyntaxError>>> {
+    let mut parser = Parser::new(file_name)?;
+    let ast = parser.parse();
+    if !parser.errors().is_empty() {
+        return Ok(Err(parser.errors().to_vec()));
+    }
+    let formatted = unparse_with_comments(&ast, parser.take_comments());
+
+    // 整形でプログラムが変わっていないことを確認する
+    let mut check = Parser::from_source(file_name, formatted.clone());
+    if check.parse() != ast || !check.errors().is_empty() {
+        panic!("formatting changed the program: {}", file_name);
+    }
+    Ok(Ok(formatted))
+}
+
+// 差分の1行
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Edit {
+    Same(usize, usize), // line of old, line of new(0-based)
+    Delete(usize),
+    Insert(usize),
+}
+
+// lines of the shortest edit, by LCS
+fn edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    // lcs[i][j]: LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut res = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            res.push(Edit::Same(i, j));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            // 削除を先に出す
+            res.push(Edit::Delete(i));
+            i += 1;
+        } else {
+            res.push(Edit::Insert(j));
+            j += 1;
+        }
+    }
+    res
+}
+
+/// unified diff(as `diff -u`) with 3 lines of context. empty if old == new
+pub fn unified_diff(file_name: &str, old: &str, new: &str) -> String {
+    const CONTEXT: usize = 3;
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = edits(&old_lines, &new_lines);
+
+    // 変更箇所の前後CONTEXT行をhunkにまとめる
+    let changed: Vec<usize> = (0..edits.len())
+        .filter(|&k| !matches!(edits[k], Edit::Same(..)))
+        .collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &k in &changed {
+        let start = k.saturating_sub(CONTEXT);
+        let end = (k + CONTEXT + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut res = format!("--- {}\n+++ {}\n", file_name, file_name);
+    for (start, end) in hunks {
+        // hunk の前にある各側の行数
+        let old_before = edits[..start]
+            .iter()
+            .filter(|e| !matches!(e, Edit::Insert(_)))
+            .count();
+        let new_before = edits[..start]
+            .iter()
+            .filter(|e| !matches!(e, Edit::Delete(_)))
+            .count();
+        let (mut old_count, mut new_count) = (0, 0);
+        let mut body = String::new();
+        for edit in &edits[start..end] {
+            match *edit {
+                Edit::Same(i, _) => {
+                    old_count += 1;
+                    new_count += 1;
+                    body.push_str(&format!(" {}\n", old_lines[i]));
+                }
+                Edit::Delete(i) => {
+                    old_count += 1;
+                    body.push_str(&format!("-{}\n", old_lines[i]));
+                }
+                Edit::Insert(j) => {
+                    new_count += 1;
+                    body.push_str(&format!("+{}\n", new_lines[j]));
+                }
+            }
+        }
+        // 空の側は直前の行番号になる(diff -u と同じ)
+        let line = |before: usize, count: usize| if count == 0 { before } else { before + 1 };
+        res.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n{}",
+            line(old_before, old_count),
+            old_count,
+            line(new_before, new_count),
+            new_count,
+            body
+        ));
+    }
+    res
+}
@@ -0,0 +1,152 @@
+//! # interpreter
+//! Rustのアプリケーションに埋め込むためのAPI
+//! snippets of source run one after another in the same __main__ module, so globals persist.
+//! errors raised by the code come back as PyError, and the interpreter can be used again
+
+use std::io;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+
+use crate::ast::*;
+use crate::eval::convert::{FromPy, IntoPy, IntoPyArgs};
+use crate::eval::evaluator::{Engine, Evaluator};
+use crate::eval::limits::{CancelHandle, Limits};
+use crate::eval::types::*;
+use crate::parser::Parser;
+
+// file name of the snippets in tracebacks, as CPython's exec
+const SOURCE_NAME: &str = "<string>";
+
+pub struct Interpreter {
+    evaluator: Evaluator,
+}
+
+impl Interpreter {
+    pub fn new() -> io::Result<Interpreter> {
+        Interpreter::with_engine(Engine::Tree)
+    }
+
+    pub fn with_engine(engine: Engine) -> io::Result<Interpreter> {
+        let mut evaluator = Evaluator::from_source(SOURCE_NAME, "", engine)?;
+        evaluator.embedded = true;
+        // the embedder gives filesystem access explicitly
+        evaluator.set_filesystem_access(false);
+        Ok(Interpreter { evaluator })
+    }
+
+    /// limits of each run, eval_expr and call
+    pub fn with_limits(mut self, limits: Limits) -> Interpreter {
+        self.evaluator.set_limits(limits);
+        self
+    }
+
+    /// a handle to stop the running code from another thread
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.evaluator.cancel_handle()
+    }
+
+    /// the evaluator, to register native functions and modules
+    pub fn evaluator(&mut self) -> &mut Evaluator {
+        &mut self.evaluator
+    }
+
+    /// run the statements. the result is the value of the last one if it is an expression
+    /// statement, None otherwise
+    pub fn run(&mut self, source: &str) -> Result<Value, PyError> {
+        let mut ast = parse(source)?;
+        let last = match ast.last() {
+            Some(ASTStmt {
+                node: ASTStmtKind::Expr(_),
+                ..
+            }) => ast.pop(),
+            _ => None,
+        };
+        self.catch(|evaluator| {
+            evaluator.exec_module_body(&ast);
+            match last {
+                Some(ASTStmt {
+                    node: ASTStmtKind::Expr(expr),
+                    ..
+                }) => evaluator.eval_module_expr(&expr),
+                _ => py_val::new(py_val::None),
+            }
+        })
+    }
+
+    /// value of a single expression
+    pub fn eval_expr(&mut self, source: &str) -> Result<Value, PyError> {
+        match parse(source)?.as_slice() {
+            [ASTStmt {
+                node: ASTStmtKind::Expr(expr),
+                ..
+            }] => self.catch(|evaluator| evaluator.eval_module_expr(expr)),
+            _ => Err(PyError::new(
+                "SyntaxError: expected a single expression".to_string(),
+            )),
+        }
+    }
+
+    /// global variable of __main__ as a Rust value
+    pub fn get_global<T: FromPy>(&self, name: &str) -> Result<T, PyError> {
+        match self.evaluator.get_global(name) {
+            Some(value) => T::from_py(&value),
+            None => Err(PyError::new(format!("NameError: {} is not defined", name))),
+        }
+    }
+
+    pub fn set_global<T: IntoPy>(&mut self, name: &str, value: T) {
+        self.evaluator.set_global(name, value.into_py());
+    }
+
+    /// call the global function(or any callable) `name` of __main__
+    pub fn call<A: IntoPyArgs, R: FromPy>(&mut self, name: &str, args: A) -> Result<R, PyError> {
+        let func = match self.evaluator.lookup_global(name) {
+            Some(func) => func.clone(),
+            None => return Err(PyError::new(format!("NameError: {} is not defined", name))),
+        };
+        let args = args.into_py_args();
+        let res = self.catch(|evaluator| evaluator.call_func(func, args, Vec::new()))?;
+        R::from_py(&res)
+    }
+
+    // errors of the evaluator unwind to here
+    fn catch<F>(&mut self, f: F) -> Result<Value, PyError>
+    where
+        F: FnOnce(&mut Evaluator) -> Value,
+    {
+        let evaluator = &mut self.evaluator;
+        evaluator.budget.start();
+        let run = || {
+            let value = f(evaluator);
+            evaluator.check_budget();
+            value
+        };
+        match catch_unwind(AssertUnwindSafe(run)) {
+            Ok(value) => Ok(value),
+            Err(payload) => match payload.downcast::<PyError>() {
+                Ok(error) => {
+                    self.evaluator.recover();
+                    Err(*error)
+                }
+                // a bug of the interpreter, not an error of the code
+                Err(payload) => resume_unwind(payload),
+            },
+        }
+    }
+}
+
+fn parse(source: &str) -> Result<AST, PyError> {
+    // a block needs the newline at its end
+    let mut source = source.to_string();
+    if !source.ends_with('\n') {
+        source.push('\n');
+    }
+    let mut parser = Parser::from_source(SOURCE_NAME, source);
+    let ast = parser.parse();
+    match parser.errors().first() {
+        Some(error) => Err(PyError {
+            message: format!("SyntaxError: {}", error.message),
+            traceback: error.format(SOURCE_NAME),
+        }),
+        None => Ok(ast),
+    }
+}
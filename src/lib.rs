@@ -1,9 +1,16 @@
 pub mod ast;
+pub mod ast_dump;
 pub mod char_stream;
+pub mod check;
 pub mod eval;
+pub mod fmt;
+pub mod interpreter;
+pub mod lint;
 pub mod parser;
 pub mod token;
 pub mod tokenizer;
+pub mod traceback;
+pub mod unparse;
 
 #[cfg(test)]
 pub mod test;
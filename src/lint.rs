@@ -0,0 +1,503 @@
+//! # lint
+//! 静的検査(minipython lint)
+//! reports the mistakes the evaluator only finds at runtime(or never), without running the file.
+//! names are resolved as the evaluator does: closures are not supported, so a function sees
+//! its own locals, the module globals and the builtins only
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::*;
+use crate::ast_dump::json_str;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    // kebab-case name of the check, e.g. `undefined-name`
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(code: &'static str, message: String, span: Span) -> Diagnostic {
+        Diagnostic {
+            code,
+            message,
+            span,
+        }
+    }
+
+    // `file:line:column: message [code]`(the column is 1-based, as other linters)
+    pub fn format(&self, file_name: &str) -> String {
+        format!(
+            "{}:{}:{}: {} [{}]",
+            file_name,
+            self.span.line,
+            self.span.column + 1,
+            self.message,
+            self.code
+        )
+    }
+}
+
+/// diagnostics of the module, sorted by position. builtins are the names defined before the
+/// file is run
+pub fn lint(ast: &AST, builtins: &[String]) -> Vec<Diagnostic> {
+    let mut module_names: HashSet<String> = builtins.iter().cloned().collect();
+    module_bindings(ast, &mut module_names);
+    let mut linter = Linter {
+        module_names,
+        scopes: vec![Scope::default()],
+        loop_depth: 0,
+        diagnostics: Vec::new(),
+    };
+    linter.body(ast);
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort_by_key(|d| (d.span.line, d.span.column));
+    diagnostics
+}
+
+/// JSON array of the diagnostics
+pub fn to_json(file_name: &str, diagnostics: &[Diagnostic]) -> String {
+    let items: Vec<String> = diagnostics
+        .iter()
+        .map(|d| {
+            format!(
+                "  {{\"file\": {}, \"code\": {}, \"message\": {}, \"span\": {{\"line\": {}, \"column\": {}, \"end_line\": {}, \"end_column\": {}}}}}",
+                json_str(file_name),
+                json_str(d.code),
+                json_str(&d.message),
+                d.span.line,
+                d.span.column,
+                d.span.end_line,
+                d.span.end_column
+            )
+        })
+        .collect();
+    if items.is_empty() {
+        String::from("[]")
+    } else {
+        format!("[\n{}\n]", items.join(",\n"))
+    }
+}
+
+// 束縛の種類(未使用の報告に使う)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Binding {
+    Argument,
+    Assignment,
+    // for target, def: not reported when unused
+    Other,
+}
+
+#[derive(Debug)]
+struct Local {
+    kind: Binding,
+    span: Span,
+    used: bool,
+}
+
+// module(scopes[0]) or function
+#[derive(Debug, Default)]
+struct Scope {
+    // empty for the module, whose names are in Linter::module_names
+    locals: HashMap<String, Local>,
+    globals: HashSet<String>,
+    // names bound or read so far, for `global` declarations after them
+    assigned: HashSet<String>,
+    used: HashSet<String>,
+}
+
+struct Linter {
+    // globals and builtins
+    module_names: HashSet<String>,
+    scopes: Vec<Scope>,
+    loop_depth: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+// names bound at module level, including the ones assigned after `global` in functions
+fn module_bindings(body: &[ASTStmt], names: &mut HashSet<String>) {
+    let mut bindings = Vec::new();
+    bound_names(body, &mut bindings);
+    names.extend(bindings.into_iter().map(|(name, _, _)| name));
+    for_each_function(body, &mut |_, body| {
+        let mut globals = HashSet::new();
+        global_names(body, &mut globals);
+        let mut bindings = Vec::new();
+        bound_names(body, &mut bindings);
+        for (name, _, _) in bindings {
+            if globals.contains(&name) {
+                names.insert(name);
+            }
+        }
+    });
+}
+
+// every function(nested ones too) defined in body
+fn for_each_function(body: &[ASTStmt], f: &mut dyn FnMut(&ASTArguments, &[ASTStmt])) {
+    for stmt in body {
+        match &stmt.node {
+            ASTStmtKind::FuncDef(_, args, body, _) => {
+                f(args, body);
+                for_each_function(body, f);
+            }
+            ASTStmtKind::For(_, _, body)
+            | ASTStmtKind::While(_, body)
+            | ASTStmtKind::With(_, body) => for_each_function(body, f),
+            ASTStmtKind::If(_, body, orelse) => {
+                for_each_function(body, f);
+                for_each_function(orelse, f);
+            }
+            _ => (),
+        }
+    }
+}
+
+// names bound in body(not in nested functions), in order of appearance
+fn bound_names(body: &[ASTStmt], res: &mut Vec<(String, Binding, Span)>) {
+    fn target(expr: &ASTExpr, kind: Binding, res: &mut Vec<(String, Binding, Span)>) {
+        match &expr.node {
+            ASTExprKind::Name(name) => res.push((name.clone(), kind, expr.span)),
+            ASTExprKind::Tuple(elts) | ASTExprKind::List(elts) => {
+                elts.iter().for_each(|elt| target(elt, kind, res))
+            }
+            _ => (),
+        }
+    }
+    for stmt in body {
+        match &stmt.node {
+            ASTStmtKind::FuncDef(name, _, _, _) => {
+                res.push((name.clone(), Binding::Other, stmt.span))
+            }
+            ASTStmtKind::Assign(targets, _) => targets
+                .iter()
+                .for_each(|t| target(t, Binding::Assignment, res)),
+            // `x: int` alone declares x only
+            ASTStmtKind::AnnAssign(t, _, value) => {
+                let kind = match value {
+                    Some(_) => Binding::Assignment,
+                    None => Binding::Other,
+                };
+                target(t, kind, res)
+            }
+            ASTStmtKind::For(t, _, body) => {
+                target(t, Binding::Other, res);
+                bound_names(body, res);
+            }
+            ASTStmtKind::While(_, body) => bound_names(body, res),
+            ASTStmtKind::With(items, body) => {
+                for item in items {
+                    if let Some(t) = &item.node.1 {
+                        target(t, Binding::Other, res);
+                    }
+                }
+                bound_names(body, res);
+            }
+            ASTStmtKind::If(_, body, orelse) => {
+                bound_names(body, res);
+                bound_names(orelse, res);
+            }
+            ASTStmtKind::Import(_) | ASTStmtKind::ImportFrom(..) => res.extend(
+                stmt.node
+                    .imported_names()
+                    .into_iter()
+                    .map(|name| (name.to_string(), Binding::Other, stmt.span)),
+            ),
+            _ => (),
+        }
+    }
+}
+
+// names declared `global` in body(not in nested functions)
+fn global_names(body: &[ASTStmt], res: &mut HashSet<String>) {
+    for stmt in body {
+        match &stmt.node {
+            ASTStmtKind::Global(names) => res.extend(names.iter().cloned()),
+            ASTStmtKind::For(_, _, body)
+            | ASTStmtKind::While(_, body)
+            | ASTStmtKind::With(_, body) => global_names(body, res),
+            ASTStmtKind::If(_, body, orelse) => {
+                global_names(body, res);
+                global_names(orelse, res);
+            }
+            _ => (),
+        }
+    }
+}
+
+// control never reaches the statement after this one
+fn terminates(stmt: &ASTStmt) -> bool {
+    match &stmt.node {
+        ASTStmtKind::Return(_) | ASTStmtKind::Break | ASTStmtKind::Continue => true,
+        ASTStmtKind::If(_, body, orelse) => {
+            body.iter().any(terminates) && orelse.iter().any(terminates)
+        }
+        _ => false,
+    }
+}
+
+impl Linter {
+    fn report(&mut self, code: &'static str, message: String, span: Span) {
+        self.diagnostics.push(Diagnostic::new(code, message, span));
+    }
+
+    fn scope(&mut self) -> &mut Scope {
+        self.scopes.last_mut().unwrap()
+    }
+
+    fn in_function(&self) -> bool {
+        self.scopes.len() > 1
+    }
+
+    fn body(&mut self, body: &[ASTStmt]) {
+        for (i, stmt) in body.iter().enumerate() {
+            self.stmt(stmt);
+            if terminates(stmt) && i + 1 < body.len() {
+                let span = body[i + 1].span.to(body.last().unwrap().span);
+                self.report("unreachable-code", String::from("unreachable code"), span);
+                // the rest is checked still, for the names in it
+                body[i + 1..].iter().for_each(|stmt| self.stmt(stmt));
+                return;
+            }
+        }
+    }
+
+    fn function(&mut self, args: &ASTArguments, body: &[ASTStmt]) {
+        let mut scope = Scope::default();
+        global_names(body, &mut scope.globals);
+        let mut bindings: Vec<(String, Binding, Span)> = args
+            .iter()
+            .map(|arg| (arg.node.0.clone(), Binding::Argument, arg.span))
+            .collect();
+        bound_names(body, &mut bindings);
+        for (name, kind, span) in bindings {
+            if !scope.globals.contains(&name) && !scope.locals.contains_key(&name) {
+                let used = false;
+                scope.locals.insert(name, Local { kind, span, used });
+            }
+        }
+        scope
+            .assigned
+            .extend(args.iter().map(|arg| arg.node.0.clone()));
+
+        self.scopes.push(scope);
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        self.body(body);
+        self.loop_depth = loop_depth;
+        let scope = self.scopes.pop().unwrap();
+
+        let mut unused: Vec<(String, Local)> = scope
+            .locals
+            .into_iter()
+            .filter(|(name, local)| !local.used && !name.starts_with('_'))
+            .collect();
+        unused.sort_by_key(|(_, local)| (local.span.line, local.span.column));
+        for (name, local) in unused {
+            match local.kind {
+                Binding::Argument => self.report(
+                    "unused-argument",
+                    format!("unused argument '{}'", name),
+                    local.span,
+                ),
+                Binding::Assignment => self.report(
+                    "unused-variable",
+                    format!("local variable '{}' is assigned to but never used", name),
+                    local.span,
+                ),
+                Binding::Other => (),
+            }
+        }
+    }
+
+    fn stmt(&mut self, stmt: &ASTStmt) {
+        use ASTStmtKind::*;
+        match &stmt.node {
+            FuncDef(name, args, body, _) => {
+                self.scope().assigned.insert(name.clone());
+                self.function(args, body);
+            }
+            Return(value) => {
+                if !self.in_function() {
+                    self.report(
+                        "return-outside-function",
+                        String::from("'return' outside function"),
+                        stmt.span,
+                    );
+                }
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            // `del x` needs x to be defined
+            Delete(targets) => self.exprs(targets),
+            Assign(targets, value) => {
+                self.expr(value);
+                targets.iter().for_each(|target| self.target(target));
+            }
+            // annotations are not evaluated at runtime
+            AnnAssign(target, _, value) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                    self.target(target);
+                }
+            }
+            For(target, iter, body) => {
+                self.expr(iter);
+                self.target(target);
+                self.loop_depth += 1;
+                self.body(body);
+                self.loop_depth -= 1;
+            }
+            While(test, body) => {
+                self.expr(test);
+                self.loop_depth += 1;
+                self.body(body);
+                self.loop_depth -= 1;
+            }
+            With(items, body) => {
+                for item in items {
+                    let (context_expr, optional_vars) = &item.node;
+                    self.expr(context_expr);
+                    if let Some(target) = optional_vars {
+                        self.target(target);
+                    }
+                }
+                self.body(body);
+            }
+            If(test, body, orelse) => {
+                self.expr(test);
+                self.body(body);
+                self.body(orelse);
+            }
+            Global(names) => {
+                for name in names {
+                    // CPython rejects these as SyntaxError
+                    let message = if self.scope().assigned.contains(name) {
+                        format!("name '{}' is assigned to before global declaration", name)
+                    } else if self.scope().used.contains(name) {
+                        format!("name '{}' is used prior to global declaration", name)
+                    } else {
+                        continue;
+                    };
+                    self.report("global-after-use", message, stmt.span);
+                }
+            }
+            Import(_) | ImportFrom(..) => {
+                for name in stmt.node.imported_names() {
+                    self.scope().assigned.insert(name.to_string());
+                }
+            }
+            Expr(value) => self.expr(value),
+            Break | Continue => {
+                if self.loop_depth == 0 {
+                    let keyword = if stmt.node == Break {
+                        "break"
+                    } else {
+                        "continue"
+                    };
+                    self.report(
+                        "outside-loop",
+                        format!("'{}' outside loop", keyword),
+                        stmt.span,
+                    );
+                }
+            }
+            Init | End | Error | Pass => (),
+        }
+    }
+
+    fn target(&mut self, target: &ASTExpr) {
+        match &target.node {
+            ASTExprKind::Name(name) => {
+                self.scope().assigned.insert(name.clone());
+            }
+            ASTExprKind::Tuple(elts) | ASTExprKind::List(elts) => {
+                elts.iter().for_each(|elt| self.target(elt))
+            }
+            // `a[i] = v` and `a.x = v` read a
+            _ => self.expr(target),
+        }
+    }
+
+    // a name is read
+    fn name(&mut self, name: &str, span: Span) {
+        self.scope().used.insert(name.to_string());
+        let scope = self.scopes.last_mut().unwrap();
+        if let Some(local) = scope.locals.get_mut(name) {
+            local.used = true;
+            return;
+        }
+        if self.module_names.contains(name) {
+            return;
+        }
+        // the local of the enclosing function is reported here only, not as unused too
+        let depth = self.scopes.len();
+        let mut enclosing = false;
+        for scope in &mut self.scopes[1.min(depth - 1)..depth - 1] {
+            if let Some(local) = scope.locals.get_mut(name) {
+                local.used = true;
+                enclosing = true;
+            }
+        }
+        let message = if enclosing && !self.scope().globals.contains(name) {
+            format!(
+                "name '{}' of the enclosing function is not visible here (closures are not supported)",
+                name
+            )
+        } else {
+            format!("name '{}' is not defined", name)
+        };
+        self.report("undefined-name", message, span);
+    }
+
+    fn exprs(&mut self, exprs: &[ASTExpr]) {
+        exprs.iter().for_each(|expr| self.expr(expr));
+    }
+
+    fn expr(&mut self, expr: &ASTExpr) {
+        use ASTExprKind::*;
+        match &expr.node {
+            BoolOp(_, values) => self.exprs(values),
+            BinOp(lhs, _, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            UnaryOp(_, operand) => self.expr(operand),
+            IfExp(test, body, orelse) => {
+                self.expr(test);
+                self.expr(body);
+                self.expr(orelse);
+            }
+            Dict(keys, values) => {
+                self.exprs(keys);
+                self.exprs(values);
+            }
+            Set(elts) | List(elts) | Tuple(elts) => self.exprs(elts),
+            Compare(left, _, comparators) => {
+                self.expr(left);
+                self.exprs(comparators);
+            }
+            Call(func, args, keywords) => {
+                self.expr(func);
+                self.exprs(args);
+                keywords
+                    .iter()
+                    .for_each(|keyword| self.expr(&keyword.node.1));
+            }
+            Constant(_) => (),
+            Attribute(value, _) => self.expr(value),
+            Subscript(value, slice) => {
+                self.expr(value);
+                match &slice.node {
+                    ASTSliceKind::Index(index) => self.expr(index),
+                    ASTSliceKind::Slice(lower, upper, step) => {
+                        for e in [lower, upper, step].iter().copied().flatten() {
+                            self.expr(e);
+                        }
+                    }
+                }
+            }
+            Name(name) => self.name(name, expr.span),
+        }
+    }
+}
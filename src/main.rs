@@ -1,35 +1,166 @@
 use clap::{App, Arg, SubCommand};
 use std::io;
 
+use minipython::ast::AST;
+use minipython::ast_dump;
 use minipython::char_stream::CharStream;
-use minipython::eval::evaluator::Evaluator;
+use minipython::check;
+use minipython::eval::evaluator::{Engine, Evaluator};
+use minipython::fmt;
+use minipython::lint::{self, Diagnostic};
 use minipython::parser::Parser;
 use minipython::tokenizer::Tokenizer;
 
+// AST of the file. syntax errors are printed and the process exits
+fn parse_or_exit(file_name: &str) -> io::Result<AST> {
+    let mut parser = Parser::new(file_name)?;
+    let ast = parser.parse();
+    if !parser.errors().is_empty() {
+        for error in parser.errors() {
+            eprint!("{}", error.format(file_name));
+        }
+        std::process::exit(1);
+    }
+    Ok(ast)
+}
+
+// print the diagnostics of lint or check, and exit with 1 if there are any
+fn report(file_name: &str, diagnostics: &[Diagnostic], format: Option<&str>) {
+    match format {
+        Some("json") => println!("{}", lint::to_json(file_name, diagnostics)),
+        _ => {
+            for diagnostic in diagnostics {
+                println!("{}", diagnostic.format(file_name));
+            }
+        }
+    }
+    if !diagnostics.is_empty() {
+        std::process::exit(1);
+    }
+}
+
 fn main() -> io::Result<()> {
     let matches = App::new("minipython")
         .subcommands(vec![
             SubCommand::with_name("lc").about("count up lines"),
             SubCommand::with_name("apos").about("output 'a' position"),
             SubCommand::with_name("tokenize").about("run only tokenizer"),
-            SubCommand::with_name("parse").about("output AST"),
+            SubCommand::with_name("parse").about("output AST").arg(
+                Arg::with_name("format")
+                    .long("format")
+                    .help("json: for other tools, dump: as ast.dump(indent=2) of CPython")
+                    .takes_value(true)
+                    .possible_values(&["dump", "json", "sexpr", "debug"])
+                    .default_value("dump"),
+            ),
+            SubCommand::with_name("lint")
+                .about("report mistakes without running the file")
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["text", "json"])
+                        .default_value("text"),
+                ),
+            SubCommand::with_name("check")
+                .about("check the types without running the file")
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["text", "json"])
+                        .default_value("text"),
+                ),
+            SubCommand::with_name("fmt")
+                .about("format the file in place")
+                .arg(
+                    Arg::with_name("check")
+                        .long("check")
+                        .help("exit with 1 if the file is not formatted, without writing"),
+                )
+                .arg(
+                    Arg::with_name("diff")
+                        .long("diff")
+                        .help("print the changes as unified diff, without writing"),
+                ),
+            SubCommand::with_name("debug").about("run the file under the debugger(as pdb)"),
         ])
         .arg(Arg::with_name("file").required(true))
+        .arg(
+            Arg::with_name("engine")
+                .long("engine")
+                .help("how the program is executed")
+                .takes_value(true)
+                .possible_values(&["tree", "vm"])
+                .default_value("tree"),
+        )
         .get_matches();
 
-    if let Some(_) = matches.subcommand_matches("lc") {
+    if matches.subcommand_matches("lc").is_some() {
         CharStream::new(matches.value_of("file").unwrap())?.lc();
-    } else if let Some(_) = matches.subcommand_matches("apos") {
+    } else if matches.subcommand_matches("apos").is_some() {
         CharStream::new(matches.value_of("file").unwrap())?.apos();
-    } else if let Some(_) = matches.subcommand_matches("tokenize") {
+    } else if matches.subcommand_matches("tokenize").is_some() {
         Tokenizer::new(matches.value_of("file").unwrap())?.tokenize();
-    } else if let Some(_) = matches.subcommand_matches("parse") {
-        println!(
-            "{:?}",
-            Parser::new(matches.value_of("file").unwrap())?.parse()
+    } else if let Some(parse) = matches.subcommand_matches("parse") {
+        let mut parser = Parser::new(matches.value_of("file").unwrap())?;
+        let ast = parser.parse();
+        match parse.value_of("format") {
+            Some("json") => println!("{}", ast_dump::to_json(&ast)),
+            Some("sexpr") => println!("{}", ast_dump::to_sexpr(&ast)),
+            Some("debug") => println!("{:?}", ast),
+            _ => println!("{}", ast_dump::dump(&ast)),
+        }
+        for error in parser.errors() {
+            eprint!("{}", error.format(parser.get_file_name()));
+        }
+        if !parser.errors().is_empty() {
+            std::process::exit(1);
+        }
+    } else if let Some(lint) = matches.subcommand_matches("lint") {
+        let file_name = matches.value_of("file").unwrap();
+        let ast = parse_or_exit(file_name)?;
+        let builtins = Evaluator::new(file_name)?.global_names();
+        report(
+            file_name,
+            &lint::lint(&ast, &builtins),
+            lint.value_of("format"),
         );
+    } else if let Some(check) = matches.subcommand_matches("check") {
+        let file_name = matches.value_of("file").unwrap();
+        let ast = parse_or_exit(file_name)?;
+        report(file_name, &check::check(&ast), check.value_of("format"));
+    } else if let Some(fmt) = matches.subcommand_matches("fmt") {
+        let file_name = matches.value_of("file").unwrap();
+        let formatted = match fmt::format_file(file_name)? {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                for error in errors {
+                    eprint!("{}", error.format(file_name));
+                }
+                std::process::exit(1);
+            }
+        };
+        let source = std::fs::read_to_string(file_name)?;
+        if fmt.is_present("diff") || fmt.is_present("check") {
+            if fmt.is_present("diff") {
+                print!("{}", fmt::unified_diff(file_name, &source, &formatted));
+            }
+            if fmt.is_present("check") && formatted != source {
+                eprintln!("would reformat {}", file_name);
+                std::process::exit(1);
+            }
+        } else if formatted != source {
+            std::fs::write(file_name, formatted)?;
+        }
+    } else if matches.subcommand_matches("debug").is_some() {
+        Evaluator::new(matches.value_of("file").unwrap())?.debug();
     } else {
-        Evaluator::new(matches.value_of("file").unwrap())?.eval();
+        let engine = match matches.value_of("engine") {
+            Some("vm") => Engine::Vm,
+            _ => Engine::Tree,
+        };
+        Evaluator::with_engine(matches.value_of("file").unwrap(), engine)?.eval();
     }
 
     Ok(())
@@ -3,33 +3,89 @@
 // LALRPOP(https://github.com/lalrpop/lalrpop)でも良いかも...?
 // follow setの判定は行わないことにする
 // (次のトークンでfirst setに含まれないとして弾く)
+// エラーは文単位で回復し、すべて errors() に集める(字句解析のエラーも含む)
 
 use crate::ast::*;
-use crate::token::Token;
+use crate::token::{Comment, Token};
 use crate::tokenizer::Tokenizer;
+use crate::traceback::format_frame;
 use ordered_float::OrderedFloat;
 use std::io;
 
 pub struct Parser {
     tokenizer: Tokenizer,
     current_stmt: ASTStmt,
+    // end of the last token consumed(newlines and indents are not counted), for spans
+    last_end: (usize, usize),
+    // the last token consumed was NEWLINE, INDENT or DEDENT
+    at_line_start: bool,
+    // expressions and blocks being parsed, one in another
+    depth: usize,
+    errors: Vec<SyntaxError>,
 }
 
+// the AST is walked recursively(evaluator, compiler, fmt...), so its nesting is limited here,
+// where a deep source is still harmless
+const MAX_NESTING: usize = 200;
+// the native stack left when a level is entered, and the size of a new segment below it
+const RED_ZONE: usize = 256 * 1024;
+const STACK_SEGMENT_SIZE: usize = 4 * 1024 * 1024;
+
+/// syntax error with the span of the offending token
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SyntaxError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl SyntaxError {
+    pub fn new(message: &str, span: Span) -> SyntaxError {
+        SyntaxError {
+            message: message.to_string(),
+            span,
+        }
+    }
+
+    // the same format as CPython, without a traceback header
+    pub fn format(&self, file_name: &str) -> String {
+        format!(
+            "{}SyntaxError: {}\n",
+            format_frame(file_name, self.span, None),
+            self.message
+        )
+    }
+}
+
+type ParseResult<T> = Result<T, SyntaxError>;
+
 impl Parser {
     /// generate new Parser
     pub fn new(file_name: &str) -> io::Result<Parser> {
-        let mut tokenizer = Tokenizer::new(file_name)?;
+        Ok(Parser::with_tokenizer(Tokenizer::new(file_name)?))
+    }
+
+    // source in memory. file_name is only used in messages
+    pub fn from_source(file_name: &str, source: String) -> Parser {
+        Parser::with_tokenizer(Tokenizer::from_source(file_name, source))
+    }
+
+    fn with_tokenizer(mut tokenizer: Tokenizer) -> Parser {
         tokenizer.next_token();
-        Ok(Parser {
-            tokenizer: tokenizer,
-            current_stmt: ASTStmt::Init,
-        })
+        let errors = tokenizer.take_errors();
+        Parser {
+            tokenizer,
+            current_stmt: ASTStmtKind::Init.into(),
+            last_end: (1, 0),
+            at_line_start: true,
+            depth: 0,
+            errors,
+        }
     }
 
     // parse and return the stmt
     pub fn get_next_stmt(&mut self) -> ASTStmt {
         self.next_stmt();
-        std::mem::replace(&mut self.current_stmt, ASTStmt::Init)
+        std::mem::replace(&mut self.current_stmt, ASTStmtKind::Init.into())
     }
 
     // or, use these 2 methods
@@ -38,86 +94,105 @@ impl Parser {
     }
 
     pub fn next_stmt(&mut self) {
-        match self.tokenizer.get_current_token() {
-            Token::NEWLINE => (),
-            Token::EOF => {
-                self.current_stmt = ASTStmt::End;
-            }
-            Token::NOT
-            | Token::PLUS
-            | Token::MINUS
-            | Token::TILDE
-            | Token::LPAREN
-            | Token::LBRACE
-            | Token::LBRACKET
-            | Token::ID(_)
-            | Token::INT(_)
-            | Token::FLOAT(_)
-            | Token::STRING(_)
-            | Token::NONE
-            | Token::TRUE
-            | Token::FALSE
-            | Token::DEL
-            | Token::PASS
-            | Token::BREAK
-            | Token::CONTINUE
-            | Token::RETURN
-            | Token::GLOBAL
-            | Token::IF
-            | Token::WHILE
-            | Token::FOR
-            | Token::DEF => {
-                self.current_stmt = self.parse_stmt();
+        loop {
+            match self.tokenizer.get_current_token() {
+                Token::NEWLINE => self.advance(),
+                Token::EOF => {
+                    self.current_stmt = ASTStmtKind::End.into();
+                    break;
+                }
+                _ => {
+                    self.current_stmt = self.parse_stmt_or_error();
+                    break;
+                }
             }
-            _ => self.error(format!("SyntaxError: unexpected token")),
-        };
+        }
     }
 
     // independent of next_stmt. emit AST and ends
+    // syntax errors are replaced with ASTStmtKind::Error, see errors()
     pub fn parse(&mut self) -> AST {
         let mut tree: Vec<ASTStmt> = Vec::new();
-        self.tokenizer.next_token();
         loop {
             match self.tokenizer.get_current_token() {
-                Token::NEWLINE => {
-                    continue;
+                Token::NEWLINE => self.advance(),
+                Token::EOF => break,
+                _ => tree.push(self.parse_stmt_or_error()),
+            };
+        }
+        tree
+    }
+
+    /// comments read so far, in order
+    pub fn take_comments(&mut self) -> Vec<Comment> {
+        self.tokenizer.take_comments()
+    }
+
+    /// syntax errors found so far, in order
+    pub fn errors(&self) -> &[SyntaxError] {
+        &self.errors
+    }
+
+    // 文単位でのエラー回復
+    // a broken statement becomes ASTStmtKind::Error and parsing resumes at the next statement
+    fn parse_stmt_or_error(&mut self) -> ASTStmt {
+        let start = self.start();
+        match self.parse_stmt() {
+            Ok(stmt) => stmt,
+            Err(error) => {
+                // the tokenizer has reported what is wrong with an ERROR token
+                if *self.tokenizer.get_current_token() != Token::ERROR {
+                    self.errors.push(error);
                 }
-                Token::EOF => {
+                self.synchronize(start);
+                Located::new(
+                    ASTStmtKind::Error,
+                    Span::new(start, self.last_end.max(start)),
+                )
+            }
+        }
+    }
+
+    // skip the rest of the line, and the indented block after it (the body of a broken header).
+    // a line already ended by its NEWLINE is kept, ex. `if x:` without the indented block
+    fn synchronize(&mut self, start: (usize, usize)) {
+        if *self.tokenizer.get_current_token() != Token::INDENT
+            && (!self.at_line_start || self.start() == start)
+        {
+            loop {
+                match self.tokenizer.get_current_token() {
+                    Token::NEWLINE => {
+                        self.advance();
+                        break;
+                    }
+                    Token::DEDENT | Token::EOF => break,
+                    _ => self.advance(),
+                }
+            }
+        }
+        if *self.tokenizer.get_current_token() == Token::INDENT {
+            let mut depth = 0;
+            loop {
+                match self.tokenizer.get_current_token() {
+                    Token::INDENT => depth += 1,
+                    Token::DEDENT => depth -= 1,
+                    Token::EOF => break,
+                    _ => (),
+                }
+                self.advance();
+                if depth == 0 {
                     break;
                 }
-                Token::NOT
-                | Token::PLUS
-                | Token::MINUS
-                | Token::TILDE
-                | Token::LPAREN
-                | Token::LBRACE
-                | Token::LBRACKET
-                | Token::ID(_)
-                | Token::INT(_)
-                | Token::FLOAT(_)
-                | Token::STRING(_)
-                | Token::NONE
-                | Token::TRUE
-                | Token::FALSE
-                | Token::DEL
-                | Token::PASS
-                | Token::BREAK
-                | Token::CONTINUE
-                | Token::RETURN
-                | Token::GLOBAL
-                | Token::IF
-                | Token::WHILE
-                | Token::FOR
-                | Token::DEF => tree.push(self.parse_stmt()),
-                _ => self.error(format!("SyntaxError: unexpected token")),
-            };
+            }
         }
-        tree
     }
 
-    fn parse_stmt(&mut self) -> ASTStmt {
-        match self.tokenizer.get_current_token() {
-            Token::IF | Token::WHILE | Token::FOR | Token::DEF => self.parse_compound_stmt(),
+    fn parse_stmt(&mut self) -> ParseResult<ASTStmt> {
+        let start = self.start();
+        let stmt = match self.tokenizer.get_current_token() {
+            Token::IF | Token::WHILE | Token::FOR | Token::WITH | Token::DEF => {
+                self.parse_compound_stmt()?
+            }
             Token::NOT
             | Token::PLUS
             | Token::MINUS
@@ -137,25 +212,30 @@ impl Parser {
             | Token::BREAK
             | Token::CONTINUE
             | Token::RETURN
-            | Token::GLOBAL => self.parse_simple_stmt(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
-        }
+            | Token::GLOBAL
+            | Token::IMPORT
+            | Token::FROM => self.parse_simple_stmt()?,
+            Token::INDENT => return Err(self.error("unexpected indent")),
+            _ => return Err(self.expected("statement")),
+        };
+        Ok(self.located(start, stmt))
     }
 
-    fn parse_compound_stmt(&mut self) -> ASTStmt {
+    fn parse_compound_stmt(&mut self) -> ParseResult<ASTStmtKind> {
         match self.tokenizer.get_current_token() {
             Token::IF => self.parse_if_stmt(false),
             Token::WHILE => self.parse_while_stmt(),
             Token::FOR => self.parse_for_stmt(),
+            Token::WITH => self.parse_with_stmt(),
             Token::DEF => self.parse_funcdef(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            _ => Err(self.expected("statement")),
         }
     }
 
-    fn parse_if_stmt(&mut self, is_orelse: bool) -> ASTStmt {
+    fn parse_if_stmt(&mut self, is_orelse: bool) -> ParseResult<ASTStmtKind> {
         // orelseで呼ばれた場合、'if' はない
         if !is_orelse {
-            self.eat(&Token::IF);
+            self.eat(&Token::IF)?;
         }
         let test = match self.tokenizer.get_current_token() {
             Token::NOT
@@ -171,10 +251,17 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_test(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_test()?,
+            _ => return Err(self.expected("expression")),
         };
-        self.eat(&Token::COLON);
+        self.expect(
+            &Token::COLON,
+            if is_orelse {
+                "after elif condition"
+            } else {
+                "after if condition"
+            },
+        )?;
         let body = match self.tokenizer.get_current_token() {
             Token::NOT
             | Token::PLUS
@@ -196,13 +283,16 @@ impl Parser {
             | Token::CONTINUE
             | Token::RETURN
             | Token::GLOBAL
-            | Token::NEWLINE => self.parse_suite(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::IMPORT
+            | Token::FROM
+            | Token::NEWLINE => self.parse_suite()?,
+            _ => return Err(self.expected("statement")),
         };
         let orelse: Vec<ASTStmt> = match self.tokenizer.get_current_token() {
             Token::ELIF => {
                 // elifをelse ifに分解する(elifをeatした後、if文として再パース)
-                self.eat(&Token::ELIF);
+                let start = self.start();
+                self.eat(&Token::ELIF)?;
                 match self.tokenizer.get_current_token() {
                     Token::NOT
                     | Token::PLUS
@@ -217,14 +307,17 @@ impl Parser {
                     | Token::STRING(_)
                     | Token::NONE
                     | Token::TRUE
-                    | Token::FALSE => vec![self.parse_if_stmt(true)],
-                    _ => self.error(format!("SyntaxError: unexpected token")),
+                    | Token::FALSE => {
+                        let stmt = self.parse_if_stmt(true)?;
+                        vec![self.located(start, stmt)]
+                    }
+                    _ => return Err(self.expected("expression")),
                 }
             }
             Token::ELSE => {
                 // elseのsuite
-                self.eat(&Token::ELSE);
-                self.eat(&Token::COLON);
+                self.eat(&Token::ELSE)?;
+                self.expect(&Token::COLON, "after 'else'")?;
                 match self.tokenizer.get_current_token() {
                     Token::NOT
                     | Token::PLUS
@@ -246,16 +339,18 @@ impl Parser {
                     | Token::CONTINUE
                     | Token::RETURN
                     | Token::GLOBAL
-                    | Token::NEWLINE => self.parse_suite(),
-                    _ => self.error(format!("SyntaxError: unexpected token")),
+                    | Token::IMPORT
+                    | Token::FROM
+                    | Token::NEWLINE => self.parse_suite()?,
+                    _ => return Err(self.expected("statement")),
                 }
             }
             _ => Vec::new(), // 特に何もしない
         };
-        ASTStmt::If(test, body, orelse)
+        Ok(ASTStmtKind::If(test, body, orelse))
     }
 
-    fn parse_simple_stmt(&mut self) -> ASTStmt {
+    fn parse_simple_stmt(&mut self) -> ParseResult<ASTStmtKind> {
         let small_stmt = match self.tokenizer.get_current_token() {
             Token::NOT
             | Token::PLUS
@@ -276,17 +371,25 @@ impl Parser {
             | Token::BREAK
             | Token::CONTINUE
             | Token::RETURN
-            | Token::GLOBAL => self.parse_small_stmt(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::GLOBAL
+            | Token::IMPORT
+            | Token::FROM => self.parse_small_stmt()?,
+            _ => return Err(self.expected("statement")),
         };
         // 最後の改行の省略を許容
         if *self.tokenizer.get_current_token() != Token::EOF {
-            self.eat(&Token::NEWLINE);
+            self.expect(&Token::NEWLINE, "after statement")?;
         }
-        small_stmt
+        Ok(small_stmt)
+    }
+
+    fn parse_test(&mut self) -> ParseResult<ASTExpr> {
+        self.nested(Parser::parse_conditional)
     }
 
-    fn parse_test(&mut self) -> ASTExpr {
+    // or_test ['if' or_test 'else' test]
+    fn parse_conditional(&mut self) -> ParseResult<ASTExpr> {
+        let start = self.start();
         let body = match self.tokenizer.get_current_token() {
             Token::NOT
             | Token::PLUS
@@ -301,12 +404,12 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_or_test(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_or_test()?,
+            _ => return Err(self.expected("expression")),
         };
         // ternary operator
         if *self.tokenizer.get_current_token() == Token::IF {
-            self.eat(&Token::IF);
+            self.eat(&Token::IF)?;
             let test = match self.tokenizer.get_current_token() {
                 Token::NOT
                 | Token::PLUS
@@ -321,10 +424,10 @@ impl Parser {
                 | Token::STRING(_)
                 | Token::NONE
                 | Token::TRUE
-                | Token::FALSE => self.parse_or_test(),
-                _ => self.error(format!("SyntaxError: unexpected token")),
+                | Token::FALSE => self.parse_or_test()?,
+                _ => return Err(self.expected("expression")),
             };
-            self.eat(&Token::ELSE);
+            self.expect(&Token::ELSE, "in conditional expression")?;
             let orelse = match self.tokenizer.get_current_token() {
                 Token::NOT
                 | Token::PLUS
@@ -339,55 +442,37 @@ impl Parser {
                 | Token::STRING(_)
                 | Token::NONE
                 | Token::TRUE
-                | Token::FALSE => self.parse_test(),
-                _ => self.error(format!("SyntaxError: unexpected token")),
+                | Token::FALSE => self.parse_test()?,
+                _ => return Err(self.expected("expression")),
             };
-            ASTExpr::IfExp(Box::new(test), Box::new(body), Box::new(orelse))
+            Ok(self.located(
+                start,
+                ASTExprKind::IfExp(Box::new(test), Box::new(body), Box::new(orelse)),
+            ))
         } else {
-            body
+            Ok(body)
         }
     }
 
-    fn parse_suite(&mut self) -> Vec<ASTStmt> {
+    fn parse_suite(&mut self) -> ParseResult<Vec<ASTStmt>> {
         match self.tokenizer.get_current_token() {
             Token::NEWLINE => {
-                self.eat(&Token::NEWLINE);
-                self.eat(&Token::INDENT);
-                let mut stmt: Vec<ASTStmt> = Vec::new();
-                loop {
-                    match self.tokenizer.get_current_token() {
-                        Token::NOT
-                        | Token::PLUS
-                        | Token::MINUS
-                        | Token::TILDE
-                        | Token::LPAREN
-                        | Token::LBRACE
-                        | Token::LBRACKET
-                        | Token::ID(_)
-                        | Token::INT(_)
-                        | Token::FLOAT(_)
-                        | Token::STRING(_)
-                        | Token::NONE
-                        | Token::TRUE
-                        | Token::FALSE
-                        | Token::DEL
-                        | Token::PASS
-                        | Token::BREAK
-                        | Token::CONTINUE
-                        | Token::RETURN
-                        | Token::GLOBAL
-                        | Token::IF
-                        | Token::WHILE
-                        | Token::FOR
-                        | Token::DEF => {
-                            stmt.push(self.parse_stmt());
-                        }
-                        Token::DEDENT => break,
-                        _ => self.error(format!("SyntaxError: unexpected token")),
-                    };
+                self.eat(&Token::NEWLINE)?;
+                if *self.tokenizer.get_current_token() != Token::INDENT {
+                    return Err(self.error("expected an indented block"));
                 }
-                self.eat(&Token::DEDENT);
-                stmt
+                self.advance();
+                self.nested(|parser| {
+                    let mut stmt: Vec<ASTStmt> = Vec::new();
+                    loop {
+                        match parser.tokenizer.get_current_token() {
+                            Token::DEDENT | Token::EOF => break,
+                            _ => stmt.push(parser.parse_stmt_or_error()),
+                        };
+                    }
+                    parser.eat(&Token::DEDENT)?;
+                    Ok(stmt)
+                })
             }
             Token::NOT
             | Token::PLUS
@@ -408,13 +493,19 @@ impl Parser {
             | Token::BREAK
             | Token::CONTINUE
             | Token::RETURN
-            | Token::GLOBAL => vec![self.parse_small_stmt()],
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::GLOBAL
+            | Token::IMPORT
+            | Token::FROM => {
+                let start = self.start();
+                let stmt = self.parse_small_stmt()?;
+                Ok(vec![self.located(start, stmt)])
+            }
+            _ => Err(self.expected("statement")),
         }
     }
 
-    fn parse_while_stmt(&mut self) -> ASTStmt {
-        self.eat(&Token::WHILE);
+    fn parse_while_stmt(&mut self) -> ParseResult<ASTStmtKind> {
+        self.eat(&Token::WHILE)?;
         let test = match self.tokenizer.get_current_token() {
             Token::NOT
             | Token::PLUS
@@ -429,10 +520,10 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_test(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_test()?,
+            _ => return Err(self.expected("expression")),
         };
-        self.eat(&Token::COLON);
+        self.expect(&Token::COLON, "after while condition")?;
         let body = match self.tokenizer.get_current_token() {
             Token::NOT
             | Token::PLUS
@@ -454,14 +545,17 @@ impl Parser {
             | Token::CONTINUE
             | Token::RETURN
             | Token::GLOBAL
-            | Token::NEWLINE => self.parse_suite(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::IMPORT
+            | Token::FROM
+            | Token::NEWLINE => self.parse_suite()?,
+            _ => return Err(self.expected("statement")),
         };
-        ASTStmt::While(test, body)
+        Ok(ASTStmtKind::While(test, body))
     }
 
-    fn parse_for_stmt(&mut self) -> ASTStmt {
-        self.eat(&Token::FOR);
+    fn parse_for_stmt(&mut self) -> ParseResult<ASTStmtKind> {
+        self.eat(&Token::FOR)?;
+        let start = self.start();
         let target = match self.tokenizer.get_current_token() {
             Token::PLUS
             | Token::MINUS
@@ -476,16 +570,16 @@ impl Parser {
             | Token::NONE
             | Token::TRUE
             | Token::FALSE => {
-                let mut exprlist = self.parse_exprlist();
+                let mut exprlist = self.parse_exprlist()?;
                 if exprlist.len() == 1 {
                     exprlist.swap_remove(0)
                 } else {
-                    ASTExpr::Tuple(exprlist)
+                    self.located(start, ASTExprKind::Tuple(exprlist))
                 }
             }
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            _ => return Err(self.expected("expression")),
         };
-        self.eat(&Token::IN);
+        self.expect(&Token::IN, "after for target")?;
         let iter = match self.tokenizer.get_current_token() {
             Token::NOT
             | Token::PLUS
@@ -500,10 +594,48 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_testlist(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_testlist()?,
+            _ => return Err(self.expected("expression")),
+        };
+        self.expect(&Token::COLON, "after for iterable")?;
+        let body = match self.tokenizer.get_current_token() {
+            Token::NOT
+            | Token::PLUS
+            | Token::MINUS
+            | Token::TILDE
+            | Token::LPAREN
+            | Token::LBRACE
+            | Token::LBRACKET
+            | Token::ID(_)
+            | Token::INT(_)
+            | Token::FLOAT(_)
+            | Token::STRING(_)
+            | Token::NONE
+            | Token::TRUE
+            | Token::FALSE
+            | Token::DEL
+            | Token::PASS
+            | Token::BREAK
+            | Token::CONTINUE
+            | Token::RETURN
+            | Token::GLOBAL
+            | Token::IMPORT
+            | Token::FROM
+            | Token::NEWLINE => self.parse_suite()?,
+            _ => return Err(self.expected("statement")),
         };
-        self.eat(&Token::COLON);
+        Ok(ASTStmtKind::For(target, iter, body))
+    }
+
+    // with_stmt ::= 'with' with_item (',' with_item)* ':' suite
+    fn parse_with_stmt(&mut self) -> ParseResult<ASTStmtKind> {
+        self.eat(&Token::WITH)?;
+        let mut items = vec![self.parse_with_item()?];
+        while *self.tokenizer.get_current_token() == Token::COMMA {
+            self.eat(&Token::COMMA)?;
+            items.push(self.parse_with_item()?);
+        }
+        self.expect(&Token::COLON, "after with items")?;
         let body = match self.tokenizer.get_current_token() {
             Token::NOT
             | Token::PLUS
@@ -525,20 +657,57 @@ impl Parser {
             | Token::CONTINUE
             | Token::RETURN
             | Token::GLOBAL
-            | Token::NEWLINE => self.parse_suite(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::IMPORT
+            | Token::FROM
+            | Token::NEWLINE => self.parse_suite()?,
+            _ => return Err(self.expected("statement")),
         };
-        ASTStmt::For(target, iter, body)
+        Ok(ASTStmtKind::With(items, body))
     }
 
-    fn parse_funcdef(&mut self) -> ASTStmt {
-        self.eat(&Token::DEF);
-        let name = self.eat_id();
+    // with_item ::= test ['as' expr]
+    fn parse_with_item(&mut self) -> ParseResult<ASTWithItem> {
+        let start = self.start();
+        let context_expr = match self.tokenizer.get_current_token() {
+            Token::NOT
+            | Token::PLUS
+            | Token::MINUS
+            | Token::TILDE
+            | Token::LPAREN
+            | Token::LBRACE
+            | Token::LBRACKET
+            | Token::ID(_)
+            | Token::INT(_)
+            | Token::FLOAT(_)
+            | Token::STRING(_)
+            | Token::NONE
+            | Token::TRUE
+            | Token::FALSE => self.parse_test()?,
+            _ => return Err(self.expected("expression")),
+        };
+        let optional_vars = if *self.tokenizer.get_current_token() == Token::AS {
+            self.eat(&Token::AS)?;
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        Ok(self.located(start, (context_expr, optional_vars)))
+    }
+
+    fn parse_funcdef(&mut self) -> ParseResult<ASTStmtKind> {
+        self.eat(&Token::DEF)?;
+        let name = self.eat_id()?;
         let arguments = match self.tokenizer.get_current_token() {
-            Token::LPAREN => self.parse_parameters(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            Token::LPAREN => self.parse_parameters()?,
+            _ => return Err(self.expected("'(' after function name")),
+        };
+        let returns = if *self.tokenizer.get_current_token() == Token::ARROW {
+            self.eat(&Token::ARROW)?;
+            Some(self.parse_test()?)
+        } else {
+            None
         };
-        self.eat(&Token::COLON);
+        self.expect(&Token::COLON, "after function signature")?;
         let body = match self.tokenizer.get_current_token() {
             Token::NOT
             | Token::PLUS
@@ -560,13 +729,15 @@ impl Parser {
             | Token::CONTINUE
             | Token::RETURN
             | Token::GLOBAL
-            | Token::NEWLINE => self.parse_suite(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::IMPORT
+            | Token::FROM
+            | Token::NEWLINE => self.parse_suite()?,
+            _ => return Err(self.expected("statement")),
         };
-        ASTStmt::FuncDef(name, arguments, body)
+        Ok(ASTStmtKind::FuncDef(name, arguments, body, returns))
     }
 
-    fn parse_small_stmt(&mut self) -> ASTStmt {
+    fn parse_small_stmt(&mut self) -> ParseResult<ASTStmtKind> {
         match self.tokenizer.get_current_token() {
             Token::NOT
             | Token::PLUS
@@ -586,11 +757,14 @@ impl Parser {
             Token::PASS => self.parse_pass_stmt(),
             Token::BREAK | Token::CONTINUE | Token::RETURN => self.parse_flow_stmt(),
             Token::GLOBAL => self.parse_global_stmt(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            Token::IMPORT => self.parse_import_name(),
+            Token::FROM => self.parse_import_from(),
+            _ => Err(self.expected("statement")),
         }
     }
 
-    fn parse_or_test(&mut self) -> ASTExpr {
+    fn parse_or_test(&mut self) -> ParseResult<ASTExpr> {
+        let start = self.start();
         let mut and_test: Vec<ASTExpr> = Vec::new();
         and_test.push(match self.tokenizer.get_current_token() {
             Token::NOT
@@ -606,11 +780,11 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_and_test(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_and_test()?,
+            _ => return Err(self.expected("expression")),
         });
         while *self.tokenizer.get_current_token() == Token::OR {
-            self.eat(&Token::OR);
+            self.eat(&Token::OR)?;
             and_test.push(match self.tokenizer.get_current_token() {
                 Token::NOT
                 | Token::PLUS
@@ -625,18 +799,18 @@ impl Parser {
                 | Token::STRING(_)
                 | Token::NONE
                 | Token::TRUE
-                | Token::FALSE => self.parse_and_test(),
-                _ => self.error(format!("SyntaxError: unexpected token")),
+                | Token::FALSE => self.parse_and_test()?,
+                _ => return Err(self.expected("expression")),
             });
         }
         if and_test.len() == 1 {
-            and_test.swap_remove(0)
+            Ok(and_test.swap_remove(0))
         } else {
-            ASTExpr::BoolOp(ASTBoolOp::Or, and_test)
+            Ok(self.located(start, ASTExprKind::BoolOp(ASTBoolOp::Or, and_test)))
         }
     }
 
-    fn parse_exprlist(&mut self) -> Vec<ASTExpr> {
+    fn parse_exprlist(&mut self) -> ParseResult<Vec<ASTExpr>> {
         let mut res: Vec<ASTExpr> = Vec::new();
         res.push(match self.tokenizer.get_current_token() {
             Token::PLUS
@@ -651,11 +825,11 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_expr(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_expr()?,
+            _ => return Err(self.expected("expression")),
         });
         while *self.tokenizer.get_current_token() == Token::COMMA {
-            self.eat(&Token::COMMA);
+            self.eat(&Token::COMMA)?;
             res.push(match self.tokenizer.get_current_token() {
                 Token::PLUS
                 | Token::MINUS
@@ -669,14 +843,15 @@ impl Parser {
                 | Token::STRING(_)
                 | Token::NONE
                 | Token::TRUE
-                | Token::FALSE => self.parse_expr(),
+                | Token::FALSE => self.parse_expr()?,
                 _ => break,
             });
         }
-        res
+        Ok(res)
     }
 
-    fn parse_testlist(&mut self) -> ASTExpr {
+    fn parse_testlist(&mut self) -> ParseResult<ASTExpr> {
+        let start = self.start();
         let mut res: Vec<ASTExpr> = Vec::new();
         res.push(match self.tokenizer.get_current_token() {
             Token::NOT
@@ -692,11 +867,11 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_test(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_test()?,
+            _ => return Err(self.expected("expression")),
         });
         while *self.tokenizer.get_current_token() == Token::COMMA {
-            self.eat(&Token::COMMA);
+            self.eat(&Token::COMMA)?;
             res.push(match self.tokenizer.get_current_token() {
                 Token::NOT
                 | Token::PLUS
@@ -711,29 +886,29 @@ impl Parser {
                 | Token::STRING(_)
                 | Token::NONE
                 | Token::TRUE
-                | Token::FALSE => self.parse_test(),
+                | Token::FALSE => self.parse_test()?,
                 _ => break,
             });
         }
         if res.len() == 1 {
-            res.swap_remove(0)
+            Ok(res.swap_remove(0))
         } else {
-            ASTExpr::Tuple(res)
+            Ok(self.located(start, ASTExprKind::Tuple(res)))
         }
     }
 
-    fn parse_parameters(&mut self) -> ASTArguments {
-        self.eat(&Token::LPAREN);
+    fn parse_parameters(&mut self) -> ParseResult<ASTArguments> {
+        self.eat(&Token::LPAREN)?;
         let typedargslist = match self.tokenizer.get_current_token() {
-            Token::ID(_) => self.parse_typedargslist(),
+            Token::ID(_) => self.parse_typedargslist()?,
             Token::RPAREN => ASTArguments::new(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            _ => return Err(self.expected("parameter or ')'")),
         };
-        self.eat(&Token::RPAREN);
-        typedargslist
+        self.expect(&Token::RPAREN, "to close parameter list")?;
+        Ok(typedargslist)
     }
 
-    fn parse_expr_stmt(&mut self) -> ASTStmt {
+    fn parse_expr_stmt(&mut self) -> ParseResult<ASTStmtKind> {
         let mut testlist_star_expr = match self.tokenizer.get_current_token() {
             Token::NOT
             | Token::PLUS
@@ -748,11 +923,14 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => vec![self.parse_testlist_star_expr()],
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => vec![self.parse_testlist_star_expr()?],
+            _ => return Err(self.expected("expression")),
         };
+        if *self.tokenizer.get_current_token() == Token::COLON {
+            return self.parse_annassign(testlist_star_expr.swap_remove(0));
+        }
         if *self.tokenizer.get_current_token() == Token::EQ {
-            self.eat(&Token::EQ);
+            self.eat(&Token::EQ)?;
             let mut tmp = match self.tokenizer.get_current_token() {
                 Token::NOT
                 | Token::PLUS
@@ -767,12 +945,12 @@ impl Parser {
                 | Token::STRING(_)
                 | Token::NONE
                 | Token::TRUE
-                | Token::FALSE => self.parse_testlist_star_expr(),
-                _ => self.error(format!("SyntaxError: unexpected token")),
+                | Token::FALSE => self.parse_testlist_star_expr()?,
+                _ => return Err(self.expected("expression")),
             };
             while *self.tokenizer.get_current_token() == Token::EQ {
                 testlist_star_expr.push(tmp);
-                self.eat(&Token::EQ);
+                self.eat(&Token::EQ)?;
                 tmp = match self.tokenizer.get_current_token() {
                     Token::NOT
                     | Token::PLUS
@@ -787,68 +965,162 @@ impl Parser {
                     | Token::STRING(_)
                     | Token::NONE
                     | Token::TRUE
-                    | Token::FALSE => self.parse_testlist_star_expr(),
-                    _ => self.error(format!("SyntaxError: unexpected token")),
+                    | Token::FALSE => self.parse_testlist_star_expr()?,
+                    _ => return Err(self.expected("expression")),
                 };
             }
-            ASTStmt::Assign(testlist_star_expr, tmp)
+            Ok(ASTStmtKind::Assign(testlist_star_expr, tmp))
         } else {
-            ASTStmt::Expr(testlist_star_expr.swap_remove(0))
+            Ok(ASTStmtKind::Expr(testlist_star_expr.swap_remove(0)))
         }
     }
 
-    fn parse_del_stmt(&mut self) -> ASTStmt {
-        self.eat(&Token::DEL);
-        ASTStmt::Delete(match self.tokenizer.get_current_token() {
-            Token::PLUS
-            | Token::MINUS
-            | Token::TILDE
-            | Token::LPAREN
-            | Token::LBRACE
-            | Token::LBRACKET
-            | Token::ID(_)
-            | Token::INT(_)
-            | Token::FLOAT(_)
-            | Token::STRING(_)
-            | Token::NONE
-            | Token::TRUE
-            | Token::FALSE => self.parse_exprlist(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
-        })
+    // `target: annotation [= value]`
+    fn parse_annassign(&mut self, target: ASTExpr) -> ParseResult<ASTStmtKind> {
+        match target.node {
+            ASTExprKind::Name(_) | ASTExprKind::Attribute(..) | ASTExprKind::Subscript(..) => (),
+            ASTExprKind::Tuple(_) | ASTExprKind::List(_) => {
+                return Err(SyntaxError::new(
+                    "only single target (not tuple) can be annotated",
+                    target.span,
+                ))
+            }
+            _ => {
+                return Err(SyntaxError::new(
+                    "illegal target for annotation",
+                    target.span,
+                ))
+            }
+        }
+        self.eat(&Token::COLON)?;
+        let annotation = self.parse_test()?;
+        let value = if *self.tokenizer.get_current_token() == Token::EQ {
+            self.eat(&Token::EQ)?;
+            Some(self.parse_testlist_star_expr()?)
+        } else {
+            None
+        };
+        Ok(ASTStmtKind::AnnAssign(target, annotation, value))
+    }
+
+    fn parse_del_stmt(&mut self) -> ParseResult<ASTStmtKind> {
+        self.eat(&Token::DEL)?;
+        Ok(ASTStmtKind::Delete(
+            match self.tokenizer.get_current_token() {
+                Token::PLUS
+                | Token::MINUS
+                | Token::TILDE
+                | Token::LPAREN
+                | Token::LBRACE
+                | Token::LBRACKET
+                | Token::ID(_)
+                | Token::INT(_)
+                | Token::FLOAT(_)
+                | Token::STRING(_)
+                | Token::NONE
+                | Token::TRUE
+                | Token::FALSE => self.parse_exprlist()?,
+                _ => return Err(self.expected("expression")),
+            },
+        ))
     }
 
-    fn parse_pass_stmt(&mut self) -> ASTStmt {
-        self.eat(&Token::PASS);
-        ASTStmt::Pass
+    fn parse_pass_stmt(&mut self) -> ParseResult<ASTStmtKind> {
+        self.eat(&Token::PASS)?;
+        Ok(ASTStmtKind::Pass)
     }
 
-    fn parse_flow_stmt(&mut self) -> ASTStmt {
+    fn parse_flow_stmt(&mut self) -> ParseResult<ASTStmtKind> {
         match self.tokenizer.get_current_token() {
             Token::BREAK => self.parse_break_stmt(),
             Token::CONTINUE => self.parse_continue_stmt(),
             Token::RETURN => self.parse_return_stmt(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            _ => Err(self.expected("statement")),
         }
     }
 
-    fn parse_global_stmt(&mut self) -> ASTStmt {
-        self.eat(&Token::GLOBAL);
-        let mut name: Vec<String> = Vec::new();
-        name.push(match self.tokenizer.get_current_token() {
-            Token::ID(name) => name.to_owned(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
-        });
+    fn parse_global_stmt(&mut self) -> ParseResult<ASTStmtKind> {
+        self.eat(&Token::GLOBAL)?;
+        let mut name: Vec<String> = vec![self.eat_id()?];
         while *self.tokenizer.get_current_token() == Token::COMMA {
-            self.eat(&Token::COMMA);
-            name.push(match self.tokenizer.get_current_token() {
-                Token::ID(name) => name.to_owned(),
-                _ => break,
-            });
+            self.eat(&Token::COMMA)?;
+            name.push(self.eat_id()?);
+        }
+        Ok(ASTStmtKind::Global(name))
+    }
+
+    // import_name ::= 'import' dotted_as_name (',' dotted_as_name)*
+    fn parse_import_name(&mut self) -> ParseResult<ASTStmtKind> {
+        self.eat(&Token::IMPORT)?;
+        let mut names = vec![self.parse_alias(true)?];
+        while *self.tokenizer.get_current_token() == Token::COMMA {
+            self.eat(&Token::COMMA)?;
+            names.push(self.parse_alias(true)?);
         }
-        ASTStmt::Global(name)
+        Ok(ASTStmtKind::Import(names))
     }
 
-    fn parse_and_test(&mut self) -> ASTExpr {
+    // import_from ::= 'from' dotted_name 'import' ('(' import_as_names ')' | import_as_names)
+    // import_as_names ::= import_as_name (',' import_as_name)* [',']
+    fn parse_import_from(&mut self) -> ParseResult<ASTStmtKind> {
+        self.eat(&Token::FROM)?;
+        let module = self.parse_dotted_name()?;
+        self.expect(&Token::IMPORT, "after module name")?;
+        let parenthesized = *self.tokenizer.get_current_token() == Token::LPAREN;
+        if parenthesized {
+            self.eat(&Token::LPAREN)?;
+        }
+        let mut names = vec![self.parse_alias(false)?];
+        while *self.tokenizer.get_current_token() == Token::COMMA {
+            self.eat(&Token::COMMA)?;
+            match self.tokenizer.get_current_token() {
+                Token::ID(_) => names.push(self.parse_alias(false)?),
+                Token::RPAREN if parenthesized => break,
+                _ if !parenthesized => {
+                    return Err(
+                        self.error("trailing comma not allowed without surrounding parentheses")
+                    )
+                }
+                _ => return Err(self.expected("identifier")),
+            }
+        }
+        if parenthesized {
+            self.expect(&Token::RPAREN, "after imported names")?;
+        }
+        Ok(ASTStmtKind::ImportFrom(module, names))
+    }
+
+    // dotted_name ::= NAME ('.' NAME)*
+    fn parse_dotted_name(&mut self) -> ParseResult<String> {
+        let mut name = self.eat_id()?;
+        while *self.tokenizer.get_current_token() == Token::PERIOD {
+            self.eat(&Token::PERIOD)?;
+            name.push('.');
+            name.push_str(&self.eat_id()?);
+        }
+        Ok(name)
+    }
+
+    // dotted_as_name ::= dotted_name ['as' NAME]
+    // import_as_name ::= NAME ['as' NAME]
+    fn parse_alias(&mut self, dotted: bool) -> ParseResult<ASTAlias> {
+        let start = self.start();
+        let name = if dotted {
+            self.parse_dotted_name()?
+        } else {
+            self.eat_id()?
+        };
+        let asname = if *self.tokenizer.get_current_token() == Token::AS {
+            self.eat(&Token::AS)?;
+            Some(self.eat_id()?)
+        } else {
+            None
+        };
+        Ok(self.located(start, (name, asname)))
+    }
+
+    fn parse_and_test(&mut self) -> ParseResult<ASTExpr> {
+        let start = self.start();
         let mut not_test: Vec<ASTExpr> = Vec::new();
         not_test.push(match self.tokenizer.get_current_token() {
             Token::NOT
@@ -864,11 +1136,11 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_not_test(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_not_test()?,
+            _ => return Err(self.expected("expression")),
         });
         while *self.tokenizer.get_current_token() == Token::AND {
-            self.eat(&Token::AND);
+            self.eat(&Token::AND)?;
             not_test.push(match self.tokenizer.get_current_token() {
                 Token::NOT
                 | Token::PLUS
@@ -883,19 +1155,20 @@ impl Parser {
                 | Token::STRING(_)
                 | Token::NONE
                 | Token::TRUE
-                | Token::FALSE => self.parse_not_test(),
-                _ => self.error(format!("SyntaxError: unexpected token")),
+                | Token::FALSE => self.parse_not_test()?,
+                _ => return Err(self.expected("expression")),
             });
         }
         if not_test.len() == 1 {
-            not_test.swap_remove(0)
+            Ok(not_test.swap_remove(0))
         } else {
-            ASTExpr::BoolOp(ASTBoolOp::And, not_test)
+            Ok(self.located(start, ASTExprKind::BoolOp(ASTBoolOp::And, not_test)))
         }
     }
 
-    fn parse_expr(&mut self) -> ASTExpr {
-        let left = match self.tokenizer.get_current_token() {
+    fn parse_expr(&mut self) -> ParseResult<ASTExpr> {
+        let start = self.start();
+        let mut left = match self.tokenizer.get_current_token() {
             Token::PLUS
             | Token::MINUS
             | Token::TILDE
@@ -908,11 +1181,11 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_xor_expr(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_xor_expr()?,
+            _ => return Err(self.expected("expression")),
         };
-        if *self.tokenizer.get_current_token() == Token::BAR {
-            self.eat(&Token::BAR);
+        while *self.tokenizer.get_current_token() == Token::BAR {
+            self.eat(&Token::BAR)?;
             let right = match self.tokenizer.get_current_token() {
                 Token::PLUS
                 | Token::MINUS
@@ -926,29 +1199,32 @@ impl Parser {
                 | Token::STRING(_)
                 | Token::NONE
                 | Token::TRUE
-                | Token::FALSE => self.parse_expr(),
-                _ => self.error(format!("SyntaxError: unexpected token")),
+                | Token::FALSE => self.parse_xor_expr()?,
+                _ => return Err(self.expected("expression")),
             };
-            ASTExpr::BinOp(Box::new(left), ASTOperator::BitOr, Box::new(right))
-        } else {
-            left
+            left = self.located(
+                start,
+                ASTExprKind::BinOp(Box::new(left), ASTOperator::BitOr, Box::new(right)),
+            );
         }
+        Ok(left)
     }
 
-    fn parse_typedargslist(&mut self) -> ASTArguments {
-        let mut name: Vec<String> = Vec::new();
-        name.push(self.eat_id());
+    fn parse_typedargslist(&mut self) -> ParseResult<ASTArguments> {
+        let mut name: Vec<ASTArg> = Vec::new();
+        name.push(self.eat_arg()?);
         while *self.tokenizer.get_current_token() == Token::COMMA {
-            self.eat(&Token::COMMA);
+            self.eat(&Token::COMMA)?;
             name.push(match self.tokenizer.get_current_token() {
-                Token::ID(_) => self.eat_id(),
+                Token::ID(_) => self.eat_arg()?,
                 _ => break,
             });
         }
-        name
+        Ok(name)
     }
 
-    fn parse_testlist_star_expr(&mut self) -> ASTExpr {
+    fn parse_testlist_star_expr(&mut self) -> ParseResult<ASTExpr> {
+        let start = self.start();
         let mut body = vec![match self.tokenizer.get_current_token() {
             Token::NOT
             | Token::PLUS
@@ -963,11 +1239,11 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_test(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_test()?,
+            _ => return Err(self.expected("expression")),
         }];
         while *self.tokenizer.get_current_token() == Token::COMMA {
-            self.eat(&Token::COMMA);
+            self.eat(&Token::COMMA)?;
             body.push(match self.tokenizer.get_current_token() {
                 Token::NOT
                 | Token::PLUS
@@ -982,51 +1258,54 @@ impl Parser {
                 | Token::STRING(_)
                 | Token::NONE
                 | Token::TRUE
-                | Token::FALSE => self.parse_test(),
+                | Token::FALSE => self.parse_test()?,
                 _ => break,
             });
         }
         if body.len() == 1 {
-            body.swap_remove(0)
+            Ok(body.swap_remove(0))
         } else {
             // ?
-            ASTExpr::Tuple(body)
+            Ok(self.located(start, ASTExprKind::Tuple(body)))
         }
     }
 
-    fn parse_break_stmt(&mut self) -> ASTStmt {
-        self.eat(&Token::BREAK);
-        ASTStmt::Break
+    fn parse_break_stmt(&mut self) -> ParseResult<ASTStmtKind> {
+        self.eat(&Token::BREAK)?;
+        Ok(ASTStmtKind::Break)
     }
 
-    fn parse_continue_stmt(&mut self) -> ASTStmt {
-        self.eat(&Token::CONTINUE);
-        ASTStmt::Continue
+    fn parse_continue_stmt(&mut self) -> ParseResult<ASTStmtKind> {
+        self.eat(&Token::CONTINUE)?;
+        Ok(ASTStmtKind::Continue)
     }
 
-    fn parse_return_stmt(&mut self) -> ASTStmt {
-        self.eat(&Token::RETURN);
-        ASTStmt::Return(match self.tokenizer.get_current_token() {
-            Token::NOT
-            | Token::PLUS
-            | Token::MINUS
-            | Token::TILDE
-            | Token::LPAREN
-            | Token::LBRACE
-            | Token::LBRACKET
-            | Token::ID(_)
-            | Token::INT(_)
-            | Token::FLOAT(_)
-            | Token::STRING(_)
-            | Token::NONE
-            | Token::TRUE
-            | Token::FALSE => Some(self.parse_testlist_star_expr()),
-            _ => None,
-        })
+    fn parse_return_stmt(&mut self) -> ParseResult<ASTStmtKind> {
+        self.eat(&Token::RETURN)?;
+        Ok(ASTStmtKind::Return(
+            match self.tokenizer.get_current_token() {
+                Token::NOT
+                | Token::PLUS
+                | Token::MINUS
+                | Token::TILDE
+                | Token::LPAREN
+                | Token::LBRACE
+                | Token::LBRACKET
+                | Token::ID(_)
+                | Token::INT(_)
+                | Token::FLOAT(_)
+                | Token::STRING(_)
+                | Token::NONE
+                | Token::TRUE
+                | Token::FALSE => Some(self.parse_testlist_star_expr()?),
+                _ => None,
+            },
+        ))
     }
 
-    fn parse_xor_expr(&mut self) -> ASTExpr {
-        let left = match self.tokenizer.get_current_token() {
+    fn parse_xor_expr(&mut self) -> ParseResult<ASTExpr> {
+        let start = self.start();
+        let mut left = match self.tokenizer.get_current_token() {
             Token::PLUS
             | Token::MINUS
             | Token::TILDE
@@ -1039,11 +1318,11 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_and_expr(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_and_expr()?,
+            _ => return Err(self.expected("expression")),
         };
-        if *self.tokenizer.get_current_token() == Token::XOR {
-            self.eat(&Token::XOR);
+        while *self.tokenizer.get_current_token() == Token::XOR {
+            self.eat(&Token::XOR)?;
             let right = match self.tokenizer.get_current_token() {
                 Token::PLUS
                 | Token::MINUS
@@ -1057,39 +1336,43 @@ impl Parser {
                 | Token::STRING(_)
                 | Token::NONE
                 | Token::TRUE
-                | Token::FALSE => self.parse_xor_expr(),
-                _ => self.error(format!("SyntaxError: unexpected token")),
+                | Token::FALSE => self.parse_and_expr()?,
+                _ => return Err(self.expected("expression")),
             };
-            ASTExpr::BinOp(Box::new(left), ASTOperator::BitXor, Box::new(right))
-        } else {
-            left
+            left = self.located(
+                start,
+                ASTExprKind::BinOp(Box::new(left), ASTOperator::BitXor, Box::new(right)),
+            );
         }
+        Ok(left)
     }
 
-    fn parse_not_test(&mut self) -> ASTExpr {
+    fn parse_not_test(&mut self) -> ParseResult<ASTExpr> {
+        let start = self.start();
         match self.tokenizer.get_current_token() {
             Token::NOT => {
-                self.eat(&Token::NOT);
-                ASTExpr::UnaryOp(
-                    ASTUnaryOp::Not,
-                    match self.tokenizer.get_current_token() {
-                        Token::NOT
-                        | Token::PLUS
-                        | Token::MINUS
-                        | Token::TILDE
-                        | Token::LPAREN
-                        | Token::LBRACE
-                        | Token::LBRACKET
-                        | Token::ID(_)
-                        | Token::INT(_)
-                        | Token::FLOAT(_)
-                        | Token::STRING(_)
-                        | Token::NONE
-                        | Token::TRUE
-                        | Token::FALSE => Box::new(self.parse_not_test()),
-                        _ => self.error(format!("SyntaxError: unexpected token")),
-                    },
-                )
+                self.eat(&Token::NOT)?;
+                let operand = match self.tokenizer.get_current_token() {
+                    Token::NOT
+                    | Token::PLUS
+                    | Token::MINUS
+                    | Token::TILDE
+                    | Token::LPAREN
+                    | Token::LBRACE
+                    | Token::LBRACKET
+                    | Token::ID(_)
+                    | Token::INT(_)
+                    | Token::FLOAT(_)
+                    | Token::STRING(_)
+                    | Token::NONE
+                    | Token::TRUE
+                    | Token::FALSE => self.nested(Parser::parse_not_test)?,
+                    _ => return Err(self.expected("expression")),
+                };
+                Ok(self.located(
+                    start,
+                    ASTExprKind::UnaryOp(ASTUnaryOp::Not, Box::new(operand)),
+                ))
             }
             Token::PLUS
             | Token::MINUS
@@ -1104,12 +1387,13 @@ impl Parser {
             | Token::NONE
             | Token::TRUE
             | Token::FALSE => self.parse_comparison(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            _ => Err(self.expected("expression")),
         }
     }
 
-    fn parse_and_expr(&mut self) -> ASTExpr {
-        let left = match self.tokenizer.get_current_token() {
+    fn parse_and_expr(&mut self) -> ParseResult<ASTExpr> {
+        let start = self.start();
+        let mut left = match self.tokenizer.get_current_token() {
             Token::PLUS
             | Token::MINUS
             | Token::TILDE
@@ -1122,11 +1406,11 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_shift_expr(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_shift_expr()?,
+            _ => return Err(self.expected("expression")),
         };
-        if *self.tokenizer.get_current_token() == Token::AMP {
-            self.eat(&Token::AMP);
+        while *self.tokenizer.get_current_token() == Token::AMP {
+            self.eat(&Token::AMP)?;
             let right = match self.tokenizer.get_current_token() {
                 Token::PLUS
                 | Token::MINUS
@@ -1140,17 +1424,20 @@ impl Parser {
                 | Token::STRING(_)
                 | Token::NONE
                 | Token::TRUE
-                | Token::FALSE => self.parse_and_expr(),
-                _ => self.error(format!("SyntaxError: unexpected token")),
+                | Token::FALSE => self.parse_shift_expr()?,
+                _ => return Err(self.expected("expression")),
             };
-            ASTExpr::BinOp(Box::new(left), ASTOperator::BitAnd, Box::new(right))
-        } else {
-            left
+            left = self.located(
+                start,
+                ASTExprKind::BinOp(Box::new(left), ASTOperator::BitAnd, Box::new(right)),
+            );
         }
+        Ok(left)
     }
 
-    fn parse_shift_expr(&mut self) -> ASTExpr {
-        let left = match self.tokenizer.get_current_token() {
+    fn parse_shift_expr(&mut self) -> ParseResult<ASTExpr> {
+        let start = self.start();
+        let mut left = match self.tokenizer.get_current_token() {
             Token::PLUS
             | Token::MINUS
             | Token::TILDE
@@ -1163,45 +1450,43 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_arith_expr(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_arith_expr()?,
+            _ => return Err(self.expected("expression")),
         };
-        match self.tokenizer.get_current_token() {
-            t @ Token::LSHIFT | t @ Token::RSHIFT => {
-                let t = t.clone();
-                self.eat(&t);
-                let right = match self.tokenizer.get_current_token() {
-                    Token::PLUS
-                    | Token::MINUS
-                    | Token::TILDE
-                    | Token::LPAREN
-                    | Token::LBRACE
-                    | Token::LBRACKET
-                    | Token::ID(_)
-                    | Token::INT(_)
-                    | Token::FLOAT(_)
-                    | Token::STRING(_)
-                    | Token::NONE
-                    | Token::TRUE
-                    | Token::FALSE => self.parse_shift_expr(),
-                    _ => self.error(format!("SyntaxError: unexpected token")),
-                };
-                ASTExpr::BinOp(
-                    Box::new(left),
-                    if t == Token::LSHIFT {
-                        ASTOperator::LShift
-                    } else {
-                        ASTOperator::RShift
-                    },
-                    Box::new(right),
-                )
-            }
-            _ => left,
+        loop {
+            let op = match self.tokenizer.get_current_token() {
+                Token::LSHIFT => ASTOperator::LShift,
+                Token::RSHIFT => ASTOperator::RShift,
+                _ => break,
+            };
+            self.advance();
+            let right = match self.tokenizer.get_current_token() {
+                Token::PLUS
+                | Token::MINUS
+                | Token::TILDE
+                | Token::LPAREN
+                | Token::LBRACE
+                | Token::LBRACKET
+                | Token::ID(_)
+                | Token::INT(_)
+                | Token::FLOAT(_)
+                | Token::STRING(_)
+                | Token::NONE
+                | Token::TRUE
+                | Token::FALSE => self.parse_arith_expr()?,
+                _ => return Err(self.expected("expression")),
+            };
+            left = self.located(
+                start,
+                ASTExprKind::BinOp(Box::new(left), op, Box::new(right)),
+            );
         }
+        Ok(left)
     }
 
-    fn parse_arith_expr(&mut self) -> ASTExpr {
-        let left = match self.tokenizer.get_current_token() {
+    fn parse_arith_expr(&mut self) -> ParseResult<ASTExpr> {
+        let start = self.start();
+        let mut left = match self.tokenizer.get_current_token() {
             Token::PLUS
             | Token::MINUS
             | Token::TILDE
@@ -1214,44 +1499,43 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_term(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_term()?,
+            _ => return Err(self.expected("expression")),
         };
-        match self.tokenizer.get_current_token() {
-            t @ Token::PLUS | t @ Token::MINUS => {
-                let t = t.clone();
-                self.eat(&t);
-                let right = match self.tokenizer.get_current_token() {
-                    Token::PLUS
-                    | Token::MINUS
-                    | Token::TILDE
-                    | Token::LPAREN
-                    | Token::LBRACE
-                    | Token::LBRACKET
-                    | Token::ID(_)
-                    | Token::INT(_)
-                    | Token::FLOAT(_)
-                    | Token::STRING(_)
-                    | Token::NONE
-                    | Token::TRUE
-                    | Token::FALSE => self.parse_arith_expr(),
-                    _ => self.error(format!("SyntaxError: unexpected token")),
-                };
-                ASTExpr::BinOp(
-                    Box::new(left),
-                    if t == Token::PLUS {
-                        ASTOperator::Add
-                    } else {
-                        ASTOperator::Sub
-                    },
-                    Box::new(right),
-                )
-            }
-            _ => left,
+        // 同じ優先順位の演算子は左結合(`a - b - c` is `(a - b) - c`)
+        loop {
+            let op = match self.tokenizer.get_current_token() {
+                Token::PLUS => ASTOperator::Add,
+                Token::MINUS => ASTOperator::Sub,
+                _ => break,
+            };
+            self.advance();
+            let right = match self.tokenizer.get_current_token() {
+                Token::PLUS
+                | Token::MINUS
+                | Token::TILDE
+                | Token::LPAREN
+                | Token::LBRACE
+                | Token::LBRACKET
+                | Token::ID(_)
+                | Token::INT(_)
+                | Token::FLOAT(_)
+                | Token::STRING(_)
+                | Token::NONE
+                | Token::TRUE
+                | Token::FALSE => self.parse_term()?,
+                _ => return Err(self.expected("expression")),
+            };
+            left = self.located(
+                start,
+                ASTExprKind::BinOp(Box::new(left), op, Box::new(right)),
+            );
         }
+        Ok(left)
     }
 
-    fn parse_comparison(&mut self) -> ASTExpr {
+    fn parse_comparison(&mut self) -> ParseResult<ASTExpr> {
+        let start = self.start();
         let left = match self.tokenizer.get_current_token() {
             Token::PLUS
             | Token::MINUS
@@ -1265,53 +1549,53 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_expr(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_expr()?,
+            _ => return Err(self.expected("expression")),
         };
         let mut ops: Vec<ASTCmpOp> = Vec::new();
         let mut comparators: Vec<ASTExpr> = Vec::new();
-        loop {
-            match self.tokenizer.get_current_token() {
-                Token::LT
-                | Token::GT
-                | Token::EQEQ
-                | Token::GEQ
-                | Token::LEQ
-                | Token::NEQ
-                | Token::IN
-                | Token::NOT
-                | Token::IS => {
-                    ops.push(self.parse_comp_op());
-                    comparators.push(match self.tokenizer.get_current_token() {
-                        Token::PLUS
-                        | Token::MINUS
-                        | Token::TILDE
-                        | Token::LPAREN
-                        | Token::LBRACE
-                        | Token::LBRACKET
-                        | Token::ID(_)
-                        | Token::INT(_)
-                        | Token::FLOAT(_)
-                        | Token::STRING(_)
-                        | Token::NONE
-                        | Token::TRUE
-                        | Token::FALSE => self.parse_comparison(),
-                        _ => self.error(format!("SyntaxError: unexpected token")),
-                    });
-                }
-                _ => break,
-            };
+        while let Token::LT
+        | Token::GT
+        | Token::EQEQ
+        | Token::GEQ
+        | Token::LEQ
+        | Token::NEQ
+        | Token::IN
+        | Token::NOT
+        | Token::IS = self.tokenizer.get_current_token()
+        {
+            ops.push(self.parse_comp_op()?);
+            comparators.push(match self.tokenizer.get_current_token() {
+                Token::PLUS
+                | Token::MINUS
+                | Token::TILDE
+                | Token::LPAREN
+                | Token::LBRACE
+                | Token::LBRACKET
+                | Token::ID(_)
+                | Token::INT(_)
+                | Token::FLOAT(_)
+                | Token::STRING(_)
+                | Token::NONE
+                | Token::TRUE
+                | Token::FALSE => self.parse_expr()?,
+                _ => return Err(self.expected("expression")),
+            });
         }
-        // assert_eq!(ops.len(), comparators.len());
-        if ops.len() == 0 {
-            left
+        // `a < b == c` is a single Compare
+        if ops.is_empty() {
+            Ok(left)
         } else {
-            ASTExpr::Compare(Box::new(left), ops, comparators)
+            Ok(self.located(
+                start,
+                ASTExprKind::Compare(Box::new(left), ops, comparators),
+            ))
         }
     }
 
-    fn parse_term(&mut self) -> ASTExpr {
-        let left = match self.tokenizer.get_current_token() {
+    fn parse_term(&mut self) -> ParseResult<ASTExpr> {
+        let start = self.start();
+        let mut left = match self.tokenizer.get_current_token() {
             Token::PLUS
             | Token::MINUS
             | Token::TILDE
@@ -1324,98 +1608,96 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_factor(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_factor()?,
+            _ => return Err(self.expected("expression")),
         };
-        match self.tokenizer.get_current_token() {
-            t @ Token::MUL | t @ Token::DIV | t @ Token::MOD => {
-                let t = t.clone();
-                self.eat(&t);
-                let right = match self.tokenizer.get_current_token() {
-                    Token::PLUS
-                    | Token::MINUS
-                    | Token::TILDE
-                    | Token::LPAREN
-                    | Token::LBRACE
-                    | Token::LBRACKET
-                    | Token::ID(_)
-                    | Token::INT(_)
-                    | Token::FLOAT(_)
-                    | Token::STRING(_)
-                    | Token::NONE
-                    | Token::TRUE
-                    | Token::FALSE => self.parse_term(),
-                    _ => self.error(format!("SyntaxError: unexpected token")),
-                };
-                ASTExpr::BinOp(
-                    Box::new(left),
-                    match t {
-                        Token::MUL => ASTOperator::Mul,
-                        Token::DIV => ASTOperator::Div,
-                        Token::MOD => ASTOperator::Mod,
-                        _ => self.error(format!("SyntaxError: unexpected token")),
-                    },
-                    Box::new(right),
-                )
-            }
-            _ => left,
+        loop {
+            let op = match self.tokenizer.get_current_token() {
+                Token::MUL => ASTOperator::Mul,
+                Token::DIV => ASTOperator::Div,
+                Token::MOD => ASTOperator::Mod,
+                _ => break,
+            };
+            self.advance();
+            let right = match self.tokenizer.get_current_token() {
+                Token::PLUS
+                | Token::MINUS
+                | Token::TILDE
+                | Token::LPAREN
+                | Token::LBRACE
+                | Token::LBRACKET
+                | Token::ID(_)
+                | Token::INT(_)
+                | Token::FLOAT(_)
+                | Token::STRING(_)
+                | Token::NONE
+                | Token::TRUE
+                | Token::FALSE => self.parse_factor()?,
+                _ => return Err(self.expected("expression")),
+            };
+            left = self.located(
+                start,
+                ASTExprKind::BinOp(Box::new(left), op, Box::new(right)),
+            );
         }
+        Ok(left)
     }
 
-    fn parse_comp_op(&mut self) -> ASTCmpOp {
+    fn parse_comp_op(&mut self) -> ParseResult<ASTCmpOp> {
         match self.tokenizer.get_current_token() {
             Token::LT => {
-                self.eat(&Token::LT);
-                ASTCmpOp::Lt
+                self.eat(&Token::LT)?;
+                Ok(ASTCmpOp::Lt)
             }
             Token::GT => {
-                self.eat(&Token::GT);
-                ASTCmpOp::Gt
+                self.eat(&Token::GT)?;
+                Ok(ASTCmpOp::Gt)
             }
             Token::LEQ => {
-                self.eat(&Token::LEQ);
-                ASTCmpOp::LtE
+                self.eat(&Token::LEQ)?;
+                Ok(ASTCmpOp::LtE)
             }
             Token::GEQ => {
-                self.eat(&Token::GEQ);
-                ASTCmpOp::GtE
+                self.eat(&Token::GEQ)?;
+                Ok(ASTCmpOp::GtE)
             }
             Token::EQEQ => {
-                self.eat(&Token::EQEQ);
-                ASTCmpOp::Eq
+                self.eat(&Token::EQEQ)?;
+                Ok(ASTCmpOp::Eq)
             }
             Token::NEQ => {
-                self.eat(&Token::NEQ);
-                ASTCmpOp::NotEq
+                self.eat(&Token::NEQ)?;
+                Ok(ASTCmpOp::NotEq)
             }
             Token::IN => {
-                self.eat(&Token::IN);
-                ASTCmpOp::In
+                self.eat(&Token::IN)?;
+                Ok(ASTCmpOp::In)
             }
             Token::NOT => {
-                self.eat(&Token::NOT);
-                self.eat(&Token::IN);
-                ASTCmpOp::NotIn
+                self.eat(&Token::NOT)?;
+                self.eat(&Token::IN)?;
+                Ok(ASTCmpOp::NotIn)
             }
             Token::IS => {
-                self.eat(&Token::IS);
+                self.eat(&Token::IS)?;
                 match self.tokenizer.get_current_token() {
                     Token::NOT => {
-                        self.eat(&Token::NOT);
-                        ASTCmpOp::IsNot
+                        self.eat(&Token::NOT)?;
+                        Ok(ASTCmpOp::IsNot)
                     }
-                    _ => ASTCmpOp::Is,
+                    _ => Ok(ASTCmpOp::Is),
                 }
             }
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            _ => Err(self.expected("comparison operator")),
         }
     }
 
-    fn parse_factor(&mut self) -> ASTExpr {
+    fn parse_factor(&mut self) -> ParseResult<ASTExpr> {
+        let start = self.start();
         match self.tokenizer.get_current_token() {
             t @ Token::PLUS | t @ Token::MINUS | t @ Token::TILDE => {
                 let t = t.clone();
-                self.eat(&t);
+                self.eat(&t)?;
                 let operand = match self.tokenizer.get_current_token() {
                     Token::PLUS
                     | Token::MINUS
@@ -1429,18 +1711,21 @@ impl Parser {
                     | Token::STRING(_)
                     | Token::NONE
                     | Token::TRUE
-                    | Token::FALSE => self.parse_factor(),
-                    _ => self.error(format!("SyntaxError: unexpected token")),
+                    | Token::FALSE => self.nested(Parser::parse_factor)?,
+                    _ => return Err(self.expected("expression")),
                 };
-                ASTExpr::UnaryOp(
-                    match t {
-                        Token::PLUS => ASTUnaryOp::UAdd,
-                        Token::MINUS => ASTUnaryOp::USub,
-                        Token::TILDE => ASTUnaryOp::Invert,
-                        _ => self.error(format!("SyntaxError: unexpected token")),
-                    },
-                    Box::new(operand),
-                )
+                Ok(self.located(
+                    start,
+                    ASTExprKind::UnaryOp(
+                        match t {
+                            Token::PLUS => ASTUnaryOp::UAdd,
+                            Token::MINUS => ASTUnaryOp::USub,
+                            Token::TILDE => ASTUnaryOp::Invert,
+                            _ => return Err(self.expected("operator")),
+                        },
+                        Box::new(operand),
+                    ),
+                ))
             }
             Token::LPAREN
             | Token::LBRACE
@@ -1452,11 +1737,12 @@ impl Parser {
             | Token::NONE
             | Token::TRUE
             | Token::FALSE => self.parse_atom_expr(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            _ => Err(self.expected("expression")),
         }
     }
 
-    fn parse_atom_expr(&mut self) -> ASTExpr {
+    fn parse_atom_expr(&mut self) -> ParseResult<ASTExpr> {
+        let start = self.start();
         let mut atom = match self.tokenizer.get_current_token() {
             Token::LPAREN
             | Token::LBRACE
@@ -1467,37 +1753,44 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_atom(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_atom()?,
+            _ => return Err(self.expected("expression")),
         };
-        // trailerはCallかSubscriptで、atomや直前のtrailerの結果を包んでいく形になる
+        // trailerはCall/Subscript/Attributeで、atomや直前のtrailerの結果を包んでいく形になる
         loop {
             match self.tokenizer.get_current_token() {
                 Token::LPAREN => {
                     // function call
-                    self.eat(&Token::LPAREN);
-                    let args = self.parse_arglist();
-                    self.eat(&Token::RPAREN);
-                    atom = ASTExpr::Call(Box::new(atom), args);
+                    self.eat(&Token::LPAREN)?;
+                    let (args, keywords) = self.parse_arglist()?;
+                    self.expect(&Token::RPAREN, "to close call")?;
+                    atom = self.located(start, ASTExprKind::Call(Box::new(atom), args, keywords));
                 }
                 Token::LBRACKET => {
                     // list/set/dict subscription
-                    self.eat(&Token::LBRACKET);
-                    let slice = self.parse_subscript();
-                    self.eat(&Token::RBRACKET);
-                    atom = ASTExpr::Subscript(Box::new(atom), slice);
+                    self.eat(&Token::LBRACKET)?;
+                    let slice = self.parse_subscript()?;
+                    self.expect(&Token::RBRACKET, "to close subscript")?;
+                    atom = self.located(start, ASTExprKind::Subscript(Box::new(atom), slice));
+                }
+                Token::PERIOD => {
+                    // attribute reference
+                    self.eat(&Token::PERIOD)?;
+                    let attr = self.eat_id()?;
+                    atom = self.located(start, ASTExprKind::Attribute(Box::new(atom), attr));
                 }
                 _ => break,
             }
         }
-        atom
+        Ok(atom)
     }
 
-    fn parse_atom(&mut self) -> ASTExpr {
+    fn parse_atom(&mut self) -> ParseResult<ASTExpr> {
+        let start = self.start();
         match self.tokenizer.get_current_token() {
             Token::LPAREN => {
-                self.eat(&Token::LPAREN);
-                let res = match self.tokenizer.get_current_token() {
+                self.eat(&Token::LPAREN)?;
+                let elts = match self.tokenizer.get_current_token() {
                     Token::NOT
                     | Token::PLUS
                     | Token::MINUS
@@ -1512,21 +1805,23 @@ impl Parser {
                     | Token::NONE
                     | Token::TRUE
                     | Token::FALSE => {
-                        let (mut elts, trailing_comma) = self.parse_testlist_comp();
+                        let (mut elts, trailing_comma) = self.parse_testlist_comp()?;
                         if elts.len() == 1 && !trailing_comma {
-                            elts.swap_remove(0)
-                        } else {
-                            ASTExpr::Tuple(elts)
+                            // 括弧で囲まれた式(spanに括弧は含まない)
+                            let res = elts.swap_remove(0);
+                            self.expect(&Token::RPAREN, "to close parenthesis")?;
+                            return Ok(res);
                         }
+                        elts
                     }
-                    _ => ASTExpr::Tuple(Vec::new()),
+                    _ => Vec::new(),
                 };
-                self.eat(&Token::RPAREN);
-                res
+                self.expect(&Token::RPAREN, "to close tuple")?;
+                Ok(self.located(start, ASTExprKind::Tuple(elts)))
             }
             Token::LBRACKET => {
-                self.eat(&Token::LBRACKET);
-                let res = match self.tokenizer.get_current_token() {
+                self.eat(&Token::LBRACKET)?;
+                let elts = match self.tokenizer.get_current_token() {
                     Token::NOT
                     | Token::PLUS
                     | Token::MINUS
@@ -1540,52 +1835,61 @@ impl Parser {
                     | Token::STRING(_)
                     | Token::NONE
                     | Token::TRUE
-                    | Token::FALSE => ASTExpr::List(self.parse_testlist_comp().0),
-                    _ => ASTExpr::List(Vec::new()),
+                    | Token::FALSE => self.parse_testlist_comp()?.0,
+                    _ => Vec::new(),
                 };
-                self.eat(&Token::RBRACKET);
-                res
+                self.expect(&Token::RBRACKET, "to close list")?;
+                Ok(self.located(start, ASTExprKind::List(elts)))
             }
             Token::LBRACE => {
-                self.eat(&Token::LBRACE);
-                let res = self.parse_dictorsetmaker();
-                self.eat(&Token::RBRACE);
-                res
+                self.eat(&Token::LBRACE)?;
+                let res = if *self.tokenizer.get_current_token() == Token::RBRACE {
+                    // `{}` is an empty dict
+                    ASTExprKind::Dict(Vec::new(), Vec::new())
+                } else {
+                    self.parse_dictorsetmaker()?
+                };
+                self.expect(&Token::RBRACE, "to close dict or set")?;
+                Ok(self.located(start, res))
             }
             Token::ID(_) => {
-                let name = self.eat_id();
-                ASTExpr::Name(name)
+                let name = self.eat_id()?;
+                Ok(self.located(start, ASTExprKind::Name(name)))
             }
             Token::INT(_) => {
-                let num = self.eat_int();
-                ASTExpr::Constant(ASTConstant::Int(num))
+                let num = self.eat_int()?;
+                Ok(self.located(start, ASTExprKind::Constant(ASTConstant::Int(num))))
             }
             Token::FLOAT(_) => {
-                let num = self.eat_float();
-                ASTExpr::Constant(ASTConstant::Float(OrderedFloat(num)))
+                let num = self.eat_float()?;
+                Ok(self.located(
+                    start,
+                    ASTExprKind::Constant(ASTConstant::Float(OrderedFloat(num))),
+                ))
             }
             Token::STRING(_) => {
-                let val = self.eat_str();
-                ASTExpr::Constant(ASTConstant::String(val))
+                let val = self.eat_str()?;
+                Ok(self.located(start, ASTExprKind::Constant(ASTConstant::String(val))))
             }
             Token::NONE => {
-                self.eat(&Token::NONE);
-                ASTExpr::Constant(ASTConstant::None)
+                self.eat(&Token::NONE)?;
+                Ok(self.located(start, ASTExprKind::Constant(ASTConstant::None)))
             }
             Token::TRUE => {
-                self.eat(&Token::TRUE);
-                ASTExpr::Constant(ASTConstant::True)
+                self.eat(&Token::TRUE)?;
+                Ok(self.located(start, ASTExprKind::Constant(ASTConstant::True)))
             }
             Token::FALSE => {
-                self.eat(&Token::FALSE);
-                ASTExpr::Constant(ASTConstant::False)
+                self.eat(&Token::FALSE)?;
+                Ok(self.located(start, ASTExprKind::Constant(ASTConstant::False)))
             }
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            _ => Err(self.expected("expression")),
         }
     }
 
-    fn parse_subscript(&mut self) -> ASTSlice {
-        match self.tokenizer.get_current_token() {
+    fn parse_subscript(&mut self) -> ParseResult<ASTSlice> {
+        let start = self.start();
+        let slice = match self.tokenizer.get_current_token() {
             Token::NOT
             | Token::PLUS
             | Token::MINUS
@@ -1600,9 +1904,9 @@ impl Parser {
             | Token::NONE
             | Token::TRUE
             | Token::FALSE => {
-                let lower = Box::new(self.parse_test());
+                let lower = Box::new(self.parse_test()?);
                 if *self.tokenizer.get_current_token() == Token::COLON {
-                    self.eat(&Token::COLON);
+                    self.eat(&Token::COLON)?;
                     let upper = match *self.tokenizer.get_current_token() {
                         Token::NOT
                         | Token::PLUS
@@ -1617,12 +1921,12 @@ impl Parser {
                         | Token::STRING(_)
                         | Token::NONE
                         | Token::TRUE
-                        | Token::FALSE => Some(Box::new(self.parse_test())),
+                        | Token::FALSE => Some(Box::new(self.parse_test()?)),
                         _ => None,
                     };
                     let step = match *self.tokenizer.get_current_token() {
                         Token::COLON => {
-                            self.eat(&Token::COLON);
+                            self.eat(&Token::COLON)?;
                             match self.tokenizer.get_current_token() {
                                 Token::NOT
                                 | Token::PLUS
@@ -1637,20 +1941,32 @@ impl Parser {
                                 | Token::STRING(_)
                                 | Token::NONE
                                 | Token::TRUE
-                                | Token::FALSE => Some(Box::new(self.parse_test())),
+                                | Token::FALSE => Some(Box::new(self.parse_test()?)),
                                 _ => None,
                             }
                         }
                         _ => None,
                     };
-                    ASTSlice::Slice(Some(lower), upper, step)
+                    ASTSliceKind::Slice(Some(lower), upper, step)
+                } else if *self.tokenizer.get_current_token() == Token::COMMA {
+                    // `x[a, b]` はタプルで添字付け(dict[str, int] など)
+                    let mut elts = vec![*lower];
+                    while *self.tokenizer.get_current_token() == Token::COMMA {
+                        self.eat(&Token::COMMA)?;
+                        if *self.tokenizer.get_current_token() == Token::RBRACKET {
+                            break;
+                        }
+                        elts.push(self.parse_test()?);
+                    }
+                    let tuple = self.located(start, ASTExprKind::Tuple(elts));
+                    ASTSliceKind::Index(Box::new(tuple))
                 } else {
                     // 単体
-                    ASTSlice::Index(lower)
+                    ASTSliceKind::Index(lower)
                 }
             }
             Token::COLON => {
-                self.eat(&Token::COLON);
+                self.eat(&Token::COLON)?;
                 let upper = match *self.tokenizer.get_current_token() {
                     Token::NOT
                     | Token::PLUS
@@ -1665,12 +1981,12 @@ impl Parser {
                     | Token::STRING(_)
                     | Token::NONE
                     | Token::TRUE
-                    | Token::FALSE => Some(Box::new(self.parse_test())),
+                    | Token::FALSE => Some(Box::new(self.parse_test()?)),
                     _ => None,
                 };
                 let step = match *self.tokenizer.get_current_token() {
                     Token::COLON => {
-                        self.eat(&Token::COLON);
+                        self.eat(&Token::COLON)?;
                         match self.tokenizer.get_current_token() {
                             Token::NOT
                             | Token::PLUS
@@ -1685,19 +2001,20 @@ impl Parser {
                             | Token::STRING(_)
                             | Token::NONE
                             | Token::TRUE
-                            | Token::FALSE => Some(Box::new(self.parse_test())),
+                            | Token::FALSE => Some(Box::new(self.parse_test()?)),
                             _ => None,
                         }
                     }
                     _ => None,
                 };
-                ASTSlice::Slice(None, upper, step)
+                ASTSliceKind::Slice(None, upper, step)
             }
-            _ => self.error(format!("SyntaxError: unexpected token")),
-        }
+            _ => return Err(self.expected("expression")),
+        };
+        Ok(self.located(start, slice))
     }
 
-    fn parse_dictorsetmaker(&mut self) -> ASTExpr {
+    fn parse_dictorsetmaker(&mut self) -> ParseResult<ASTExprKind> {
         let first_element = match self.tokenizer.get_current_token() {
             Token::NOT
             | Token::PLUS
@@ -1712,15 +2029,15 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_test(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_test()?,
+            _ => return Err(self.expected("expression")),
         };
         match *self.tokenizer.get_current_token() {
             Token::COLON => {
                 // dict
                 let mut keys = vec![first_element];
                 let mut values = Vec::new();
-                self.eat(&Token::COLON);
+                self.eat(&Token::COLON)?;
                 values.push(match self.tokenizer.get_current_token() {
                     Token::NOT
                     | Token::PLUS
@@ -1735,11 +2052,11 @@ impl Parser {
                     | Token::STRING(_)
                     | Token::NONE
                     | Token::TRUE
-                    | Token::FALSE => self.parse_test(),
-                    _ => self.error(format!("SyntaxError: unexpected token")),
+                    | Token::FALSE => self.parse_test()?,
+                    _ => return Err(self.expected("expression")),
                 });
-                while *self.tokenizer.get_current_token() == Token::COLON {
-                    self.eat(&Token::COLON);
+                while *self.tokenizer.get_current_token() == Token::COMMA {
+                    self.eat(&Token::COMMA)?;
                     keys.push(match self.tokenizer.get_current_token() {
                         Token::NOT
                         | Token::PLUS
@@ -1754,9 +2071,10 @@ impl Parser {
                         | Token::STRING(_)
                         | Token::NONE
                         | Token::TRUE
-                        | Token::FALSE => self.parse_test(),
+                        | Token::FALSE => self.parse_test()?,
                         _ => break,
                     });
+                    self.eat(&Token::COLON)?;
                     values.push(match self.tokenizer.get_current_token() {
                         Token::NOT
                         | Token::PLUS
@@ -1771,17 +2089,17 @@ impl Parser {
                         | Token::STRING(_)
                         | Token::NONE
                         | Token::TRUE
-                        | Token::FALSE => self.parse_test(),
-                        _ => self.error(format!("SyntaxError: unexpected token")),
+                        | Token::FALSE => self.parse_test()?,
+                        _ => return Err(self.expected("expression")),
                     });
                 }
-                ASTExpr::Dict(keys, values)
+                Ok(ASTExprKind::Dict(keys, values))
             }
             Token::COMMA => {
                 let mut body = vec![first_element];
                 // set
                 while *self.tokenizer.get_current_token() == Token::COMMA {
-                    self.eat(&Token::COMMA);
+                    self.eat(&Token::COMMA)?;
                     body.push(match self.tokenizer.get_current_token() {
                         Token::NOT
                         | Token::PLUS
@@ -1796,23 +2114,25 @@ impl Parser {
                         | Token::STRING(_)
                         | Token::NONE
                         | Token::TRUE
-                        | Token::FALSE => self.parse_test(),
+                        | Token::FALSE => self.parse_test()?,
                         _ => break,
                     });
                 }
-                ASTExpr::Set(body)
+                Ok(ASTExprKind::Set(body))
             }
             _ => {
                 // set with one element
-                ASTExpr::Set(vec![first_element])
+                Ok(ASTExprKind::Set(vec![first_element]))
             }
         }
     }
 
-    fn parse_arglist(&mut self) -> Vec<ASTExpr> {
-        let mut arglist = Vec::new();
+    // return value's second is keyword arguments
+    fn parse_arglist(&mut self) -> ParseResult<(Vec<ASTExpr>, Vec<ASTKeyword>)> {
+        let mut args = Vec::new();
+        let mut keywords = Vec::new();
         if *self.tokenizer.get_current_token() != Token::RPAREN {
-            arglist.push(match self.tokenizer.get_current_token() {
+            match self.tokenizer.get_current_token() {
                 Token::NOT
                 | Token::PLUS
                 | Token::MINUS
@@ -1826,13 +2146,13 @@ impl Parser {
                 | Token::STRING(_)
                 | Token::NONE
                 | Token::TRUE
-                | Token::FALSE => self.parse_test(),
-                _ => self.error(format!("SyntaxError: unexpected token")),
-            });
+                | Token::FALSE => self.parse_argument(&mut args, &mut keywords)?,
+                _ => return Err(self.expected("expression")),
+            };
         }
         while *self.tokenizer.get_current_token() == Token::COMMA {
-            self.eat(&Token::COMMA);
-            arglist.push(match self.tokenizer.get_current_token() {
+            self.eat(&Token::COMMA)?;
+            match self.tokenizer.get_current_token() {
                 Token::NOT
                 | Token::PLUS
                 | Token::MINUS
@@ -1846,15 +2166,63 @@ impl Parser {
                 | Token::STRING(_)
                 | Token::NONE
                 | Token::TRUE
-                | Token::FALSE => self.parse_test(),
+                | Token::FALSE => self.parse_argument(&mut args, &mut keywords)?,
                 _ => break,
-            });
+            };
+        }
+        Ok((args, keywords))
+    }
+
+    // argument ::= test | NAME '=' test
+    fn parse_argument(
+        &mut self,
+        args: &mut Vec<ASTExpr>,
+        keywords: &mut Vec<ASTKeyword>,
+    ) -> ParseResult<()> {
+        let test = self.parse_test()?;
+        if *self.tokenizer.get_current_token() == Token::EQ {
+            let start = (test.span.line, test.span.column);
+            self.eat(&Token::EQ)?;
+            let name = match test.node {
+                ASTExprKind::Name(name) => name,
+                _ => {
+                    return Err(SyntaxError::new(
+                        "expression cannot contain assignment",
+                        test.span,
+                    ))
+                }
+            };
+            let value = match self.tokenizer.get_current_token() {
+                Token::NOT
+                | Token::PLUS
+                | Token::MINUS
+                | Token::TILDE
+                | Token::LPAREN
+                | Token::LBRACE
+                | Token::LBRACKET
+                | Token::ID(_)
+                | Token::INT(_)
+                | Token::FLOAT(_)
+                | Token::STRING(_)
+                | Token::NONE
+                | Token::TRUE
+                | Token::FALSE => self.parse_test()?,
+                _ => return Err(self.expected("expression")),
+            };
+            keywords.push(self.located(start, (name, value)));
+        } else if !keywords.is_empty() {
+            return Err(SyntaxError::new(
+                "positional argument follows keyword argument",
+                test.span,
+            ));
+        } else {
+            args.push(test);
         }
-        arglist
+        Ok(())
     }
 
     // return value's second is if it has trailing comma(for tuple)
-    fn parse_testlist_comp(&mut self) -> (Vec<ASTExpr>, bool) {
+    fn parse_testlist_comp(&mut self) -> ParseResult<(Vec<ASTExpr>, bool)> {
         let mut trailing_comma = false;
         let mut res = vec![match self.tokenizer.get_current_token() {
             Token::NOT
@@ -1870,11 +2238,11 @@ impl Parser {
             | Token::STRING(_)
             | Token::NONE
             | Token::TRUE
-            | Token::FALSE => self.parse_test(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            | Token::FALSE => self.parse_test()?,
+            _ => return Err(self.expected("expression")),
         }];
         while *self.tokenizer.get_current_token() == Token::COMMA {
-            self.eat(&Token::COMMA);
+            self.eat(&Token::COMMA)?;
             res.push(match self.tokenizer.get_current_token() {
                 Token::NOT
                 | Token::PLUS
@@ -1889,58 +2257,106 @@ impl Parser {
                 | Token::STRING(_)
                 | Token::NONE
                 | Token::TRUE
-                | Token::FALSE => self.parse_test(),
+                | Token::FALSE => self.parse_test()?,
                 _ => {
                     trailing_comma = true;
                     break;
                 }
             });
         }
-        (res, trailing_comma)
+        Ok((res, trailing_comma))
     }
 
-    fn eat(&mut self, expected: &Token) {
+    fn eat(&mut self, expected: &Token) -> ParseResult<()> {
         if self.tokenizer.get_current_token() != expected {
-            self.error(format!("SyntaxError: unexpected token"));
-        } else {
-            self.tokenizer.next_token();
+            return Err(self.expected(&expected.to_string()));
+        }
+        self.advance();
+        Ok(())
+    }
+
+    // eat, saying where the token is expected in the error message
+    fn expect(&mut self, expected: &Token, context: &str) -> ParseResult<()> {
+        if self.tokenizer.get_current_token() != expected {
+            return Err(self.expected(&format!("{} {}", expected, context)));
         }
+        self.advance();
+        Ok(())
+    }
+
+    // go to the next token, remembering where the consumed one ended
+    fn advance(&mut self) {
+        match self.tokenizer.get_current_token() {
+            Token::NEWLINE | Token::INDENT | Token::DEDENT => self.at_line_start = true,
+            Token::EOF => (),
+            _ => {
+                let span = self.tokenizer.get_current_span();
+                self.last_end = (span.end_line, span.end_column);
+                self.at_line_start = false;
+            }
+        }
+        self.tokenizer.next_token();
+        self.errors.extend(self.tokenizer.take_errors());
+    }
+
+    // start of the current token
+    fn start(&self) -> (usize, usize) {
+        let span = self.tokenizer.get_current_span();
+        (span.line, span.column)
+    }
+
+    // node from start to the end of the last consumed token
+    fn located<T>(&self, start: (usize, usize), node: T) -> Located<T> {
+        Located::new(node, Span::new(start, self.last_end))
+    }
+
+    // parameter of a function, with the annotation
+    fn eat_arg(&mut self) -> ParseResult<ASTArg> {
+        let start = self.start();
+        let name = self.eat_id()?;
+        let annotation = if *self.tokenizer.get_current_token() == Token::COLON {
+            self.eat(&Token::COLON)?;
+            Some(self.parse_test()?)
+        } else {
+            None
+        };
+        Ok(self.located(start, (name, annotation)))
     }
 
-    fn eat_id(&mut self) -> String {
+    fn eat_id(&mut self) -> ParseResult<String> {
         let name = match self.tokenizer.get_current_token() {
             Token::ID(name) => name.to_owned(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            _ => return Err(self.expected("identifier")),
         };
-        self.tokenizer.next_token();
-        name
+        self.advance();
+        Ok(name)
     }
 
-    fn eat_int(&mut self) -> i64 {
+    fn eat_int(&mut self) -> ParseResult<i64> {
         let num = *(match self.tokenizer.get_current_token() {
             Token::INT(num) => num,
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            _ => return Err(self.expected("integer")),
         });
-        self.tokenizer.next_token();
-        num
+        self.advance();
+        Ok(num)
     }
 
-    fn eat_float(&mut self) -> f64 {
+    fn eat_float(&mut self) -> ParseResult<f64> {
         let num = *(match self.tokenizer.get_current_token() {
             Token::FLOAT(num) => num,
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            _ => return Err(self.expected("number")),
         });
-        self.tokenizer.next_token();
-        num
+        self.advance();
+        Ok(num)
     }
 
-    fn eat_str(&mut self) -> String {
+    fn eat_str(&mut self) -> ParseResult<String> {
         let name = match self.tokenizer.get_current_token() {
             Token::STRING(name) => name.to_owned(),
-            _ => self.error(format!("SyntaxError: unexpected token")),
+            _ => return Err(self.expected("string")),
         };
-        self.tokenizer.next_token();
-        name
+        self.advance();
+        Ok(name)
     }
 
     pub fn get_file_name(&self) -> &String {
@@ -1971,15 +2387,24 @@ impl Parser {
         self.tokenizer.get_current_line_content()
     }
 
-    fn error(&self, message: String) -> ! {
-        eprintln!(
-            "File {}, line {}\n{}\n{}\n{}",
-            self.tokenizer.get_file_name(),
-            self.tokenizer.get_current_line(),
-            self.tokenizer.get_current_line_content(),
-            " ".repeat(self.tokenizer.get_current_line_content().len()),
-            message
-        );
-        std::process::exit(1);
+    // parse something inside the current expression or block
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Parser) -> ParseResult<T>) -> ParseResult<T> {
+        if self.depth >= MAX_NESTING {
+            return Err(self.error("too many nesting levels"));
+        }
+        self.depth += 1;
+        // each level takes a few frames of the recursive descent
+        let res = stacker::maybe_grow(RED_ZONE, STACK_SEGMENT_SIZE, || parse(self));
+        self.depth -= 1;
+        res
+    }
+
+    // error at the current token
+    fn error(&self, message: &str) -> SyntaxError {
+        SyntaxError::new(message, self.tokenizer.get_current_span())
+    }
+
+    fn expected(&self, what: &str) -> SyntaxError {
+        self.error(&format!("expected {}", what))
     }
 }
@@ -1,6 +1,3 @@
-def range(n):
-    return ntv_range(n)
-
 def __add__(lhs, rhs):
     if ntv_is_int(lhs):
         if ntv_is_int(rhs):
@@ -50,6 +47,8 @@ def __sub__(lhs, rhs):
             return ntv_sub_float(lhs, rhs)
         else:
             ntv_panic()
+    elif ntv_is_set(lhs) and ntv_is_set(rhs):
+        return ntv_sub_set(lhs, rhs)
     else:
         ntv_panic()
 
@@ -59,6 +58,8 @@ def __mul__(lhs, rhs):
             return ntv_mul_int(lhs, rhs)
         elif ntv_is_float(rhs):
             return ntv_mul_float(ntv_itof(lhs), rhs)
+        elif ntv_is_list(rhs):
+            return ntv_mul_list(rhs, lhs)
         else:
             ntv_panic()
     elif ntv_is_float(lhs):
@@ -72,14 +73,13 @@ def __mul__(lhs, rhs):
         if ntv_is_int(rhs):
             res = ""
             for i in range(rhs):
-                res + lhs
+                res = res + lhs
             return res
         else:
             ntv_panic()
     elif ntv_is_list(lhs):
         if ntv_is_int(rhs):
-            # repeat
-            ntv_panic()
+            return ntv_mul_list(lhs, rhs)
         else:
             ntv_panic()
     elif ntv_is_string(lhs):
@@ -145,18 +145,26 @@ def __rshift__(lhs, rhs):
 def __or__(lhs, rhs):
     if ntv_is_int(lhs) and ntv_is_int(rhs):
         return ntv_or_int(lhs, rhs)
+    elif ntv_is_set(lhs) and ntv_is_set(rhs):
+        return ntv_or_set(lhs, rhs)
+    elif ntv_is_dict(lhs) and ntv_is_dict(rhs):
+        return ntv_or_dict(lhs, rhs)
     else:
         ntv_panic()
 
 def __and__(lhs, rhs):
     if ntv_is_int(lhs) and ntv_is_int(rhs):
         return ntv_and_int(lhs, rhs)
+    elif ntv_is_set(lhs) and ntv_is_set(rhs):
+        return ntv_and_set(lhs, rhs)
     else:
         ntv_panic()
 
 def __xor__(lhs, rhs):
     if ntv_is_int(lhs) and ntv_is_int(rhs):
         return ntv_xor_int(lhs, rhs)
+    elif ntv_is_set(lhs) and ntv_is_set(rhs):
+        return ntv_xor_set(lhs, rhs)
     else:
         ntv_panic()
 
@@ -177,7 +185,7 @@ def __plus__(val):
 
 def __minus__(val):
     if ntv_is_int(val) or ntv_is_float(val):
-        return val * -1
+        return 0 - val
     else:
         ntv_panic()
 
@@ -185,32 +193,75 @@ def __eq__(lhs, rhs):
     if ntv_is_int(lhs):
         if ntv_is_int(rhs):
             return ntv_eq_int(lhs, rhs)
-        elif ntv_is_float(lhs):
+        elif ntv_is_float(rhs):
             return ntv_cmp_float(ntv_itof(lhs), rhs) == 0
         else:
-            ntv_panic()
+            return ntv_eq(lhs, rhs)
     elif ntv_is_float(lhs):
         if ntv_is_int(rhs):
             return ntv_cmp_float(lhs, ntv_itof(rhs)) == 0
         elif ntv_is_float(rhs):
             return ntv_cmp_float(lhs, rhs) == 0
         else:
-            ntv_panic()
-    elif ntv_is_string(lhs) and ntv_is_string(rhs):
-        # unimplemented
-        ntv_panic()
-    elif ntv_is_list(lhs) and ntv_is_list(rhs):
-        # unimplemented
-        ntv_panic()
-    elif ntv_is_tuple(lhs) and ntv_is_tuple(rhs):
-        # unimplemented
-        ntv_panic()
-    elif ntv_is_dict(lhs) and ntv_is_dict(rhs):
-        # unimplemented
-        ntv_panic()
-    elif ntv_is_set(lhs) and ntv_is_set(rhs):
-        # unimplemented
+            return ntv_eq(lhs, rhs)
+    else:
+        # str, list, tuple, dict, set, None, ...
+        return ntv_eq(lhs, rhs)
+
+def __neq__(lhs, rhs):
+    return not __eq__(lhs, rhs)
+
+def __lt__(lhs, rhs):
+    return ntv_lt(lhs, rhs)
+
+def __le__(lhs, rhs):
+    return ntv_le(lhs, rhs)
+
+def __gt__(lhs, rhs):
+    return ntv_gt(lhs, rhs)
+
+def __ge__(lhs, rhs):
+    return ntv_ge(lhs, rhs)
+
+def __is__(lhs, rhs):
+    return ntv_is(lhs, rhs)
+
+def __is_not__(lhs, rhs):
+    return not ntv_is(lhs, rhs)
+
+def __hash__(val):
+    return ntv_hash(val)
+
+def __in__(lhs, rhs):
+    if ntv_is_string(rhs):
+        return ntv_contains_string(rhs, lhs)
+    elif ntv_is_dict(rhs) or ntv_is_set(rhs):
+        return ntv_contains_hashed(rhs, __hash__(lhs), lhs)
+    for val in rhs:
+        if val == lhs:
+            return True
+    return False
+
+def __not_in__(lhs, rhs):
+    return not __in__(lhs, rhs)
+
+def __getitem__(val, index):
+    if ntv_is_string(val):
+        return ntv_getitem_string(val, index)
+    elif ntv_is_tuple(val):
+        return ntv_getitem_tuple(val, index)
+    elif ntv_is_list(val):
+        return ntv_getitem_list(val, index)
+    elif ntv_is_dict(val):
+        return ntv_getitem_dict(val, __hash__(index), index)
+    else:
         ntv_panic()
+
+def __setitem__(val, index, value):
+    if ntv_is_list(val):
+        ntv_setitem_list(val, index, value)
+    elif ntv_is_dict(val):
+        ntv_setitem_dict(val, __hash__(index), index, value)
     else:
         ntv_panic()
 
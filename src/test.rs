@@ -1,6 +1,55 @@
 #[cfg(test)]
+pub mod ast_dump;
+#[cfg(test)]
+pub mod builtins;
+#[cfg(test)]
+pub mod char_stream;
+#[cfg(test)]
+pub mod check;
+#[cfg(test)]
+pub mod debugger;
+#[cfg(test)]
+pub mod dict_set;
+#[cfg(test)]
+pub mod file;
+#[cfg(test)]
+pub mod fmt;
+#[cfg(test)]
+pub mod gc;
+#[cfg(test)]
+pub mod helpers;
+#[cfg(test)]
+pub mod import;
+
+#[cfg(test)]
+pub mod interpreter;
+
+#[cfg(test)]
+pub mod io;
+
+#[cfg(test)]
+pub mod limits;
+#[cfg(test)]
+pub mod lint;
+#[cfg(test)]
+pub mod list_methods;
+#[cfg(test)]
+pub mod native_module;
+#[cfg(test)]
+pub mod operators;
+#[cfg(test)]
 pub mod parser;
 #[cfg(test)]
+pub mod scope;
+#[cfg(test)]
+pub mod str_methods;
+#[cfg(test)]
 pub mod tokenizer;
 #[cfg(test)]
-pub mod char_stream;
+pub mod traceback;
+#[cfg(test)]
+pub mod unparse;
+#[cfg(test)]
+pub mod value;
+#[cfg(test)]
+pub mod vm;
@@ -0,0 +1,112 @@
+use crate::ast_dump::{dump, to_json, to_sexpr};
+use crate::parser::Parser;
+
+fn parse(file_name: &str) -> crate::ast::AST {
+    Parser::new(file_name).unwrap().parse()
+}
+
+#[test]
+fn test_dump_matches_cpython() {
+    // testcase/ast_dump.txt is the output of `ast.dump(ast.parse(source), indent=2)` of CPython 3.11
+    let expected = std::fs::read_to_string("testcase/ast_dump.txt").unwrap();
+    assert_eq!(dump(&parse("testcase/ast_dump.py")), expected.trim_end());
+}
+
+#[test]
+fn test_json() {
+    assert_eq!(
+        to_json(&parse("testcase/test_1.py")),
+        r#"{
+  "type": "Module",
+  "body": [
+    {
+      "type": "Expr",
+      "value": {
+        "type": "Call",
+        "func": {
+          "type": "Name",
+          "id": "print",
+          "ctx": {
+            "type": "Load"
+          },
+          "span": {"line": 1, "column": 0, "end_line": 1, "end_column": 5}
+        },
+        "args": [
+          {
+            "type": "BinOp",
+            "left": {
+              "type": "Constant",
+              "value": 1,
+              "span": {"line": 1, "column": 6, "end_line": 1, "end_column": 7}
+            },
+            "op": {
+              "type": "Add"
+            },
+            "right": {
+              "type": "Constant",
+              "value": 2,
+              "span": {"line": 1, "column": 10, "end_line": 1, "end_column": 11}
+            },
+            "span": {"line": 1, "column": 6, "end_line": 1, "end_column": 11}
+          }
+        ],
+        "keywords": [],
+        "span": {"line": 1, "column": 0, "end_line": 1, "end_column": 12}
+      },
+      "span": {"line": 1, "column": 0, "end_line": 1, "end_column": 12}
+    }
+  ],
+  "type_ignores": []
+}"#
+    );
+}
+
+#[test]
+fn test_sexpr() {
+    assert_eq!(
+        to_sexpr(&parse("testcase/spans.py")),
+        r#"(Module
+  (
+    (FunctionDef
+      "scale"
+      (arguments
+        ()
+        ((arg "value" nil) (arg "factor" nil))
+        ()
+        ()
+        ())
+      (
+        (Return
+          (BinOp
+            (BinOp (Name "value" Load) Add (Constant 1))
+            Mult
+            (Name "factor" Load))))
+      ()
+      nil)
+    (If
+      (Compare
+        (Call
+          (Name "scale" Load)
+          ((Constant 2))
+          ((keyword "factor" (Constant 3))))
+        (Gt)
+        ((Constant 5)))
+      (
+        (Assign
+          ((Name "items" Store))
+          (List
+            (
+              (Constant 1)
+              (Constant "two")
+              (Tuple ((Constant 3) (Constant 4)) Load))
+            Load))
+        (Assign
+          ((Name "first" Store))
+          (Subscript
+            (Name "items" Load)
+            (Slice (Constant 0) (Constant 2) nil)
+            Load)))
+      ()))
+  ())"#
+    );
+}
@@ -0,0 +1,77 @@
+use crate::eval::evaluator::Engine;
+use crate::eval::types::*;
+use crate::test::helpers::*;
+
+engine_tests!(check_builtins: test_builtins, test_builtins_vm);
+
+fn check_builtins(engine: Engine) {
+    let evaluator = run_file("testcase/builtins.py", engine);
+    let get = |name: &str| evaluator.get_global(name).unwrap();
+
+    assert_eq!(get("len_str"), int(5));
+    assert_eq!(get("len_list"), int(3));
+    assert_eq!(get("len_tuple"), int(2));
+    assert_eq!(get("abs_int"), int(3));
+    assert_eq!(get("abs_float"), float(2.5));
+    assert_eq!(get("min_args"), int(1));
+    assert_eq!(get("min_iter"), int(2));
+    assert_eq!(get("min_key"), string("fig"));
+    assert_eq!(get("min_default"), int(7));
+    assert_eq!(get("max_args"), int(3));
+    assert_eq!(get("max_key"), int(1));
+    assert_eq!(get("sum_ints"), int(6));
+    assert_eq!(get("sum_start"), int(16));
+    assert_eq!(get("sum_floats"), float(3.0));
+    assert_eq!(get("sorted_ints"), list(vec![int(1), int(2), int(3)]));
+    assert_eq!(get("sorted_reverse"), list(vec![int(3), int(2), int(1)]));
+    assert_eq!(
+        get("sorted_key"),
+        list(vec![
+            string("fig"),
+            string("pear"),
+            string("kiwi"),
+            string("apple")
+        ])
+    );
+    // equal elements keep their order even when reversed
+    assert_eq!(
+        get("sorted_stable"),
+        list(vec![
+            string("apple"),
+            string("pear"),
+            string("kiwi"),
+            string("fig")
+        ])
+    );
+    assert_eq!(get("reversed_list"), list(vec![int(3), int(2), int(1)]));
+    assert_eq!(
+        get("reversed_str"),
+        list(vec![string("c"), string("b"), string("a")])
+    );
+    assert_eq!(
+        get("enumerated"),
+        list(vec![
+            tuple(vec![int(1), string("a")]),
+            tuple(vec![int(2), string("b")])
+        ])
+    );
+    assert_eq!(
+        get("zipped"),
+        list(vec![
+            tuple(vec![int(1), string("a")]),
+            tuple(vec![int(2), string("b")])
+        ])
+    );
+    assert_eq!(get("mapped"), list(vec![int(-1), int(-2)]));
+    assert_eq!(get("mapped2"), list(vec![int(11), int(22)]));
+    assert_eq!(get("filtered"), list(vec![int(1), int(3), int(5)]));
+    assert_eq!(get("filtered_none"), list(vec![int(1), string("x")]));
+    assert_eq!(get("any_true"), py_val::from_bool(true));
+    assert_eq!(get("any_empty"), py_val::from_bool(false));
+    assert_eq!(get("all_true"), py_val::from_bool(true));
+    assert_eq!(get("all_false"), py_val::from_bool(false));
+    assert_eq!(get("range_1"), list(vec![int(0), int(1), int(2)]));
+    assert_eq!(get("range_2"), list(vec![int(2), int(3), int(4)]));
+    assert_eq!(get("range_3"), list(vec![int(10), int(7), int(4), int(1)]));
+    assert_eq!(get("kw_call"), int(3));
+}
@@ -3,7 +3,7 @@ use crate::char_stream::CharStream;
 use std::fs::{read_to_string,remove_file};
 use stdio_override::StdoutOverride;
 
-const TEST_OUTPUT_FILENAME: &'static str = "tmp.txt";
+const TEST_OUTPUT_FILENAME: &str = "tmp.txt";
 
 // ?????
 
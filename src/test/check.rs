@@ -0,0 +1,101 @@
+use crate::ast::*;
+use crate::check::check;
+use crate::eval::evaluator::Engine;
+use crate::eval::types::*;
+use crate::parser::Parser;
+use crate::test::helpers::*;
+
+fn parse(file_name: &str) -> AST {
+    Parser::new(file_name).unwrap().parse()
+}
+
+#[test]
+fn test_annotations_ast() {
+    let ast = parse("testcase/annotations.py");
+    let name = |id: &str| -> ASTExpr { ASTExprKind::Name(String::from(id)).into() };
+    match &ast[0].node {
+        ASTStmtKind::FuncDef(_, args, _, returns) => {
+            assert_eq!(args[0].node, (String::from("w"), Some(name("int"))));
+            assert_eq!(returns, &Some(name("int")));
+        }
+        _ => unreachable!(),
+    }
+    // 値のない注釈は宣言だけ
+    assert_eq!(
+        ast[6].node,
+        ASTStmtKind::AnnAssign(
+            name("pending"),
+            ASTExprKind::Subscript(
+                Box::new(name("list")),
+                ASTSliceKind::Index(Box::new(name("str"))).into()
+            )
+            .into(),
+            None
+        )
+    );
+}
+
+#[test]
+fn test_annotations_ignored() {
+    for engine in [Engine::Tree, Engine::Vm] {
+        let mut evaluator = evaluator("testcase/annotations.py", engine);
+        run(&mut evaluator).unwrap();
+        assert_eq!(
+            evaluator.get_global("size").unwrap(),
+            py_val::new(py_val::int(12))
+        );
+        assert!(evaluator.get_global("pending").is_none());
+    }
+}
+
+#[test]
+fn test_check() {
+    let diagnostics: Vec<(usize, usize, &str)> = check(&parse("testcase/check.py"))
+        .iter()
+        .map(|d| (d.span.line, d.span.column, d.code))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        vec![
+            (6, 11, "return-value"),
+            (10, 0, "assignment"),
+            (12, 6, "operator"),
+            (13, 14, "arg-type"),
+            (14, 6, "call-arg"),
+            (15, 8, "operator"),
+            (17, 6, "operator"),
+            (18, 8, "arg-type"),
+            (19, 9, "not-iterable"),
+            (22, 2, "index"),
+            (23, 4, "attribute"),
+            (25, 0, "assignment"),
+        ]
+    );
+    let messages: Vec<String> = check(&parse("testcase/check.py"))
+        .into_iter()
+        .map(|d| d.message)
+        .collect();
+    assert_eq!(
+        messages[2],
+        "unsupported operand type(s) for -: 'list' and 'int'"
+    );
+    assert_eq!(
+        messages[3],
+        "argument 'h' to area() has incompatible type str; expected int"
+    );
+}
+
+#[test]
+fn test_check_clean() {
+    // unannotated programs are Any mostly, and working ones have nothing to report
+    for file_name in &[
+        "testcase/annotations.py",
+        "testcase/fizzbuzz.py",
+        "testcase/builtins.py",
+        "testcase/dict_set.py",
+        "testcase/str_methods.py",
+        "testcase/list_methods.py",
+    ] {
+        assert_eq!(check(&parse(file_name)), vec![], "{}", file_name);
+    }
+}
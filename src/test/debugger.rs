@@ -0,0 +1,152 @@
+use std::io::Cursor;
+
+use crate::eval::evaluator::{Engine, Evaluator};
+use crate::eval::io::OutputBuffer;
+use crate::eval::types::*;
+use crate::test::helpers::*;
+
+// what the session with the commands printed, and the evaluator after it
+fn debug(commands: &str) -> (String, Evaluator) {
+    let stdout = OutputBuffer::new();
+    let mut evaluator = evaluator("testcase/debug.py", Engine::Tree);
+    evaluator.set_stdout(stdout.clone());
+    evaluator.set_stdin(Cursor::new(commands.to_string()));
+    evaluator.debug();
+    (stdout.contents(), evaluator)
+}
+
+#[test]
+fn test_debug_next() {
+    // an empty line repeats the last command
+    let (output, evaluator) = debug("n\n\nn\nn\n");
+    assert_eq!(
+        output,
+        "> testcase/debug.py(1)<module>()\n\
+         -> def add(a, b):\n\
+         (Pdb) > testcase/debug.py(6)<module>()\n\
+         -> def main():\n\
+         (Pdb) > testcase/debug.py(13)<module>()\n\
+         -> total = main()\n\
+         (Pdb) 3\n\
+         > testcase/debug.py(14)<module>()\n\
+         -> print(total + 1)\n\
+         (Pdb) 4\n"
+    );
+    assert_eq!(
+        evaluator.get_global("total").unwrap(),
+        py_val::new(py_val::int(3))
+    );
+}
+
+#[test]
+fn test_debug_step_and_return() {
+    let (output, _) = debug("b 8\nc\ns\np a, b\nr\np y\nq\n");
+    assert_eq!(
+        output,
+        "> testcase/debug.py(1)<module>()\n\
+         -> def add(a, b):\n\
+         (Pdb) Breakpoint 1 at testcase/debug.py:8\n\
+         (Pdb) > testcase/debug.py(8)main()\n\
+         -> y = add(x, 2)\n\
+         (Pdb) > testcase/debug.py(2)add()\n\
+         -> c = a + b\n\
+         (Pdb) (1, 2)\n\
+         (Pdb) > testcase/debug.py(9)main()\n\
+         -> print(y)\n\
+         (Pdb) 3\n\
+         (Pdb) "
+    );
+}
+
+#[test]
+fn test_debug_function_breakpoint() {
+    let (output, _) = debug("n\nn\nb add\nb main\nb x\nb total\nc\nw\nb\ncl 1\ncl 1\nc\n");
+    assert_eq!(
+        output,
+        "> testcase/debug.py(1)<module>()\n\
+         -> def add(a, b):\n\
+         (Pdb) > testcase/debug.py(6)<module>()\n\
+         -> def main():\n\
+         (Pdb) > testcase/debug.py(13)<module>()\n\
+         -> total = main()\n\
+         (Pdb) Breakpoint 1 at testcase/debug.py:2\n\
+         (Pdb) Breakpoint 2 at testcase/debug.py:7\n\
+         (Pdb) *** NameError: x is not defined\n\
+         (Pdb) *** NameError: total is not defined\n\
+         (Pdb) > testcase/debug.py(7)main()\n\
+         -> x = 1\n\
+         (Pdb)   testcase/debug.py(13)<module>()\n\
+         -> total = main()\n\
+         > testcase/debug.py(7)main()\n\
+         -> x = 1\n\
+         (Pdb) Num Type         Disp Enb   Where\n\
+         1   breakpoint   keep yes   at testcase/debug.py:2\n\
+         2   breakpoint   keep yes   at testcase/debug.py:7\n\
+         (Pdb) Deleted breakpoint 1 at testcase/debug.py:2\n\
+         (Pdb) *** Breakpoint 1 already deleted\n\
+         (Pdb) 3\n\
+         4\n"
+    );
+}
+
+#[test]
+fn test_debug_print() {
+    // the expression sees the locals of the frame, and its errors don't end the program
+    let (output, _) = debug("b 3\nc\np c * 10\np [a, b]\np d\np 1 +\nb c\nfoo\nc\n");
+    let expected = [
+        "(Pdb) 30",
+        "(Pdb) [1, 2]",
+        "(Pdb) *** NameError: d is not defined",
+        "(Pdb) *** The specified object 'c' is not a function",
+        "(Pdb) *** Unknown command: foo",
+        "(Pdb) 3",
+        "4",
+    ];
+    for line in expected.iter() {
+        assert!(output.lines().any(|l| l == *line), "{}", output);
+    }
+    assert!(output.contains("(Pdb) *** SyntaxError: "), "{}", output);
+}
+
+#[test]
+fn test_debug_list() {
+    let (output, _) = debug("b 9\nc\nl\nq\n");
+    assert!(output.ends_with(
+        "(Pdb)   4  \t\n  \
+         5  \t\n  \
+         6  \tdef main():\n  \
+         7  \t    x = 1\n  \
+         8  \t    y = add(x, 2)\n  \
+         9 B->\t    print(y)\n \
+         10  \t    return y\n \
+         11  \t\n \
+         12  \t\n \
+         13  \ttotal = main()\n \
+         14  \tprint(total + 1)\n\
+         (Pdb) "
+    ));
+}
+
+#[test]
+fn test_debug_eof() {
+    // the program stops at the end of the commands
+    let (output, evaluator) = debug("");
+    assert_eq!(
+        output,
+        "> testcase/debug.py(1)<module>()\n-> def add(a, b):\n(Pdb) \n"
+    );
+    assert!(evaluator.get_global("total").is_none());
+}
+
+#[test]
+fn test_debug_vm() {
+    // the debugger runs the file on the tree-walker
+    let stdout = OutputBuffer::new();
+    let mut evaluator = evaluator("testcase/debug.py", Engine::Vm);
+    evaluator.set_stdout(stdout.clone());
+    evaluator.set_stdin(Cursor::new("b 2\nc\nc\n".to_string()));
+    evaluator.debug();
+    assert!(stdout
+        .contents()
+        .contains("(Pdb) > testcase/debug.py(2)add()\n"));
+}
@@ -0,0 +1,138 @@
+use crate::eval::dict::DictTable;
+use crate::eval::evaluator::Engine;
+use crate::eval::types::*;
+use crate::test::helpers::*;
+
+fn pair(k: py_val_t, v: py_val_t) -> py_val_t {
+    py_val::new(py_val::tuple(vec![k, v]))
+}
+
+fn set(v: Vec<py_val_t>) -> py_val_t {
+    let mut table = DictTable::new();
+    for elt in v {
+        table.insert(elt.py_hash().unwrap(), elt.clone(), elt);
+    }
+    py_val::new(py_val::set(table.into()))
+}
+
+fn keys_of(v: &py_val_t) -> Vec<py_val_t> {
+    v.iter_values().unwrap()
+}
+
+engine_tests!(check_dict_set: test_dict_set, test_dict_set_vm);
+
+fn check_dict_set(engine: Engine) {
+    let evaluator = run_file("testcase/dict_set.py", engine);
+    let get = |name: &str| evaluator.get_global(name).unwrap();
+
+    // insertion order is kept, and overwriting a key doesn't move it
+    assert_eq!(
+        get("keys"),
+        list(vec![string("b"), string("a"), int(3), string("c")])
+    );
+    assert_eq!(
+        get("values"),
+        list(vec![int(10), int(2), string("x"), int(4)])
+    );
+    assert_eq!(
+        get("items"),
+        list(vec![pair(string("x"), int(1)), pair(string("y"), int(2))])
+    );
+    assert_eq!(get("lookup"), string("x"));
+    assert_eq!(get("lookup_float"), string("x"));
+    assert_eq!(get("get_missing"), py_val::new(py_val::None));
+    assert_eq!(get("get_default"), int(0));
+    assert_eq!(get("setdefault_new"), int(5));
+    assert_eq!(get("setdefault_old"), int(2));
+    assert_eq!(get("popped"), int(2));
+    assert_eq!(get("pop_default"), int(-1));
+    assert_eq!(
+        keys_of(&get("alias")),
+        vec![
+            string("b"),
+            int(3),
+            string("c"),
+            string("e"),
+            string("f"),
+            string("g"),
+            string("h")
+        ]
+    );
+    assert_eq!(get("in_dict"), py_val::from_bool(true));
+    assert_eq!(get("not_in_dict"), py_val::from_bool(true));
+    assert_eq!(get("d_len"), int(7));
+    assert_eq!(keys_of(&get("copied")).len(), 8);
+    assert_eq!(keys_of(&get("empty")), vec![]);
+    assert_eq!(get("tuple_key"), string("t"));
+    assert_eq!(
+        keys_of(&get("merged")),
+        vec![string("a"), string("b"), string("c")]
+    );
+    assert_eq!(keys_of(&get("from_pairs")), vec![string("k"), string("v")]);
+    assert_eq!(get("dict_eq"), py_val::from_bool(true));
+
+    assert_eq!(keys_of(&get("s")), vec![int(1), int(5)]);
+    assert_eq!(get("in_set"), py_val::from_bool(true));
+    assert_eq!(get("s_len"), int(2));
+    assert_eq!(get("ordered"), list(vec![int(1), int(5)]));
+    assert_eq!(keys_of(&get("union")), vec![int(1), int(2), int(3), int(4)]);
+    assert_eq!(get("intersection"), set(vec![int(2), int(3)]));
+    assert_eq!(get("difference"), set(vec![int(2), int(3)]));
+    assert_eq!(get("or_set"), set(vec![int(1), int(2), int(3)]));
+    assert_eq!(get("and_set"), set(vec![int(2)]));
+    assert_eq!(get("sub_set"), set(vec![int(1)]));
+    assert_eq!(get("xor_set"), set(vec![int(1), int(3)]));
+    assert_eq!(
+        keys_of(&get("from_str")),
+        vec![string("a"), string("b"), string("c")]
+    );
+    assert_eq!(get("set_eq"), py_val::from_bool(true));
+    assert_eq!(get("hash_int"), int(42));
+    assert_eq!(get("hash_eq"), py_val::from_bool(true));
+    assert_eq!(get("hash_tuple"), py_val::from_bool(true));
+}
+
+engine_tests!(check_dict_user_eq: test_dict_user_eq, test_dict_user_eq_vm);
+
+// keys are compared by the builtin equality even if __eq__ is overridden(and __hash__
+// makes them collide)
+fn check_dict_user_eq(engine: Engine) {
+    let evaluator = run_file("testcase/dict_user_eq.py", engine);
+    let get = |name: &str| evaluator.get_global(name).unwrap();
+
+    assert_eq!(get("d_len"), int(3));
+    assert_eq!(get("second"), string("b"));
+    assert_eq!(get("s_len"), int(2));
+    // while == goes through the override
+    assert_eq!(get("user_eq"), py_val::from_bool(true));
+}
+
+#[test]
+fn test_unhashable() {
+    let l = py_val::new_list(vec![int(1)]);
+    assert_eq!(
+        l.py_hash(),
+        Err("TypeError: unhashable type: 'list'".to_string())
+    );
+    let t = py_val::new(py_val::tuple(vec![int(1), l]));
+    assert_eq!(
+        t.py_hash(),
+        Err("TypeError: unhashable type: 'list'".to_string())
+    );
+    assert!(set(vec![]).py_hash().is_err());
+}
+
+#[test]
+fn test_dict_table() {
+    let mut table = DictTable::new();
+    for i in 0..100 {
+        table.insert(i % 7, int(i), int(i * 2));
+    }
+    for i in 0..90 {
+        assert!(table.remove(i % 7, &int(i)).is_some());
+    }
+    // still in insertion order after the holes are compacted
+    assert_eq!(table.keys(), (90..100).map(int).collect::<Vec<_>>());
+    assert_eq!(table.get(92 % 7, &int(92)), Some(int(184)));
+    assert_eq!(table.get(2, &int(2)), None);
+}
@@ -0,0 +1,140 @@
+use std::fs;
+use std::io::Cursor;
+
+use crate::eval::evaluator::Engine;
+use crate::eval::io::OutputBuffer;
+use crate::eval::types::*;
+use crate::interpreter::Interpreter;
+use crate::test::helpers::*;
+
+engine_tests!(check_file_io: test_file_io, test_file_io_vm);
+
+fn check_file_io(engine: Engine) {
+    let path = std::env::temp_dir().join(format!("minipython_file_io_{:?}.txt", engine));
+    let path = path.to_str().unwrap().to_string();
+    let mut evaluator = evaluator("testcase/file_io.py", engine);
+    evaluator.set_stdin(Cursor::new(path.clone()));
+    run(&mut evaluator).unwrap();
+    let get = |name: &str| evaluator.get_global(name).unwrap();
+
+    assert_eq!(get("written"), py_val::new(py_val::int(11)));
+    assert_eq!(get("first"), string("first line\n"));
+    // read(3) counts characters
+    assert_eq!(get("chunk"), string("ähm"));
+    assert_eq!(get("rest"), strings(&[", second\n", "last"]));
+    assert_eq!(
+        get("lines"),
+        strings(&["first line\n", "ähm, second\n", "last"])
+    );
+    assert_eq!(get("top"), string("first line\n"));
+    assert_eq!(get("count"), py_val::new(py_val::int(1)));
+    assert_eq!(get("same"), py_val::new(py_val::True));
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "first line\nähm, second\nlast\nappended"
+    );
+    fs::remove_file(&path).unwrap();
+
+    // the files are closed when the with blocks are left by return, break and continue too
+    let mut interp = Interpreter::with_engine(engine).unwrap();
+    for name in ["f", "returned", "broken", "continued", "a", "whole"] {
+        interp.set_global(name, get(name));
+        let error = interp.run(&format!("{}.read()", name)).unwrap_err();
+        assert_eq!(
+            error.message, "ValueError: I/O operation on closed file.",
+            "{}",
+            name
+        );
+    }
+}
+
+engine_tests!(check_with_error: test_with_error, test_with_error_vm);
+
+// an error in the block closes the file, and what was written is in it
+fn check_with_error(engine: Engine) {
+    let path = std::env::temp_dir().join(format!("minipython_with_error_{:?}.txt", engine));
+    let path = path.to_str().unwrap().to_string();
+    let mut interp = Interpreter::with_engine(engine).unwrap();
+    interp.evaluator().set_filesystem_access(true);
+    interp.set_global("path", path.clone());
+    let error = interp
+        .run("def fail():\n    return 1 / 0\nwith open(path, \"w\") as f:\n    f.write(\"kept\")\n    fail()\n")
+        .unwrap_err();
+    assert_eq!(error.message, "ZeroDivisionError: division by zero");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "kept");
+    let error = interp.run("f.write(\"more\")").unwrap_err();
+    assert_eq!(error.message, "ValueError: I/O operation on closed file.");
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_file_errors() {
+    for engine in [Engine::Tree, Engine::Vm] {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("minipython_file_errors_{:?}.txt", engine));
+        let path = path.to_str().unwrap().to_string();
+        let mut interp = Interpreter::with_engine(engine).unwrap();
+        interp.evaluator().set_stdout(OutputBuffer::new());
+        interp.set_global("path", path.clone());
+        // embedded interpreters have no filesystem access unless it is given
+        let error = interp.run("f = open(path, \"w\")").unwrap_err();
+        assert_eq!(
+            error.message,
+            "PermissionError: filesystem access is disabled"
+        );
+        interp.evaluator().set_filesystem_access(true);
+
+        let missing = dir.join("minipython_no_such_file.txt");
+        interp.set_global("missing", missing.to_str().unwrap());
+        let error = interp.run("open(missing)").unwrap_err();
+        assert_eq!(
+            error.message,
+            format!(
+                "FileNotFoundError: [Errno 2] No such file or directory: '{}'",
+                missing.display()
+            )
+        );
+        interp.set_global("dir", dir.to_str().unwrap());
+        let error = interp.run("open(dir)").unwrap_err();
+        assert_eq!(
+            error.message,
+            format!(
+                "IsADirectoryError: [Errno 21] Is a directory: '{}'",
+                dir.display()
+            )
+        );
+        let error = interp.run("open(path, \"rb\")").unwrap_err();
+        assert_eq!(error.message, "ValueError: binary mode is not supported");
+        let error = interp.run("open(path, \"r+\")").unwrap_err();
+        assert_eq!(error.message, "ValueError: invalid mode: 'r+'");
+
+        interp.run("f = open(path, \"w\")").unwrap();
+        assert_eq!(
+            interp.eval_expr("f").unwrap().repr(),
+            format!(
+                "<_io.TextIOWrapper name='{}' mode='w' encoding='utf-8'>",
+                path
+            )
+        );
+        let error = interp.run("f.read()").unwrap_err();
+        assert_eq!(error.message, "io.UnsupportedOperation: not readable");
+        let error = interp.run("f.write(1)").unwrap_err();
+        assert_eq!(
+            error.message,
+            "TypeError: write() argument must be str, not int"
+        );
+        interp.run("f.close()\nf = open(path, \"x\")").unwrap_err();
+        interp.run("f = open(path)").unwrap();
+        let error = interp.run("f.write(\"x\")").unwrap_err();
+        assert_eq!(error.message, "io.UnsupportedOperation: not writable");
+        let error = interp.run("with 1 as x:\n    pass").unwrap_err();
+        assert_eq!(
+            error.message,
+            "TypeError: 'int' object does not support the context manager protocol"
+        );
+        interp.run("f.close()").unwrap();
+        let error = interp.run("with f:\n    pass").unwrap_err();
+        assert_eq!(error.message, "ValueError: I/O operation on closed file.");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,123 @@
+use std::fs;
+
+use crate::ast::Span;
+use crate::fmt::{format_file, unified_diff};
+use crate::interpreter::Interpreter;
+use crate::token::{Comment, Token};
+use crate::tokenizer::Tokenizer;
+
+#[test]
+fn test_format() {
+    let formatted = format_file("testcase/fmt_input.py").unwrap().unwrap();
+    assert_eq!(
+        formatted,
+        fs::read_to_string("testcase/fmt_expected.py").unwrap()
+    );
+    // formatting the formatted file changes nothing
+    assert_eq!(
+        format_file("testcase/fmt_expected.py").unwrap().unwrap(),
+        formatted
+    );
+}
+
+#[test]
+fn test_format_keeps_meaning() {
+    // left-associative operators and chained comparisons
+    let formatted = format_file("testcase/fmt_operators.py").unwrap().unwrap();
+    assert_eq!(
+        formatted,
+        "a = 10 - 3 - 2\n\
+         b = 10 - (3 - 2)\n\
+         c = 3 > 2 == True\n\
+         d = 3 > (2 == True)\n\
+         e = 100 / 10 / 2\n\
+         f = 2 * (6 / 3)\n\
+         g = 1 << 3 >> 1\n"
+    );
+    let values = |source: &str| {
+        let mut interp = Interpreter::new().unwrap();
+        interp.run(source).unwrap();
+        interp.eval_expr("[a, b, c, d, e, f, g]").unwrap()
+    };
+    let original = fs::read_to_string("testcase/fmt_operators.py").unwrap();
+    assert_eq!(values(&formatted), values(&original));
+}
+
+#[test]
+fn test_format_syntax_error() {
+    let errors = format_file("testcase/syntax_errors.py")
+        .unwrap()
+        .unwrap_err();
+    assert!(!errors.is_empty());
+}
+
+#[test]
+fn test_comments() {
+    let mut tokenizer = Tokenizer::from_source(
+        "comments.py",
+        String::from("# own line\nx = 1  # trailing   \n"),
+    );
+    loop {
+        tokenizer.next_token();
+        if tokenizer.get_current_token() == &Token::EOF {
+            break;
+        }
+    }
+    assert_eq!(
+        tokenizer.take_comments(),
+        vec![
+            Comment {
+                text: String::from("# own line"),
+                span: Span {
+                    line: 1,
+                    column: 0,
+                    end_line: 1,
+                    end_column: 10
+                },
+                own_line: true,
+            },
+            Comment {
+                text: String::from("# trailing"),
+                span: Span {
+                    line: 2,
+                    column: 7,
+                    end_line: 2,
+                    end_column: 17
+                },
+                own_line: false,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_unified_diff() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\n";
+    let new = "a\nB\nc\nd\ne\nf\ng\nh\n";
+    assert_eq!(
+        unified_diff("x.py", old, new),
+        "--- x.py
++++ x.py
+@@ -1,9 +1,8 @@
+ a
+-b
++B
+ c
+ d
+ e
+ f
+ g
+ h
+-i
+"
+    );
+    assert_eq!(
+        unified_diff(
+            "x.py",
+            "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n",
+            "1\n2\n3\n4\n5\n6\n7\n8\n9\n"
+        ),
+        "--- x.py\n+++ x.py\n@@ -7,4 +7,3 @@\n 7\n 8\n 9\n-10\n"
+    );
+    assert_eq!(unified_diff("x.py", old, old), "");
+}
@@ -0,0 +1,63 @@
+use crate::eval::evaluator::Engine;
+use crate::eval::gc;
+use crate::eval::types::*;
+use crate::interpreter::Interpreter;
+use crate::test::helpers::*;
+
+engine_tests!(check_gc: test_gc, test_gc_vm);
+
+fn check_gc(engine: Engine) {
+    let evaluator = run_file("testcase/gc.py", engine);
+    let get = |name: &str| evaluator.get_global(name).unwrap();
+    // a list, a dict, a tuple and its list, a list and its bound method in each
+    assert_eq!(get("collected"), int(60));
+    // kept is referred to from a global
+    assert_eq!(get("after"), int(0));
+    assert_eq!(get("enabled"), py_val::new(py_val::False));
+    // the cycle through the global is kept as it was
+    let kept = get("kept");
+    let dict = match kept.heap() {
+        Some(py_val::list(l)) => l.borrow()[2].clone(),
+        _ => panic!(),
+    };
+    match dict.heap() {
+        Some(py_val::dict(d)) => assert!(Value::ptr_eq(&d.borrow().values()[0], &kept)),
+        _ => panic!(),
+    }
+    assert!(gc::is_enabled());
+}
+
+#[test]
+fn test_gc_frees_cycles() {
+    let before = gc::tracked_objects();
+    {
+        let a = py_val::new_list(vec![int(1)]);
+        let b = py_val::new_list(vec![a.clone()]);
+        if let Some(py_val::list(l)) = a.heap() {
+            l.borrow_mut().push(b.clone());
+        }
+        assert_eq!(gc::tracked_objects(), before + 2);
+        // referred to from here
+        assert_eq!(gc::collect(), 0);
+    }
+    assert_eq!(gc::tracked_objects(), before + 2);
+    assert_eq!(gc::collect(), 2);
+    assert_eq!(gc::tracked_objects(), before);
+}
+
+#[test]
+fn test_gc_threshold() {
+    for engine in [Engine::Tree, Engine::Vm] {
+        let mut interp = Interpreter::with_engine(engine).unwrap();
+        gc::set_threshold(100);
+        let before = gc::tracked_objects();
+        interp
+            .run("for i in range(20000):\n    a = [i]\n    a.append(a)")
+            .unwrap();
+        // collected every few thousand steps instead of keeping 20000 lists
+        assert!(gc::tracked_objects() < before + 2000);
+        interp.run("a = None").unwrap();
+        gc::collect();
+        assert_eq!(gc::tracked_objects(), before);
+    }
+}
@@ -0,0 +1,85 @@
+//! values and engine runs shared by the test modules
+
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+
+use ordered_float::OrderedFloat;
+
+use crate::eval::evaluator::{Engine, Evaluator};
+use crate::eval::types::*;
+
+pub fn int(i: i64) -> py_val_t {
+    py_val::new(py_val::int(i))
+}
+
+pub fn float(f: f64) -> py_val_t {
+    py_val::new(py_val::float(OrderedFloat(f)))
+}
+
+pub fn string(s: &str) -> py_val_t {
+    py_val::new(py_val::string(s.to_string()))
+}
+
+pub fn list(v: Vec<py_val_t>) -> py_val_t {
+    py_val::new_list(v)
+}
+
+pub fn tuple(v: Vec<py_val_t>) -> py_val_t {
+    py_val::new(py_val::tuple(v))
+}
+
+pub fn ints(v: &[i64]) -> py_val_t {
+    list(v.iter().map(|&i| int(i)).collect())
+}
+
+pub fn strings(v: &[&str]) -> py_val_t {
+    list(v.iter().map(|s| string(s)).collect())
+}
+
+// errors of the script come back as PyError(see run) instead of ending the tests
+pub fn evaluator(file_name: &str, engine: Engine) -> Evaluator {
+    let mut evaluator = Evaluator::with_engine(file_name, engine).unwrap();
+    evaluator.embedded = true;
+    evaluator
+}
+
+// the script of evaluator run to the end, or the error it raised
+pub fn run(evaluator: &mut Evaluator) -> Result<(), PyError> {
+    match catch_unwind(AssertUnwindSafe(|| evaluator.eval())) {
+        Ok(()) => Ok(()),
+        Err(payload) => match payload.downcast::<PyError>() {
+            Ok(error) => Err(*error),
+            Err(payload) => resume_unwind(payload),
+        },
+    }
+}
+
+// the script run to the end with the engine, to read its globals. an error fails the test
+// with its traceback
+pub fn run_file(file_name: &str, engine: Engine) -> Evaluator {
+    let mut evaluator = evaluator(file_name, engine);
+    if let Err(error) = run(&mut evaluator) {
+        panic!("{}", error.traceback);
+    }
+    evaluator
+}
+
+// the error raised by the script
+pub fn run_error(file_name: &str, engine: Engine) -> PyError {
+    run(&mut evaluator(file_name, engine)).expect_err("no error")
+}
+
+// a test for each engine, which calls check with it
+macro_rules! engine_tests {
+    ($check:ident: $tree:ident, $vm:ident) => {
+        #[test]
+        fn $tree() {
+            $check(crate::eval::evaluator::Engine::Tree);
+        }
+
+        #[test]
+        fn $vm() {
+            $check(crate::eval::evaluator::Engine::Vm);
+        }
+    };
+}
+pub(crate) use engine_tests;
@@ -0,0 +1,132 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use crate::eval::evaluator::Engine;
+use crate::eval::import::module_search_path;
+use crate::eval::types::*;
+use crate::test::helpers::*;
+
+engine_tests!(check_import: test_import, test_import_vm);
+
+fn check_import(engine: Engine) {
+    let mut evaluator = evaluator("testcase/imports/main.py", engine);
+    // as MINIPYTHON_PATH=testcase/imports/lib
+    evaluator
+        .search_path
+        .push(PathBuf::from("testcase/imports/lib"));
+    run(&mut evaluator).unwrap();
+    let get = |name: &str| evaluator.get_global(name).unwrap();
+
+    // the functions of shapes update its globals, not the ones of __main__
+    assert_eq!(get("count"), int(100));
+    assert_eq!(get("total"), int(2));
+    assert_eq!(get("names"), list(vec![string("circle"), string("square")]));
+    let t = py_val::new(py_val::True);
+    assert_eq!(
+        get("cached"),
+        list(vec![t.clone(), t.clone(), t.clone(), int(2)])
+    );
+    assert_eq!(get("moved"), tuple(vec![int(3), int(6)]));
+    assert_eq!(get("at_origin"), t);
+    assert_eq!(
+        get("module_names"),
+        list(vec![
+            string("__main__"),
+            string("shapes"),
+            string("geometry.vector"),
+            string("geometry"),
+        ])
+    );
+    // the circular import gets the partially initialized module
+    assert_eq!(get("from_b"), string("b"));
+    assert_eq!(get("a_name_in_b"), string("cycle_a"));
+    assert_eq!(get("extra_value"), int(42));
+    assert_eq!(
+        get("local_imports"),
+        tuple(vec![
+            tuple(vec![int(2), int(2)]),
+            list(vec![string("circle"), string("square")]),
+        ])
+    );
+    assert_eq!(get("ran_as_main"), t);
+    // names imported in a function are its locals
+    assert_eq!(evaluator.get_global("v"), None);
+    assert_eq!(
+        get("shapes").repr(),
+        "<module 'shapes' from 'testcase/imports/shapes.py'>"
+    );
+}
+
+// the message of the error raised by the file
+fn error(file_name: &str, engine: Engine) -> String {
+    run_error(file_name, engine).message
+}
+
+#[test]
+fn test_import_errors() {
+    for &engine in [Engine::Tree, Engine::Vm].iter() {
+        assert_eq!(
+            error("testcase/imports/circular.py", engine),
+            "ImportError: cannot import name 'x' from partially initialized module 'cycle_c' \
+             (most likely due to a circular import) (testcase/imports/cycle_c.py)"
+        );
+        assert_eq!(
+            error("testcase/imports/missing.py", engine),
+            "ModuleNotFoundError: No module named 'geometry.nowhere'"
+        );
+        assert_eq!(
+            error("testcase/imports/not_package.py", engine),
+            "ModuleNotFoundError: No module named 'shapes.square'; 'shapes' is not a package"
+        );
+    }
+    // the VM points at the same part of the statement as the tree-walker
+    for file_name in [
+        "testcase/imports/circular.py",
+        "testcase/imports/missing.py",
+        "testcase/imports/not_package.py",
+        "testcase/native_module.py",
+    ] {
+        assert_eq!(
+            run_error(file_name, Engine::Vm).traceback,
+            run_error(file_name, Engine::Tree).traceback,
+            "{}",
+            file_name
+        );
+    }
+}
+
+#[test]
+fn test_import_traceback() {
+    // the frames of the importing modules come first
+    let mut evaluator = evaluator("testcase/imports/circular.py", Engine::Tree);
+    let traceback = run(&mut evaluator).unwrap_err().traceback;
+    assert!(traceback.starts_with(
+        r#"Traceback (most recent call last):
+  File "testcase/imports/circular.py", line 1, in <module>
+    from cycle_c import x
+    ^^^^^^^^^^^^^^^^^^^^^
+  File "testcase/imports/cycle_c.py", line 1, in <module>
+    from cycle_d import y
+    ^^^^^^^^^^^^^^^^^^^^^
+  File "testcase/imports/cycle_d.py", line 1, in <module>
+    from cycle_c import x
+                        ^
+"#
+    ));
+}
+
+#[test]
+fn test_search_path() {
+    assert_eq!(
+        module_search_path("testcase/imports/main.py", None),
+        vec![PathBuf::from("testcase/imports")]
+    );
+    assert_eq!(
+        module_search_path("main.py", Some(OsString::from("lib::/usr/lib/py"))),
+        vec![
+            PathBuf::from("."),
+            PathBuf::from("lib"),
+            PathBuf::from("/usr/lib/py"),
+        ]
+    );
+}
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use crate::eval::evaluator::Engine;
+use crate::eval::types::*;
+use crate::interpreter::Interpreter;
+
+fn interpreters() -> Vec<Interpreter> {
+    vec![
+        Interpreter::with_engine(Engine::Tree).unwrap(),
+        Interpreter::with_engine(Engine::Vm).unwrap(),
+    ]
+}
+
+#[test]
+fn test_interpreter_run() {
+    for mut interp in interpreters() {
+        // the value of the last expression statement
+        let value = interp.run("x = 20\nx * 2 + 2").unwrap();
+        assert_eq!(value.extract::<i64>(), Ok(42));
+        // None if the last statement is not an expression
+        let value = interp.run("y = x + 1").unwrap();
+        assert_eq!(value.extract::<Option<i64>>(), Ok(None));
+        // globals persist across runs
+        assert_eq!(interp.get_global::<i64>("y"), Ok(21));
+        let value = interp.eval_expr("[x, y, \"z\"]").unwrap();
+        assert_eq!(value.repr(), "[20, 21, 'z']");
+        assert_eq!(
+            interp.eval_expr("x = 1").unwrap_err().message,
+            "SyntaxError: expected a single expression"
+        );
+    }
+}
+
+#[test]
+fn test_interpreter_conversions() {
+    for mut interp in interpreters() {
+        interp.set_global("n", 3i64);
+        interp.set_global("ratio", 0.5f64);
+        interp.set_global("name", "py");
+        interp.set_global("flags", vec![true, false]);
+        let mut scores = HashMap::new();
+        scores.insert("a".to_string(), 1i64);
+        scores.insert("b".to_string(), 2i64);
+        interp.set_global("scores", scores.clone());
+        interp.set_global("pair", (1i64, "one".to_string()));
+        interp.set_global("nothing", None::<i64>);
+
+        let value = interp
+            .run("[n * ratio, name * n, flags, scores[\"a\"] + scores[\"b\"], pair[1], nothing]")
+            .unwrap();
+        assert_eq!(
+            value.repr(),
+            "[1.5, 'pypypy', [True, False], 3, 'one', None]"
+        );
+
+        interp
+            .run("matrix = [[1, 2], (3, 4)]\nrow = (1, 2.5, \"x\", None)\nd = {\"k\": [1]}")
+            .unwrap();
+        assert_eq!(
+            interp.get_global::<Vec<Vec<i64>>>("matrix"),
+            Ok(vec![vec![1, 2], vec![3, 4]])
+        );
+        assert_eq!(
+            interp.get_global::<(i64, f64, String, Option<bool>)>("row"),
+            Ok((1, 2.5, "x".to_string(), None))
+        );
+        assert_eq!(interp.get_global("scores"), Ok(scores));
+        let d: HashMap<String, Vec<i64>> = interp.get_global("d").unwrap();
+        assert_eq!(d["k"], vec![1]);
+        // int is accepted as float
+        assert_eq!(interp.get_global::<f64>("n"), Ok(3.0));
+
+        assert_eq!(
+            interp.get_global::<i64>("name").unwrap_err().message,
+            "TypeError: expected int, got str"
+        );
+        assert_eq!(
+            interp.get_global::<Vec<i64>>("row").unwrap_err().message,
+            "TypeError: expected int, got float"
+        );
+        assert_eq!(
+            interp.get_global::<(i64, i64)>("row").unwrap_err().message,
+            "TypeError: expected tuple of length 2, got tuple"
+        );
+        assert_eq!(
+            interp.get_global::<i64>("undefined").unwrap_err().message,
+            "NameError: undefined is not defined"
+        );
+    }
+}
+
+#[test]
+fn test_interpreter_call() {
+    for mut interp in interpreters() {
+        interp
+            .run("def add(a, b):\n    return a + b\ndef greet():\n    return \"hi\"\n")
+            .unwrap();
+        assert_eq!(interp.call::<_, i64>("add", (2i64, 3i64)), Ok(5));
+        assert_eq!(
+            interp.call::<_, String>("add", ("a", "b")),
+            Ok("ab".to_string())
+        );
+        assert_eq!(
+            interp.call::<_, Vec<i64>>("add", (vec![1i64], vec![2i64])),
+            Ok(vec![1, 2])
+        );
+        assert_eq!(interp.call::<_, String>("greet", ()), Ok("hi".to_string()));
+        // builtins too
+        assert_eq!(interp.call::<_, i64>("len", ("abc",)), Ok(3));
+        assert_eq!(
+            interp.call::<_, i64>("nope", ()).unwrap_err().message,
+            "NameError: nope is not defined"
+        );
+    }
+}
+
+#[test]
+fn test_interpreter_errors() {
+    for mut interp in interpreters() {
+        interp
+            .run("def inner(x):\n    return x[3]\ndef outer():\n    return inner([1])\n")
+            .unwrap();
+        let error = interp.run("outer()").unwrap_err();
+        assert_eq!(error.message, "IndexError: list index out of range");
+        assert!(error
+            .traceback
+            .starts_with("Traceback (most recent call last):\n"));
+        assert!(error.traceback.contains("in outer\n"));
+        assert!(error.traceback.contains("in inner\n"));
+        assert!(error
+            .traceback
+            .ends_with("IndexError: list index out of range\n"));
+        assert_eq!(error.to_string(), error.traceback.trim_end());
+
+        // the same error from a call
+        let error = interp.call::<_, Value>("outer", ()).unwrap_err();
+        assert_eq!(error.message, "IndexError: list index out of range");
+
+        // the statements before the error have run
+        let error = interp.run("a = 1\nb = undefined_name\nc = 3").unwrap_err();
+        assert_eq!(error.message, "NameError: undefined_name is not defined");
+        assert_eq!(interp.get_global::<i64>("a"), Ok(1));
+        assert!(interp.get_global::<i64>("c").is_err());
+
+        // the interpreter still works, with a fresh traceback
+        let error = interp.run("1 // 0").unwrap_err();
+        assert!(!error.traceback.contains("in outer"));
+        assert_eq!(interp.run("a + 1").unwrap().extract::<i64>(), Ok(2));
+
+        let error = interp.run("x = (1 +").unwrap_err();
+        assert!(error.message.starts_with("SyntaxError: "));
+        assert!(error.traceback.contains("File \"<string>\", line 1"));
+    }
+}
+
+#[test]
+fn test_interpreter_no_host_panics() {
+    for mut interp in interpreters() {
+        // what CPython rejects after parsing is a SyntaxError at the statement
+        let mut error = |source: &str| interp.run(source).unwrap_err();
+        let cases = [
+            ("break", "'break' outside loop", 1),
+            (
+                "if True:\n    continue\n",
+                "'continue' not properly in loop",
+                2,
+            ),
+            ("x = 1\nreturn x\n", "'return' outside function", 2),
+            ("def f():\n    break\n", "'break' outside loop", 2),
+            (
+                "while True:\n    def g():\n        break\n    break\n",
+                "'break' outside loop",
+                3,
+            ),
+            ("x = [1, 2]\ndel x\n", "'del' is not supported", 2),
+            ("x = [1, 2]\ny = x[0:1]\n", "slices are not supported", 2),
+            ("x = [1, 2]\nx[0:1]", "slices are not supported", 2),
+        ];
+        for (source, message, line) in cases.iter() {
+            let error = error(source);
+            assert_eq!(error.message, format!("SyntaxError: {}", message));
+            assert!(
+                error.traceback.contains(&format!("line {}", line)),
+                "{}",
+                error.traceback
+            );
+        }
+        assert_eq!(
+            error("x = \"unterminated").message,
+            "SyntaxError: EOL while scanning string literal"
+        );
+        assert_eq!(
+            error("1 / 0").message,
+            "ZeroDivisionError: division by zero"
+        );
+
+        // conditional expressions
+        assert_eq!(
+            interp
+                .eval_expr("1 if True else 2")
+                .unwrap()
+                .extract::<i64>(),
+            Ok(1)
+        );
+        interp
+            .run("def pick(c):\n    return \"a\" if c else \"b\"\n")
+            .unwrap();
+        assert_eq!(
+            interp.eval_expr("pick(0)").unwrap().extract::<String>(),
+            Ok("b".to_string())
+        );
+    }
+}
+
+#[test]
+fn test_nesting_limit() {
+    let nested = |open: &str, close: &str, n: usize| {
+        format!("x = {}1{}\nx", open.repeat(n), close.repeat(n))
+    };
+    for mut interp in interpreters() {
+        // a deep source is a SyntaxError, not an overflow of the native stack
+        for source in [
+            nested("(", ")", 3000),
+            nested("[", "]", 3000),
+            nested("-", "", 30000),
+            nested("not ", "", 30000),
+        ] {
+            assert_eq!(
+                interp.run(&source).unwrap_err().message,
+                "SyntaxError: too many nesting levels"
+            );
+        }
+        // up to the limit, it runs
+        assert_eq!(
+            interp.run(&nested("(", ")", 199)).unwrap().extract::<i64>(),
+            Ok(1)
+        );
+        assert_eq!(
+            interp.run(&nested("- -", "", 99)).unwrap().extract::<i64>(),
+            Ok(1)
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use std::io::Cursor;
+
+use crate::eval::evaluator::Engine;
+use crate::eval::io::OutputBuffer;
+use crate::eval::types::*;
+use crate::interpreter::Interpreter;
+use crate::test::helpers::*;
+
+engine_tests!(check_capture_stdout: test_capture_stdout, test_capture_stdout_vm);
+
+fn check_capture_stdout(engine: Engine) {
+    let stdout = OutputBuffer::new();
+    let mut evaluator = evaluator("testcase/fizzbuzz.py", engine);
+    evaluator.set_stdout(stdout.clone());
+    run(&mut evaluator).unwrap();
+    let expected: Vec<String> = (0..100)
+        .map(|i| match i {
+            i if i % 15 == 0 => "fizzbuzz".to_string(),
+            i if i % 5 == 0 => "fizz".to_string(),
+            i if i % 3 == 0 => "buzz".to_string(),
+            i => i.to_string(),
+        })
+        .collect();
+    assert_eq!(stdout.contents(), expected.join("\n") + "\n");
+}
+
+#[test]
+fn test_interpreter_output() {
+    for engine in [Engine::Tree, Engine::Vm] {
+        let stdout = OutputBuffer::new();
+        let mut interp = Interpreter::with_engine(engine).unwrap();
+        interp.evaluator().set_stdout(stdout.clone());
+        interp.run("print(\"start\")\nprint(1.5)").unwrap();
+        assert_eq!(stdout.take(), "start\n1.5\n");
+        // the output before an error is kept
+        interp.run("print(42)\nprint(undefined)").unwrap_err();
+        assert_eq!(stdout.take(), "42\n");
+        assert_eq!(stdout.contents(), "");
+    }
+}
+
+engine_tests!(check_input: test_input, test_input_vm);
+
+fn check_input(engine: Engine) {
+    let stdout = OutputBuffer::new();
+    let mut evaluator = evaluator("testcase/input.py", engine);
+    evaluator.set_stdout(stdout.clone());
+    evaluator.set_stdin(Cursor::new(
+        "mini\nfoo bar  baz\r\nfirst line\nähm, rest\nlast",
+    ));
+    run(&mut evaluator).unwrap();
+    let get = |name: &str| evaluator.get_global(name).unwrap();
+
+    assert_eq!(get("name"), string("mini"));
+    assert_eq!(
+        get("words"),
+        py_val::new_list(vec![string("foo"), string("bar"), string("baz")])
+    );
+    assert_eq!(get("first"), string("first line\n"));
+    // read(3) counts characters
+    assert_eq!(get("chunk"), string("ähm"));
+    assert_eq!(
+        get("rest"),
+        py_val::new_list(vec![string(", rest\n"), string("last")])
+    );
+    assert_eq!(get("after"), string(""));
+    assert_eq!(get("remaining"), string(""));
+    assert_eq!(stdout.contents(), "name? hello mini\n");
+}
+
+#[test]
+fn test_input_errors() {
+    for engine in [Engine::Tree, Engine::Vm] {
+        let mut interp = Interpreter::with_engine(engine).unwrap();
+        interp.evaluator().set_stdout(OutputBuffer::new());
+        interp.evaluator().set_stdin(Cursor::new("only line\n"));
+        interp.run("import sys").unwrap();
+        assert_eq!(
+            interp.eval_expr("sys.stdin").unwrap().repr(),
+            "<_io.TextIOWrapper name='<stdin>' mode='r' encoding='utf-8'>"
+        );
+        assert_eq!(
+            interp.eval_expr("input()").unwrap().extract::<String>(),
+            Ok("only line".to_string())
+        );
+        let error = interp.run("line = input(\"more? \")").unwrap_err();
+        assert_eq!(error.message, "EOFError: EOF when reading a line");
+        assert_eq!(
+            interp.eval_expr("sys.stdin.readlines()").unwrap().repr(),
+            "[]"
+        );
+        let error = interp.run("sys.stdin.write(\"x\")").unwrap_err();
+        assert_eq!(error.message, "io.UnsupportedOperation: not writable");
+        let error = interp.run("sys.stdin.read(\"x\")").unwrap_err();
+        assert_eq!(error.message, "TypeError: read() expected int, got 'str'");
+    }
+}
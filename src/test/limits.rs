@@ -0,0 +1,157 @@
+use std::thread;
+use std::time::Duration;
+
+use crate::eval::evaluator::Engine;
+use crate::eval::limits::Limits;
+use crate::interpreter::Interpreter;
+
+fn interpreters(limits: Limits) -> Vec<Interpreter> {
+    vec![
+        Interpreter::with_engine(Engine::Tree)
+            .unwrap()
+            .with_limits(limits),
+        Interpreter::with_engine(Engine::Vm)
+            .unwrap()
+            .with_limits(limits),
+    ]
+}
+
+#[test]
+fn test_recursion_limit() {
+    for mut interp in interpreters(Limits::new().max_call_depth(50)) {
+        interp
+            .run("def down(n):\n    if n == 0:\n        return 0\n    return down(n - 1) + 1\n")
+            .unwrap();
+        // the frame of <module> counts(and with no-fast-paths, the one of __eq__)
+        assert_eq!(interp.call::<_, i64>("down", (40i64,)), Ok(40));
+        let error = interp.run("down(49)").unwrap_err();
+        assert_eq!(
+            error.message,
+            "RecursionError: maximum recursion depth exceeded"
+        );
+        // the recursive frames are folded
+        assert!(error.traceback.contains(
+            "  File \"<string>\", line 4, in down\n  [Previous line repeated 4"
+        ));
+        assert_eq!(interp.run("down(10)").unwrap().extract::<i64>(), Ok(10));
+    }
+}
+
+#[test]
+fn test_default_recursion_limit() {
+    // far deeper than the stack of a test thread would allow without growing it
+    for mut interp in interpreters(Limits::new()) {
+        interp
+            .run("def down(n):\n    if n == 0:\n        return 0\n    return down(n - 1) + 1\n")
+            .unwrap();
+        assert_eq!(interp.call::<_, i64>("down", (990i64,)), Ok(990));
+        let error = interp.run("down(100000)").unwrap_err();
+        assert_eq!(
+            error.message,
+            "RecursionError: maximum recursion depth exceeded"
+        );
+        assert!(error.traceback.contains("  [Previous line repeated 99"));
+    }
+}
+
+#[test]
+fn test_step_limit() {
+    for mut interp in interpreters(Limits::new().max_steps(5000)) {
+        let error = interp.run("while True:\n    pass").unwrap_err();
+        assert_eq!(
+            error.message,
+            "RuntimeError: maximum number of steps exceeded"
+        );
+        // each run has its own budget
+        for _ in 0..3 {
+            let value = interp
+                .run("i = 0\nwhile i < 100:\n    i = i + 1\ni")
+                .unwrap();
+            assert_eq!(value.extract::<i64>(), Ok(100));
+        }
+    }
+}
+
+#[test]
+fn test_timeout() {
+    for mut interp in interpreters(Limits::new().timeout(Duration::from_millis(50))) {
+        let error = interp.run("x = 0\nwhile True:\n    x = x + 1").unwrap_err();
+        assert_eq!(error.message, "TimeoutError: execution timed out");
+        assert!(interp.get_global::<i64>("x").unwrap() > 0);
+        assert_eq!(interp.run("x = 1\nx").unwrap().extract::<i64>(), Ok(1));
+    }
+}
+
+#[test]
+fn test_cancel() {
+    for mut interp in interpreters(Limits::new()) {
+        let handle = interp.cancel_handle();
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            handle.cancel();
+        });
+        let error = interp.run("while True:\n    pass").unwrap_err();
+        canceller.join().unwrap();
+        assert_eq!(error.message, "KeyboardInterrupt");
+        assert!(error.traceback.ends_with("\nKeyboardInterrupt\n"));
+        // the cancellation was for that run only
+        assert_eq!(interp.run("1 + 1").unwrap().extract::<i64>(), Ok(2));
+    }
+}
+
+#[test]
+fn test_memory_limit() {
+    for mut interp in interpreters(Limits::new().max_memory(1_000_000)) {
+        let error = interp
+            .run("a = []\nwhile True:\n    a.append(1)")
+            .unwrap_err();
+        assert_eq!(error.message, "MemoryError");
+        interp.run("a = None").unwrap();
+
+        let error = interp
+            .run("s = \"memory\"\nwhile True:\n    s = s + s")
+            .unwrap_err();
+        assert_eq!(error.message, "MemoryError");
+        assert!(interp.get_global::<String>("s").unwrap().len() > 100_000);
+        interp.run("s = None").unwrap();
+
+        // what is freed doesn't count
+        let value = interp
+            .run("i = 0\nwhile i < 20000:\n    l = [i, (i, i), {i: i}]\n    i = i + 1\nlen(l)")
+            .unwrap();
+        assert_eq!(value.extract::<i64>(), Ok(3));
+        let value = interp
+            .run("q = []\ni = 0\nwhile i < 50000:\n    q.append([i])\n    q.pop()\n    i = i + 1\nlen(q)")
+            .unwrap();
+        assert_eq!(value.extract::<i64>(), Ok(0));
+    }
+}
+
+#[test]
+fn test_memory_limit_single_allocation() {
+    for mut interp in interpreters(Limits::new().max_memory(1_000_000)) {
+        // refused before the allocation is made
+        for source in [
+            "x = \"a\" * 100000000000",
+            "x = [0] * 100000000000",
+            "x = range(100000000000)",
+            "x = \"a\".zfill(100000000000)",
+            "x = \"a\".center(100000000000)",
+            "x = \"a\" * 50000000\nlen(x)",
+        ] {
+            let error = interp.run(source).unwrap_err();
+            assert_eq!(error.message, "MemoryError", "{}", source);
+        }
+        // a trailing expression is checked at the end of the run
+        interp.set_global("s", "a".repeat(600_000));
+        assert_eq!(interp.run("s + s").unwrap_err().message, "MemoryError");
+        assert_eq!(
+            interp.eval_expr("s + s").unwrap_err().message,
+            "MemoryError"
+        );
+        assert_eq!(
+            interp.run("len([0] * 1000)").unwrap().extract::<i64>(),
+            Ok(1000)
+        );
+    }
+}
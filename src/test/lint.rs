@@ -0,0 +1,61 @@
+use crate::eval::evaluator::Evaluator;
+use crate::lint::{lint, to_json, Diagnostic};
+use crate::parser::Parser;
+
+fn lint_file(file_name: &str) -> Vec<Diagnostic> {
+    let ast = Parser::new(file_name).unwrap().parse();
+    let builtins = Evaluator::new(file_name).unwrap().global_names();
+    lint(&ast, &builtins)
+}
+
+#[test]
+fn test_lint() {
+    let diagnostics: Vec<(usize, usize, &str)> = lint_file("testcase/lint.py")
+        .iter()
+        .map(|d| (d.span.line, d.span.column, d.code))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        vec![
+            (4, 4, "unused-variable"),
+            (5, 12, "undefined-name"),
+            (7, 4, "unreachable-code"),
+            (11, 4, "global-after-use"),
+            (15, 15, "undefined-name"),
+            (22, 0, "outside-loop"),
+            (23, 0, "unreachable-code"),
+            (23, 0, "return-outside-function"),
+            (24, 13, "undefined-name"),
+            (30, 4, "unreachable-code"),
+        ]
+    );
+}
+
+#[test]
+fn test_lint_clean() {
+    // working programs have nothing to report
+    for file_name in &[
+        "testcase/fizzbuzz.py",
+        "testcase/dict_set.py",
+        "testcase/builtins.py",
+        "testcase/file_io.py",
+    ] {
+        assert_eq!(lint_file(file_name), vec![], "{}", file_name);
+    }
+}
+
+#[test]
+fn test_format() {
+    let diagnostics = lint_file("testcase/lint.py");
+    assert_eq!(
+        diagnostics[1].format("lint.py"),
+        "lint.py:5:13: name 'z' is not defined [undefined-name]"
+    );
+    assert_eq!(
+        to_json("lint.py", &diagnostics[..1]),
+        r#"[
+  {"file": "lint.py", "code": "unused-variable", "message": "local variable 'x' is assigned to but never used", "span": {"line": 4, "column": 4, "end_line": 4, "end_column": 5}}
+]"#
+    );
+    assert_eq!(to_json("lint.py", &[]), "[]");
+}
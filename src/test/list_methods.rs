@@ -0,0 +1,77 @@
+use crate::eval::evaluator::Engine;
+use crate::eval::types::*;
+use crate::interpreter::Interpreter;
+use crate::test::helpers::*;
+
+engine_tests!(check_list_methods: test_list_methods, test_list_methods_vm);
+
+fn check_list_methods(engine: Engine) {
+    let evaluator = run_file("testcase/list_methods.py", engine);
+    let get = |name: &str| evaluator.get_global(name).unwrap();
+
+    assert_eq!(get("a"), ints(&[3, 1, 2, 4, 5, 6]));
+    // the alias shares the storage with `a`
+    assert_eq!(get("appended"), ints(&[3, 1, 2, 4, 5, 6]));
+    assert_eq!(get("popped"), int(7));
+    assert_eq!(get("popped_first"), int(0));
+    assert_eq!(get("index"), int(2));
+    assert_eq!(get("index_float"), int(3));
+    assert_eq!(get("count"), int(3));
+    assert_eq!(
+        get("words"),
+        py_val::new_list(vec![
+            string("apple"),
+            string("pear"),
+            string("kiwi"),
+            string("fig")
+        ])
+    );
+    assert_eq!(get("nums"), ints(&[3, 2, 1]));
+    assert_eq!(get("c"), ints(&[1, 2]));
+    assert_eq!(get("d"), ints(&[1, 2, 3]));
+    assert_eq!(get("e"), ints(&[]));
+    assert_eq!(get("added"), ints(&[1, 2, 3]));
+    assert_eq!(get("repeated"), ints(&[0, 1, 0, 1]));
+    assert_eq!(
+        get("repeated_left"),
+        py_val::new_list(vec![string("x"), string("x")])
+    );
+    assert_eq!(get("repeated_zero"), ints(&[]));
+    assert_eq!(get("f"), ints(&[10, 2, 30]));
+    assert_eq!(get("g"), ints(&[10, 2, 30]));
+    assert_eq!(get("item"), int(2));
+    assert_eq!(
+        get("bool_index"),
+        py_val::new_list(vec![int(2), string("b"), int(7)])
+    );
+    assert_eq!(get("h"), ints(&[1, 20]));
+    assert_eq!(get("in_list"), py_val::from_bool(true));
+    assert_eq!(get("not_in_list"), py_val::from_bool(true));
+    assert_eq!(get("in_tuple"), py_val::from_bool(true));
+    assert_eq!(get("in_str"), py_val::from_bool(true));
+    assert_eq!(get("eq_list"), py_val::from_bool(true));
+}
+
+engine_tests!(check_index_errors: test_index_errors, test_index_errors_vm);
+
+// an error of the script, not a panic of the host
+fn check_index_errors(engine: Engine) {
+    let mut interp = Interpreter::with_engine(engine).unwrap();
+    let mut error = |source: &str| interp.run(source).unwrap_err().message;
+    assert_eq!(
+        error("[1, 2][1.0]"),
+        "TypeError: list indices must be integers or slices, not float"
+    );
+    assert_eq!(
+        error("\"abc\"[1.0]"),
+        "TypeError: string indices must be integers, not 'float'"
+    );
+    assert_eq!(
+        error("(1,)[None]"),
+        "TypeError: tuple indices must be integers or slices, not NoneType"
+    );
+    assert_eq!(
+        error("x = [1]\nx[\"a\"] = 2"),
+        "TypeError: list indices must be integers or slices, not str"
+    );
+}
@@ -0,0 +1,78 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use crate::eval::evaluator::{Engine, Evaluator};
+use crate::eval::native_module::NativeModule;
+use crate::eval::types::*;
+use crate::test::helpers::*;
+
+// the application side: a counter shared with the script and the `app` modules
+fn setup(evaluator: &mut Evaluator, ticks: Rc<Cell<i64>>) {
+    evaluator.register_native("tick", 0, move |_| {
+        ticks.set(ticks.get() + 1);
+        Ok(int(ticks.get()))
+    });
+
+    let mut app = NativeModule::new("app");
+    app.add_function("greet", 1, |args| match args[0].as_str() {
+        Some(name) => Ok(string(&format!("hello, {}", name))),
+        None => Err(format!(
+            "TypeError: greet() expects str, not {}",
+            args[0].type_name()
+        )),
+    });
+    app.add_value("VERSION", string("1.2"));
+    let mut geometry = NativeModule::new("app.geometry");
+    geometry.add_function("area", 2, |args| {
+        match (args[0].as_i64(), args[1].as_i64()) {
+            (Some(w), Some(h)) => Ok(int(w * h)),
+            _ => Err("TypeError: area() expects int".to_string()),
+        }
+    });
+    // the package is registered after its submodule
+    evaluator.register_module(geometry);
+    evaluator.register_module(app);
+}
+
+engine_tests!(check_native_module: test_native_module, test_native_module_vm);
+
+fn check_native_module(engine: Engine) {
+    let ticks = Rc::new(Cell::new(0));
+    let mut evaluator = evaluator("testcase/native_module.py", engine);
+    setup(&mut evaluator, Rc::clone(&ticks));
+    run(&mut evaluator).unwrap();
+    let get = |name: &str| evaluator.get_global(name).unwrap();
+
+    assert_eq!(get("greeting"), string("hello, world"));
+    assert_eq!(get("sized"), int(12));
+    assert_eq!(get("version"), string("1.2"));
+    assert_eq!(
+        get("counted"),
+        py_val::new_list(vec![int(1), int(2), int(3)])
+    );
+    assert_eq!(get("counted_in_function"), int(5));
+    assert_eq!(ticks.get(), 5);
+    assert_eq!(
+        get("module_repr").repr(),
+        "[<module 'app' (built-in)>, <module 'app.geometry' (built-in)>]"
+    );
+    // registered natives are builtins, which lint knows about
+    assert!(evaluator.global_names().contains(&"tick".to_string()));
+}
+
+#[test]
+fn test_native_errors() {
+    let mut evaluator = evaluator("testcase/native_error.py", Engine::Tree);
+    setup(&mut evaluator, Rc::new(Cell::new(0)));
+    let traceback = run(&mut evaluator).unwrap_err().traceback;
+    // Err of the closure is raised at the call site
+    assert_eq!(
+        traceback,
+        r#"Traceback (most recent call last):
+  File "testcase/native_error.py", line 3, in <module>
+    greet(1)
+    ^^^^^^^^
+TypeError: greet() expects str, not int
+"#
+    );
+}
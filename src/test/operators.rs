@@ -0,0 +1,103 @@
+use crate::ast::*;
+use crate::eval::evaluator::Engine;
+use crate::eval::operators::*;
+use crate::eval::types::*;
+use crate::interpreter::Interpreter;
+use crate::test::helpers::*;
+
+engine_tests!(check_operators: test_operators, test_operators_vm);
+
+fn check_operators(engine: Engine) {
+    let evaluator = run_file("testcase/operators.py", engine);
+    let get = |name: &str| evaluator.get_global(name).unwrap();
+
+    // the fast paths give the same values(and types) as the prelude
+    let cases = get("cases").iter_values().unwrap();
+    assert_eq!(cases.len(), 42);
+    for (i, case) in cases.iter().enumerate() {
+        let pair = case.iter_values().unwrap();
+        assert_eq!(pair[0], pair[1], "case {}", i);
+    }
+
+    assert_eq!(
+        get("associativity").iter_values().unwrap(),
+        vec![
+            int(5),
+            int(5),
+            int(6),
+            int(4),
+            py_val::new(py_val::True),
+            py_val::new(py_val::False)
+        ]
+    );
+
+    // user overrides are honoured, also where the prelude calls them
+    assert_eq!(get("overridden_add"), string("added"));
+    assert_eq!(get("overridden_mul"), string("added"));
+    assert_eq!(get("overridden_eq"), py_val::new(py_val::True));
+    assert_eq!(get("overridden_neq"), py_val::new(py_val::False));
+    assert_eq!(get("overridden_in"), py_val::new(py_val::True));
+}
+
+#[test]
+fn test_fast_paths() {
+    use ASTOperator::*;
+    assert_eq!(fast_binary_op(&Add, &int(1), &int(2)), Some(int(3)));
+    assert_eq!(
+        fast_binary_op(&Div, &int(1), &float(4.0)),
+        Some(float(0.25))
+    );
+    // left to the prelude
+    assert_eq!(fast_binary_op(&Div, &int(1), &int(0)), None);
+    assert_eq!(
+        fast_binary_op(&Add, &int(i64::MAX >> 1), &int(i64::MAX)),
+        None
+    );
+    assert_eq!(fast_binary_op(&Add, &int(1), &string("a")), None);
+    assert_eq!(
+        fast_binary_op(&Add, &py_val::from_bool(true), &int(1)),
+        None
+    );
+    assert_eq!(fast_binary_op(&LShift, &int(1), &int(64)), None);
+    assert_eq!(
+        fast_binary_op(&Mul, &string("ab"), &int(2)),
+        Some(string("abab"))
+    );
+
+    assert_eq!(
+        fast_unary_op(&ASTUnaryOp::USub, &float(0.0)),
+        Some(float(0.0))
+    );
+    assert_eq!(fast_unary_op(&ASTUnaryOp::USub, &string("a")), None);
+
+    assert_eq!(
+        fast_compare_op(&ASTCmpOp::Lt, &int(1), &float(1.5)),
+        Some(true)
+    );
+    // TypeError is raised by the prelude
+    assert_eq!(fast_compare_op(&ASTCmpOp::Lt, &int(1), &string("a")), None);
+    assert_eq!(
+        fast_compare_op(&ASTCmpOp::Eq, &py_val::new(py_val::None), &int(1)),
+        None
+    );
+    assert_eq!(
+        fast_compare_op(&ASTCmpOp::NotIn, &string("z"), &string("abc")),
+        Some(true)
+    );
+}
+
+engine_tests!(check_zero_division: test_zero_division, test_zero_division_vm);
+
+// an error of the script, not a panic of the host
+fn check_zero_division(engine: Engine) {
+    let mut interp = Interpreter::with_engine(engine).unwrap();
+    let mut error = |source: &str| interp.eval_expr(source).unwrap_err().message;
+    assert_eq!(error("1 / 0"), "ZeroDivisionError: division by zero");
+    assert_eq!(error("1 % 0"), "ZeroDivisionError: integer modulo by zero");
+    assert_eq!(
+        error("1.5 / 0"),
+        "ZeroDivisionError: float division by zero"
+    );
+    assert_eq!(error("1 % 0.0"), "ZeroDivisionError: float modulo by zero");
+    assert_eq!(interp.eval_expr("7 / 2").unwrap(), int(3));
+}
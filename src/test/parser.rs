@@ -1,5 +1,5 @@
 use crate::ast::*;
-use crate::parser::Parser;
+use crate::parser::{Parser, SyntaxError};
 use ordered_float::OrderedFloat;
 
 #[test]
@@ -8,14 +8,20 @@ fn test_test_1() {
     let ast = parser.parse();
     assert_eq!(
         ast,
-        vec![ASTStmt::Expr(ASTExpr::Call(
-            Box::new(ASTExpr::Name(String::from("print"))),
-            vec![ASTExpr::BinOp(
-                Box::new(ASTExpr::Constant(ASTConstant::Int(1))),
-                ASTOperator::Add,
-                Box::new(ASTExpr::Constant(ASTConstant::Int(2)))
-            )],
-        ))]
+        vec![ASTStmtKind::Expr(
+            ASTExprKind::Call(
+                Box::new(ASTExprKind::Name(String::from("print")).into()),
+                vec![ASTExprKind::BinOp(
+                    Box::new(ASTExprKind::Constant(ASTConstant::Int(1)).into()),
+                    ASTOperator::Add,
+                    Box::new(ASTExprKind::Constant(ASTConstant::Int(2)).into())
+                )
+                .into()],
+                vec![],
+            )
+            .into()
+        )
+        .into()]
     );
 }
 
@@ -26,34 +32,49 @@ fn test_test_2() {
     assert_eq!(
         ast,
         vec![
-            ASTStmt::FuncDef(
+            ASTStmtKind::FuncDef(
                 String::from("test"),
-                vec![String::from("poi")],
+                vec![(String::from("poi"), None).into()],
                 vec![
-                    ASTStmt::Assign(
-                        vec![ASTExpr::Name(String::from("a"))],
-                        ASTExpr::Constant(ASTConstant::Float(OrderedFloat(1.0)))
-                    ),
-                    ASTStmt::Expr(ASTExpr::Call(
-                        Box::new(ASTExpr::Name(String::from("print"))),
-                        vec![ASTExpr::Name(String::from("a"))]
-                    ))
-                ]
-            ),
-            ASTStmt::If(
-                ASTExpr::Compare(
-                    Box::new(ASTExpr::Name(String::from("__name__"))),
+                    ASTStmtKind::Assign(
+                        vec![ASTExprKind::Name(String::from("a")).into()],
+                        ASTExprKind::Constant(ASTConstant::Float(OrderedFloat(1.0))).into()
+                    )
+                    .into(),
+                    ASTStmtKind::Expr(
+                        ASTExprKind::Call(
+                            Box::new(ASTExprKind::Name(String::from("print")).into()),
+                            vec![ASTExprKind::Name(String::from("a")).into()],
+                            vec![],
+                        )
+                        .into()
+                    )
+                    .into()
+                ],
+                None,
+            )
+            .into(),
+            ASTStmtKind::If(
+                ASTExprKind::Compare(
+                    Box::new(ASTExprKind::Name(String::from("__name__")).into()),
                     vec![ASTCmpOp::Eq],
-                    vec![ASTExpr::Constant(ASTConstant::String(String::from(
-                        "__main__"
-                    )))]
-                ),
-                vec![ASTStmt::Expr(ASTExpr::Call(
-                    Box::new(ASTExpr::Name(String::from("test"))),
-                    vec![ASTExpr::Constant(ASTConstant::Int(2))]
-                ))],
+                    vec![
+                        ASTExprKind::Constant(ASTConstant::String(String::from("__main__"))).into()
+                    ]
+                )
+                .into(),
+                vec![ASTStmtKind::Expr(
+                    ASTExprKind::Call(
+                        Box::new(ASTExprKind::Name(String::from("test")).into()),
+                        vec![ASTExprKind::Constant(ASTConstant::Int(2)).into()],
+                        vec![],
+                    )
+                    .into()
+                )
+                .into()],
                 Vec::new()
             )
+            .into()
         ]
     )
 }
@@ -64,64 +85,109 @@ fn test_fizzbuzz() {
     let ast = parser.parse();
     assert_eq!(
         ast,
-        vec![ASTStmt::For(
-            ASTExpr::Name(String::from("i")),
-            ASTExpr::Call(
-                Box::new(ASTExpr::Name(String::from("range"))),
-                vec![ASTExpr::Constant(ASTConstant::Int(100))]
-            ),
-            vec![ASTStmt::If(
-                ASTExpr::Compare(
-                    Box::new(ASTExpr::BinOp(
-                        Box::new(ASTExpr::Name(String::from("i"))),
-                        ASTOperator::Mod,
-                        Box::new(ASTExpr::Constant(ASTConstant::Int(15)))
-                    )),
-                    vec![ASTCmpOp::Eq],
-                    vec![ASTExpr::Constant(ASTConstant::Int(0))]
-                ),
-                vec![ASTStmt::Expr(ASTExpr::Call(
-                    Box::new(ASTExpr::Name(String::from("print"))),
-                    vec![ASTExpr::Constant(ASTConstant::String(String::from(
-                        "fizzbuzz"
-                    )))],
-                ))],
-                vec![ASTStmt::If(
-                    ASTExpr::Compare(
-                        Box::new(ASTExpr::BinOp(
-                            Box::new(ASTExpr::Name(String::from("i"))),
+        vec![ASTStmtKind::For(
+            ASTExprKind::Name(String::from("i")).into(),
+            ASTExprKind::Call(
+                Box::new(ASTExprKind::Name(String::from("range")).into()),
+                vec![ASTExprKind::Constant(ASTConstant::Int(100)).into()],
+                vec![],
+            )
+            .into(),
+            vec![ASTStmtKind::If(
+                ASTExprKind::Compare(
+                    Box::new(
+                        ASTExprKind::BinOp(
+                            Box::new(ASTExprKind::Name(String::from("i")).into()),
                             ASTOperator::Mod,
-                            Box::new(ASTExpr::Constant(ASTConstant::Int(5)))
-                        )),
-                        vec![ASTCmpOp::Eq],
-                        vec![ASTExpr::Constant(ASTConstant::Int(0))]
+                            Box::new(ASTExprKind::Constant(ASTConstant::Int(15)).into())
+                        )
+                        .into()
                     ),
-                    vec![ASTStmt::Expr(ASTExpr::Call(
-                        Box::new(ASTExpr::Name(String::from("print"))),
-                        vec![ASTExpr::Constant(ASTConstant::String(String::from("fizz")))],
-                    ))],
-                    vec![ASTStmt::If(
-                        ASTExpr::Compare(
-                            Box::new(ASTExpr::BinOp(
-                                Box::new(ASTExpr::Name(String::from("i"))),
+                    vec![ASTCmpOp::Eq],
+                    vec![ASTExprKind::Constant(ASTConstant::Int(0)).into()]
+                )
+                .into(),
+                vec![ASTStmtKind::Expr(
+                    ASTExprKind::Call(
+                        Box::new(ASTExprKind::Name(String::from("print")).into()),
+                        vec![
+                            ASTExprKind::Constant(ASTConstant::String(String::from("fizzbuzz")))
+                                .into()
+                        ],
+                        vec![],
+                    )
+                    .into()
+                )
+                .into()],
+                vec![ASTStmtKind::If(
+                    ASTExprKind::Compare(
+                        Box::new(
+                            ASTExprKind::BinOp(
+                                Box::new(ASTExprKind::Name(String::from("i")).into()),
                                 ASTOperator::Mod,
-                                Box::new(ASTExpr::Constant(ASTConstant::Int(3)))
-                            )),
-                            vec![ASTCmpOp::Eq],
-                            vec![ASTExpr::Constant(ASTConstant::Int(0))]
+                                Box::new(ASTExprKind::Constant(ASTConstant::Int(5)).into())
+                            )
+                            .into()
                         ),
-                        vec![ASTStmt::Expr(ASTExpr::Call(
-                            Box::new(ASTExpr::Name(String::from("print"))),
-                            vec![ASTExpr::Constant(ASTConstant::String(String::from("buzz")))],
-                        ))],
-                        vec![ASTStmt::Expr(ASTExpr::Call(
-                            Box::new(ASTExpr::Name(String::from("print"))),
-                            vec![ASTExpr::Name(String::from("i"))]
-                        ))]
-                    )]
-                )],
-            )]
-        )]
+                        vec![ASTCmpOp::Eq],
+                        vec![ASTExprKind::Constant(ASTConstant::Int(0)).into()]
+                    )
+                    .into(),
+                    vec![ASTStmtKind::Expr(
+                        ASTExprKind::Call(
+                            Box::new(ASTExprKind::Name(String::from("print")).into()),
+                            vec![
+                                ASTExprKind::Constant(ASTConstant::String(String::from("fizz")))
+                                    .into()
+                            ],
+                            vec![],
+                        )
+                        .into()
+                    )
+                    .into()],
+                    vec![ASTStmtKind::If(
+                        ASTExprKind::Compare(
+                            Box::new(
+                                ASTExprKind::BinOp(
+                                    Box::new(ASTExprKind::Name(String::from("i")).into()),
+                                    ASTOperator::Mod,
+                                    Box::new(ASTExprKind::Constant(ASTConstant::Int(3)).into())
+                                )
+                                .into()
+                            ),
+                            vec![ASTCmpOp::Eq],
+                            vec![ASTExprKind::Constant(ASTConstant::Int(0)).into()]
+                        )
+                        .into(),
+                        vec![ASTStmtKind::Expr(
+                            ASTExprKind::Call(
+                                Box::new(ASTExprKind::Name(String::from("print")).into()),
+                                vec![ASTExprKind::Constant(ASTConstant::String(String::from(
+                                    "buzz"
+                                )))
+                                .into()],
+                                vec![],
+                            )
+                            .into()
+                        )
+                        .into()],
+                        vec![ASTStmtKind::Expr(
+                            ASTExprKind::Call(
+                                Box::new(ASTExprKind::Name(String::from("print")).into()),
+                                vec![ASTExprKind::Name(String::from("i")).into()],
+                                vec![],
+                            )
+                            .into()
+                        )
+                        .into()]
+                    )
+                    .into()]
+                )
+                .into()],
+            )
+            .into()]
+        )
+        .into()]
     );
 }
 
@@ -131,13 +197,208 @@ fn test_test_3() {
     let ast = parser.parse();
     assert_eq!(
         ast,
-        vec![ASTStmt::Expr(ASTExpr::Call(
-            Box::new(ASTExpr::Name(String::from("print"))),
-            vec![ASTExpr::List(vec![
-                ASTExpr::Constant(ASTConstant::Int(1)),
-                ASTExpr::Constant(ASTConstant::Int(2)),
-                ASTExpr::Constant(ASTConstant::Int(3))
-            ])],
-        ))]
+        vec![ASTStmtKind::Expr(
+            ASTExprKind::Call(
+                Box::new(ASTExprKind::Name(String::from("print")).into()),
+                vec![ASTExprKind::List(vec![
+                    ASTExprKind::Constant(ASTConstant::Int(1)).into(),
+                    ASTExprKind::Constant(ASTConstant::Int(2)).into(),
+                    ASTExprKind::Constant(ASTConstant::Int(3)).into()
+                ])
+                .into()],
+                vec![],
+            )
+            .into()
+        )
+        .into()]
     );
 }
+
+#[test]
+fn test_keyword_args() {
+    let mut parser = Parser::new("testcase/keyword_args.py").unwrap();
+    let ast = parser.parse();
+    assert_eq!(
+        ast,
+        vec![ASTStmtKind::Expr(
+            ASTExprKind::Call(
+                Box::new(ASTExprKind::Name(String::from("sorted")).into()),
+                vec![ASTExprKind::Name(String::from("a")).into()],
+                vec![
+                    (
+                        String::from("key"),
+                        ASTExprKind::Name(String::from("f")).into()
+                    )
+                        .into(),
+                    (
+                        String::from("reverse"),
+                        ASTExprKind::Constant(ASTConstant::True).into()
+                    )
+                        .into()
+                ],
+            )
+            .into()
+        )
+        .into()]
+    );
+}
+
+fn span(line: usize, column: usize, end_line: usize, end_column: usize) -> Span {
+    Span {
+        line,
+        column,
+        end_line,
+        end_column,
+    }
+}
+
+#[test]
+fn test_spans() {
+    let ast = Parser::new("testcase/spans.py").unwrap().parse();
+    // compound statements end with the last token of their body
+    assert_eq!(ast[0].span, span(1, 0, 2, 31));
+    assert_eq!(ast[1].span, span(5, 0, 7, 22));
+
+    let (args, body) = match &ast[0].node {
+        ASTStmtKind::FuncDef(_, args, body, _) => (args, body),
+        _ => unreachable!(),
+    };
+    assert_eq!(args[0].span, span(1, 10, 1, 15));
+    assert_eq!(args[1].span, span(1, 17, 1, 23));
+    assert_eq!(body[0].span, span(2, 4, 2, 31));
+    let value = match &body[0].node {
+        ASTStmtKind::Return(Some(value)) => value,
+        _ => unreachable!(),
+    };
+    // parentheses belong to the enclosing expression
+    assert_eq!(value.span, span(2, 11, 2, 31));
+    match &value.node {
+        ASTExprKind::BinOp(lhs, _, rhs) => {
+            assert_eq!(lhs.span, span(2, 12, 2, 21));
+            assert_eq!(rhs.span, span(2, 25, 2, 31));
+        }
+        _ => unreachable!(),
+    }
+
+    let (test, body) = match &ast[1].node {
+        ASTStmtKind::If(test, body, _) => (test, body),
+        _ => unreachable!(),
+    };
+    assert_eq!(test.span, span(5, 3, 5, 25));
+    let call = match &test.node {
+        ASTExprKind::Compare(call, _, _) => call,
+        _ => unreachable!(),
+    };
+    assert_eq!(call.span, span(5, 3, 5, 21));
+    match &call.node {
+        ASTExprKind::Call(_, args, keywords) => {
+            assert_eq!(args[0].span, span(5, 9, 5, 10));
+            assert_eq!(keywords[0].span, span(5, 12, 5, 20));
+            assert_eq!(keywords[0].node.1.span, span(5, 19, 5, 20));
+        }
+        _ => unreachable!(),
+    }
+
+    // brackets are part of displays
+    match &body[0].node {
+        ASTStmtKind::Assign(targets, value) => {
+            assert_eq!(targets[0].span, span(6, 4, 6, 9));
+            assert_eq!(value.span, span(6, 12, 6, 30));
+            match &value.node {
+                ASTExprKind::List(elts) => {
+                    assert_eq!(elts[1].span, span(6, 16, 6, 21));
+                    assert_eq!(elts[2].span, span(6, 23, 6, 29));
+                }
+                _ => unreachable!(),
+            }
+        }
+        _ => unreachable!(),
+    }
+    match &body[1].node {
+        ASTStmtKind::Assign(_, value) => {
+            assert_eq!(value.span, span(7, 12, 7, 22));
+            match &value.node {
+                ASTExprKind::Subscript(_, slice) => assert_eq!(slice.span, span(7, 18, 7, 21)),
+                _ => unreachable!(),
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn test_error_recovery() {
+    let mut parser = Parser::new("testcase/syntax_errors.py").unwrap();
+    let ast = parser.parse();
+    let error = |message: &str, line, column, end_column| {
+        SyntaxError::new(message, span(line, column, line, end_column))
+    };
+    // all errors are reported, each at the unexpected token
+    assert_eq!(
+        parser.errors(),
+        &[
+            error("expected expression", 1, 7, 8),
+            error("expected ':' after if condition", 2, 8, 9),
+            error("expected ')' to close parameter list", 4, 10, 11),
+            error("expected ']' to close list", 6, 9, 10),
+            error("expected newline after statement", 9, 9, 13),
+            error("expected an indented block", 12, 0, 5),
+        ][..]
+    );
+
+    // broken statements(with the block after a broken header) become placeholders
+    let kinds: Vec<&ASTStmtKind> = ast.iter().map(|stmt| &stmt.node).collect();
+    assert_eq!(kinds.len(), 8);
+    assert_eq!(kinds[0..4], [&ASTStmtKind::Error; 4]);
+    assert!(matches!(kinds[4], ASTStmtKind::Expr(_)));
+    match kinds[5] {
+        ASTStmtKind::While(_, body) => {
+            assert_eq!(body[0].node, ASTStmtKind::Error);
+            assert_eq!(body[0].span, span(9, 4, 9, 13));
+            assert!(matches!(body[1].node, ASTStmtKind::Assign(_, _)));
+        }
+        _ => unreachable!(),
+    }
+    assert_eq!(kinds[6], &ASTStmtKind::Error);
+    assert!(matches!(kinds[7], ASTStmtKind::Expr(_)));
+    assert_eq!(ast[1].span, span(2, 0, 3, 12));
+}
+
+#[test]
+fn test_lexical_error_recovery() {
+    let mut parser = Parser::new("testcase/lexical_errors.py").unwrap();
+    let ast = parser.parse();
+    let error = |message: &str, line, column| {
+        SyntaxError::new(message, span(line, column, line, column + 1))
+    };
+    // the tokenizer errors are collected with the parser ones, and the next line is read again
+    assert_eq!(
+        parser.errors(),
+        &[
+            SyntaxError::new("unexpected indent", span(3, 4, 3, 4)),
+            error("unindent does not match any outer indentation level", 4, 2),
+            error("EOL while scanning string literal", 5, 8),
+            error("invalid token", 7, 8),
+            error("unexpected character", 8, 7),
+            error("truncated \\x escape", 9, 8),
+            error("integer literal too large", 10, 24),
+        ][..]
+    );
+    let kinds: Vec<&ASTStmtKind> = ast.iter().map(|stmt| &stmt.node).collect();
+    assert!(matches!(
+        kinds[..],
+        [
+            ASTStmtKind::Assign(..),
+            ASTStmtKind::Assign(..),
+            ASTStmtKind::Error,
+            ASTStmtKind::Assign(..),
+            ASTStmtKind::Error,
+            ASTStmtKind::Expr(_),
+            ASTStmtKind::Error,
+            ASTStmtKind::Error,
+            ASTStmtKind::Error,
+            ASTStmtKind::Error,
+            ASTStmtKind::Expr(_),
+        ]
+    ));
+}
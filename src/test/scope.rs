@@ -0,0 +1,104 @@
+use crate::ast::*;
+use crate::eval::evaluator::Engine;
+use crate::eval::scope::*;
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::test::helpers::*;
+
+// scope of each function defined in body, analyzed as the evaluator does
+fn scopes(body: &[ASTStmt], enclosing: Option<&Scope>, res: &mut Vec<(String, Scope)>) {
+    for stmt in body {
+        if let ASTStmtKind::FuncDef(name, args, body, _) = &stmt.node {
+            let scope = Scope::analyze(args, body, enclosing).unwrap();
+            scopes(body, Some(&scope), res);
+            res.push((name.clone(), scope));
+        }
+    }
+}
+
+#[test]
+fn test_scope() {
+    let ast = Parser::new("testcase/scope.py").unwrap().parse();
+    let mut res = Vec::new();
+    scopes(&ast, None, &mut res);
+    let scope = |name: &str| &res.iter().find(|(n, _)| n == name).unwrap().1;
+
+    // parameters first, then the other bound names in order of appearance
+    let s = scope("locals_only");
+    assert_eq!(s.locals, vec!["a", "b", "c", "i", "d"]);
+    assert_eq!(s.kind("a"), NameKind::Local(0));
+    assert_eq!(s.kind("d"), NameKind::Local(4));
+    assert_eq!(s.kind("range"), NameKind::Global);
+
+    let s = scope("reads_global");
+    assert_eq!(s.locals, vec!["x"]);
+    assert_eq!(s.kind("limit"), NameKind::Global);
+
+    let s = scope("declares_global");
+    assert_eq!(s.locals, vec!["value"]);
+    assert_eq!(s.kind("limit"), NameKind::Global);
+    assert_eq!(s.slot("limit"), None);
+
+    // nested functions are locals of their parent
+    let s = scope("outer");
+    assert_eq!(s.locals, vec!["n", "step", "inner", "count"]);
+    let s = scope("inner");
+    assert_eq!(s.locals, vec!["m"]);
+    assert_eq!(s.kind("step"), NameKind::Free);
+    let s = scope("count");
+    assert_eq!(s.locals, vec!["total", "k"]);
+    assert_eq!(s.kind("n"), NameKind::Free);
+    assert_eq!(s.kind("range"), NameKind::Global);
+
+    // bound anywhere in the body, so local everywhere in it(UnboundLocalError if x is false)
+    let s = scope("shadows");
+    assert_eq!(s.kind("limit"), NameKind::Local(1));
+}
+
+#[test]
+fn test_global_after_assignment() {
+    let global = Span::new((2, 4), (2, 12));
+    let body = vec![
+        ASTStmtKind::Assign(
+            vec![ASTExprKind::Name("x".to_string()).into()],
+            ASTExprKind::Constant(ASTConstant::Int(1)).into(),
+        )
+        .into(),
+        ASTStmt::new(ASTStmtKind::Global(vec!["x".to_string()]), global),
+    ];
+    let error = Scope::analyze(&[], &body, None).err().unwrap();
+    assert_eq!(
+        error.message,
+        "name 'x' is assigned to before global declaration"
+    );
+    assert_eq!(error.span, global);
+}
+
+engine_tests!(check_misplaced_global: test_misplaced_global, test_misplaced_global_vm);
+
+// a SyntaxError of the definition, not a panic of the host
+fn check_misplaced_global(engine: Engine) {
+    let mut interp = Interpreter::with_engine(engine).unwrap();
+    let error = interp
+        .run("def f():\n    print(x)\n    global x\n")
+        .unwrap_err();
+    assert_eq!(
+        error.message,
+        "SyntaxError: name 'x' is used prior to global declaration"
+    );
+    assert!(error.traceback.contains("line 3"), "{}", error.traceback);
+    // the interpreter is still usable
+    assert_eq!(interp.eval_expr("1 + 1").unwrap(), int(2));
+}
+
+engine_tests!(check_scope_eval: test_scope_eval, test_scope_eval_vm);
+
+fn check_scope_eval(engine: Engine) {
+    let evaluator = run_file("testcase/scope.py", engine);
+    let get = |name: &str| evaluator.get_global(name).unwrap();
+
+    assert_eq!(get("summed"), int(2));
+    assert_eq!(get("read"), int(15));
+    assert_eq!(get("changed"), int(20));
+    assert_eq!(get("limit"), int(20));
+}
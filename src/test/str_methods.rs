@@ -0,0 +1,60 @@
+use crate::eval::evaluator::Engine;
+use crate::eval::types::*;
+use crate::test::helpers::*;
+
+engine_tests!(check_str_methods: test_str_methods, test_str_methods_vm);
+
+fn check_str_methods(engine: Engine) {
+    let evaluator = run_file("testcase/str_methods.py", engine);
+    let get = |name: &str| evaluator.get_global(name).unwrap();
+
+    assert_eq!(get("split_ws"), strings(&["a", "b", "c"]));
+    assert_eq!(get("split_sep"), strings(&["a", "b", "", "c"]));
+    assert_eq!(get("split_max"), strings(&["a", "b", "c d"]));
+    assert_eq!(get("rsplit_max"), strings(&["a,b", "c"]));
+    assert_eq!(get("joined"), string("x-y-z"));
+    assert_eq!(get("stripped"), string("Hello, World"));
+    assert_eq!(get("lstripped"), string("abcxx"));
+    assert_eq!(get("rstripped"), string("  Hello, World"));
+    assert_eq!(get("replaced"), string("bbaa"));
+    assert_eq!(get("found"), int(2));
+    assert_eq!(get("found_start"), int(3));
+    assert_eq!(get("found_none"), int(-1));
+    assert_eq!(get("rfound"), int(3));
+    assert_eq!(get("indexed"), int(3));
+    assert_eq!(get("starts"), py_val::from_bool(true));
+    assert_eq!(get("starts_tuple"), py_val::from_bool(true));
+    assert_eq!(get("ends"), py_val::from_bool(false));
+    assert_eq!(get("upper"), string("HÉLLO"));
+    assert_eq!(get("lower"), string("abc"));
+    assert_eq!(get("title"), string("Hello World It'S"));
+    // titlecase letters are cased and map to themselves
+    assert_eq!(get("title_titlecase"), string("ǅa"));
+    assert_eq!(get("title_special"), string("ǅungla Ssa Fish ᾼb"));
+    assert_eq!(get("isdigit"), py_val::from_bool(true));
+    // superscripts and other scripts' digits are digits, numeric chars in general are not
+    assert_eq!(get("isdigit_other"), py_val::from_bool(true));
+    assert_eq!(get("isdigit_fraction"), py_val::from_bool(false));
+    assert_eq!(get("isdigit_roman"), py_val::from_bool(false));
+    assert_eq!(get("isalpha"), py_val::from_bool(false));
+    assert_eq!(get("isspace"), py_val::from_bool(true));
+    assert_eq!(get("isspace_empty"), py_val::from_bool(false));
+    assert_eq!(get("counted"), int(2));
+    assert_eq!(get("counted_empty"), int(4));
+    assert_eq!(get("lines"), strings(&["a", "b", "c"]));
+    assert_eq!(get("lines_keep"), strings(&["a\n", "b\r\n", "c\n"]));
+    assert_eq!(
+        get("parted"),
+        py_val::new(py_val::tuple(vec![
+            string("key"),
+            string("="),
+            string("value=x")
+        ]))
+    );
+    assert_eq!(get("zfilled"), string("-0042"));
+    assert_eq!(get("centered"), string("**ab*"));
+    assert_eq!(get("chained"), strings(&["a", "b"]));
+    assert_eq!(get("unicode_escape"), string("あA\t"));
+    assert_eq!(get("unicode_len"), int(3));
+    assert_eq!(get("unicode_item"), string("う"));
+}
@@ -1,3 +1,4 @@
+use crate::ast::Span;
 use crate::token::Token::{self, *};
 use crate::tokenizer::Tokenizer;
 
@@ -42,3 +43,28 @@ fn test_test_3() {
         ID(String::from("print")), LPAREN, LBRACKET, INT(1), COMMA, INT(2), COMMA, INT(3), RBRACKET, RPAREN, EOF
     ]);
 }
+
+#[test]
+fn test_spans() {
+    let mut tokenizer = Tokenizer::new("testcase/test_1.py").unwrap();
+    #[rustfmt::skip]
+    let expected = vec![
+        (ID(String::from("print")), (1, 0, 1, 5)), (LPAREN, (1, 5, 1, 6)), (INT(1), (1, 6, 1, 7)),
+        (PLUS, (1, 8, 1, 9)), (INT(2), (1, 10, 1, 11)), (RPAREN, (1, 11, 1, 12)),
+        (NEWLINE, (1, 12, 1, 13)), (EOF, (2, 0, 2, 0)),
+    ];
+    for (tok, (line, column, end_line, end_column)) in expected {
+        tokenizer.next_token();
+        let token = tokenizer.get_current_located_token();
+        assert_eq!(token.node, tok);
+        assert_eq!(
+            token.span,
+            Span {
+                line,
+                column,
+                end_line,
+                end_column
+            }
+        );
+    }
+}
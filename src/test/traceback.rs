@@ -0,0 +1,52 @@
+use crate::eval::evaluator::Engine;
+use crate::test::helpers::*;
+
+// the traceback of the error raised by the file
+fn traceback(file_name: &str, engine: Engine) -> String {
+    run_error(file_name, engine).traceback
+}
+
+engine_tests!(check_traceback: test_traceback, test_traceback_vm);
+
+fn check_traceback(engine: Engine) {
+    // each frame points at the call site in it, not at where the parser stopped
+    assert_eq!(
+        traceback("testcase/traceback.py", engine),
+        r#"Traceback (most recent call last):
+  File "testcase/traceback.py", line 10, in <module>
+    print(outer(3))
+          ^^^^^^^^
+  File "testcase/traceback.py", line 7, in outer
+    return inner(v) * 2
+           ^^^^^^^^
+  File "testcase/traceback.py", line 3, in inner
+    return y + undefined_name
+               ^^^^^^^^^^^^^^
+NameError: undefined_name is not defined
+"#
+    );
+
+    // errors in the prelude are reported in its file
+    let res = traceback("testcase/traceback_prelude.py", engine);
+    assert!(res.contains(
+        r#"  File "testcase/traceback_prelude.py", line 2, in add_str
+    return a + "s"
+           ^^^^^^^
+"#
+    ));
+    assert!(res.contains("src/std/init.py\", line 8, in __add__\n"));
+    assert!(res.ends_with("TypeError: unsupported operand type(s)\n"));
+}
+
+#[test]
+fn test_syntax_error() {
+    // only the first syntax error is reported when running
+    assert_eq!(
+        traceback("testcase/syntax_errors.py", Engine::Tree),
+        r#"  File "testcase/syntax_errors.py", line 1
+    x = 1 +
+           ^
+SyntaxError: expected expression
+"#
+    );
+}
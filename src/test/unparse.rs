@@ -0,0 +1,80 @@
+use std::fs;
+
+use crate::ast::AST;
+use crate::parser::Parser;
+use crate::unparse::unparse;
+
+fn parse(file_name: &str) -> AST {
+    Parser::new(file_name).unwrap().parse()
+}
+
+// parse the source through a temporary file(the parser reads files only)
+fn parse_source(name: &str, source: &str) -> AST {
+    let path = std::env::temp_dir().join(format!("minipython_unparse_{}", name));
+    fs::write(&path, source).unwrap();
+    let ast = parse(path.to_str().unwrap());
+    fs::remove_file(&path).unwrap();
+    ast
+}
+
+#[test]
+fn test_unparse() {
+    // only the right operand of the same precedence is parenthesized
+    assert_eq!(
+        unparse(&parse("testcase/unparse.py")),
+        r#"x = (1 + 2) * 3
+y = 1 + 2 * 3
+z = -(x + y) % ~x
+w = not x == y and (x or y)
+v = (x if y else z) if w else 0.5
+u = x if y else z if w else "quote\"tab\t"
+t = f(x, key=1)[0].attr[1:][::2]
+s = (1, 2)[0, 1]
+r = [1, (2,), (), {"k": (3, 4)}, {5, 6}]
+q = 10 - 3 - 2
+p = x < y == True
+o = 1 - 2 - 3
+n = 1 - (2 - 3)
+m = (a < b) < c
+for a, b in x, y:
+    del a, b
+    continue
+
+def f(a, b):
+    global x
+    if a:
+        return a, b
+    elif b:
+        return
+    elif a is not b:
+        pass
+    while (a in b) == (b not in a):
+        break
+print((1).real, f(1, 2))
+with a if b else c as d, e[0] as e[1]:
+    pass
+"#
+    );
+}
+
+#[test]
+fn test_round_trip() {
+    // parse(unparse(parse(src))) == parse(src) for every file in testcase/
+    let mut checked = 0;
+    for entry in fs::read_dir("testcase").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension() != Some("py".as_ref()) {
+            continue;
+        }
+        let mut parser = Parser::new(path.to_str().unwrap()).unwrap();
+        let ast = parser.parse();
+        if !parser.errors().is_empty() {
+            continue;
+        }
+        let name = path.file_name().unwrap().to_str().unwrap();
+        let source = unparse(&ast);
+        assert_eq!(parse_source(name, &source), ast, "{}:\n{}", name, source);
+        checked += 1;
+    }
+    assert!(checked > 10);
+}
@@ -0,0 +1,87 @@
+use std::rc::Rc;
+
+use crate::eval::gc;
+use crate::eval::limits::heap_bytes;
+use crate::eval::types::*;
+use crate::test::helpers::*;
+
+#[test]
+fn test_immediates() {
+    for &i in &[0, 1, -1, 42, i64::MAX >> 1, i64::MIN >> 1] {
+        let v = int(i);
+        assert_eq!(v.as_i64(), Some(i));
+        assert_eq!(*v.view(), py_val::int(i));
+        assert_eq!(v.type_name(), "int");
+    }
+    assert!(py_val::new(py_val::None).is_none());
+    assert_eq!(py_val::from_bool(true).as_i64(), Some(1));
+    assert_eq!(py_val::from_bool(false).type_name(), "bool");
+    assert!(py_val::from_bool(false).is_false());
+    for s in &["", "a", "abcdefg", "あい", "é"] {
+        let v = string(s);
+        assert_eq!(v.as_str(), Some(*s));
+        assert_eq!(v.type_name(), "str");
+        assert_eq!(*v.view(), py_val::string(s.to_string()));
+    }
+    if cfg!(not(feature = "boxed-values")) {
+        assert!(int(7).heap().is_none());
+        assert!(string("abcdefg").heap().is_none());
+        assert!(py_val::new(py_val::None).heap().is_none());
+    }
+}
+
+#[test]
+fn test_heap_values() {
+    // out of the range of immediates
+    for &i in &[i64::MAX, i64::MIN, (i64::MAX >> 1) + 1] {
+        let v = int(i);
+        assert!(v.heap().is_some());
+        assert_eq!(v.as_i64(), Some(i));
+    }
+    let long = string("abcdefgh");
+    assert!(long.heap().is_some());
+    assert_eq!(long.as_str(), Some("abcdefgh"));
+    assert_eq!(long, string("abcdefgh"));
+    assert_ne!(long, string("abcdefg"));
+}
+
+#[test]
+fn test_sharing() {
+    let list = py_val::new_list(vec![int(1)]);
+    let alias = list.clone();
+    assert!(Value::ptr_eq(&list, &alias));
+    if let Some(py_val::list(l)) = list.heap() {
+        l.borrow_mut().push(int(2));
+    }
+    assert_eq!(alias, py_val::new_list(vec![int(1), int(2)]));
+    assert!(!Value::ptr_eq(
+        &list,
+        &py_val::new_list(vec![int(1), int(2)])
+    ));
+}
+
+#[test]
+fn test_refcount() {
+    let objects = gc::tracked_objects();
+    let list = py_val::new_list(vec![int(1)]).view().clone();
+    let bytes = heap_bytes();
+    let v: Value = Rc::new(list).into();
+    // made as py_val::new makes it: seen by the collector and the memory limit
+    assert_eq!(gc::tracked_objects(), objects + 1);
+    assert!(heap_bytes() > bytes);
+    assert_eq!(v.strong_count(), 1);
+    let w = v.clone();
+    assert_eq!(v.strong_count(), 2);
+    drop(w);
+    assert_eq!(v.strong_count(), 1);
+    drop(v);
+    assert_eq!(gc::tracked_objects(), objects);
+    // immediates given as Rc are unboxed
+    let one: Value = Rc::new(py_val::int(1)).into();
+    assert_eq!(one, int(1));
+    // a shared Rc is copied
+    let rc = Rc::new(py_val::string("not a short string".to_string()));
+    let s: Value = rc.clone().into();
+    assert_eq!(Rc::strong_count(&rc), 1);
+    assert_eq!(s, string("not a short string"));
+}
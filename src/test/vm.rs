@@ -0,0 +1,105 @@
+use crate::ast::*;
+use crate::eval::compiler::*;
+use crate::eval::evaluator::Engine;
+use crate::eval::types::*;
+use crate::parser::Parser;
+use crate::test::helpers::*;
+
+engine_tests!(check_vm: test_vm, test_vm_vm);
+
+fn check_vm(engine: Engine) {
+    let evaluator = run_file("testcase/vm.py", engine);
+    let get = |name: &str| evaluator.get_global(name).unwrap();
+
+    assert_eq!(get("fact_10"), int(3628800));
+    assert_eq!(get("odd"), ints(&[1, 3, 5, 7, 9]));
+    assert_eq!(get("pair"), tuple(vec![int(2), int(5)]));
+    assert_eq!(get("no_pair"), py_val::new(py_val::None));
+    assert_eq!(get("skipped"), ints(&[1, 3]));
+    assert_eq!(get("scaled"), int(16));
+    assert_eq!(get("nested"), int(10));
+    assert_eq!(get("counter"), int(2));
+    assert_eq!(get("shadowed"), int(100));
+    assert_eq!(get("a"), ints(&[1, 2]));
+    assert!(Value::ptr_eq(&get("a"), &get("b")));
+    assert_eq!(get("not_and"), py_val::new(py_val::True));
+    assert_eq!(get("either"), py_val::new(py_val::True));
+    assert_eq!(
+        get("items"),
+        py_val::new_list(vec![
+            tuple(vec![string("x"), int(1)]),
+            tuple(vec![string("y"), int(2)])
+        ])
+    );
+    assert_eq!(get("neg"), int(-3));
+    // functions defined by the VM are compiled once
+    assert_eq!(get("fact").type_name(), "function");
+}
+
+fn function(ast: &AST, name: &str) -> Code {
+    for stmt in ast {
+        if let ASTStmtKind::FuncDef(n, args, body, _) = &stmt.node {
+            if n == name {
+                return compile_function(n, args, body, None).unwrap();
+            }
+        }
+    }
+    panic!("{} is not defined", name)
+}
+
+#[test]
+fn test_compiler() {
+    let ast = Parser::new("testcase/vm.py").unwrap().parse();
+
+    // parameters first, then assigned names
+    let code = function(&ast, "count_odd");
+    assert_eq!(code.arg_names, vec!["limit"]);
+    assert_eq!(code.local_names, vec!["limit", "found", "i"]);
+    assert!(code.instrs.contains(&Instr::StoreLocal(2)));
+    assert!(code.instrs.contains(&Instr::LoadLocal(0)));
+    // constants are pooled
+    assert_eq!(code.consts.iter().filter(|c| **c == int(0)).count(), 1);
+    // attribute names share the name pool with globals
+    assert_eq!(code.names, vec!["append"]);
+
+    // `global` names are not local
+    let code = function(&ast, "bump");
+    assert!(code.local_names.is_empty());
+    assert_eq!(code.names, vec!["counter"]);
+    assert!(code.instrs.contains(&Instr::StoreGlobal(0)));
+
+    let code = function(&ast, "shadow");
+    assert_eq!(code.local_names, vec!["counter"]);
+
+    // nested functions are compiled with their parent and bound to a local
+    let code = function(&ast, "outer");
+    assert_eq!(code.local_names, vec!["x", "inner"]);
+    assert_eq!(code.codes.len(), 1);
+    assert_eq!(code.codes[0].local_names, vec!["y"]);
+
+    assert_eq!(
+        compile_module(&ast).unwrap().local_names,
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn test_compare_chain() {
+    let source = "chain_true = 1 == 1 == 1\nchain_false = 2 == 2 == 3\nchain_short = 1 == 2 == 2\n";
+    let ast = Parser::from_source("<string>", source.to_string()).parse();
+    let mut evaluator = evaluator("testcase/empty.txt", Engine::Vm);
+    evaluator.run_code(&compile_module(&ast).unwrap(), Vec::new());
+
+    assert_eq!(
+        evaluator.get_global("chain_true").unwrap(),
+        py_val::new(py_val::True)
+    );
+    assert_eq!(
+        evaluator.get_global("chain_false").unwrap(),
+        py_val::new(py_val::False)
+    );
+    assert_eq!(
+        evaluator.get_global("chain_short").unwrap(),
+        py_val::new(py_val::False)
+    );
+}
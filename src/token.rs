@@ -1,6 +1,20 @@
 //! # token
 //! 字句解析後のトークン
 
+use crate::ast::{Located, Span};
+use std::fmt;
+
+// token with the span it was read from
+pub type LocatedToken = Located<Token>;
+
+// comment, kept apart from the tokens for the formatter
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Comment {
+    pub text: String, // from '#' to the end of the line
+    pub span: Span,
+    pub own_line: bool, // nothing but spaces before it in the line
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Token {
     EQ,             // =
@@ -30,8 +44,9 @@ pub enum Token {
     PERIOD,         // .
     COMMA,          // ,
     COLON,          // :
+    ARROW,          // ->
     NEWLINE,        // \n
-    INT(i64),     // integer literal
+    INT(i64),       // integer literal
     FLOAT(f64),     // floating point number literal
     STRING(String), // str literal
     ID(String),     // identifier
@@ -47,11 +62,15 @@ pub enum Token {
     RETURN,         // return
     DEL,            // del
     GLOBAL,         // global
+    IMPORT,         // import
+    FROM,           // from
+    AS,             // as
     IF,             // if
     ELIF,           // elif
     ELSE,           // else
     FOR,            // for
     WHILE,          // while
+    WITH,           // with
     DEF,            // def
     TRUE,           // True
     FALSE,          // False
@@ -59,4 +78,76 @@ pub enum Token {
     INDENT,         // indent
     DEDENT,         // dedent
     EMPTY,          // for initial
+    ERROR,          // after a lexical error, which the tokenizer has recorded
+}
+
+// how the token is shown in syntax errors
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Token::EQ => "'='",
+            Token::EQEQ => "'=='",
+            Token::NEQ => "'!='",
+            Token::GT => "'>'",
+            Token::GEQ => "'>='",
+            Token::RSHIFT => "'>>'",
+            Token::LT => "'<'",
+            Token::LEQ => "'<='",
+            Token::LSHIFT => "'<<'",
+            Token::PLUS => "'+'",
+            Token::MINUS => "'-'",
+            Token::MUL => "'*'",
+            Token::DIV => "'/'",
+            Token::MOD => "'%'",
+            Token::TILDE => "'~'",
+            Token::XOR => "'^'",
+            Token::AMP => "'&'",
+            Token::BAR => "'|'",
+            Token::LPAREN => "'('",
+            Token::RPAREN => "')'",
+            Token::LBRACE => "'{'",
+            Token::RBRACE => "'}'",
+            Token::LBRACKET => "'['",
+            Token::RBRACKET => "']'",
+            Token::PERIOD => "'.'",
+            Token::COMMA => "','",
+            Token::COLON => "':'",
+            Token::ARROW => "'->'",
+            Token::NEWLINE => "newline",
+            Token::INT(_) => "integer",
+            Token::FLOAT(_) => "number",
+            Token::STRING(_) => "string",
+            Token::ID(_) => "identifier",
+            Token::OR => "'or'",
+            Token::AND => "'and'",
+            Token::NOT => "'not'",
+            Token::IS => "'is'",
+            Token::IN => "'in'",
+            Token::NONE => "'None'",
+            Token::BREAK => "'break'",
+            Token::CONTINUE => "'continue'",
+            Token::PASS => "'pass'",
+            Token::RETURN => "'return'",
+            Token::DEL => "'del'",
+            Token::GLOBAL => "'global'",
+            Token::IMPORT => "'import'",
+            Token::FROM => "'from'",
+            Token::AS => "'as'",
+            Token::IF => "'if'",
+            Token::ELIF => "'elif'",
+            Token::ELSE => "'else'",
+            Token::FOR => "'for'",
+            Token::WHILE => "'while'",
+            Token::WITH => "'with'",
+            Token::DEF => "'def'",
+            Token::TRUE => "'True'",
+            Token::FALSE => "'False'",
+            Token::EOF => "end of file",
+            Token::INDENT => "indent",
+            Token::DEDENT => "dedent",
+            Token::EMPTY => "nothing",
+            Token::ERROR => "invalid token",
+        };
+        write!(f, "{}", s)
+    }
 }
@@ -1,42 +1,69 @@
+use crate::ast::{Located, Span};
 use crate::char_stream::CharStream;
-use crate::token::Token;
+use crate::parser::SyntaxError;
+use crate::token::{Comment, LocatedToken, Token};
 
 // 字句解析器
 pub struct Tokenizer {
-    current_token: Token,
+    current_token: LocatedToken,
     indent_stack: Vec<usize>,
     leading_space: usize,
     char_stream: CharStream,
     token_buf: String,
+    // comments read so far, for the formatter
+    comments: Vec<Comment>,
+    // lexical errors not taken by the parser yet
+    errors: Vec<SyntaxError>,
 }
 
 impl Tokenizer {
     pub fn new(file_name: &str) -> std::io::Result<Tokenizer> {
+        Ok(Tokenizer::with_char_stream(CharStream::new(file_name)?))
+    }
+
+    pub fn from_source(file_name: &str, source: String) -> Tokenizer {
+        Tokenizer::with_char_stream(CharStream::from_source(file_name, source))
+    }
+
+    fn with_char_stream(mut char_stream: CharStream) -> Tokenizer {
         // 最初の1文字
-        let mut char_stream = CharStream::new(file_name)?;
         char_stream.next_char();
 
-        Ok(Tokenizer {
-            current_token: Token::EMPTY,
+        Tokenizer {
+            current_token: Token::EMPTY.into(),
             indent_stack: vec![0],
             leading_space: 0,
-            char_stream: char_stream,
+            char_stream,
             token_buf: String::new(),
-        })
+            comments: Vec::new(),
+            errors: Vec::new(),
+        }
     }
 
     pub fn tokenize(&mut self) {
         loop {
             self.next_token();
-            println!("{:?}", self.current_token);
-            if self.current_token == Token::EOF {
+            // as `python -m tokenize`
+            let span = self.current_token.span;
+            println!(
+                "{},{}-{},{}:\t{:?}",
+                span.line, span.column, span.end_line, span.end_column, self.current_token.node
+            );
+            if self.current_token.node == Token::EOF {
                 break;
             }
         }
+        let errors = self.take_errors();
+        for error in &errors {
+            eprint!("{}", error.format(self.get_file_name()));
+        }
+        if !errors.is_empty() {
+            std::process::exit(1);
+        }
     }
 
     pub fn get_current_token(&self) -> &Token {
-        &self.current_token
+        &self.current_token.node
     }
 
     pub fn get_file_name(&self) -> &String {
@@ -63,34 +90,70 @@ impl Tokenizer {
         self.char_stream.get_current_line_content()
     }
 
+    pub fn get_current_located_token(&self) -> &LocatedToken {
+        &self.current_token
+    }
+
+    pub fn get_current_span(&self) -> Span {
+        self.current_token.span
+    }
+
+    // comments read so far(they are not tokens)
+    pub fn take_comments(&mut self) -> Vec<Comment> {
+        std::mem::take(&mut self.comments)
+    }
+
+    // lexical errors found so far. the tokens are ERROR where they were
+    pub fn take_errors(&mut self) -> Vec<SyntaxError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    // (line, column) of the current char. columns are 0-based as in ast.rs
+    fn position(&self) -> (usize, usize) {
+        let line = self.char_stream.get_current_line().max(1);
+        match self.char_stream.get_current_char() {
+            Some(_) => (line, self.char_stream.get_current_column() - 1),
+            // EOF: just after the last char
+            None => (line, self.char_stream.get_current_column()),
+        }
+    }
+
     pub fn next_token(&mut self) {
         self.token_buf.clear(); // clear token buffer
 
         self.skip_space();
+        let start = self.position();
+        let token = self.read_token();
+        // indent/dedent are empty. NEWLINE ends with its line
+        let end = match token {
+            Token::NEWLINE => (start.0, start.1 + 1),
+            _ => self.position(),
+        };
+        self.current_token = Located::new(token, Span::new(start, end));
+    }
 
+    fn read_token(&mut self) -> Token {
         // 必要があればindent/dedentを生成
         if let Some(last) = self.indent_stack.last() {
             if self.leading_space > *last {
                 // indent
                 self.indent_stack.push(self.leading_space);
-                self.current_token = Token::INDENT;
-                return;
+                return Token::INDENT;
             } else if self.leading_space < *last {
                 // dedent
                 self.indent_stack.pop();
-                if let Some(last) = self.indent_stack.last() {
-                    if self.leading_space > *last {
-                        self.error(format!(
-                            "IndentationError: unindent does not match any outer indentation level"
-                        ));
+                if let Some(&last) = self.indent_stack.last() {
+                    if self.leading_space > last {
+                        // the line is read as if it were at the outer level
+                        self.record_error("unindent does not match any outer indentation level");
+                        self.leading_space = last;
                     }
                 }
-                self.current_token = Token::DEDENT;
-                return;
+                return Token::DEDENT;
             }
         }
 
-        self.current_token = match self.char_stream.get_current_char() {
+        match self.char_stream.get_current_char() {
             None => Token::EOF,
             Some('\n') => {
                 // NEWLINE
@@ -113,7 +176,7 @@ impl Tokenizer {
                 // !=
                 self.char_stream.next_char();
                 if self.char_stream.get_current_char() != Some('=') {
-                    self.error(format!("SyntaxError: unexpected character"))
+                    return self.error("unexpected character");
                 }
                 self.char_stream.next_char();
                 Token::NEQ
@@ -164,7 +227,14 @@ impl Tokenizer {
             }
             Some('-') => {
                 self.char_stream.next_char();
-                Token::MINUS
+                match self.char_stream.get_current_char() {
+                    Some('>') => {
+                        // ->
+                        self.char_stream.next_char();
+                        Token::ARROW
+                    }
+                    _ => Token::MINUS,
+                }
             }
             Some('*') => {
                 self.char_stream.next_char();
@@ -233,7 +303,7 @@ impl Tokenizer {
             Some('0'..='9') => self.tokenize_number(),
             Some('"') => self.tokenize_string(),
             Some(_) => self.tokenize_id_and_keywords(),
-        };
+        }
     }
 
     // space ::= \s* | #.*(?=\n)
@@ -253,20 +323,36 @@ impl Tokenizer {
         // let is_in_leading_space = self.char_stream.get_current_char() == Some('\n');
         let is_in_leading_space = self.char_stream.get_current_column() == 1;
 
-        let mut is_in_comment = false;
-
-        // // 読み進める
-        // self.char_stream.next_char();
+        // コメントはトークンにはしないが、整形用に残しておく
+        let mut comment: Option<((usize, usize), String)> = None;
 
         loop {
             let c = self.char_stream.get_current_char();
 
+            if let Some((start, text)) = &mut comment {
+                if let Some(ch) = c.filter(|&ch| ch != '\n') {
+                    text.push(ch);
+                    self.char_stream.next_char();
+                    continue;
+                }
+                // コメント終了
+                let text = text.trim_end().to_string();
+                let end = (start.0, start.1 + text.chars().count());
+                self.comments.push(Comment {
+                    text,
+                    span: Span::new(*start, end),
+                    own_line: is_in_leading_space,
+                });
+                comment = None;
+            }
+
             if c.is_none() {
                 // EOF。特にすることなし
                 return;
             } else if c == Some('#') {
                 // コメント開始
-                is_in_comment = true;
+                comment = Some((self.position(), String::new()));
+                continue;
             } else if c == Some(' ') {
                 if is_in_leading_space {
                     self.leading_space += 1;
@@ -276,17 +362,15 @@ impl Tokenizer {
                     // スペースとコメントしかない行だった 続行
                     // 行の先頭のスペース数をリセット
                     self.leading_space = 0;
-                    is_in_comment = false;
                 } else {
                     // !is_in_leading_space
                     // スペースとコメント以外になにかを含む行のコメント中
                     return;
                 }
-            } else if !is_in_comment {
+            } else {
                 // 次のトークン
                 return;
             }
-            // else : コメント中で、改行ではない文字
             self.char_stream.next_char();
         }
     }
@@ -311,57 +395,121 @@ impl Tokenizer {
                 self.token_buf.push(d);
                 self.char_stream.next_char();
             }
-            Token::FLOAT(self.token_buf.parse().unwrap())
+            match self.token_buf.parse() {
+                Ok(f) => Token::FLOAT(f),
+                Err(_) => self.error("invalid decimal literal"),
+            }
         }
         // integer
         else {
             if self.token_buf.get(0..1).unwrap() == "0" && self.token_buf.len() > 1 {
-                self.error(format!("SyntaxError: invalid token"))
+                return self.error("invalid token");
+            }
+            // ints are 64 bits
+            match self.token_buf.parse() {
+                Ok(i) => Token::INT(i),
+                Err(_) => self.error("integer literal too large"),
             }
-            Token::INT(self.token_buf.parse().unwrap())
         }
     }
 
-    // string ::= " ([^"\] | \.)* "
+    // string ::= " ([^"\\\n] | escape)* "
     fn tokenize_string(&mut self) -> Token {
-        // 直前の文字が'\'
-        let mut in_espace = false;
-
         loop {
             self.char_stream.next_char();
-            if let Some(c) = self.char_stream.get_current_char() {
-                if c == '\\' && !in_espace {
-                    in_espace = true;
-                } else if c == '"' && !in_espace {
+            match self.char_stream.get_current_char() {
+                Some('"') => {
                     self.char_stream.next_char(); // "を読み捨てる
                     break;
-                } else {
-                    in_espace = false;
                 }
-                self.token_buf.push(c);
-            } else {
-                self.error(format!("SyntaxError: EOL while scanning string literal"));
+                Some('\\') => {
+                    self.char_stream.next_char();
+                    if let Err(error) = self.tokenize_escape() {
+                        return error;
+                    }
+                }
+                Some('\n') | None => return self.error("EOL while scanning string literal"),
+                Some(c) => self.token_buf.push(c),
             }
         }
         Token::STRING(self.token_buf.clone())
     }
 
+    // escape ::= \\ [\\'"abfnrtv0] | \\x hex{2} | \\u hex{4} | \\U hex{8}
+    // 現在の文字は'\\'の次の文字。エスケープの最後の文字まで読み進める
+    // 知らないエスケープは'\\'ごとそのまま残す(CPythonと同じ)
+    // Err is the ERROR token of a broken escape
+    fn tokenize_escape(&mut self) -> Result<(), Token> {
+        let c = match self.char_stream.get_current_char() {
+            Some(c) => c,
+            None => return Err(self.error("EOL while scanning string literal")),
+        };
+        let escaped = match c {
+            // 行継続
+            '\n' => return Ok(()),
+            '\\' | '\'' | '"' => c,
+            'a' => '\x07',
+            'b' => '\x08',
+            'f' => '\x0c',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'v' => '\x0b',
+            '0' => '\0',
+            'x' | 'u' | 'U' => {
+                let digits = match c {
+                    'x' => 2,
+                    'u' => 4,
+                    _ => 8,
+                };
+                let mut code = 0;
+                for _ in 0..digits {
+                    self.char_stream.next_char();
+                    match self
+                        .char_stream
+                        .get_current_char()
+                        .and_then(|d| d.to_digit(16))
+                    {
+                        Some(d) => code = code * 16 + d,
+                        None => return Err(self.error(&format!("truncated \\{} escape", c))),
+                    }
+                }
+                match std::char::from_u32(code) {
+                    Some(escaped) => escaped,
+                    None => {
+                        return Err(self.error(&format!(
+                            "illegal Unicode character \\{}{:0width$x}",
+                            c,
+                            code,
+                            width = digits
+                        )))
+                    }
+                }
+            }
+            _ => {
+                self.token_buf.push('\\');
+                c
+            }
+        };
+        self.token_buf.push(escaped);
+        Ok(())
+    }
+
     // return ::= keyword | identifier
-    // identifier ::= [a-zA-Z_][a-zA-Z_0-9]*
+    // identifier ::= [a-zA-Z_][a-zA-Z_0-9]* (英字・数字はUnicodeのものも含む)
     // 1文字目が数値のときはnumberの解析になっているので考えなくていい
     fn tokenize_id_and_keywords(&mut self) -> Token {
-        loop {
-            match self.char_stream.get_current_char() {
-                Some(c @ '0'..='9') | Some(c @ 'a'..='z') | Some(c @ 'A'..='Z') | Some(c @ '_') => {
-                    self.token_buf.push(c);
-                }
-                _ => {
-                    // next token / EOF
-                    break;
-                }
+        // next token / EOF で終了
+        while let Some(c) = self.char_stream.get_current_char() {
+            if !(c.is_alphanumeric() || c == '_') {
+                break;
             }
+            self.token_buf.push(c);
             self.char_stream.next_char();
         }
+        if self.token_buf.is_empty() {
+            return self.error("invalid character in identifier");
+        }
         match self.token_buf.as_str() {
             "or" => Token::OR,
             "and" => Token::AND,
@@ -375,11 +523,15 @@ impl Tokenizer {
             "return" => Token::RETURN,
             "del" => Token::DEL,
             "global" => Token::GLOBAL,
+            "import" => Token::IMPORT,
+            "from" => Token::FROM,
+            "as" => Token::AS,
             "if" => Token::IF,
             "elif" => Token::ELIF,
             "else" => Token::ELSE,
             "for" => Token::FOR,
             "while" => Token::WHILE,
+            "with" => Token::WITH,
             "def" => Token::DEF,
             "True" => Token::TRUE,
             "False" => Token::FALSE,
@@ -387,15 +539,21 @@ impl Tokenizer {
         }
     }
 
-    fn error(&self, message: String) -> ! {
-        eprintln!(
-            "File {}, line {}\n{}\n{}\n{}",
-            self.char_stream.get_file_name(),
-            self.char_stream.get_current_line(),
-            self.char_stream.get_current_line_content(),
-            " ".repeat(self.char_stream.get_current_line_content().len()),
-            message
-        );
-        std::process::exit(1);
+    // a lexical error at the current char
+    fn record_error(&mut self, message: &str) {
+        let (line, column) = self.position();
+        self.errors.push(SyntaxError::new(
+            message,
+            Span::new((line, column), (line, column + 1)),
+        ));
+    }
+
+    // record the error and skip the rest of the line, where the next token starts again
+    fn error(&mut self, message: &str) -> Token {
+        self.record_error(message);
+        while !matches!(self.char_stream.get_current_char(), Some('\n') | None) {
+            self.char_stream.next_char();
+        }
+        Token::ERROR
     }
 }
@@ -0,0 +1,44 @@
+//! # traceback
+//! エラー位置の表示(CPythonのtraceback形式)
+//! source lines are read again from the file when an error is reported, like linecache
+
+use crate::ast::Span;
+
+// line(1-based) of the file, without the newline
+pub fn source_line(file_name: &str, line: usize) -> Option<String> {
+    let source = std::fs::read_to_string(file_name).ok()?;
+    source
+        .lines()
+        .nth(line.checked_sub(1)?)
+        .map(|s| s.to_string())
+}
+
+// `  File "...", line N, in f` followed by the source line and carets under the span.
+// function is None for syntax errors
+pub fn format_frame(file_name: &str, span: Span, function: Option<&str>) -> String {
+    let mut res = format!("  File \"{}\", line {}", file_name, span.line);
+    if let Some(function) = function {
+        res.push_str(&format!(", in {}", function));
+    }
+    res.push('\n');
+    if let Some(line) = source_line(file_name, span.line) {
+        let code = line.trim_start();
+        let indent = line.chars().count() - code.chars().count();
+        let code = code.trim_end();
+        let len = code.chars().count();
+        // columns in the stripped line. spans over several lines are cut at the end of the first
+        let start = span.column.saturating_sub(indent).min(len);
+        let end = if span.end_line == span.line {
+            span.end_column.saturating_sub(indent).min(len)
+        } else {
+            len
+        };
+        res.push_str(&format!(
+            "    {}\n    {}{}\n",
+            code,
+            " ".repeat(start),
+            "^".repeat(end.saturating_sub(start).max(1))
+        ));
+    }
+    res
+}
@@ -0,0 +1,523 @@
+//! # unparse
+//! ASTからソースコードを再生成する(CPythonのast.unparse)
+//! parentheses are added only where the precedence of python-grammar.ebnf requires them
+
+use std::collections::VecDeque;
+
+use crate::ast::*;
+use crate::token::Comment;
+
+// 優先順位(低い順)
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum Prec {
+    Tuple, // testlist: a bare tuple
+    Test,  // test: `a if b else c`
+    Or,
+    And,
+    Not,
+    Cmp,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
+    Arith,
+    Term,
+    Factor, // unary operators
+    Atom,   // atom_expr: calls, subscripts and attributes
+}
+
+impl Prec {
+    // the next higher precedence
+    fn next(self) -> Prec {
+        use Prec::*;
+        match self {
+            Tuple => Test,
+            Test => Or,
+            Or => And,
+            And => Not,
+            Not => Cmp,
+            Cmp => BitOr,
+            BitOr => BitXor,
+            BitXor => BitAnd,
+            BitAnd => Shift,
+            Shift => Arith,
+            Arith => Term,
+            Term => Factor,
+            Factor | Atom => Atom,
+        }
+    }
+}
+
+/// source code of the module
+pub fn unparse(ast: &AST) -> String {
+    let mut unparser = Unparser::new(Vec::new(), false);
+    unparser.module(ast);
+    unparser.res
+}
+
+/// source code of the module with the comments, keeping blank lines of the source(for fmt).
+/// top-level functions are surrounded by 2 blank lines as PEP 8
+pub fn unparse_with_comments(ast: &AST, comments: Vec<Comment>) -> String {
+    let mut unparser = Unparser::new(comments, true);
+    unparser.module(ast);
+    unparser.res
+}
+
+/// source code of the expression
+pub fn unparse_expr(expr: &ASTExpr) -> String {
+    self::expr(expr, Prec::Tuple)
+}
+
+struct Unparser {
+    res: String,
+    // comments not written yet, in order
+    comments: VecDeque<Comment>,
+    // keep blank lines of the source
+    layout: bool,
+    // last source line written
+    last_line: usize,
+}
+
+impl Unparser {
+    fn new(comments: Vec<Comment>, layout: bool) -> Unparser {
+        Unparser {
+            res: String::new(),
+            comments: comments.into(),
+            layout,
+            last_line: 0,
+        }
+    }
+
+    fn module(&mut self, ast: &AST) {
+        let is_def = |stmt: &ASTStmt| matches!(stmt.node, ASTStmtKind::FuncDef(..));
+        for (i, stmt) in ast.iter().enumerate() {
+            if i > 0 {
+                let blank_lines = if !self.layout {
+                    // functions are separated by a blank line
+                    is_def(stmt) as usize
+                } else if is_def(stmt) || is_def(&ast[i - 1]) {
+                    2
+                } else {
+                    (self.first_line(stmt.span.line) > self.last_line + 1) as usize
+                };
+                self.res.push_str(&"\n".repeat(blank_lines));
+            }
+            self.leading_comments(stmt.span.line, 0);
+            let next_line = ast.get(i + 1).map_or(usize::MAX, |next| next.span.line);
+            self.stmt(stmt, 0, next_line);
+        }
+        // comments at the end of the file
+        if let Some(comment) = self.comments.front() {
+            self.gap(comment.span.line);
+        }
+        self.leading_comments(usize::MAX, 0);
+    }
+
+    // next_line is the line of the next statement, where comments of the blocks end
+    fn stmt(&mut self, stmt: &ASTStmt, level: usize, next_line: usize) {
+        use ASTStmtKind::*;
+        let line = stmt.span.line;
+        let text = match &stmt.node {
+            Init | End => return,
+            // there is nothing to regenerate
+            Error => "pass  # syntax error".to_string(),
+            FuncDef(name, args, body, returns) => {
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|arg| match &arg.node {
+                        (name, Some(annotation)) => {
+                            format!("{}: {}", name, expr(annotation, Prec::Test))
+                        }
+                        (name, None) => name.clone(),
+                    })
+                    .collect();
+                let returns = match returns {
+                    Some(returns) => format!(" -> {}", expr(returns, Prec::Test)),
+                    None => String::new(),
+                };
+                let header = format!("def {}({}){}:", name, args.join(", "), returns);
+                self.write_line(level, &header, line);
+                self.suite(body, level, next_line);
+                return;
+            }
+            Return(None) => "return".to_string(),
+            Return(Some(value)) => format!("return {}", expr(value, Prec::Tuple)),
+            // targets of del are exprlist
+            Delete(targets) => format!("del {}", exprs(targets, Prec::BitOr)),
+            Assign(targets, value) => {
+                let mut parts: Vec<String> = targets.iter().map(|t| expr(t, Prec::Tuple)).collect();
+                parts.push(expr(value, Prec::Tuple));
+                parts.join(" = ")
+            }
+            AnnAssign(target, annotation, value) => {
+                let mut text = format!(
+                    "{}: {}",
+                    expr(target, Prec::Test),
+                    expr(annotation, Prec::Test)
+                );
+                if let Some(value) = value {
+                    text.push_str(&format!(" = {}", expr(value, Prec::Tuple)));
+                }
+                text
+            }
+            For(target, iter, body) => {
+                // for target is exprlist: elements of a bare tuple are expr
+                let target = match &target.node {
+                    ASTExprKind::Tuple(elts) if elts.len() > 1 => exprs(elts, Prec::BitOr),
+                    _ => expr(target, Prec::BitOr),
+                };
+                let header = format!("for {} in {}:", target, expr(iter, Prec::Tuple));
+                self.write_line(level, &header, line);
+                self.suite(body, level, next_line);
+                return;
+            }
+            While(test, body) => {
+                self.write_line(level, &format!("while {}:", expr(test, Prec::Test)), line);
+                self.suite(body, level, next_line);
+                return;
+            }
+            With(items, body) => {
+                // the target of `as` is expr
+                let items: Vec<String> = items
+                    .iter()
+                    .map(|item| match &item.node {
+                        (context_expr, Some(target)) => format!(
+                            "{} as {}",
+                            expr(context_expr, Prec::Test),
+                            expr(target, Prec::BitOr)
+                        ),
+                        (context_expr, None) => expr(context_expr, Prec::Test),
+                    })
+                    .collect();
+                self.write_line(level, &format!("with {}:", items.join(", ")), line);
+                self.suite(body, level, next_line);
+                return;
+            }
+            If(test, body, orelse) => {
+                self.write_line(level, &format!("if {}:", expr(test, Prec::Test)), line);
+                let mut orelse = orelse;
+                self.suite(
+                    body,
+                    level,
+                    orelse.first().map_or(next_line, |s| s.span.line),
+                );
+                // else: if ...: は elif にする(パーサーはelifを分解している)
+                while let [elif @ ASTStmt {
+                    node: If(test, body, next),
+                    ..
+                }] = orelse.as_slice()
+                {
+                    self.leading_comments(elif.span.line, level);
+                    let header = format!("elif {}:", expr(test, Prec::Test));
+                    self.write_line(level, &header, elif.span.line);
+                    self.suite(body, level, next.first().map_or(next_line, |s| s.span.line));
+                    orelse = next;
+                }
+                if !orelse.is_empty() {
+                    // `else` has no node, it is assumed to be just before the block
+                    let else_line = orelse[0].span.line.saturating_sub(1);
+                    self.leading_comments(else_line, level);
+                    self.write_line(level, "else:", else_line);
+                    self.suite(orelse, level, next_line);
+                }
+                return;
+            }
+            Global(names) => format!("global {}", names.join(", ")),
+            Import(names) => format!("import {}", aliases(names)),
+            ImportFrom(module, names) => format!("from {} import {}", module, aliases(names)),
+            Expr(value) => expr(value, Prec::Tuple),
+            Pass => "pass".to_string(),
+            Break => "break".to_string(),
+            Continue => "continue".to_string(),
+        };
+        self.write_line(level, &text, stmt.span.end_line);
+    }
+
+    fn suite(&mut self, body: &[ASTStmt], level: usize, next_line: usize) {
+        if body.is_empty() {
+            self.write_line(level + 1, "pass", self.last_line);
+        }
+        for (i, stmt) in body.iter().enumerate() {
+            if i > 0 {
+                self.gap(self.first_line(stmt.span.line));
+            }
+            self.leading_comments(stmt.span.line, level + 1);
+            let next = body.get(i + 1).map_or(next_line, |next| next.span.line);
+            self.stmt(stmt, level + 1, next);
+        }
+        // comments after the last statement, indented as the block
+        if let Some(first) = body.first() {
+            while let Some(comment) = self.comments.front() {
+                if comment.span.line >= next_line || comment.span.column < first.span.column {
+                    break;
+                }
+                let comment = self.comments.pop_front().unwrap();
+                self.gap(comment.span.line);
+                self.write_line(level + 1, &comment.text, comment.span.line);
+            }
+        }
+    }
+
+    // line where the statement starts, including the comments just before it
+    fn first_line(&self, line: usize) -> usize {
+        match self.comments.front() {
+            Some(comment) if comment.span.line < line => comment.span.line,
+            _ => line,
+        }
+    }
+
+    // comments on their own lines before the line
+    fn leading_comments(&mut self, line: usize, level: usize) {
+        let mut written = false;
+        while let Some(comment) = self.comments.front() {
+            if comment.span.line >= line {
+                break;
+            }
+            let comment = self.comments.pop_front().unwrap();
+            if written {
+                self.gap(comment.span.line);
+            }
+            self.write_line(level, &comment.text, comment.span.line);
+            written = true;
+        }
+        if written {
+            self.gap(line);
+        }
+    }
+
+    // a blank line for blank lines of the source before the line
+    fn gap(&mut self, line: usize) {
+        if self.layout && !self.res.is_empty() && line != usize::MAX && line > self.last_line + 1 {
+            self.res.push('\n');
+        }
+    }
+
+    // line is the source line of the text, a comment at the end of it follows the text
+    fn write_line(&mut self, level: usize, text: &str, line: usize) {
+        self.res.push_str(&"    ".repeat(level));
+        self.res.push_str(text);
+        if let Some(comment) = self.comments.front() {
+            if !comment.own_line && comment.span.line <= line {
+                self.res.push_str("  ");
+                self.res.push_str(&comment.text);
+                self.comments.pop_front();
+            }
+        }
+        self.res.push('\n');
+        self.last_line = self.last_line.max(line);
+    }
+}
+
+// comma separated expressions
+// `a.b as c, d`
+fn aliases(names: &[ASTAlias]) -> String {
+    names
+        .iter()
+        .map(|alias| match &alias.node {
+            (name, Some(asname)) => format!("{} as {}", name, asname),
+            (name, None) => name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn exprs(exprs: &[ASTExpr], prec: Prec) -> String {
+    exprs
+        .iter()
+        .map(|e| expr(e, prec))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// the expression, parenthesized if its precedence is lower than prec
+fn expr(expr_: &ASTExpr, prec: Prec) -> String {
+    use ASTExprKind::*;
+    let (own, s) = match &expr_.node {
+        BoolOp(op, values) => {
+            let (own, op) = match op {
+                ASTBoolOp::Or => (Prec::Or, " or "),
+                ASTBoolOp::And => (Prec::And, " and "),
+            };
+            let values: Vec<String> = values.iter().map(|v| expr(v, own.next())).collect();
+            (own, values.join(op))
+        }
+        // 二項演算子は左結合なので、左の項は同じ優先順位のまま、右の項だけ括弧で囲む
+        // (a right-associative `**` would be the other way around, but there is none)
+        BinOp(left, op, right) => {
+            let (own, op) = match op {
+                ASTOperator::BitOr => (Prec::BitOr, "|"),
+                ASTOperator::BitXor => (Prec::BitXor, "^"),
+                ASTOperator::BitAnd => (Prec::BitAnd, "&"),
+                ASTOperator::LShift => (Prec::Shift, "<<"),
+                ASTOperator::RShift => (Prec::Shift, ">>"),
+                ASTOperator::Add => (Prec::Arith, "+"),
+                ASTOperator::Sub => (Prec::Arith, "-"),
+                ASTOperator::Mul => (Prec::Term, "*"),
+                ASTOperator::Div => (Prec::Term, "/"),
+                ASTOperator::Mod => (Prec::Term, "%"),
+            };
+            (
+                own,
+                format!(
+                    "{} {} {}",
+                    expr(left, own),
+                    op,
+                    expr(right, own.next())
+                ),
+            )
+        }
+        UnaryOp(ASTUnaryOp::Not, operand) => {
+            (Prec::Not, format!("not {}", expr(operand, Prec::Not)))
+        }
+        UnaryOp(op, operand) => {
+            let op = match op {
+                ASTUnaryOp::Invert => "~",
+                ASTUnaryOp::UAdd => "+",
+                ASTUnaryOp::USub => "-",
+                ASTUnaryOp::Not => unreachable!(),
+            };
+            (
+                Prec::Factor,
+                format!("{}{}", op, expr(operand, Prec::Factor)),
+            )
+        }
+        IfExp(test, body, orelse) => (
+            Prec::Test,
+            format!(
+                "{} if {} else {}",
+                expr(body, Prec::Or),
+                expr(test, Prec::Or),
+                expr(orelse, Prec::Test)
+            ),
+        ),
+        Dict(keys, values) => {
+            let items: Vec<String> = keys
+                .iter()
+                .zip(values)
+                .map(|(k, v)| format!("{}: {}", expr(k, Prec::Test), expr(v, Prec::Test)))
+                .collect();
+            (Prec::Atom, format!("{{{}}}", items.join(", ")))
+        }
+        // `{}` is a dict
+        Set(elts) if elts.is_empty() => (Prec::Atom, "set()".to_string()),
+        Set(elts) => (Prec::Atom, format!("{{{}}}", exprs(elts, Prec::Test))),
+        Compare(left, ops, comparators) => {
+            let mut s = expr(left, Prec::BitOr);
+            for (op, comparator) in ops.iter().zip(comparators) {
+                let op = match op {
+                    ASTCmpOp::Eq => "==",
+                    ASTCmpOp::NotEq => "!=",
+                    ASTCmpOp::Lt => "<",
+                    ASTCmpOp::LtE => "<=",
+                    ASTCmpOp::Gt => ">",
+                    ASTCmpOp::GtE => ">=",
+                    ASTCmpOp::Is => "is",
+                    ASTCmpOp::IsNot => "is not",
+                    ASTCmpOp::In => "in",
+                    ASTCmpOp::NotIn => "not in",
+                };
+                s.push_str(&format!(" {} {}", op, expr(comparator, Prec::BitOr)));
+            }
+            (Prec::Cmp, s)
+        }
+        Call(func, args, keywords) => {
+            let mut items: Vec<String> = args.iter().map(|a| expr(a, Prec::Test)).collect();
+            items.extend(keywords.iter().map(|keyword| {
+                let (name, value) = &keyword.node;
+                format!("{}={}", name, expr(value, Prec::Test))
+            }));
+            (
+                Prec::Atom,
+                format!("{}({})", expr(func, Prec::Atom), items.join(", ")),
+            )
+        }
+        Constant(value) => constant(value),
+        // `1.real` would be read as a float
+        Attribute(value, attr) => match value.node {
+            Constant(ASTConstant::Int(_)) => (
+                Prec::Atom,
+                format!("({}).{}", expr(value, Prec::Tuple), attr),
+            ),
+            _ => (Prec::Atom, format!("{}.{}", expr(value, Prec::Atom), attr)),
+        },
+        Subscript(value, slice) => {
+            let slice = match &slice.node {
+                // x[a, b] の添字はタプル
+                ASTSliceKind::Index(index) => match &index.node {
+                    Tuple(elts) if elts.len() > 1 => exprs(elts, Prec::Test),
+                    _ => expr(index, Prec::Test),
+                },
+                ASTSliceKind::Slice(lower, upper, step) => {
+                    let part = |e: &Option<Box<ASTExpr>>| {
+                        e.as_ref().map_or(String::new(), |e| expr(e, Prec::Test))
+                    };
+                    match step {
+                        Some(_) => format!("{}:{}:{}", part(lower), part(upper), part(step)),
+                        None => format!("{}:{}", part(lower), part(upper)),
+                    }
+                }
+            };
+            (
+                Prec::Atom,
+                format!("{}[{}]", expr(value, Prec::Atom), slice),
+            )
+        }
+        Name(id) => (Prec::Atom, id.clone()),
+        List(elts) => (Prec::Atom, format!("[{}]", exprs(elts, Prec::Test))),
+        Tuple(elts) if elts.len() == 1 => {
+            (Prec::Atom, format!("({},)", expr(&elts[0], Prec::Test)))
+        }
+        Tuple(elts) if elts.is_empty() => (Prec::Atom, "()".to_string()),
+        Tuple(elts) => (Prec::Tuple, exprs(elts, Prec::Test)),
+    };
+    if own < prec {
+        format!("({})", s)
+    } else {
+        s
+    }
+}
+
+fn constant(value: &ASTConstant) -> (Prec, String) {
+    match value {
+        // a negative number is a unary minus for the parser
+        ASTConstant::Int(i) if *i < 0 => (Prec::Factor, i.to_string()),
+        ASTConstant::Int(i) => (Prec::Atom, i.to_string()),
+        ASTConstant::Float(f) => {
+            // the tokenizer has no exponent form, so always write all digits
+            let mut s = f.to_string();
+            if !s.contains('.') {
+                s.push_str(".0");
+            }
+            let prec = if f.into_inner() < 0.0 {
+                Prec::Factor
+            } else {
+                Prec::Atom
+            };
+            (prec, s)
+        }
+        ASTConstant::String(s) => (Prec::Atom, string(s)),
+        ASTConstant::None => (Prec::Atom, "None".to_string()),
+        ASTConstant::True => (Prec::Atom, "True".to_string()),
+        ASTConstant::False => (Prec::Atom, "False".to_string()),
+    }
+}
+
+// string literal. the tokenizer only reads "..."
+fn string(s: &str) -> String {
+    let mut res = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '\\' => res.push_str("\\\\"),
+            '"' => res.push_str("\\\""),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 || c as u32 == 0x7f => {
+                res.push_str(&format!("\\x{:02x}", c as u32))
+            }
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
@@ -0,0 +1,22 @@
+# annotations are checked by `minipython check` and ignored at runtime
+def area(w: int, h: int) -> int:
+    return w * h
+
+
+def average(xs: list[float]) -> float:
+    total = 0.0
+    for x in xs:
+        total = total + x
+    return total / len(xs)
+
+
+def describe(name: str, counts: dict[str, int]) -> tuple[str, int]:
+    return name, counts.get(name, 0)
+
+
+size: int = area(3, 4)
+mean: float = average([1.0, 2.0, 4.5])
+summary = describe("a", {"a": 2})
+pending: list[str]
+table = {"a": [1]}
+table["b"]: list[int] = [2, 3]
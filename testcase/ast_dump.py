@@ -0,0 +1,42 @@
+def scale(value, factor):
+    if value is not None and factor > 0:
+        return value * factor
+    elif not value:
+        return
+    else:
+        return -value
+
+
+total = 0
+for i in range(10):
+    total = total + scale(i, factor=2.5)
+while total >= 100:
+    total = total % 7
+pairs = {"a": [1, 2], "b": (3,)}
+names = {"x", "y"}
+first = pairs["a"][0:1]
+last = pairs["a"][1]
+a, b = 1, 2
+pairs["c"] = []
+del names
+global total
+print(total, pairs.get("b"), 1 << 3, ~5 & 3 | 2 ^ 1, "tab\there", 1.5, 0.0001)
+x = 1 if total else 0.5
+
+
+def area(w: int, h: list[float]) -> dict[str, tuple[int, float]]:
+    pass
+
+
+count: int = 0
+limit: float
+pairs["d"]: list = []
+import os.path as osp, sys
+from collections import OrderedDict as OD, deque
+from typing import (List, Dict,)
+with open("a.txt") as f, lock:
+    data = f.read()
+q = 10 - 3 - 2
+r = 100 / 10 / 2 * 3 % 4
+s = 1 << 2 >> 1 | 2 | 4 & 5 & 6 ^ 7 ^ 8
+p = x < y == True
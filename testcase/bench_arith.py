@@ -0,0 +1,13 @@
+fizz = 0
+buzz = 0
+fizzbuzz = 0
+total = 0
+for i in range(3000):
+    if i % 15 == 0:
+        fizzbuzz = fizzbuzz + 1
+    elif i % 5 == 0:
+        fizz = fizz + 1
+    elif i % 3 == 0:
+        buzz = buzz + 1
+    else:
+        total = total + i
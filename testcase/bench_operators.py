@@ -0,0 +1,14 @@
+total = 0.0
+hits = 0
+word = ""
+pairs = []
+for i in range(2000):
+    x = i * 0.5
+    total = total + x / 3
+    if x >= 10 and x < 500:
+        hits = hits + 1
+    if i % 100 == 0:
+        word = word + "ab"
+        pairs = pairs + [(i, -i)]
+    if (i, 0) < (5, 1) or "abab" in word:
+        hits = hits - 1
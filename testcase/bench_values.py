@@ -0,0 +1,6 @@
+xs = range(20000)
+ys = sorted(xs, reverse=True)
+pairs = zip(xs, ys)
+words = "a bb ccc dddd eeeee ffffff ggggggg hhhhhhhh".split() * 500
+lengths = map(len, words)
+longest = max(lengths)
@@ -0,0 +1,45 @@
+def neg(x):
+    return -x
+
+def is_odd(x):
+    return x % 2
+
+def add(x, y):
+    return x + y
+
+words = ["pear", "fig", "apple", "kiwi"]
+
+len_str = len("hello")
+len_list = len([1, 2, 3])
+len_tuple = len((1, 2))
+abs_int = abs(-3)
+abs_float = abs(-2.5)
+min_args = min(3, 1, 2)
+min_iter = min([4, 2, 8])
+min_key = min(words, key=len)
+min_default = min([], default=7)
+max_args = max(3, 1, 2)
+max_key = max([1, 5, 3], key=neg)
+sum_ints = sum([1, 2, 3])
+sum_start = sum([1, 2, 3], 10)
+sum_floats = sum([0.5, 1.5], start=1)
+sorted_ints = sorted([3, 1, 2])
+sorted_reverse = sorted([3, 1, 2], reverse=True)
+sorted_key = sorted(words, key=len)
+sorted_stable = sorted(words, key=len, reverse=True)
+reversed_list = reversed([1, 2, 3])
+reversed_str = reversed("abc")
+enumerated = enumerate(["a", "b"], start=1)
+zipped = zip([1, 2, 3], "ab")
+mapped = map(neg, [1, 2])
+mapped2 = map(add, [1, 2], [10, 20, 30])
+filtered = filter(is_odd, range(6))
+filtered_none = filter(None, [0, 1, "", "x"])
+any_true = any([0, "", 3])
+any_empty = any([])
+all_true = all([1, "a", [0]])
+all_false = all([1, 0])
+range_1 = range(3)
+range_2 = range(2, 5)
+range_3 = range(10, 0, -3)
+kw_call = add(y=2, x=1)
@@ -0,0 +1,26 @@
+def area(w: int, h: int) -> int:
+    return w * h
+
+
+def name(n: int) -> str:
+    return n
+
+
+count: int = 0
+count = "many"
+items = [1, 2, 3]
+print(items - 1)
+print(area(2, "3"))
+print(area(2))
+label = "n: " + area(2, 3)
+pair = (1, "a")
+print(pair[1] + 1)
+n = len(5)
+for c in 10:
+    pass
+d = {"a": 1}
+d[1] = 2
+s = "abc".upper().foo()
+nums: list[int] = [1, 2]
+nums[0] = "x"
+ok = area(1, 2) + nums[0] * 1.5
@@ -0,0 +1,14 @@
+def add(a, b):
+    c = a + b
+    return c
+
+
+def main():
+    x = 1
+    y = add(x, 2)
+    print(y)
+    return y
+
+
+total = main()
+print(total + 1)
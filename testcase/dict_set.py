@@ -0,0 +1,48 @@
+d = {"b": 1, "a": 2, 3: "x"}
+d["c"] = 4
+d["b"] = 10
+keys = d.keys()
+values = d.values()
+items = {"x": 1, "y": 2}.items()
+lookup = d[3]
+lookup_float = d[3.0]
+get_missing = d.get("z")
+get_default = d.get("z", 0)
+setdefault_new = d.setdefault("e", 5)
+setdefault_old = d.setdefault("a", 99)
+popped = d.pop("a")
+pop_default = d.pop("a", -1)
+alias = d
+d.update({"f": 6}, g=7)
+d.update([("h", 8)])
+in_dict = "c" in d
+not_in_dict = "a" not in d
+copied = d.copy()
+copied["z"] = 0
+d_len = len(d)
+empty = {}
+tuple_key = {(1, 2): "t"}[(1, 2)]
+merged = {"a": 1, "b": 2} | {"b": 3, "c": 4}
+from_pairs = dict([("k", 1)], v=2)
+dict_eq = {"a": 1, "b": 2} == {"b": 2, "a": 1}
+
+s = {3, 1, 2, 1}
+s.add(5)
+s.add(1)
+s.discard(2)
+s.remove(3)
+in_set = 5 in s
+s_len = len(s)
+ordered = sorted(s)
+union = {1, 2}.union([2, 3], (4,))
+intersection = {1, 2, 3}.intersection({2, 3, 4})
+difference = {1, 2, 3}.difference([1])
+or_set = {1, 2} | {2, 3}
+and_set = {1, 2} & {2, 3}
+sub_set = {1, 2} - {2, 3}
+xor_set = {1, 2} ^ {2, 3}
+from_str = set("abca")
+set_eq = {1, 2} == {2, 1}
+hash_int = hash(42)
+hash_eq = hash(1) == hash(1.0)
+hash_tuple = hash((1, "a")) == hash((1, "a"))
@@ -0,0 +1,14 @@
+# every key has the same hash, and __eq__ says any two values are equal
+def __hash__(val):
+    return 0
+
+def __eq__(lhs, rhs):
+    return True
+
+d = {1: "a", 2: "b"}
+d[3] = "c"
+d_len = len(d)
+second = d[2]
+s = {1, 2}
+s_len = len(s)
+user_eq = 1 == 2
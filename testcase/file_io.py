@@ -0,0 +1,45 @@
+# the path of a temporary file is given as the input
+path = input()
+
+with open(path, "w") as f:
+    written = f.write("first line\n")
+    f.write("ähm, second\n")
+    f.write("last")
+
+f = open(path)
+first = f.readline()
+chunk = f.read(3)
+rest = f.readlines()
+f.close()
+f.close()
+
+lines = []
+for line in open(path, "r"):
+    lines.append(line)
+
+with open(path, "a", encoding="utf-8") as out:
+    out.write("\nappended")
+
+
+def first_line(p):
+    with open(p) as f:
+        return [f, f.readline()]
+
+
+res = first_line(path)
+returned = res[0]
+top = res[1]
+
+count = 0
+for i in range(3):
+    with open(path) as broken:
+        if i == 1:
+            break
+        count = count + 1
+for i in range(2):
+    with open(path) as continued:
+        continue
+
+with open(path) as a, open(path) as b:
+    same = a.read() == b.read()
+whole = b
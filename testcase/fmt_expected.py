@@ -0,0 +1,29 @@
+# header comment
+x = 1 + 2  # trailing
+
+
+def f(a, b):
+    # inside
+    if a > b:
+        return a * (b + 1)
+    elif a == b:  # equal
+        return 0
+    else:
+        return b
+    # end of f
+
+
+# about g
+def g(n):
+    total = 0
+    while n > 0:
+        total = total + n
+        n = n - 1
+        # end of while
+    return total
+
+
+y = f(1, 2)
+
+print(y)
+print(g(3))
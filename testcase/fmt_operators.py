@@ -0,0 +1,7 @@
+a = (10 - 3) - 2
+b = 10-(3-2)
+c = 3>2==True
+d = 3 > (2 == True)
+e = (100/10)/2
+f = 2*(6/3)
+g = 1<<3>>1
@@ -0,0 +1,26 @@
+import gc
+
+# garbage left by the prelude, if any
+gc.collect()
+
+
+def make_cycles(n):
+    for i in range(n):
+        a = [i]
+        a.append(a)
+        d = {"self": None}
+        d["self"] = d
+        t = ([], 1)
+        t[0].append(t)
+        m = []
+        m.append(m.append)
+
+
+gc.disable()
+make_cycles(10)
+collected = gc.collect()
+kept = [1, 2]
+kept.append({"list": kept})
+after = gc.collect()
+enabled = gc.isenabled()
+gc.enable()
@@ -0,0 +1 @@
+from cycle_c import x
@@ -0,0 +1,4 @@
+import cycle_b
+
+value = "a"
+from_b = cycle_b.value
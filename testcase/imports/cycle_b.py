@@ -0,0 +1,5 @@
+import cycle_a
+
+value = "b"
+# cycle_a is partially initialized here, it has not defined value yet
+a_name = cycle_a.__name__
@@ -0,0 +1,3 @@
+from cycle_d import y
+
+x = 1
@@ -0,0 +1,3 @@
+from cycle_c import x
+
+y = 2
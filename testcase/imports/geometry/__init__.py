@@ -0,0 +1 @@
+ORIGIN = (0, 0)
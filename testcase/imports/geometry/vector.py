@@ -0,0 +1,13 @@
+from geometry import ORIGIN
+
+
+def add(a, b):
+    return (a[0] + b[0], a[1] + b[1])
+
+
+def scale(a, k):
+    return (a[0] * k, a[1] * k)
+
+
+def is_origin(a):
+    return a == ORIGIN
@@ -0,0 +1,39 @@
+import shapes
+import geometry.vector
+import geometry.vector as vec
+from geometry import vector, ORIGIN
+from geometry.vector import add as vadd, scale
+import cycle_a
+import extra
+
+# each module has its own globals
+count = 100
+shapes.add_shape("square")
+shapes.add_shape("circle")
+total = shapes.count
+names = shapes.names()
+
+import shapes as shapes_again
+from shapes import count as shapes_count
+
+# a module is run once, and the same object is bound every time
+cached = [shapes_again is shapes, geometry.vector is vec, vec is vector, shapes_count]
+
+moved = vadd(scale((1, 2), 3), ORIGIN)
+at_origin = vector.is_origin((0, 0))
+module_names = [__name__, shapes.__name__, vec.__name__, geometry.__name__]
+from_b = cycle_a.from_b
+a_name_in_b = cycle_a.cycle_b.a_name
+extra_value = extra.VALUE
+
+
+def use_locally():
+    import geometry.vector as v
+    from shapes import names
+    return v.scale((1, 1), 2), names()
+
+
+local_imports = use_locally()
+ran_as_main = False
+if __name__ == "__main__":
+    ran_as_main = True
@@ -0,0 +1,2 @@
+import geometry
+import geometry.nowhere
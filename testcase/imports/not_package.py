@@ -0,0 +1 @@
+import shapes.square
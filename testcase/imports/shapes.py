@@ -0,0 +1,16 @@
+shapes = []
+count = 0
+
+
+def add_shape(name):
+    global count
+    shapes.append(name)
+    count = count + 1
+
+
+def names():
+    return sorted(shapes)
+
+
+if __name__ == "__main__":
+    print("not imported")
@@ -0,0 +1,12 @@
+import sys
+
+name = input("name? ")
+words = input().split()
+first = sys.stdin.readline()
+chunk = sys.stdin.read(3)
+rest = []
+for line in sys.stdin:
+    rest.append(line)
+after = sys.stdin.readline()
+remaining = sys.stdin.read()
+print("hello " + name)
@@ -0,0 +1 @@
+sorted(a, key=f, reverse=True)
@@ -0,0 +1,11 @@
+x = 1
+y = 2
+    z = 3
+  w = 4
+s = "abc
+print(x)
+t = 0123
+u = 1 ! 2
+v = "\x4"
+n = 99999999999999999999
+print(y)
@@ -0,0 +1,30 @@
+count = 0
+
+def f(a, b):
+    x = 1
+    y = a + z
+    return y
+    print(b)
+
+def g():
+    count = 1
+    global count
+    for i in range(3):
+        pass
+    def h():
+        return i
+    return h
+
+def k(_unused):
+    global total
+    total = 0
+
+break
+return 1
+print(total, undefined)
+while True:
+    if count:
+        break
+    else:
+        continue
+    count = 2
@@ -0,0 +1,49 @@
+a = [3, 1, 2]
+alias = a
+a.append(4)
+a.extend((5, 6))
+a.insert(0, 0)
+a.insert(-1, 9)
+a.insert(100, 7)
+popped = a.pop()
+popped_first = a.pop(0)
+a.remove(9)
+appended = alias
+
+b = [1, 2, 1, 3, 1]
+index = b.index(1, 1)
+index_float = b.index(3.0)
+count = b.count(1)
+
+words = ["pear", "fig", "apple", "kiwi"]
+words.sort(key=len, reverse=True)
+nums = [3, 1, 2]
+nums.sort()
+nums.reverse()
+
+c = [1, 2]
+d = c.copy()
+d.append(3)
+e = [1, 2]
+e.clear()
+
+added = [1] + [2, 3]
+repeated = [0, 1] * 2
+repeated_left = 2 * ["x"]
+repeated_zero = [1] * 0
+
+f = [1, 2, 3]
+g = f
+f[0] = 10
+g[-1] = 30
+item = f[1]
+# a bool is an index too
+bool_index = [f[True], "abc"[True], (7, 8)[False]]
+h = [1, 2]
+h[True] = 20
+
+in_list = 2 in [1, 2, 3]
+not_in_list = 4 not in [1, 2, 3]
+in_tuple = "b" in ("a", "b")
+in_str = "ell" in "hello"
+eq_list = [1, [2, "x"]] == [1.0, [2, "x"]]
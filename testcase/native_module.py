@@ -0,0 +1,18 @@
+import app
+import app.geometry
+from app.geometry import area as rect_area
+from app import greet
+
+greeting = greet("world")
+sized = rect_area(3, 4)
+version = app.VERSION
+counted = [tick(), tick(), tick()]
+
+
+def tick_twice():
+    tick()
+    return tick()
+
+
+counted_in_function = tick_twice()
+module_repr = [app, app.geometry]
@@ -0,0 +1,61 @@
+# each pair is (operator, prelude function called directly)
+cases = []
+cases.append((1 + 2, __add__(1, 2)))
+cases.append((1 + 2.5, __add__(1, 2.5)))
+cases.append((2.5 + 1, __add__(2.5, 1)))
+cases.append(("ab" + "cd", __add__("ab", "cd")))
+cases.append(([1] + [2, 3], __add__([1], [2, 3])))
+cases.append(((1,) + (2,), __add__((1,), (2,))))
+cases.append((7 - 10, __sub__(7, 10)))
+cases.append((7.5 - 1, __sub__(7.5, 1)))
+cases.append((6 * 7, __mul__(6, 7)))
+cases.append((1.5 * 2, __mul__(1.5, 2)))
+cases.append(("ab" * 3, __mul__("ab", 3)))
+cases.append(("ab" * -1, __mul__("ab", -1)))
+cases.append(([1, 2] * 2, __mul__([1, 2], 2)))
+cases.append((2 * [0], __mul__(2, [0])))
+cases.append((7 / 2, __div__(7, 2)))
+cases.append((7 / 2.0, __div__(7, 2.0)))
+cases.append((-7 % 3, __mod__(-7, 3)))
+cases.append((7.5 % 2, __mod__(7.5, 2)))
+cases.append((1 << 3, __lshift__(1, 3)))
+cases.append((-8 >> 1, __rshift__(-8, 1)))
+cases.append((6 & 3, __and__(6, 3)))
+cases.append((6 | 3, __or__(6, 3)))
+cases.append((6 ^ 3, __xor__(6, 3)))
+cases.append((~5, __invert__(5)))
+cases.append((-5, __minus__(5)))
+cases.append((-2.5, __minus__(2.5)))
+cases.append((+1.5, __plus__(1.5)))
+cases.append((not [], __not__([])))
+cases.append((1 == 1.0, __eq__(1, 1.0)))
+cases.append(("a" == "a", __eq__("a", "a")))
+cases.append(([1, 2] == [1, 2], __eq__([1, 2], [1, 2])))
+cases.append(((1, 2) == (1, 3), __eq__((1, 2), (1, 3))))
+cases.append((1 != 2, __neq__(1, 2)))
+cases.append((1 < 2, __lt__(1, 2)))
+cases.append((1.5 <= 1, __le__(1.5, 1)))
+cases.append(("b" > "a", __gt__("b", "a")))
+cases.append(([1, 2] >= [1, 3], __ge__([1, 2], [1, 3])))
+cases.append(("b" in "abc", __in__("b", "abc")))
+cases.append((2 in [1, 2], __in__(2, [1, 2])))
+cases.append((3 not in (1, 2), __not_in__(3, (1, 2))))
+cases.append((None is None, __is__(None, None)))
+cases.append(([] is not [], __is_not__([], [])))
+
+# operators of the same precedence are applied from the left, comparisons are chained
+associativity = [10 - 3 - 2, 100 / 10 / 2, 7 % 4 * 2, 1 << 3 >> 1, 1 < 2 < 3, 3 > 2 == True]
+
+def __add__(lhs, rhs):
+    return "added"
+
+overridden_add = 1 + 2
+# str * int adds up the string in the prelude
+overridden_mul = "ab" * 2
+
+def __eq__(lhs, rhs):
+    return True
+
+overridden_eq = 1 == 2
+overridden_neq = 1 != 2
+overridden_in = 5 in [1, 2]
@@ -0,0 +1,35 @@
+limit = 10
+
+def locals_only(a, b):
+    c = a + b
+    for i in range(c):
+        d = i
+    return d
+
+def reads_global(x):
+    return x + limit
+
+def declares_global(value):
+    global limit
+    limit = value
+
+def outer(n):
+    step = 2
+    def inner(m):
+        return m * step
+    def count():
+        total = 0
+        for k in range(n):
+            total = total + 1
+        return total
+    return inner(n) + count()
+
+def shadows(x):
+    if x:
+        limit = 1
+    return limit
+
+summed = locals_only(1, 2)
+read = reads_global(5)
+declares_global(20)
+changed = limit
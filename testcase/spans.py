@@ -0,0 +1,7 @@
+def scale(value, factor):
+    return (value + 1) * factor
+
+
+if scale(2, factor=3) > 5:
+    items = [1, "two", (3, 4)]
+    first = items[0:2]
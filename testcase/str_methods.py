@@ -0,0 +1,41 @@
+s = "  Hello, World  "
+split_ws = "  a b\t c\n".split()
+split_sep = "a,b,,c".split(",")
+split_max = "a b c d".split(None, 2)
+rsplit_max = "a,b,c".rsplit(",", 1)
+joined = "-".join(["x", "y", "z"])
+stripped = s.strip()
+lstripped = "xxabcxx".lstrip("x")
+rstripped = s.rstrip()
+replaced = "aaaa".replace("a", "b", 2)
+found = "hello".find("l")
+found_start = "hello".find("l", 3)
+found_none = "hello".find("z")
+rfound = "hello".rfind("l")
+indexed = "hello".index("lo")
+starts = "hello".startswith("he")
+starts_tuple = "hello".startswith(("x", "hel"))
+ends = "hello".endswith("lo", 0, 4)
+upper = "héllo".upper()
+lower = "ABC".lower()
+title = "hello wORLD it's".title()
+title_titlecase = "ǅa".title()
+title_special = "ǆungla ßa ﬁsh ᾳb".title()
+isdigit = "0123".isdigit()
+isdigit_other = "²٣".isdigit()
+isdigit_fraction = "½".isdigit()
+isdigit_roman = "Ⅻ".isdigit()
+isalpha = "abc1".isalpha()
+isspace = " \t\n".isspace()
+isspace_empty = "".isspace()
+counted = "banana".count("an")
+counted_empty = "abc".count("")
+lines = "a\nb\r\nc".splitlines()
+lines_keep = "a\nb\r\nc\n".splitlines(True)
+parted = "key=value=x".partition("=")
+zfilled = "-42".zfill(5)
+centered = "ab".center(5, "*")
+chained = "  A,B ".strip().lower().split(",")
+unicode_escape = "あ\x41\t"
+unicode_len = len("あいう")
+unicode_item = "あいう"[-1]
@@ -0,0 +1,12 @@
+x = 1 +
+if x > 0
+    print(x)
+def f(a, b
+    return a
+y = [1, 2
+print(y)
+while True:
+    pass pass
+    z = 2
+if z:
+print(z)
@@ -0,0 +1,10 @@
+def inner(x):
+    y = x + 1
+    return y + undefined_name
+
+
+def outer(v):
+    return inner(v) * 2
+
+
+print(outer(3))
@@ -0,0 +1,5 @@
+def add_str(a):
+    return a + "s"
+
+
+add_str(1)
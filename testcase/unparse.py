@@ -0,0 +1,32 @@
+# comments are not kept
+x = (1 + 2) * 3
+y = 1 + (2 * 3)
+z = -(x + y) % ~x
+w = not x == y and (x or y)
+v = (x if y else z) if w else 0.5
+u = x if y else (z if w else "quote\"tab\t")
+t = f(x, key=1)[0].attr[1:][::2]
+s = (1, 2)[(0, 1)]
+r = [1, (2,), (), {"k": (3, 4)}, {5, 6}]
+q = 10 - 3 - 2
+p = x < y == True
+o = (1 - 2) - 3
+n = 1 - (2 - 3)
+m = (a < b) < c
+for a, b in x, y:
+    del a, b
+    continue
+def f(a, b):
+    global x
+    if a:
+        return a, b
+    elif b:
+        return
+    else:
+        if a is not b:
+            pass
+    while (a in b) == (b not in a):
+        break
+print((1).real, f(1, 2))
+with (a if b else c) as d, e[0] as e[1]:
+    pass
@@ -0,0 +1,69 @@
+def fact(n):
+    if n == 0:
+        return 1
+    return n * fact(n - 1)
+
+def count_odd(limit):
+    found = []
+    i = 0
+    while True:
+        i = i + 1
+        if i == limit:
+            break
+        if i % 2 == 0:
+            continue
+        found.append(i)
+    return found
+
+def first_pair(values, target):
+    for a in values:
+        for b in values:
+            if not a == b and a + b == target:
+                return (a, b)
+    return None
+
+def skip(values, bad):
+    res = []
+    for v in values:
+        if v in bad:
+            continue
+        if v == 9:
+            break
+        res.append(v)
+    return res
+
+def scale(x, factor, offset):
+    return x * factor + offset
+
+def outer(x):
+    def inner(y):
+        return y + 1
+    return inner(x) * 2
+
+counter = 0
+def bump():
+    global counter
+    counter = counter + 1
+
+def shadow():
+    counter = 100
+    return counter
+
+fact_10 = fact(10)
+odd = count_odd(10)
+pair = first_pair([1, 2, 3, 4, 5], 7)
+no_pair = first_pair([1, 2], 10)
+skipped = skip([1, 2, 3, 4, 9, 5], {2, 4})
+scaled = scale(3, offset=1, factor=5)
+nested = outer(4)
+bump()
+bump()
+shadowed = shadow()
+a = b = [1]
+a.append(2)
+not_and = not (1 == 1 and 2 == 3)
+either = 0 or 2 == 2
+d = {"x": 1}
+d["y"] = 2
+items = d.items()
+neg = -5 + 2